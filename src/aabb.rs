@@ -0,0 +1,56 @@
+use nalgebra_glm::Vec3;
+
+// Caja alineada a los ejes, en coordenadas de mundo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb {
+            min: Vec3::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            max: Vec3::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+
+    // Distancias (entrada, salida) del rayo `origin + t * direction` dentro de la caja,
+    // por el método de los slabs. Solo cuenta la parte del rayo con t >= 0.
+    pub fn ray_intersection(&self, origin: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+        let mut t_near = 0.0f32;
+        let mut t_far = f32::INFINITY;
+
+        for axis in 0..3 {
+            let inverse = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // Con dirección 0 en un eje los t son ±inf (o NaN si el origen está en el borde)
+            if t0.is_nan() || t1.is_nan() {
+                continue;
+            }
+            t_near = t_near.max(t0);
+            t_far = t_far.min(t1);
+            if t_near > t_far {
+                return None;
+            }
+        }
+
+        Some((t_near, t_far))
+    }
+}
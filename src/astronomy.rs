@@ -0,0 +1,101 @@
+use std::f32::consts::PI;
+
+use crate::Planet;
+
+// Período sinódico de dos cuerpos que orbitan el mismo centro: cada cuánto se repite su
+// configuración vista desde ese centro (en tiempo de la simulación). Infinito si van a la par.
+pub fn synodic_period(a: &Planet, b: &Planet) -> f32 {
+    let relative = (a.orbit_speed - b.orbit_speed).abs();
+    if relative == 0.0 { f32::INFINITY } else { 2.0 * PI / relative }
+}
+
+// Próxima vez, después de `current_time`, en que `outer` está en oposición vista desde `inner`:
+// los dos del mismo lado del Sol y alineados (Sol, `inner`, `outer`), con una separación de 0°
+// vistos desde el Sol. Se resuelve con el período sinódico de las órbitas circulares (las
+// efemérides de `--spk` no cuentan); infinito si no se alcanzan nunca.
+pub fn next_opposition_time(inner: &Planet, outer: &Planet, current_time: f64) -> f64 {
+    next_separation_time(inner, outer, current_time, 0.0)
+}
+
+// Lo mismo para la conjunción de `outer` con el Sol vista desde `inner`: cada uno de un lado del
+// Sol, a 180° vistos desde él
+pub fn next_conjunction_time(inner: &Planet, outer: &Planet, current_time: f64) -> f64 {
+    next_separation_time(inner, outer, current_time, PI)
+}
+
+// Próximo tiempo en que el ángulo de `inner` menos el de `outer`, visto desde el Sol, vale
+// `separation` (radianes, módulo una vuelta)
+fn next_separation_time(inner: &Planet, outer: &Planet, current_time: f64, separation: f32) -> f64 {
+    let relative = inner.orbit_speed - outer.orbit_speed;
+    if relative == 0.0 {
+        return f64::INFINITY;
+    }
+    // Lo que le falta a la separación en el sentido en que cambia; justo en el evento, el siguiente
+    let current = inner.orbit_angle(current_time) - outer.orbit_angle(current_time);
+    let remaining = ((separation - current) * relative.signum()).rem_euclid(2.0 * PI);
+    let remaining = if remaining == 0.0 { 2.0 * PI } else { remaining };
+    current_time + (remaining / relative.abs()) as f64
+}
+
+// Densidad media (kg/m³) de los cuerpos con lunas y de las lunas
+const MEAN_DENSITIES: [(&str, f32); 6] = [
+    ("Tierra", 5514.0),
+    ("Marte", 3933.0),
+    ("Júpiter", 1326.0),
+    ("Saturno", 687.0),
+    ("Urano", 1270.0),
+    ("Luna", 3344.0),
+];
+
+// Coeficiente del límite de Roche para un satélite fluido (el de uno rígido es 1.26)
+const FLUID_ROCHE_COEFFICIENT: f32 = 2.44;
+
+pub fn mean_density(planet: &Planet) -> Option<f32> {
+    MEAN_DENSITIES.iter().find(|(name, _)| *name == planet.name).map(|(_, density)| *density)
+}
+
+// Límite de Roche de un satélite fluido con densidad `moon_density` alrededor de un cuerpo de
+// densidad `planet_density`, en radios del cuerpo: más cerca, las mareas lo deshacen
+pub fn roche_limit_radii(planet_density: f32, moon_density: f32) -> f32 {
+    FLUID_ROCHE_COEFFICIENT * (planet_density / moon_density).cbrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solar_system, body_position};
+
+    // En la oposición de Marte la Tierra y Marte quedan alineados del mismo lado del Sol, en la
+    // conjunción de lados opuestos, y las oposiciones se repiten cada período sinódico
+    #[test]
+    fn oppositions() -> Result<(), String> {
+        let planets = solar_system();
+        let index = |name: &str| planets.iter().position(|planet| planet.name == name).ok_or(format!("no está {}", name));
+        let (earth, mars) = (index("Tierra")?, index("Marte")?);
+        let direction = |i: usize, time: f64| body_position(&planets, i, time).normalize();
+
+        let start = 123.0;
+        let opposition = next_opposition_time(&planets[earth], &planets[mars], start);
+        let conjunction = next_conjunction_time(&planets[earth], &planets[mars], start);
+        let period = synodic_period(&planets[earth], &planets[mars]) as f64;
+        if !(opposition > start && opposition <= start + period && conjunction > start && conjunction <= start + period) {
+            return Err(format!("desde {} la oposición quedó en {} y la conjunción en {} (período {})", start, opposition, conjunction, period));
+        }
+        let alignment = direction(earth, opposition).dot(&direction(mars, opposition));
+        if alignment < 0.9999 {
+            return Err(format!("en la oposición el coseno de la separación es {}", alignment));
+        }
+        let alignment = direction(earth, conjunction).dot(&direction(mars, conjunction));
+        if alignment > -0.9999 {
+            return Err(format!("en la conjunción el coseno de la separación es {}", alignment));
+        }
+        let next = next_opposition_time(&planets[earth], &planets[mars], opposition);
+        if ((next - opposition) / period - 1.0).abs() > 1e-3 {
+            return Err(format!("la oposición siguiente llegó {} después, no {}", next - opposition, period));
+        }
+        if next_opposition_time(&planets[earth], &planets[earth], start).is_finite() {
+            return Err("dos cuerpos a la par tienen oposiciones".to_string());
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,47 @@
-use nalgebra_glm::{Vec3, rotate_vec3};
+use nalgebra_glm::{Vec3, Vec4, Mat4, rotate_vec3};
 use std::f32::consts::PI;
 
+use crate::uniforms::DEFAULT_FOV_Y;
+
 pub struct Camera {
   pub eye: Vec3,
   pub center: Vec3,
   pub up: Vec3,
   pub has_changed: bool,
   pub fov: f32,
+  // Campo de visión vertical de la proyección, en radianes
+  pub fov_y: f32,
+}
+
+// Límites para la posición del ojo respecto al punto de giro: distancia mínima y máxima
+// (para no atravesar el cuerpo enfocado ni perderse a lo lejos) y elevación mínima sobre
+// el plano XZ, en radianes. `keep_out` es una esfera (centro, radio) de la que el ojo queda
+// afuera aunque no sea el punto de giro, como el cuerpo más cercano con la vista sin enfoque.
+pub struct CameraConstraints {
+  pub min_distance_from_center: f32,
+  pub max_distance_from_center: f32,
+  pub min_elevation: f32,
+  pub keep_out: Option<(Vec3, f32)>,
+}
+
+impl CameraConstraints {
+  // Cuánto puede avanzar el ojo hacia el centro sin pasar de la distancia mínima ni entrar en
+  // la esfera de `keep_out`. Con cuerpos pequeños un paso entero podría atravesarla de lado a lado.
+  pub fn zoom_room(&self, camera: &Camera) -> f32 {
+    let offset = camera.center - camera.eye;
+    let mut room = offset.magnitude() - self.min_distance_from_center;
+    if let Some((body, radius)) = self.keep_out {
+      // Primer corte del rayo ojo -> centro con la esfera, si va hacia ella
+      let direction = offset.normalize();
+      let relative = camera.eye - body;
+      let along = direction.dot(&relative);
+      let discriminant = along * along - (relative.magnitude_squared() - radius * radius);
+      if along < 0.0 && discriminant > 0.0 {
+        room = room.min(-along - discriminant.sqrt());
+      }
+    }
+    room.max(0.0)
+  }
 }
 
 impl Camera {
@@ -17,6 +52,7 @@ impl Camera {
       up,
       has_changed: true,
       fov: 100.0,
+      fov_y: DEFAULT_FOV_Y,
     }
   }
 
@@ -77,6 +113,55 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Traslada el punto de giro (y el ojo con él) sobre los ejes derecha/arriba de la cámara.
+  // Los deltas están en píxeles; se escalan por la distancia para que el arrastre se sienta
+  // igual con cualquier zoom.
+  pub fn pan(&mut self, delta_x: f32, delta_y: f32, speed: f32) {
+    let offset = self.center - self.eye;
+    let distance = offset.magnitude();
+    let forward = offset.normalize();
+    let right = forward.cross(&self.up).normalize();
+    let up = right.cross(&forward).normalize();
+
+    let translation = (right * -delta_x + up * delta_y) * speed * distance;
+    self.eye += translation;
+    self.center += translation;
+    self.has_changed = true;
+  }
+
+  // Lleva el ojo dentro de los límites sin cambiar el rumbo: solo corrige la distancia al
+  // centro y la elevación cuando se salen del rango. Si queda dentro de `keep_out` sale por
+  // el punto más cercano de su superficie.
+  pub fn apply_constraints(&mut self, constraints: &CameraConstraints) {
+    let offset = self.eye - self.center;
+    let distance = offset.magnitude();
+    let horizontal = (offset.x * offset.x + offset.z * offset.z).sqrt();
+    let elevation = offset.y.atan2(horizontal);
+
+    let max_distance = constraints.max_distance_from_center.max(constraints.min_distance_from_center);
+    let new_distance = distance.clamp(constraints.min_distance_from_center, max_distance);
+    let new_elevation = elevation.max(constraints.min_elevation);
+    if new_distance != distance || new_elevation != elevation {
+      let yaw = offset.z.atan2(offset.x);
+      self.eye = self.center + Vec3::new(
+        new_distance * yaw.cos() * new_elevation.cos(),
+        new_distance * new_elevation.sin(),
+        new_distance * yaw.sin() * new_elevation.cos()
+      );
+      self.has_changed = true;
+    }
+
+    if let Some((body, radius)) = constraints.keep_out {
+      let outward = self.eye - body;
+      if outward.magnitude() < radius {
+        // En el centro mismo del cuerpo se sale hacia atrás de la mirada
+        let direction = outward.try_normalize(1e-6).unwrap_or_else(|| (self.eye - self.center).normalize());
+        self.eye = body + direction * radius;
+        self.has_changed = true;
+      }
+    }
+  }
+
   pub fn check_if_changed(&mut self) -> bool {
     if self.has_changed {
       self.has_changed = false;
@@ -92,4 +177,206 @@ impl Camera {
       self.fov = fov.clamp(30.0, 120.0); // Limitar el FOV a un rango razonable
       self.has_changed = true;
   }
-}
\ No newline at end of file
+
+  // Cambia el campo de visión vertical (en grados) con el que se proyecta. El cono con el que se
+  // descartan cuerpos (`fov`) se abre al doble, como los 100° de siempre para los 50° por omisión.
+  pub fn set_vertical_fov(&mut self, degrees: f32) {
+      self.fov_y = degrees.to_radians();
+      self.fov = degrees * 2.0;
+      self.has_changed = true;
+  }
+
+  // Caja (min, max) alineada a los ejes que contiene el frustum en coordenadas de mundo,
+  // para descartar rápido lo que seguro no se ve. Es holgada: no todo lo que la toca es visible.
+  pub fn compute_frustum_aabb(proj: &Mat4, view: &Mat4) -> (Vec3, Vec3) {
+    let corners = frustum_corners(proj, view);
+    let mut min = corners[0];
+    let mut max = corners[0];
+    for corner in &corners[1..] {
+      min = min.inf(corner);
+      max = max.sup(corner);
+    }
+    (min, max)
+  }
+}
+
+// Esquinas del frustum en el mundo, desproyectando las ocho esquinas NDC (±1, ±1, ±1)
+// con (proj * view)^-1. Las cuatro primeras están en el plano near y las otras en el far,
+// ambas en el orden (-1,-1), (1,-1), (1,1), (-1,1).
+pub fn frustum_corners(projection: &Mat4, view: &Mat4) -> [Vec3; 8] {
+  let inverse = (projection * view).try_inverse().unwrap_or(Mat4::identity());
+  let mut corners = [Vec3::new(0.0, 0.0, 0.0); 8];
+
+  for (i, z) in [-1.0, 1.0].iter().enumerate() {
+    for (j, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter().enumerate() {
+      let world = inverse * Vec4::new(*x, *y, *z, 1.0);
+      corners[i * 4 + j] = Vec3::new(world.x, world.y, world.z) / world.w;
+    }
+  }
+
+  corners
+}
+
+// Semiángulos (horizontal, vertical) del campo de visión, leídos de la matriz de proyección
+pub fn half_fov_angles(projection: &Mat4) -> (f32, f32) {
+  ((1.0 / projection[(0, 0)]).atan(), (1.0 / projection[(1, 1)]).abs().atan())
+}
+
+// Encuadre de un conjunto de esferas (centro, radio): devuelve (ojo, centro) mirando desde
+// `view_direction` (del centro hacia el ojo) a la distancia justa para que la esfera que las
+// envuelve quepa en el lado más estrecho del frustum, multiplicada por `margin`
+pub fn frame_spheres(spheres: &[(Vec3, f32)], view_direction: Vec3, projection: &Mat4, margin: f32) -> (Vec3, Vec3) {
+  if spheres.is_empty() {
+    return (view_direction.normalize(), Vec3::new(0.0, 0.0, 0.0));
+  }
+
+  // Centro de la caja que contiene todas las esferas y radio que las envuelve desde ahí
+  let mut min = spheres[0].0 - Vec3::repeat(spheres[0].1);
+  let mut max = spheres[0].0 + Vec3::repeat(spheres[0].1);
+  for (center, radius) in spheres {
+    min = min.inf(&(center - Vec3::repeat(*radius)));
+    max = max.sup(&(center + Vec3::repeat(*radius)));
+  }
+  let center = (min + max) * 0.5;
+  let radius = spheres
+    .iter()
+    .map(|(position, r)| (position - center).magnitude() + r)
+    .fold(0.0f32, f32::max);
+
+  let (half_horizontal, half_vertical) = half_fov_angles(projection);
+  let distance = radius / half_horizontal.min(half_vertical).sin() * margin;
+
+  (center + view_direction.normalize() * distance, center)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::selftest::{WIDTH, HEIGHT};
+  use crate::uniforms::{create_view_matrix, create_perspective_matrix, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE};
+
+  // Cada esfera encuadrada debe quedar completa dentro de los cuatro planos laterales del frustum
+  #[test]
+  fn framing() -> Result<(), String> {
+    let projection = create_perspective_matrix(WIDTH, HEIGHT, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE);
+    let (half_horizontal, half_vertical) = half_fov_angles(&projection);
+
+    let configurations: Vec<(Vec<(Vec3, f32)>, Vec3)> = vec![
+      (vec![(Vec3::new(0.0, 0.0, 0.0), 10.0)], Vec3::new(0.0, 1.0, 1.0)),
+      (vec![(Vec3::new(-80.0, 0.0, 0.0), 3.0), (Vec3::new(60.0, 0.0, 20.0), 2.0)], Vec3::new(1.0, 0.5, 0.0)),
+      (vec![(Vec3::new(100.0, 0.0, 0.0), 7.0), (Vec3::new(0.0, 0.0, -140.0), 5.0), (Vec3::new(-120.0, 0.0, 0.0), 6.0)], Vec3::new(0.3, 1.0, 0.8)),
+      (vec![(Vec3::new(0.0, 0.0, 0.0), 1.0), (Vec3::new(0.0, 0.0, 300.0), 1.0)], Vec3::new(1.0, 0.2, 0.0)),
+    ];
+
+    for (index, (spheres, direction)) in configurations.iter().enumerate() {
+      let (eye, center) = frame_spheres(spheres, *direction, &projection, 1.0);
+      let view = create_view_matrix(eye, center, Vec3::new(0.0, 1.0, 0.0));
+
+      for (position, radius) in spheres {
+        let p = view * Vec4::new(position.x, position.y, position.z, 1.0);
+        let depth = -p.z;
+        // Distancia con signo del centro a cada plano lateral (positiva hacia adentro)
+        let inside_horizontal = depth * half_horizontal.sin() - p.x.abs() * half_horizontal.cos();
+        let inside_vertical = depth * half_vertical.sin() - p.y.abs() * half_vertical.cos();
+        if inside_horizontal < radius - 1e-3 || inside_vertical < radius - 1e-3 {
+          return Err(format!("configuracion {}: la esfera en {:?} queda fuera de la vista", index, position));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // La caja del frustum debe contener sus ocho esquinas y un punto frente a la cámara, y dejar
+  // fuera un punto muy por detrás del ojo
+  #[test]
+  fn frustum_aabb() -> Result<(), String> {
+    let projection = create_perspective_matrix(WIDTH, HEIGHT, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, 200.0);
+    let eye = Vec3::new(50.0, 100.0, 250.0);
+    let view = create_view_matrix(eye, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let (min, max) = Camera::compute_frustum_aabb(&projection, &view);
+    let contains = |p: Vec3| (0..3).all(|axis| p[axis] >= min[axis] - 1e-3 && p[axis] <= max[axis] + 1e-3);
+
+    if let Some(corner) = frustum_corners(&projection, &view).iter().find(|corner| !contains(**corner)) {
+      return Err(format!("la esquina {:?} queda fuera de la caja", corner));
+    }
+    let forward = (-eye).normalize();
+    if !contains(eye + forward * 100.0) {
+      return Err("un punto frente a la camara queda fuera de la caja".to_string());
+    }
+    if contains(eye - forward * 400.0) {
+      return Err("un punto detras de la camara queda dentro de la caja".to_string());
+    }
+    Ok(())
+  }
+
+  // Al acercarse de más, el ojo vuelve a la distancia mínima sin cambiar de rumbo; por debajo
+  // de la elevación mínima sube hasta ella, y dentro de los límites no se toca
+  #[test]
+  fn camera_constraints() -> Result<(), String> {
+    let constraints = CameraConstraints {
+      min_distance_from_center: 2.2,
+      max_distance_from_center: 100.0,
+      min_elevation: -0.5,
+      keep_out: None,
+    };
+    let center = Vec3::new(60.0, 0.0, 0.0);
+    let mut camera = Camera::new(center + Vec3::new(0.3, 0.4, 0.0), center, Vec3::new(0.0, 1.0, 0.0));
+    for _ in 0..20 {
+      camera.zoom(0.5);
+      camera.apply_constraints(&constraints);
+    }
+    let offset = camera.eye - camera.center;
+    if (offset.magnitude() - 2.2).abs() > 1e-3 {
+      return Err(format!("distancia {} tras acercarse, se esperaba 2.2", offset.magnitude()));
+    }
+
+    let mut camera = Camera::new(center + Vec3::new(0.0, -40.0, 10.0), center, Vec3::new(0.0, 1.0, 0.0));
+    camera.apply_constraints(&constraints);
+    let offset = camera.eye - camera.center;
+    let elevation = offset.y.atan2(offset.x.hypot(offset.z));
+    if (elevation + 0.5).abs() > 1e-3 || offset.x.abs() > 1e-3 || offset.z <= 0.0 {
+      return Err(format!("ojo en {:?} con elevacion {}, se esperaba -0.5 sobre +z", offset, elevation));
+    }
+
+    let eye = center + Vec3::new(5.0, 3.0, -4.0);
+    let mut camera = Camera::new(eye, center, Vec3::new(0.0, 1.0, 0.0));
+    camera.apply_constraints(&constraints);
+    if camera.eye != eye {
+      return Err("se movio un ojo que ya estaba dentro de los limites".to_string());
+    }
+
+    // Sin enfoque el punto de giro está en el vacío y el cuerpo queda entre él y el ojo: el
+    // acercamiento se detiene en su superficie y un ojo que quedó adentro sale por la más cercana
+    let body = Vec3::new(30.0, 0.0, 0.0);
+    let free = CameraConstraints {
+      min_distance_from_center: 0.5,
+      max_distance_from_center: 100.0,
+      min_elevation: -0.5,
+      keep_out: Some((body, 2.0)),
+    };
+    let mut camera = Camera::new(Vec3::new(40.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let room = free.zoom_room(&camera);
+    if (room - 8.0).abs() > 1e-4 {
+      return Err(format!("hay {} de recorrido hasta el cuerpo, se esperaba 8", room));
+    }
+    for _ in 0..40 {
+      camera.zoom(0.5f32.min(free.zoom_room(&camera)));
+      camera.apply_constraints(&free);
+    }
+    if ((camera.eye - body).magnitude() - 2.0).abs() > 1e-3 {
+      return Err(format!("el acercamiento dejó el ojo en {:?}, se esperaba en la superficie", camera.eye));
+    }
+    camera.eye = body + Vec3::new(0.5, 1.0, 0.0);
+    camera.apply_constraints(&free);
+    let outward = (camera.eye - body).normalize();
+    if ((camera.eye - body).magnitude() - 2.0).abs() > 1e-3 || (outward - Vec3::new(0.5, 1.0, 0.0).normalize()).magnitude() > 1e-3 {
+      return Err(format!("el ojo dentro del cuerpo salió a {:?}", camera.eye));
+    }
+    // Alejándose del cuerpo no hay nada que lo frene
+    let camera = Camera::new(body + Vec3::new(0.0, 0.0, 2.0), body + Vec3::new(0.0, 0.0, 12.0), Vec3::new(0.0, 1.0, 0.0));
+    if (free.zoom_room(&camera) - 9.5).abs() > 1e-4 {
+      return Err(format!("alejándose del cuerpo hay {} de recorrido, se esperaba 9.5", free.zoom_room(&camera)));
+    }
+    Ok(())
+  }
+}
@@ -27,6 +27,32 @@ impl Color {
     }
   }
 
+  // Color de una luz monocromática de `nm` nanómetros (380 a 700, negro fuera de ese rango),
+  // con la aproximación por tramos de Dan Bruton del espectro visible. La intensidad cae
+  // hacia los bordes, donde el ojo es menos sensible, y se aplica una gamma de 0.8.
+  pub fn from_wavelength(nm: f32) -> Self {
+    let (r, g, b) = match nm {
+      nm if (380.0..440.0).contains(&nm) => (-(nm - 440.0) / (440.0 - 380.0), 0.0, 1.0),
+      nm if (440.0..490.0).contains(&nm) => (0.0, (nm - 440.0) / (490.0 - 440.0), 1.0),
+      nm if (490.0..510.0).contains(&nm) => (0.0, 1.0, -(nm - 510.0) / (510.0 - 490.0)),
+      nm if (510.0..580.0).contains(&nm) => ((nm - 510.0) / (580.0 - 510.0), 1.0, 0.0),
+      nm if (580.0..645.0).contains(&nm) => (1.0, -(nm - 645.0) / (645.0 - 580.0), 0.0),
+      nm if (645.0..=700.0).contains(&nm) => (1.0, 0.0, 0.0),
+      _ => return Color::black(),
+    };
+
+    let intensity = if nm < 420.0 {
+      0.3 + 0.7 * (nm - 380.0) / (420.0 - 380.0)
+    } else if nm > 645.0 {
+      0.3 + 0.7 * (700.0 - nm) / (700.0 - 645.0)
+    } else {
+      1.0
+    };
+
+    let adjust = |channel: f32| (channel * intensity).powf(0.8);
+    Color::from_float(adjust(r), adjust(g), adjust(b))
+  }
+
   // Function to create a color from a hex value
   pub fn from_hex(hex: u32) -> Self {
     let r = ((hex >> 16) & 0xFF) as u8;
@@ -50,6 +76,20 @@ impl Color {
     }
   }
 
+  // Exposición en pasos: multiplica el color en luz lineal por 2^stops (un paso dobla la luz)
+  pub fn exposure(&self, stops: f32) -> Color {
+    let scale = stops.exp2();
+    let channel = |c: u8| (linear_to_srgb(srgb_to_linear(c) * scale) * 255.0).round() as u8;
+    Color::new(channel(self.r), channel(self.g), channel(self.b))
+  }
+
+  // Contraste alrededor del gris medio: con `factor` > 1 los canales se alejan de 0.5 y con < 1 se
+  // acercan; 1 deja el color igual
+  pub fn contrast(&self, factor: f32) -> Color {
+    let channel = |c: u8| (((c as f32 / 255.0 - 0.5) * factor + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Color::new(channel(self.r), channel(self.g), channel(self.b))
+  }
+
   pub fn is_black(&self) -> bool {
     self.r == 0 && self.g == 0 && self.b == 0 
   }
@@ -67,20 +107,23 @@ impl Color {
     )
   }
 
+  // Suma por canal saturada en 255, para componer luz sobre la escena (fulguraciones,
+  // niebla). También se puede llamar como `Color::blend_add(&a, &b)`.
   pub fn blend_add(&self, blend: &Color) -> Color {
     Color::new(
-      (self.r as u16 + blend.r as u16).min(255) as u8,
-      (self.g as u16 + blend.g as u16).min(255) as u8,
-      (self.b as u16 + blend.b as u16).min(255) as u8
+      self.r.saturating_add(blend.r),
+      self.g.saturating_add(blend.g),
+      self.b.saturating_add(blend.b)
     )
   }
 
+  // Resta por canal saturada en 0, para quitar luz (máscaras de sombra)
   pub fn blend_subtract(&self, blend: &Color) -> Color {
-    let r = (self.r as i16 - blend.r as i16).max(0).min(255) as u8;
-    let g = (self.g as i16 - blend.g as i16).max(0).min(255) as u8;
-    let b = (self.b as i16 - blend.b as i16).max(0).min(255) as u8;
-
-    Color::new(r, g, b)
+    Color::new(
+      self.r.saturating_sub(blend.r),
+      self.g.saturating_sub(blend.g),
+      self.b.saturating_sub(blend.b)
+    )
   }
 
   pub fn blend_screen(&self, blend: &Color) -> Color {
@@ -93,6 +136,67 @@ impl Color {
 
 }
 
+// Simulación de daltonismo de Brettel, Viénot y Mollon (1997). Cada deficiencia proyecta los
+// colores sobre dos semiplanos del espacio LMS, elegidos según de qué lado del plano que los
+// separa cae el color; las matrices ya componen RGB lineal -> LMS, la proyección y LMS -> RGB
+// lineal (conos de Smith y Pokorny, primarios sRGB, las de libDaltonLens).
+struct Brettel {
+  first: [[f32; 3]; 3],
+  second: [[f32; 3]; 3],
+  separation: [f32; 3],
+}
+
+const PROTANOPIA: Brettel = Brettel {
+  first: [[0.14980, 1.19548, -0.34528], [0.10764, 0.84864, 0.04372], [0.00384, -0.00540, 1.00156]],
+  second: [[0.14570, 1.16172, -0.30742], [0.10816, 0.85291, 0.03892], [0.00386, -0.00524, 1.00139]],
+  separation: [0.00048, 0.00393, -0.00441],
+};
+
+const DEUTERANOPIA: Brettel = Brettel {
+  first: [[0.36477, 0.86381, -0.22858], [0.26294, 0.64245, 0.09462], [-0.02006, 0.02728, 0.99278]],
+  second: [[0.37298, 0.88166, -0.25464], [0.25954, 0.63506, 0.10540], [-0.01980, 0.02784, 0.99196]],
+  separation: [-0.00281, -0.00611, 0.00892],
+};
+
+const TRITANOPIA: Brettel = Brettel {
+  first: [[1.01277, 0.13548, -0.14826], [-0.01243, 0.86812, 0.14431], [0.07589, 0.80500, 0.11911]],
+  second: [[0.93678, 0.18979, -0.12657], [0.06154, 0.81526, 0.12320], [-0.37562, 1.12767, 0.24796]],
+  separation: [0.03901, -0.02788, -0.01113],
+};
+
+pub fn srgb_to_linear(channel: u8) -> f32 {
+  let c = channel as f32 / 255.0;
+  if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+  let c = c.clamp(0.0, 1.0);
+  if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn simulate(color: Color, deficiency: &Brettel) -> Color {
+  let rgb = [srgb_to_linear(color.r), srgb_to_linear(color.g), srgb_to_linear(color.b)];
+  let side: f32 = (0..3).map(|i| deficiency.separation[i] * rgb[i]).sum();
+  let matrix = if side >= 0.0 { &deficiency.first } else { &deficiency.second };
+  let channel = |row: usize| linear_to_srgb((0..3).map(|i| matrix[row][i] * rgb[i]).sum());
+  Color::from_float(channel(0), channel(1), channel(2))
+}
+
+// Sin conos L (rojo)
+pub fn simulate_protanopia(color: Color) -> Color {
+  simulate(color, &PROTANOPIA)
+}
+
+// Sin conos M (verde)
+pub fn simulate_deuteranopia(color: Color) -> Color {
+  simulate(color, &DEUTERANOPIA)
+}
+
+// Sin conos S (azul)
+pub fn simulate_tritanopia(color: Color) -> Color {
+  simulate(color, &TRITANOPIA)
+}
+
 // Implement addition for Color
 use std::ops::Add;
 
@@ -128,4 +232,152 @@ impl fmt::Display for Color {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use nalgebra_glm::{Vec2, Vec3};
+  use crate::Framebuffer;
+  use crate::exposure::AutoExposure;
+  use crate::fragment::Fragment;
+  use crate::seed::{SeedSource, DEFAULT_SEED};
+  use crate::selftest::test_uniforms;
+  use crate::shaders::select_shader;
+  use crate::uniforms::ColorBlindMode;
+
+  // Hα y SII son rojos (SII más oscuro), OIII verde azulado, 450 nm azul y fuera del visible negro
+  #[test]
+  fn wavelength_colors() -> Result<(), String> {
+    let h_alpha = Color::from_wavelength(656.3);
+    let oxygen = Color::from_wavelength(500.7);
+    let sulfur = Color::from_wavelength(672.4);
+    let blue = Color::from_wavelength(450.0);
+
+    if h_alpha.g != 0 || h_alpha.b != 0 || h_alpha.r < 200 {
+      return Err(format!("H-alfa da {}", h_alpha));
+    }
+    if sulfur.g != 0 || sulfur.b != 0 || sulfur.r >= h_alpha.r {
+      return Err(format!("SII da {}, H-alfa {}", sulfur, h_alpha));
+    }
+    if oxygen.r != 0 || oxygen.g < 200 || oxygen.b < 100 {
+      return Err(format!("OIII da {}", oxygen));
+    }
+    if blue.b != 255 || blue.r != 0 || blue.g >= blue.b {
+      return Err(format!("450 nm da {}", blue));
+    }
+    for nm in [300.0, 379.0, 701.0, 900.0] {
+      if !Color::from_wavelength(nm).is_black() {
+        return Err(format!("{} nm fuera del visible no es negro", nm));
+      }
+    }
+    Ok(())
+  }
+
+  // Las mezclas saturan en vez de desbordar
+  #[test]
+  fn additive_blending() -> Result<(), String> {
+    let (a, b) = (Color::new(200, 100, 10), Color::new(100, 100, 20));
+    let sum = Color::blend_add(&a, &b);
+    if sum != Color::new(255, 200, 30) {
+      return Err(format!("suma {}, se esperaba (255, 200, 30)", sum));
+    }
+    let difference = Color::blend_subtract(&a, &b);
+    if difference != Color::new(100, 0, 0) {
+      return Err(format!("resta {}, se esperaba (100, 0, 0)", difference));
+    }
+    Ok(())
+  }
+
+  // Los grises no cambian; sin conos L o M el rojo y el verde quedan con el mismo tono (solo
+  // cambia el brillo), y sin conos S el azul se ve verdoso
+  #[test]
+  fn color_blindness() -> Result<(), String> {
+    let seeds = &SeedSource::new(DEFAULT_SEED);
+    let modes = [ColorBlindMode::Protanopia, ColorBlindMode::Deuteranopia, ColorBlindMode::Tritanopia];
+    for mode in modes {
+      for gray in [Color::black(), Color::new(128, 128, 128), Color::new(255, 255, 255)] {
+        let simulated = mode.apply(gray);
+        if (simulated.r as i32 - gray.r as i32).abs() > 2 || (simulated.g as i32 - gray.g as i32).abs() > 2 || (simulated.b as i32 - gray.b as i32).abs() > 2 {
+          return Err(format!("{} cambia el gris {} a {}", mode.name(), gray, simulated));
+        }
+      }
+    }
+
+    let hue = |color: Color| color.g as f32 / color.r.max(1) as f32;
+    for mode in &modes[..2] {
+      let (red, green) = (mode.apply(Color::new(255, 0, 0)), mode.apply(Color::new(0, 255, 0)));
+      if (hue(red) - hue(green)).abs() > 0.1 {
+        return Err(format!("con {} el rojo es {} y el verde {}", mode.name(), red, green));
+      }
+    }
+    if simulate_deuteranopia(Color::new(255, 0, 0)) == simulate_protanopia(Color::new(255, 0, 0)) {
+      return Err("protanopia y deuteranopia dan el mismo rojo".to_string());
+    }
+    let blue = simulate_tritanopia(Color::new(0, 0, 255));
+    if blue.g < 64 || blue.b > 200 {
+      return Err(format!("con tritanopia el azul sigue siendo {}", blue));
+    }
+
+    // El modo de los uniforms se aplica a lo que devuelve el shader
+    let mut uniforms = test_uniforms(seeds);
+    let fragment = Fragment::new(
+      Vec2::new(400.0, 300.0),
+      Color::new(100, 100, 100),
+      7.0,
+      Vec3::new(-1.0, 0.0, 0.0),
+      1.0,
+      Vec3::new(0.3, 0.0, 0.0),
+      Vec3::new(10.0, 0.0, 0.0),
+    );
+    let normal = select_shader(4, &fragment, &uniforms);
+    uniforms.color_blind_mode = ColorBlindMode::Protanopia;
+    if select_shader(4, &fragment, &uniforms) != simulate_protanopia(normal) {
+      return Err("select_shader no aplica el modo de daltonismo".to_string());
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn color_exposure_contrast() -> Result<(), String> {
+    for color in [Color::new(0, 0, 0), Color::new(12, 128, 255), Color::new(200, 90, 31)] {
+      if color.exposure(0.0) != color || color.contrast(1.0) != color {
+        return Err(format!("{:?} cambia con exposure(0) o contrast(1)", color));
+      }
+    }
+
+    // Un paso más duplica la luz lineal; uno menos la reduce a la mitad
+    let gray = Color::new(100, 100, 100);
+    for (stops, factor) in [(1.0, 2.0), (-1.0, 0.5)] {
+      let exposed = gray.exposure(stops);
+      let ratio = srgb_to_linear(exposed.r) / srgb_to_linear(gray.r);
+      if (ratio - factor).abs() > 0.05 {
+        return Err(format!("exposure({}) multiplica la luz lineal por {}", stops, ratio));
+      }
+    }
+    if Color::new(255, 255, 255).exposure(3.0) != Color::new(255, 255, 255) {
+      return Err("la exposición no se satura en el blanco".to_string());
+    }
+
+    // El contraste separa los tonos del gris medio, satura en los extremos y con 0 los lleva al gris
+    let stretched = Color::new(100, 150, 250).contrast(1.5);
+    if stretched != Color::new(86, 161, 255) {
+      return Err(format!("contrast(1.5) da {:?}", stretched));
+    }
+    if Color::new(10, 240, 128).contrast(0.0) != Color::new(128, 128, 128) {
+      return Err(format!("contrast(0) da {:?}", Color::new(10, 240, 128).contrast(0.0)));
+    }
+
+    // La etapa de exposición de la imagen da lo mismo que Color::exposure en cada canal
+    let mut framebuffer = Framebuffer::new(3, 1);
+    framebuffer.buffer = vec![0x0C80FF, 0x646464, 0xC85A1F];
+    AutoExposure::scale_framebuffer(&mut framebuffer, 0.7);
+    for (&pixel, &original) in framebuffer.buffer.iter().zip(&[0x0C80FFu32, 0x646464, 0xC85A1F]) {
+      let expected = Color::from_hex(original).exposure(0.7).to_hex();
+      if pixel != expected {
+        return Err(format!("{:06X} expuesto da {:06X}, no {:06X}", original, pixel, expected));
+      }
+    }
+    Ok(())
+  }
+}
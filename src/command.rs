@@ -0,0 +1,297 @@
+use minifb::{Key, KeyRepeat, Window};
+
+use crate::Framebuffer;
+use crate::Planet;
+use crate::hud::Contrast;
+use crate::overlay::Overlay;
+
+const TEXT_SCALE: usize = 2;
+const PADDING: usize = 6;
+const PROMPT: &str = "> ";
+
+// Nombres en inglés de los cuerpos, además de los suyos (sin tildes ni mayúsculas)
+const BODY_ALIASES: [(&str, &str); 10] = [
+    ("sun", "Sol"),
+    ("mercury", "Mercurio"),
+    ("venus", "Venus"),
+    ("earth", "Tierra"),
+    ("mars", "Marte"),
+    ("jupiter", "Júpiter"),
+    ("saturn", "Saturno"),
+    ("uranus", "Urano"),
+    ("neptune", "Neptuno"),
+    ("moon", "Luna"),
+];
+
+// Lo que se puede mostrar u ocultar con `toggle`, con la misma tecla que lo alterna
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Toggle {
+    Orbits,
+    Markers,
+    Ecliptic,
+    Gravity,
+    Table,
+    Contrast,
+    Pause,
+    Profile,
+    Sizes,
+    Warp,
+    Aberration,
+    Roche,
+}
+
+const TOGGLES: [(&str, Toggle); 12] = [
+    ("orbits", Toggle::Orbits),
+    ("markers", Toggle::Markers),
+    ("ecliptic", Toggle::Ecliptic),
+    ("gravity", Toggle::Gravity),
+    ("table", Toggle::Table),
+    ("contrast", Toggle::Contrast),
+    ("pause", Toggle::Pause),
+    ("profile", Toggle::Profile),
+    ("sizes", Toggle::Sizes),
+    ("warp", Toggle::Warp),
+    ("aberration", Toggle::Aberration),
+    ("roche", Toggle::Roche),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    // `focus <cuerpo>`: índice del cuerpo en `planets`
+    Focus(usize),
+    // `set timescale <x>`
+    SetTimeScale(f32),
+    // `toggle <orbits|markers|ecliptic|gravity|table|contrast|pause|profile|sizes|warp|aberration|roche>`
+    Toggle(Toggle),
+    // `reset camera`: vista inicial, sin enfoque
+    ResetCamera,
+    // `dump stats`: guarda las estadísticas del último cuadro como traza JSON
+    DumpStats,
+}
+
+// Palabras de la línea, en minúsculas y sin tildes
+pub fn tokenize(line: &str) -> Vec<String> {
+    line.split_whitespace().map(normalize).collect()
+}
+
+fn normalize(word: &str) -> String {
+    word.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' => 'a',
+            'é' => 'e',
+            'í' => 'i',
+            'ó' => 'o',
+            'ú' => 'u',
+            other => other,
+        })
+        .collect()
+}
+
+// Cuerpo con ese nombre (el suyo o el alias en inglés)
+pub fn find_body(planets: &[Planet], name: &str) -> Option<usize> {
+    let name = normalize(name);
+    let name = BODY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name.clone(), |(_, body)| normalize(body));
+    planets.iter().position(|planet| normalize(planet.name) == name)
+}
+
+pub fn parse(line: &str, planets: &[Planet]) -> Result<Command, String> {
+    let tokens = tokenize(line);
+    let words: Vec<&str> = tokens.iter().map(|token| token.as_str()).collect();
+    match words.as_slice() {
+        ["focus", name @ ..] if !name.is_empty() => {
+            let name = name.join(" ");
+            find_body(planets, &name)
+                .map(Command::Focus)
+                .ok_or_else(|| format!("no hay ningun cuerpo llamado {}", name))
+        }
+        ["set", "timescale", value] => match value.parse::<f32>() {
+            Ok(scale) if scale > 0.0 && scale.is_finite() => Ok(Command::SetTimeScale(scale)),
+            _ => Err(format!("escala de tiempo invalida: {}", value)),
+        },
+        ["toggle", what] => TOGGLES
+            .iter()
+            .find(|(name, _)| name == what)
+            .map(|(_, toggle)| Command::Toggle(*toggle))
+            .ok_or_else(|| format!("no se puede alternar {}", what)),
+        ["reset", "camera"] => Ok(Command::ResetCamera),
+        ["dump", "stats"] => Ok(Command::DumpStats),
+        [] => Err("escribe un comando".to_string()),
+        _ => Err(format!("comando desconocido: {}", line.trim())),
+    }
+}
+
+// Carácter que escribe una tecla en la paleta (la fuente del HUD solo tiene ASCII)
+fn key_char(key: Key) -> Option<char> {
+    const LETTERS: [Key; 26] = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+        Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9];
+    const NUMPAD: [Key; 10] = [
+        Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
+        Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    ];
+
+    if let Some(i) = LETTERS.iter().position(|&letter| letter == key) {
+        return Some((b'a' + i as u8) as char);
+    }
+    if let Some(i) = DIGITS.iter().position(|&digit| digit == key).or_else(|| NUMPAD.iter().position(|&digit| digit == key)) {
+        return Some((b'0' + i as u8) as char);
+    }
+    match key {
+        Key::Space => Some(' '),
+        Key::Period | Key::NumPadDot => Some('.'),
+        Key::Minus | Key::NumPadMinus => Some('-'),
+        _ => None,
+    }
+}
+
+// Paleta de comandos, abierta con Ctrl+P: una línea de texto al pie de la pantalla donde se
+// escriben comandos en vez de recordar las teclas. Mientras está visible se queda con todo el
+// teclado; Esc la cierra y las flechas arriba/abajo recorren los comandos anteriores.
+pub struct CommandPalette {
+    pub visible: bool,
+    pub input: String,
+    pub history: Vec<String>,
+    // Posición en `history` mientras se recorre con las flechas
+    history_cursor: Option<usize>,
+    // Error del último comando, mostrado sobre la línea
+    message: String,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        CommandPalette { visible: false, input: String::new(), history: Vec::new(), history_cursor: None, message: String::new() }
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+        self.input.clear();
+        self.history_cursor = None;
+        self.message.clear();
+    }
+
+    // Procesa las teclas del frame; devuelve el comando aceptado con Enter, y entonces se cierra
+    pub fn handle_input(&mut self, window: &Window, planets: &[Planet]) -> Option<Command> {
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            match key {
+                Key::Escape => {
+                    self.visible = false;
+                    return None;
+                }
+                Key::Enter | Key::NumPadEnter => return self.submit(planets),
+                Key::Backspace => {
+                    self.input.pop();
+                }
+                Key::Up => self.browse_history(-1),
+                Key::Down => self.browse_history(1),
+                _ => {
+                    if let Some(c) = key_char(key) {
+                        self.input.push(c);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // Interpreta la línea escrita; si no es válida la paleta sigue abierta con el error
+    pub fn submit(&mut self, planets: &[Planet]) -> Option<Command> {
+        match parse(&self.input, planets) {
+            Ok(command) => {
+                if self.history.last() != Some(&self.input) {
+                    self.history.push(self.input.clone());
+                }
+                self.visible = false;
+                Some(command)
+            }
+            Err(message) => {
+                self.message = message;
+                None
+            }
+        }
+    }
+
+    fn browse_history(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        let cursor = match (self.history_cursor, step < 0) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(cursor), true) => Some(cursor.saturating_sub(1)),
+            (Some(cursor), false) if cursor < last => Some(cursor + 1),
+            (Some(_), false) => None,
+        };
+        self.history_cursor = cursor;
+        self.input = cursor.map_or(String::new(), |cursor| self.history[cursor].clone());
+    }
+
+    pub fn render(&self, overlay: &mut Overlay, contrast: Contrast) {
+        let scale = contrast.text_scale(TEXT_SCALE);
+        let line_height = Framebuffer::measure_text("X", scale).1 + 2 * PADDING;
+        let lines = if self.message.is_empty() { 1 } else { 2 };
+        let top = overlay.height().saturating_sub(lines * line_height);
+        let width = overlay.width();
+        overlay.blend_rect(0, top, width, lines * line_height, 0x000000, contrast.opacity(0.8));
+        let framebuffer = overlay.region(0, top, width, lines * line_height);
+
+        if !self.message.is_empty() {
+            framebuffer.draw_text(PADDING, top + PADDING, &self.message.to_uppercase(), contrast.text_color(0xFF6347), scale);
+        }
+        let line = format!("{}{}_", PROMPT, self.input.to_uppercase());
+        framebuffer.draw_text(PADDING, top + (lines - 1) * line_height + PADDING, &line, contrast.text_color(0xFFFFFF), scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solar_system;
+
+    // Los comandos de la paleta, con nombres en inglés o en español, con o sin tildes y mayúsculas
+    #[test]
+    fn command_palette() -> Result<(), String> {
+        let planets = solar_system();
+        let valid = [
+            ("focus earth", Command::Focus(3)),
+            ("focus Júpiter", Command::Focus(5)),
+            ("FOCUS jupiter", Command::Focus(5)),
+            ("focus luna", Command::Focus(9)),
+            ("set timescale 8", Command::SetTimeScale(8.0)),
+            ("set timescale 0.5", Command::SetTimeScale(0.5)),
+            ("toggle orbits", Command::Toggle(Toggle::Orbits)),
+            ("  reset   camera ", Command::ResetCamera),
+            ("dump stats", Command::DumpStats),
+        ];
+        for (line, expected) in valid {
+            match parse(line, &planets) {
+                Ok(command) if command == expected => {}
+                other => return Err(format!("'{}' dio {:?}, se esperaba {:?}", line, other, expected)),
+            }
+        }
+        for line in ["", "focus pluto", "focus", "set timescale -1", "set timescale x", "toggle foo", "reset", "fly away"] {
+            if let Ok(command) = parse(line, &planets) {
+                return Err(format!("'{}' se aceptó como {:?}", line, command));
+            }
+        }
+
+        // Un comando inválido deja la paleta abierta; uno válido la cierra y queda en el historial
+        let mut palette = CommandPalette::new();
+        palette.open();
+        palette.input = "toggle nada".to_string();
+        if palette.submit(&planets).is_some() || !palette.visible {
+            return Err("un comando inválido cerró la paleta".to_string());
+        }
+        palette.input = "reset camera".to_string();
+        if palette.submit(&planets) != Some(Command::ResetCamera) || palette.visible || palette.history != ["reset camera"] {
+            return Err(format!("historial {:?} después de un comando válido", palette.history));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::Path;
+
+use crate::Framebuffer;
+use crate::hud::Contrast;
+use crate::reference_frame::{FrameDiff, ReferenceRenderer, ReferenceView, REFERENCE_HEIGHT, REFERENCE_SCENES, REFERENCE_WIDTH, load_png, pixel_delta, save_png};
+use crate::seed::SeedSource;
+
+// Directorio de salida si no se da `--compare-out`
+const DEFAULT_OUTPUT_DIR: &str = "compare_out";
+// Diferencia de un canal que ya se pinta con el color más intenso del mapa de calor
+const HEATMAP_FULL_SCALE: u32 = 64;
+// Separación entre las imágenes del compuesto
+const COMPOSITE_GAP: usize = 4;
+const COMPOSITE_GAP_COLOR: u32 = 0x202020;
+
+// Color del mapa de calor para una diferencia de 0 a 255: negro sin cambios, y de azul a rojo y a
+// amarillo según crece hasta HEATMAP_FULL_SCALE
+pub fn heat_color(delta: u32) -> u32 {
+    if delta == 0 {
+        return 0x000000;
+    }
+    let t = (delta as f32 / HEATMAP_FULL_SCALE as f32).min(1.0);
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+    let (r, g, b) = if t < 0.5 {
+        (channel(t * 2.0), 0, channel(1.0 - t * 2.0))
+    } else {
+        (255, channel((t - 0.5) * 2.0), 0)
+    };
+    r << 16 | g << 8 | b
+}
+
+// Mapa de calor de la diferencia por píxel entre dos imágenes del mismo tamaño
+pub fn heatmap(expected: &[u32], actual: &[u32]) -> Vec<u32> {
+    expected.iter().zip(actual).map(|(&a, &b)| heat_color(pixel_delta(a, b))).collect()
+}
+
+// Compuesto lado a lado: la referencia, el cuadro actual y el mapa de calor, separados por una
+// franja gris
+pub fn side_by_side(expected: &[u32], actual: &[u32], width: usize, height: usize) -> Framebuffer {
+    let heat = heatmap(expected, actual);
+    let mut composite = Framebuffer::new(width * 3 + COMPOSITE_GAP * 2, height);
+    composite.buffer.fill(COMPOSITE_GAP_COLOR);
+    for (column, image) in [expected, actual, heat.as_slice()].into_iter().enumerate() {
+        let left = column * (width + COMPOSITE_GAP);
+        for (row, pixels) in image.chunks(width).take(height).enumerate() {
+            let start = row * composite.width + left;
+            composite.buffer[start..start + pixels.len()].copy_from_slice(pixels);
+        }
+    }
+    composite
+}
+
+// Línea del resumen de una escena
+pub fn summary_line(name: &str, diff: &FrameDiff) -> String {
+    format!(
+        "{}: mayor diferencia {}  media {:.3}  píxeles cambiados {:.2}%",
+        name,
+        diff.max_delta,
+        diff.mean_delta(),
+        diff.changed_percent()
+    )
+}
+
+// `--compare <dir>`: dibuja cada escena de referencia con el código actual y la compara con
+// `<dir>/<escena>.png`, en la cámara e instante guardados a su lado si los hay. Escribe en
+// `--compare-out <dir>` (compare_out por omisión) `<escena>_ab.png` con el compuesto y
+// `<escena>_diff.png` con el mapa de calor, e imprime el resumen de cada una. Una escena sin
+// referencia se guarda en el directorio como nueva línea base.
+pub fn run_compare(seeds: &SeedSource, args: &[String], use_texture_cache: bool) -> Result<(), String> {
+    let position = args.iter().position(|arg| arg == "--compare").ok_or("falta --compare")?;
+    let baseline_dir = args.get(position + 1).ok_or("--compare espera el directorio de los cuadros de referencia")?;
+    let output_dir = match args.iter().position(|arg| arg == "--compare-out") {
+        Some(position) => args.get(position + 1).ok_or("--compare-out espera un directorio")?.as_str(),
+        None => DEFAULT_OUTPUT_DIR,
+    };
+    std::fs::create_dir_all(baseline_dir).map_err(|error| format!("{}: {}", baseline_dir, error))?;
+    std::fs::create_dir_all(output_dir).map_err(|error| format!("{}: {}", output_dir, error))?;
+
+    let mut renderer = ReferenceRenderer::new(seeds, use_texture_cache)?;
+    for (name, default_view) in REFERENCE_SCENES {
+        let baseline = Path::new(baseline_dir).join(format!("{}.png", name));
+        let baseline = baseline.to_string_lossy();
+        if !Path::new(baseline.as_ref()).exists() {
+            let frame = renderer.render(&default_view, REFERENCE_WIDTH, REFERENCE_HEIGHT);
+            save_png(&frame, &baseline)?;
+            default_view.save(&baseline)?;
+            println!("{}: sin referencia, se guardó {}", name, baseline);
+            continue;
+        }
+
+        let view = if ReferenceView::metadata_path(&baseline).exists() { ReferenceView::load(&baseline)? } else { default_view };
+        let frame = renderer.render(&view, REFERENCE_WIDTH, REFERENCE_HEIGHT);
+        let (width, height, expected) = load_png(&baseline)?;
+        if (width, height) != (frame.width, frame.height) {
+            return Err(format!("{} mide {}x{}; el cuadro actual, {}x{}", baseline, width, height, frame.width, frame.height));
+        }
+
+        let diff = FrameDiff::compare(&expected, &frame.buffer);
+        let mut heat = Framebuffer::new(width, height);
+        heat.buffer = heatmap(&expected, &frame.buffer);
+        let output = |suffix: &str| Path::new(output_dir).join(format!("{}_{}.png", name, suffix)).to_string_lossy().into_owned();
+        save_png(&side_by_side(&expected, &frame.buffer, width, height), &output("ab"))?;
+        save_png(&heat, &output("diff"))?;
+        println!("{}", summary_line(name, &diff));
+    }
+    Ok(())
+}
+
+// `--ab-reference <png>`: con F se alterna entre el render en vivo y ese cuadro de referencia.
+// Al mostrarlo la cámara y el tiempo pasan a los guardados junto al PNG y la simulación se
+// pausa, así que al volver se ve el render actual de la misma vista.
+pub struct AbReference {
+    name: String,
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+    pub view: ReferenceView,
+    pub showing: bool,
+}
+
+impl AbReference {
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let Some(position) = args.iter().position(|arg| arg == "--ab-reference") else {
+            return Ok(None);
+        };
+        let path = args.get(position + 1).ok_or("--ab-reference espera la ruta de un PNG de referencia")?;
+        let (width, height, pixels) = load_png(path)?;
+        let view = ReferenceView::load(path)?;
+        let name = Path::new(path).file_stem().map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        Ok(Some(AbReference { name, width, height, pixels, view, showing: false }))
+    }
+
+    pub fn toggle(&mut self) {
+        self.showing = !self.showing;
+    }
+
+    // Cubre el cuadro con la referencia, escalada al vecino más cercano, y un rótulo
+    pub fn render(&self, framebuffer: &mut Framebuffer, contrast: Contrast) {
+        if !self.showing {
+            return;
+        }
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        for (y, row) in framebuffer.buffer.chunks_mut(width).enumerate() {
+            let source_row = (y * self.height / height.max(1)).min(self.height - 1) * self.width;
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.pixels[source_row + (x * self.width / width.max(1)).min(self.width - 1)];
+            }
+        }
+        let label = format!("REFERENCIA: {}  (F: EN VIVO)", self.name.to_uppercase());
+        framebuffer.draw_text(10, 10, &label, contrast.text_color(0xFFD54F), contrast.text_scale(2));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference_frame::CHANNEL_TOLERANCE;
+
+    // Imágenes sintéticas de 4x2: un píxel igual, uno a 16 niveles en un canal y uno a 64 (el tope
+    // del mapa de calor), el resto iguales
+    #[test]
+    fn compare_images() -> Result<(), String> {
+        let expected = vec![0x102030; 8];
+        let mut actual = expected.clone();
+        actual[1] = 0x102040;
+        actual[6] = 0x502030;
+
+        let heat = heatmap(&expected, &actual);
+        if heat[0] != 0 || heat[1] != 0x800080 || heat[6] != 0xFFFF00 || heat_color(255) != 0xFFFF00 {
+            return Err(format!("mapa de calor {:06X?}", heat));
+        }
+
+        let composite = side_by_side(&expected, &actual, 4, 2);
+        if (composite.width, composite.height) != (20, 2) {
+            return Err(format!("compuesto de {}x{}", composite.width, composite.height));
+        }
+        // Segunda fila: la referencia en 0..4, el hueco, el cuadro actual en 8..12 y el calor en 16..20
+        let row = &composite.buffer[20..40];
+        if row[2] != expected[6] || row[4] != 0x202020 || row[10] != actual[6] || row[18] != heat[6] || composite.buffer[9] != actual[1] {
+            return Err(format!("compuesto {:06X?}", composite.buffer));
+        }
+
+        let diff = FrameDiff::compare(&expected, &actual);
+        if diff.max_delta != 64 || (diff.mean_delta() - 10.0).abs() > 1e-5 || (diff.changed_percent() - 25.0).abs() > 1e-4 {
+            return Err(format!("resumen {:?}", diff));
+        }
+        let line = summary_line("prueba", &diff);
+        if !line.contains("64") || !line.contains("25.00%") {
+            return Err(format!("línea de resumen \"{}\"", line));
+        }
+        Ok(())
+    }
+
+    // Un nivel en muchos píxeles entra en la tolerancia; un borde corrido en pocos también, pero
+    // no en muchos. Sobre un degradado sintético del tamaño de las referencias.
+    #[test]
+    fn frame_tolerance() -> Result<(), String> {
+        let frame: Vec<u32> = (0..REFERENCE_WIDTH * REFERENCE_HEIGHT)
+            .map(|index| ((index % REFERENCE_WIDTH % 256) << 16 | (index / REFERENCE_WIDTH % 256) << 8 | 0x40) as u32)
+            .collect();
+        let nudged: Vec<u32> = frame.iter().map(|&pixel| if pixel & 0xFF < 0xFF { pixel + 1 } else { pixel - 1 }).collect();
+        let diff = FrameDiff::compare(&frame, &nudged);
+        if diff.is_identical() || !diff.within_tolerance() || diff.max_delta != 1 {
+            return Err(format!("un nivel de diferencia en todo el cuadro dio {:?}", diff));
+        }
+        let edges = |every: usize| -> Vec<u32> {
+            frame.iter().enumerate().map(|(index, &pixel)| if index % every == 0 { pixel ^ 0x800000 } else { pixel }).collect()
+        };
+        let few = FrameDiff::compare(&frame, &edges(1000));
+        let many = FrameDiff::compare(&frame, &edges(50));
+        if !few.within_tolerance() || many.within_tolerance() || few.max_delta <= CHANNEL_TOLERANCE {
+            return Err(format!("con pocos píxeles muy distintos dio {:?} y con muchos {:?}", few, many));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use nalgebra_glm::{Vec3, rotate_vec3};
+
+// Color plano con que se rellena el corte: las caras traseras del cuerpo vistas por el hueco
+pub const INTERIOR_COLOR: u32 = 0x8B3A1A;
+
+// Giro y desplazamiento del plano por cada cuadro con la tecla apretada
+const ROTATION_STEP: f32 = 0.02;
+const SLIDE_STEP: f32 = 0.01;
+
+// Plano de corte en coordenadas de mundo (`normal · p = distance`). Se descartan los fragmentos
+// del lado al que apunta la normal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CutawayPlane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl CutawayPlane {
+    pub fn through(point: Vec3, normal: Vec3) -> Self {
+        let normal = normal.normalize();
+        CutawayPlane { normal, distance: normal.dot(&point) }
+    }
+
+    pub fn clips(&self, point: Vec3) -> bool {
+        self.normal.dot(&point) > self.distance
+    }
+}
+
+// Vista en corte del cuerpo enfocado. Por omisión el plano pasa por su centro mirando a la
+// cámara (y la sigue cuando gira); `yaw` y `pitch` lo giran respecto a esa orientación y
+// `offset` lo corre por su normal, en radios del cuerpo.
+pub struct Cutaway {
+    pub enabled: bool,
+    yaw: f32,
+    pitch: f32,
+    offset: f32,
+}
+
+impl Cutaway {
+    pub fn new() -> Self {
+        Cutaway { enabled: false, yaw: 0.0, pitch: 0.0, offset: 0.0 }
+    }
+
+    // Al volver a activarlo el plano arranca otra vez mirando a la cámara
+    pub fn toggle(&mut self) {
+        *self = Cutaway { enabled: !self.enabled, ..Cutaway::new() };
+    }
+
+    // `yaw` y `pitch` en pasos: -1, 0 o 1
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw * ROTATION_STEP;
+        self.pitch = (self.pitch + pitch * ROTATION_STEP).clamp(-1.5, 1.5);
+    }
+
+    pub fn slide(&mut self, steps: f32) {
+        self.offset = (self.offset + steps * SLIDE_STEP).clamp(-1.0, 1.0);
+    }
+
+    // Plano para un cuerpo en `center` de radio `radius` (en mundo) visto desde `eye`; `None`
+    // si el corte está apagado
+    pub fn plane(&self, center: Vec3, radius: f32, eye: Vec3) -> Option<CutawayPlane> {
+        if !self.enabled {
+            return None;
+        }
+        let to_camera = (eye - center).try_normalize(1e-6).unwrap_or(Vec3::new(0.0, 0.0, 1.0));
+        let vertical = Vec3::new(0.0, 1.0, 0.0);
+        let side = to_camera.cross(&vertical).try_normalize(1e-6).unwrap_or(Vec3::new(1.0, 0.0, 0.0));
+        let normal = rotate_vec3(&rotate_vec3(&to_camera, self.pitch, &side), self.yaw, &vertical);
+        Some(CutawayPlane::through(center + normal * self.offset * radius, normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Mat4;
+    use crate::{Obj, Framebuffer, SPHERE_MODEL, render};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+    use crate::uniforms::{Uniforms, create_model_matrix};
+
+    // Con el plano por el centro mirando a la cámara, el centro de la pantalla muestra el relleno
+    // plano del interior; corrido hasta el polo cercano no corta nada ahí, y detrás del cuerpo lo
+    // corta entero. Sin corte el cuerpo se sombrea como siempre.
+    #[test]
+    fn cutaway_plane() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let radius = 2.0;
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), radius / obj.bounding_radius(), Vec3::new(0.0, 0.0, 0.0));
+
+        let mut cutaway = Cutaway::new();
+        let center_pixel = |cutaway: &Cutaway, uniforms: &mut Uniforms| {
+            uniforms.cutaway = cutaway.plane(Vec3::new(0.0, 0.0, 0.0), radius, uniforms.view_pos);
+            let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+            framebuffer.clear();
+            render(&mut framebuffer, uniforms, &vertices, 1);
+            let index = (HEIGHT as usize / 2) * WIDTH as usize + WIDTH as usize / 2;
+            framebuffer.zbuffer[index].is_finite().then_some(framebuffer.buffer[index])
+        };
+
+        match center_pixel(&cutaway, &mut uniforms) {
+            Some(color) if color != INTERIOR_COLOR => {}
+            other => return Err(format!("sin corte el centro quedó en {:?}", other.map(|c| format!("{:06X}", c)))),
+        }
+        cutaway.toggle();
+        if center_pixel(&cutaway, &mut uniforms) != Some(INTERIOR_COLOR) {
+            return Err("con el plano por el centro no se ve el relleno del interior".to_string());
+        }
+        cutaway.slide(1000.0);
+        match center_pixel(&cutaway, &mut uniforms) {
+            Some(color) if color != INTERIOR_COLOR => {}
+            other => return Err(format!("con el plano en el polo cercano el centro quedó en {:?}", other.map(|c| format!("{:06X}", c)))),
+        }
+        cutaway.slide(-2000.0);
+        if center_pixel(&cutaway, &mut uniforms).is_some() {
+            return Err("con el plano detrás del cuerpo quedó algo dibujado".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4, perspective};
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::framebuffer::FillStyle;
+use crate::camera::{Camera, frustum_corners};
+use crate::sizes::SizeScale;
+use crate::uniforms::{Uniforms, create_view_matrix};
+use crate::{Planet, body_position, project_point, MIN_MESH_SIZE, HIGH_POLY_SIZE};
+
+// Recuadro de la vista de depuración (esquina inferior derecha)
+const OVERLAY_WIDTH: usize = 240;
+const OVERLAY_HEIGHT: usize = 180;
+const OVERLAY_MARGIN: usize = 10;
+
+const FRUSTUM_COLOR: u32 = 0xFFFF00;
+// Los cuerpos son discos que se oscurecen hacia el borde y el ojo un anillo
+const PLANET_COLOR: u32 = 0x87CEEB;
+const PLANET_EDGE_COLOR: u32 = 0x2F5F7A;
+const PLANET_DOT_RADIUS: usize = 2;
+const EYE_COLOR: u32 = 0xFF4040;
+const EYE_INNER_RADIUS: usize = 2;
+const EYE_OUTER_RADIUS: usize = 3;
+// El Sol se marca con un hexágono relleno y con borde, en vez de un punto
+const SUN_COLOR: u32 = 0xFFD700;
+const SUN_OUTLINE_COLOR: u32 = 0xFF8C00;
+const SUN_ICON_RADIUS: f32 = 5.0;
+// Resplandor que se suma alrededor del hexágono, apagándose hacia el borde
+const SUN_GLOW_COLOR: u32 = 0x604000;
+const SUN_GLOW_RADIUS: usize = 9;
+
+// Esferas envolventes según el nivel de detalle con que se dibuja el cuerpo: impostor de un
+// píxel, esfera normal o esfera de alta resolución
+const IMPOSTOR_SPHERE_COLOR: u32 = 0xFF4040;
+const LOW_POLY_SPHERE_COLOR: u32 = 0x40FF40;
+const HIGH_POLY_SPHERE_COLOR: u32 = 0x40C0FF;
+
+// Aristas del frustum: plano near, plano far y las cuatro que los unen
+const FRUSTUM_EDGES: [(usize, usize); 12] = [
+    (0, 1), (1, 2), (2, 3), (3, 0),
+    (4, 5), (5, 6), (6, 7), (7, 4),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+// Dibuja en una esquina una vista cenital (desde (0, 500, 0)) con el frustum de la cámara principal,
+// los cuerpos y la posición del ojo, para depurar el descarte de objetos
+pub fn render_debug_frustum(
+    framebuffer: &mut Framebuffer,
+    camera: &Camera,
+    planets: &[Planet],
+    projection: &Mat4,
+    time: f64,
+) {
+    let x0 = framebuffer.width.saturating_sub(OVERLAY_WIDTH + OVERLAY_MARGIN);
+    let y0 = framebuffer.height.saturating_sub(OVERLAY_HEIGHT + OVERLAY_MARGIN);
+    let rect = (x0 as f32, y0 as f32, OVERLAY_WIDTH as f32, OVERLAY_HEIGHT as f32);
+
+    framebuffer.blend_rect(x0, y0, OVERLAY_WIDTH, OVERLAY_HEIGHT, 0x000000, 0.7);
+
+    // Cámara temporal mirando hacia abajo; "arriba" en pantalla es -Z del mundo
+    let overhead = Camera::new(
+        Vec3::new(0.0, 500.0, 0.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -1.0),
+    );
+    let overhead_view = create_view_matrix(overhead.eye, overhead.center, overhead.up);
+    let overhead_projection = perspective(OVERLAY_WIDTH as f32 / OVERLAY_HEIGHT as f32, PI / 4.0, 1.0, 2000.0);
+    let overhead_matrix = overhead_projection * overhead_view;
+
+    let to_overlay = |point: Vec3| -> Option<Vec2> {
+        let clip = overhead_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let (ndc_x, ndc_y) = (clip.x / clip.w, clip.y / clip.w);
+        Some(Vec2::new(
+            rect.0 + (ndc_x + 1.0) * 0.5 * rect.2,
+            rect.1 + (1.0 - ndc_y) * 0.5 * rect.3,
+        ))
+    };
+
+    let primary_view = create_view_matrix(camera.eye, camera.center, camera.up);
+    let corners = frustum_corners(projection, &primary_view);
+    for (a, b) in FRUSTUM_EDGES.iter() {
+        if let (Some(start), Some(end)) = (to_overlay(corners[*a]), to_overlay(corners[*b])) {
+            draw_segment_in_rect(framebuffer, start, end, rect, FRUSTUM_COLOR);
+        }
+    }
+
+    for index in 0..planets.len() {
+        if let Some(point) = to_overlay(body_position(planets, index, time)) {
+            if planets[index].name == "Sol" {
+                draw_sun_icon(framebuffer, point, rect);
+            } else {
+                if fits_in_rect(point, PLANET_DOT_RADIUS, rect) {
+                    let (x, y) = (point.x as isize, point.y as isize);
+                    framebuffer.fill_circle_gradient(x, y, PLANET_DOT_RADIUS, PLANET_COLOR, PLANET_EDGE_COLOR, FillStyle::OPAQUE);
+                }
+            }
+        }
+    }
+
+    if let Some(point) = to_overlay(camera.eye) {
+        if fits_in_rect(point, EYE_OUTER_RADIUS, rect) {
+            let (x, y) = (point.x as isize, point.y as isize);
+            framebuffer.draw_ring(x, y, EYE_INNER_RADIUS, EYE_OUTER_RADIUS, EYE_COLOR, FillStyle::OPAQUE);
+        }
+    }
+}
+
+fn inside(point: Vec2, rect: (f32, f32, f32, f32)) -> bool {
+    point.x >= rect.0 && point.x < rect.0 + rect.2 && point.y >= rect.1 && point.y < rect.1 + rect.3
+}
+
+fn plot(framebuffer: &mut Framebuffer, point: Vec2, color: u32) {
+    let (x, y) = (point.x as usize, point.y as usize);
+    if x < framebuffer.width && y < framebuffer.height {
+        framebuffer.buffer[y * framebuffer.width + x] = color;
+    }
+}
+
+// Segmento con DDA, dibujando solo los píxeles que caen dentro del recuadro
+fn draw_segment_in_rect(framebuffer: &mut Framebuffer, start: Vec2, end: Vec2, rect: (f32, f32, f32, f32), color: u32) {
+    let delta = end - start;
+    let steps = delta.x.abs().max(delta.y.abs()).ceil().min(4096.0) as usize;
+    for i in 0..=steps {
+        let t = if steps == 0 { 0.0 } else { i as f32 / steps as f32 };
+        let point = start + delta * t;
+        if inside(point, rect) {
+            plot(framebuffer, point, color);
+        }
+    }
+}
+
+// Si el disco de `radius` píxeles centrado en `center` entra entero en el recuadro
+fn fits_in_rect(center: Vec2, radius: usize, rect: (f32, f32, f32, f32)) -> bool {
+    let reach = radius as f32;
+    inside(center - Vec2::new(reach, reach), rect) && inside(center + Vec2::new(reach, reach), rect)
+}
+
+// Hexágono del Sol centrado en `center` sobre su resplandor, solo si entra entero en el recuadro
+fn draw_sun_icon(framebuffer: &mut Framebuffer, center: Vec2, rect: (f32, f32, f32, f32)) {
+    let hexagon: Vec<(f32, f32)> = (0..6)
+        .map(|i| {
+            let angle = i as f32 * PI / 3.0;
+            (center.x + SUN_ICON_RADIUS * angle.cos(), center.y + SUN_ICON_RADIUS * angle.sin())
+        })
+        .collect();
+    if !hexagon.iter().all(|&(x, y)| inside(Vec2::new(x, y), rect)) {
+        return;
+    }
+    if fits_in_rect(center, SUN_GLOW_RADIUS, rect) {
+        let (x, y) = (center.x as isize, center.y as isize);
+        framebuffer.fill_circle_gradient(x, y, SUN_GLOW_RADIUS, SUN_GLOW_COLOR, 0x000000, FillStyle::OPAQUE.additive());
+    }
+    framebuffer.fill_polygon(&hexagon, SUN_COLOR);
+    framebuffer.draw_polygon(&hexagon, SUN_OUTLINE_COLOR);
+}
+
+// Esfera envolvente de cada cuerpo en pantalla (Ctrl+B), del color de su nivel de detalle.
+// `bodies` son los cuerpos que se dibujaron en el cuadro, con su posición.
+pub fn render_bounding_spheres(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    planets: &[Planet],
+    bodies: &[(usize, Vec3)],
+    sphere_radius: f32,
+    sizes: &SizeScale,
+) {
+    for &(index, center) in bodies {
+        let Some(screen) = project_point(uniforms, center).filter(|point| point.z > 0.0) else {
+            continue;
+        };
+        // Semiejes en píxeles por separado: la proyección y el viewport escalan X e Y distinto
+        let world_radius = sizes.radius(&planets[index]) * sphere_radius;
+        let rx = world_radius * uniforms.projection_matrix[(0, 0)].abs() / screen.z * uniforms.viewport_matrix[(0, 0)].abs();
+        let ry = world_radius * uniforms.projection_matrix[(1, 1)].abs() / screen.z * uniforms.viewport_matrix[(1, 1)].abs();
+        let color = match 2.0 * ry {
+            size if size < MIN_MESH_SIZE => IMPOSTOR_SPHERE_COLOR,
+            size if size > HIGH_POLY_SIZE => HIGH_POLY_SPHERE_COLOR,
+            _ => LOW_POLY_SPHERE_COLOR,
+        };
+        // Los impostores miden menos de un píxel: se les da un radio visible
+        framebuffer.draw_sphere_wireframe(screen.x, screen.y, rx.max(3.0), ry.max(3.0), color);
+    }
+}
@@ -0,0 +1,134 @@
+use rayon::prelude::*;
+
+use crate::Framebuffer;
+
+// Radios (en píxeles) de las copias desenfocadas; el nivel 0 es el frame nítido
+const BLUR_RADII: [usize; 2] = [2, 6];
+
+// Fracción de diferencia de profundidad a partir de la cual el desenfoque es máximo
+const FULL_BLUR_RANGE: f32 = 0.6;
+
+// Paso multiplicativo del foco manual por frame con la tecla pulsada
+const MANUAL_FOCUS_STEP: f32 = 1.03;
+const MIN_FOCUS_DISTANCE: f32 = 0.5;
+
+// Profundidad de campo para capturas: cada píxel mezcla el frame nítido con copias cada vez
+// más desenfocadas según su círculo de confusión, calculado a partir del z-buffer. El fondo
+// (profundidad infinita) recibe el desenfoque máximo.
+pub struct DepthOfField {
+    pub enabled: bool,
+    // Distancia de foco fijada a mano; con `None` se usa la automática
+    pub manual_focus: Option<f32>,
+    blurred: Vec<Vec<u32>>,
+    scratch: Vec<u32>,
+}
+
+impl DepthOfField {
+    pub fn new() -> Self {
+        DepthOfField {
+            enabled: false,
+            manual_focus: None,
+            blurred: vec![Vec::new(); BLUR_RADII.len()],
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    // Acerca (`direction` < 0) o aleja (> 0) el foco manual, partiendo del automático
+    pub fn adjust_focus(&mut self, automatic: f32, direction: f32) {
+        let focus = self.manual_focus.unwrap_or(automatic);
+        let focus = focus * MANUAL_FOCUS_STEP.powf(direction.signum());
+        self.manual_focus = Some(focus.max(MIN_FOCUS_DISTANCE));
+    }
+
+    pub fn focus_distance(&self, automatic: f32) -> f32 {
+        self.manual_focus.unwrap_or(automatic)
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, focus_distance: f32) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        for (level, radius) in BLUR_RADII.iter().enumerate() {
+            box_blur(&framebuffer.buffer, &mut self.blurred[level], &mut self.scratch, width, height, *radius);
+        }
+
+        let focus = focus_distance.max(MIN_FOCUS_DISTANCE);
+        let blurred = &self.blurred;
+        framebuffer
+            .buffer
+            .par_iter_mut()
+            .zip(framebuffer.zbuffer.par_iter())
+            .enumerate()
+            .for_each(|(index, (pixel, depth))| {
+                // 0 en el plano de foco, BLUR_RADII.len() con el desenfoque máximo
+                let coc = circle_of_confusion(*depth, focus) * BLUR_RADII.len() as f32;
+                let level = (coc.floor() as usize).min(BLUR_RADII.len() - 1);
+                let t = coc - level as f32;
+
+                let sharper = if level == 0 { *pixel } else { blurred[level - 1][index] };
+                *pixel = lerp_pixel(sharper, blurred[level][index], t);
+            });
+    }
+}
+
+// Círculo de confusión normalizado (0.0 a 1.0) de un píxel a `depth` con el foco en `focus`
+fn circle_of_confusion(depth: f32, focus: f32) -> f32 {
+    if !depth.is_finite() {
+        return 1.0;
+    }
+    let difference = (depth - focus).abs() / depth.max(focus);
+    (difference / FULL_BLUR_RANGE).min(1.0)
+}
+
+fn lerp_pixel(a: u32, b: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        ((ca + (cb - ca) * t).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+// Desenfoque de caja separable (horizontal y luego vertical) con suma acumulada: el coste no
+// depende del radio. `scratch` guarda el pase intermedio y se reutiliza entre frames.
+pub fn box_blur(source: &[u32], target: &mut Vec<u32>, scratch: &mut Vec<u32>, width: usize, height: usize, radius: usize) {
+    scratch.resize(width * height, 0);
+    target.resize(width * height, 0);
+
+    scratch
+        .par_chunks_mut(width)
+        .zip(source.par_chunks(width))
+        .for_each(|(out, row)| blur_line(|i| row[i], |i, value| out[i] = value, width, radius));
+
+    for x in 0..width {
+        blur_line(|y| scratch[y * width + x], |y, value| target[y * width + x] = value, height, radius);
+    }
+}
+
+// Promedio de la ventana [i - radius, i + radius] de una línea, con los bordes recortados
+fn blur_line(read: impl Fn(usize) -> u32, mut write: impl FnMut(usize, u32), length: usize, radius: usize) {
+    let mut sums = [0u32; 3];
+    let add = |sums: &mut [u32; 3], pixel: u32, sign: i64| {
+        for (channel, sum) in sums.iter_mut().enumerate() {
+            let value = (pixel >> (16 - 8 * channel)) & 0xFF;
+            *sum = (*sum as i64 + sign * value as i64) as u32;
+        }
+    };
+
+    for i in 0..radius.min(length) {
+        add(&mut sums, read(i), 1);
+    }
+    for i in 0..length {
+        if i + radius < length {
+            add(&mut sums, read(i + radius), 1);
+        }
+        if i > radius {
+            add(&mut sums, read(i - radius - 1), -1);
+        }
+        let count = (i + radius).min(length - 1) - i.saturating_sub(radius) + 1;
+        let average = |sum: u32| (sum / count as u32).min(255);
+        write(i, average(sums[0]) << 16 | average(sums[1]) << 8 | average(sums[2]));
+    }
+}
@@ -0,0 +1,120 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::overlay::Overlay;
+use crate::uniforms::Uniforms;
+use crate::orbit_markers::{draw_depth_tested_segment, draw_label, scale_color};
+use crate::units::{Unit, DISTANCE_SCALE, format_quantity};
+use crate::{Planet, project_point};
+
+// Separación entre círculos y entre radios de la rejilla
+const RING_SPACING: f32 = 20.0;
+const SPOKES: usize = 12;
+
+// Segmentos de cada círculo; los puntos del círculo unidad se calculan una sola vez
+const CIRCLE_SEGMENTS: usize = 96;
+
+const GRID_COLOR: u32 = 0x4A6A90;
+const LABEL_COLOR: u32 = 0x8FB0D8;
+
+// Las líneas se apagan con la distancia a la cámara hasta MIN_BRIGHTNESS, para que lo lejano
+// no se convierta en un muaré. Hasta FADE_START veces la altura de la cámara sobre el plano
+// conservan todo el brillo, así la zona bajo la cámara se ve igual de cerca o de lejos.
+const FADE_START: f32 = 1.5;
+const MIN_BRIGHTNESS: f32 = 0.15;
+
+// Rejilla de referencia sobre el plano de la eclíptica (y = 0): círculos concéntricos cada
+// RING_SPACING unidades con su distancia en círculos alternos, y radios cada 30°. Llega
+// hasta el cuerpo más lejano y se dibuja junto con las marcas de las órbitas, después de los
+// cuerpos, para que los planetas la tapen. Se activa con la tecla K.
+pub struct EclipticGrid {
+    pub enabled: bool,
+    unit_circle: Vec<Vec3>,
+    // Puntos proyectados del círculo actual, reutilizados entre círculos y frames
+    projected: Vec<Option<Vec3>>,
+    label: String,
+}
+
+impl EclipticGrid {
+    pub fn new() -> Self {
+        let unit_circle = (0..=CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+                Vec3::new(angle.cos(), 0.0, angle.sin())
+            })
+            .collect();
+
+        EclipticGrid {
+            enabled: false,
+            unit_circle,
+            projected: Vec::with_capacity(CIRCLE_SEGMENTS + 1),
+            label: String::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn render(&mut self, framebuffer: &mut Framebuffer, overlay: &mut Overlay, uniforms: &Uniforms, planets: &[Planet]) {
+        // Hasta el primer círculo más allá del cuerpo más lejano que orbita al Sol
+        let outermost = planets
+            .iter()
+            .filter(|planet| planet.parent.is_none())
+            .map(|planet| planet.distance_from_sun)
+            .fold(0.0, f32::max);
+        let rings = (outermost / RING_SPACING).floor() as usize + 1;
+        let extent = rings as f32 * RING_SPACING;
+
+        for ring in 1..=rings {
+            let radius = ring as f32 * RING_SPACING;
+            self.projected.clear();
+            for point in &self.unit_circle {
+                self.projected.push(project_visible(uniforms, point * radius));
+            }
+            for (i, pair) in self.projected.windows(2).enumerate() {
+                if let (Some(start), Some(end)) = (pair[0], pair[1]) {
+                    let midpoint = self.unit_circle[i].lerp(&self.unit_circle[i + 1], 0.5) * radius;
+                    draw_depth_tested_segment(framebuffer, start, end, faded(uniforms, midpoint));
+                }
+            }
+
+            if ring % 2 == 0 {
+                format_quantity(&mut self.label, radius as f64, Unit::Distance(DISTANCE_SCALE), 3);
+                let anchor = Vec3::new(1.0, 0.0, -1.0).normalize() * radius;
+                draw_label(framebuffer, overlay, uniforms, anchor, &self.label, LABEL_COLOR);
+            }
+        }
+
+        // Radios partidos en un tramo por círculo, para que también se apaguen con la distancia
+        for spoke in 0..SPOKES {
+            let angle = 2.0 * PI * spoke as f32 / SPOKES as f32;
+            let direction = Vec3::new(angle.cos(), 0.0, angle.sin());
+            let mut previous = project_visible(uniforms, direction * RING_SPACING);
+            let mut distance = RING_SPACING;
+            while distance < extent {
+                let next_distance = distance + RING_SPACING;
+                let next = project_visible(uniforms, direction * next_distance);
+                if let (Some(start), Some(end)) = (previous, next) {
+                    let midpoint = direction * (distance + 0.5 * RING_SPACING);
+                    draw_depth_tested_segment(framebuffer, start, end, faded(uniforms, midpoint));
+                }
+                previous = next;
+                distance = next_distance;
+            }
+        }
+    }
+}
+
+fn project_visible(uniforms: &Uniforms, point: Vec3) -> Option<Vec3> {
+    project_point(uniforms, point).filter(|p| p.z > 0.0 && p.z.is_finite())
+}
+
+// Color de la rejilla atenuado según la distancia de `point` a la cámara
+fn faded(uniforms: &Uniforms, point: Vec3) -> u32 {
+    let distance = (point - uniforms.view_pos).magnitude();
+    let full_brightness = FADE_START * uniforms.view_pos.y.abs().max(RING_SPACING);
+    let brightness = (full_brightness / distance.max(1e-3)).clamp(MIN_BRIGHTNESS, 1.0);
+    scale_color(GRID_COLOR, brightness)
+}
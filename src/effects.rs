@@ -0,0 +1,188 @@
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+
+use crate::Framebuffer;
+use crate::color::{Color, linear_to_srgb, srgb_to_linear};
+use crate::framebuffer::{BAND_HEIGHT, bresenham};
+
+// Fracción de lo que le falta a cada canal para llegar al blanco que pueden sumar los efectos
+const DEFAULT_HEADROOM: f32 = 0.75;
+// Hasta esta fracción del margen los efectos se suman tal cual; de ahí en más se comprimen
+const KNEE: f32 = 0.5;
+
+// Presupuesto de los efectos aditivos (nebulosa, fulguraciones y meteoros). En vez de sumarse
+// sobre el cuadro cada uno por su lado, todos acumulan su luz, en espacio lineal, en este buffer,
+// y al final `combine` la agrega a la escena con una rodilla suave: lo poco pasa igual y lo que se
+// apila se comprime hacia un margen por encima de la imagen base (`--effects-headroom`). Así cada
+// efecto se sigue viendo, pero juntos no queman la imagen en blanco, y cerca del Sol se mantiene
+// el detalle de su superficie.
+pub struct EffectsBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub light: Vec<Vec3>,
+    pub headroom: f32,
+    // Vista de depuración (Ctrl+E): solo los efectos, sobre negro
+    pub show_alone: bool,
+}
+
+impl EffectsBuffer {
+    pub fn new(width: usize, height: usize, headroom: f32) -> Self {
+        EffectsBuffer { width, height, light: vec![Vec3::zeros(); width * height], headroom, show_alone: false }
+    }
+
+    pub fn from_args(args: &[String], width: usize, height: usize) -> Result<Self, String> {
+        let headroom = match args.iter().position(|arg| arg == "--effects-headroom") {
+            None => DEFAULT_HEADROOM,
+            Some(position) => args
+                .get(position + 1)
+                .and_then(|value| value.parse::<f32>().ok())
+                .filter(|headroom| *headroom > 0.0 && *headroom <= 1.0)
+                .ok_or("--effects-headroom espera la fraccion (mayor que 0, hasta 1) del margen hasta el blanco, como 0.75")?,
+        };
+        Ok(EffectsBuffer::new(width, height, headroom))
+    }
+
+    pub fn clear(&mut self) {
+        self.light.fill(Vec3::zeros());
+    }
+
+    pub fn toggle_view(&mut self) {
+        self.show_alone = !self.show_alone;
+    }
+
+    // Suma `color` (sRGB) con la intensidad dada al píxel `index`
+    pub fn add(&mut self, index: usize, color: Color, intensity: f32) {
+        self.light[index] += linear(color) * intensity;
+    }
+
+    // Segmento en píxeles, recortado a la pantalla, que solo suma donde el z-buffer de
+    // `framebuffer` no está por delante de `depth`. Como `Framebuffer::blend_line`, no suma el
+    // último píxel.
+    pub fn add_line(&mut self, framebuffer: &Framebuffer, start: (f32, f32), end: (f32, f32), color: Color, intensity: f32, depth: f32) {
+        let Some((start, end)) = framebuffer.clip_line(start, end) else {
+            return;
+        };
+        let light = linear(color) * intensity;
+        bresenham(start.0 as usize, start.1 as usize, end.0 as usize, end.1 as usize, |x, y| {
+            let index = y as usize * self.width + x as usize;
+            if framebuffer.zbuffer[index] > depth {
+                self.light[index] += light;
+            }
+        });
+    }
+
+    // Descarta lo acumulado donde ya se dibujó algo con profundidad: la nebulosa se suma antes que
+    // los cuerpos y queda detrás de ellos
+    pub fn hide_behind(&mut self, zbuffer: &[f32]) {
+        for (light, depth) in self.light.iter_mut().zip(zbuffer) {
+            if depth.is_finite() {
+                *light = Vec3::zeros();
+            }
+        }
+    }
+
+    // Agrega los efectos a la escena; en la vista de depuración los dibuja solos sobre negro
+    pub fn combine(&self, framebuffer: &mut Framebuffer) {
+        let width = framebuffer.width;
+        let (headroom, show_alone) = (self.headroom, self.show_alone);
+        framebuffer
+            .split_rows_mut(BAND_HEIGHT)
+            .into_par_iter()
+            .zip(self.light.par_chunks(BAND_HEIGHT * width.max(1)))
+            .for_each(|(band, light)| {
+                for (pixel, effect) in band.buffer.iter_mut().zip(light) {
+                    if !show_alone && *effect == Vec3::zeros() {
+                        continue;
+                    }
+                    let base = if show_alone { Color::black() } else { Color::from_hex(*pixel) };
+                    let channel = |base: u8, effect: f32| {
+                        let combined = compress(srgb_to_linear(base), effect, headroom);
+                        (linear_to_srgb(combined) * 255.0).round() as u32
+                    };
+                    *pixel = channel(base.r, effect.x) << 16 | channel(base.g, effect.y) << 8 | channel(base.b, effect.z);
+                }
+            });
+    }
+}
+
+// Color sRGB pasado a luz lineal
+pub fn linear(color: Color) -> Vec3 {
+    Vec3::new(srgb_to_linear(color.r), srgb_to_linear(color.g), srgb_to_linear(color.b))
+}
+
+// Un canal de la escena (`base`, lineal de 0 a 1) con la luz de los efectos agregada. El margen es
+// `headroom` de lo que le falta para el blanco; hasta KNEE de ese margen se suma tal cual y más
+// arriba se acerca a él sin alcanzarlo, con la misma pendiente en la rodilla.
+pub fn compress(base: f32, effect: f32, headroom: f32) -> f32 {
+    let room = headroom * (1.0 - base.clamp(0.0, 1.0));
+    let knee = KNEE * room;
+    if effect <= knee {
+        return base + effect.max(0.0);
+    }
+    let rest = room - knee;
+    base + knee + rest * (1.0 - (-(effect - knee) / rest).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Mat4;
+    use crate::{Obj, SPHERE_MODEL, render_body, solar_system};
+    use crate::flares::{Flare, SolarFlares};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+    use crate::sizes::SizeScale;
+    use crate::uniforms::{create_model_matrix, create_view_matrix};
+
+    #[test]
+    fn effects_budget() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        if (compress(0.2, 0.01, 0.75) - 0.21).abs() > 1e-6 || compress(0.5, 0.0, 0.75) != 0.5 {
+            return Err("un efecto tenue no se suma tal cual".to_string());
+        }
+        for base in [0.0, 0.3, 0.9, 1.0] {
+            let limit = base + 0.75 * (1.0 - base);
+            let (strong, stronger) = (compress(base, 10.0, 0.75), compress(base, 100.0, 0.75));
+            if strong > stronger || stronger > limit + 1e-6 {
+                return Err(format!("sobre {} el efecto da {} y {} (margen hasta {})", base, strong, stronger, limit));
+            }
+        }
+
+        // El peor caso: la cámara pegada al Sol, con fulguraciones en todo su contorno y nebulosa
+        // espesa encima de todo
+        let sun = &solar_system()[0];
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let world_radius = SizeScale::new().radius(sun) * obj.bounding_radius();
+        let eye = Vec3::new(0.0, world_radius, 3.0 * world_radius);
+        let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.view_matrix = create_view_matrix(eye, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        uniforms.view_pos = eye;
+        uniforms.model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), world_radius / obj.bounding_radius(), Vec3::new(0.0, 0.0, 0.0));
+        render_body(&mut framebuffer, &uniforms, &vertices, sun.color_index, Vec3::new(0.0, 0.0, 0.0), world_radius);
+
+        let mut effects = EffectsBuffer::new(framebuffer.width, framebuffer.height, 0.75);
+        let mut flares = SolarFlares::new(seeds.derive("flares", 0));
+        flares.flares = (0..32).map(|i| Flare { start_angle: i as f32 * std::f32::consts::TAU / 32.0, duration: 100, start_time: 0.0 }).collect();
+        flares.render(&mut effects, &framebuffer, &uniforms, Vec3::new(0.0, 0.0, 0.0), world_radius, 20.0);
+        for light in effects.light.iter_mut() {
+            *light += Vec3::repeat(1.5);
+        }
+
+        let surface: Vec<usize> = (0..framebuffer.buffer.len()).filter(|&index| framebuffer.zbuffer[index].is_finite()).collect();
+        if surface.len() < framebuffer.buffer.len() / 10 {
+            return Err(format!("el Sol cubre solo {} píxeles", surface.len()));
+        }
+        let distinct = |buffer: &[u32]| surface.iter().map(|&index| buffer[index]).collect::<std::collections::HashSet<u32>>().len();
+        let before = distinct(&framebuffer.buffer);
+        effects.combine(&mut framebuffer);
+        let white = surface.iter().filter(|&&index| framebuffer.buffer[index] == 0xFFFFFF).count();
+        let after = distinct(&framebuffer.buffer);
+        if white > 0 || after * 2 < before {
+            return Err(format!("con los efectos {} píxeles del Sol quedaron blancos y sus tonos pasaron de {} a {}", white, before, after));
+        }
+        Ok(())
+    }
+}
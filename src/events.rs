@@ -0,0 +1,354 @@
+use nalgebra_glm::{Vec3, quat_rotate_vec3};
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::PI;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::overlay::Overlay;
+use crate::hud::{Panel, Contrast};
+use crate::rings::RingSystem;
+use crate::spice::{ephemeris_time, naif_id};
+use crate::vertex::Vertex;
+use crate::{Planet, body_position};
+
+// Eventos que se guardan en memoria (los más viejos se descartan) y líneas del ticker
+const EVENT_LOG_CAPACITY: usize = 64;
+const TICKER_LINES: usize = 3;
+// Un evento se ve entero TICKER_HOLD y después se apaga durante TICKER_FADE
+const TICKER_HOLD: Duration = Duration::from_secs(6);
+const TICKER_FADE: Duration = Duration::from_secs(4);
+const TICKER_WIDTH: usize = 460;
+
+// Histéresis de cada detector: un estado nuevo solo cuenta fuera de estas franjas, así un cuerpo
+// que se queda en el límite no repite el evento
+const RING_PLANE_MARGIN: f32 = 0.5;
+const PHASE_BAND_DEGREES: f32 = 10.0;
+const CONJUNCTION_DEGREES: f32 = 3.0;
+const CONJUNCTION_RELEASE_DEGREES: f32 = 5.0;
+
+// Cuerpo desde el que se miran las fases y las conjunciones
+const OBSERVER: &str = "Tierra";
+
+// Fases de la Luna cada 90° de elongación, desde la Luna nueva
+const MOON_PHASES: [&str; 4] = ["Luna nueva", "Cuarto creciente", "Luna llena", "Cuarto menguante"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    RingPlaneCrossing,
+    MoonPhase,
+    Conjunction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimEvent {
+    pub kind: EventKind,
+    // Tiempo de la simulación en que ocurrió
+    pub time: f64,
+    pub text: String,
+    // Cuándo se registró, para apagarlo en el ticker
+    pub logged_at: Instant,
+}
+
+impl SimEvent {
+    // Días desde J2000 con la misma escala que las efemérides
+    pub fn day(&self) -> f64 {
+        ephemeris_time(self.time) / 86_400.0
+    }
+}
+
+// Lo que los detectores ven de la escena en un cuadro
+pub struct SceneState<'a> {
+    pub planets: &'a [Planet],
+    pub time: f64,
+    pub camera_eye: Vec3,
+    pub ring_systems: &'a [(&'static str, RingSystem, Vec<Vertex>)],
+}
+
+// Lo que un detector observa de un sujeto (`key`) en este cuadro: su estado discreto, o `None`
+// si está en la franja de histéresis y conserva el anterior. `text` es el evento que se registra
+// si el estado cambió a este (sin texto, el cambio no se anuncia).
+pub struct Reading {
+    pub key: String,
+    pub state: Option<&'static str>,
+    pub text: Option<String>,
+}
+
+type Detector = fn(&SceneState) -> Vec<Reading>;
+
+// Detectores registrados: para un tipo de evento nuevo basta con escribir su función y sumarla aquí
+const DETECTORS: [(EventKind, Detector); 3] = [
+    (EventKind::RingPlaneCrossing, ring_plane_crossings),
+    (EventKind::MoonPhase, moon_phases),
+    (EventKind::Conjunction, conjunctions),
+];
+
+// La cámara y la Tierra cruzando el plano de cada sistema de anillos
+fn ring_plane_crossings(scene: &SceneState) -> Vec<Reading> {
+    let observer = scene.planets.iter().position(|planet| planet.name == OBSERVER);
+    let mut subjects = vec![("La camara", scene.camera_eye)];
+    if let Some(index) = observer {
+        subjects.push((OBSERVER, body_position(scene.planets, index, scene.time)));
+    }
+
+    let mut readings = Vec::new();
+    for (name, rings, _) in scene.ring_systems {
+        let Some(index) = scene.planets.iter().position(|planet| planet.name == *name) else {
+            continue;
+        };
+        let center = body_position(scene.planets, index, scene.time);
+        let normal = quat_rotate_vec3(&rings.tilt_quat, &Vec3::new(0.0, 1.0, 0.0));
+        for (subject, position) in &subjects {
+            let height = (position - center).dot(&normal);
+            let state = if height > RING_PLANE_MARGIN {
+                Some("norte")
+            } else if height < -RING_PLANE_MARGIN {
+                Some("sur")
+            } else {
+                None
+            };
+            readings.push(Reading {
+                key: format!("anillos:{}:{}", name, subject),
+                state,
+                text: state.map(|side| format!("{} cruza el plano de los anillos de {} hacia el {}", subject, name, side)),
+            });
+        }
+    }
+    readings
+}
+
+// Fase de cada luna de la Tierra según su elongación (el ángulo Sol-Tierra-Luna en la eclíptica)
+fn moon_phases(scene: &SceneState) -> Vec<Reading> {
+    let (Some(observer), Some(sun)) = (
+        scene.planets.iter().position(|planet| planet.name == OBSERVER),
+        scene.planets.iter().position(|planet| planet.parent.is_none() && planet.orbit_speed == 0.0),
+    ) else {
+        return Vec::new();
+    };
+    let earth = body_position(scene.planets, observer, scene.time);
+    let to_sun = body_position(scene.planets, sun, scene.time) - earth;
+    let band = PHASE_BAND_DEGREES.to_radians();
+
+    (0..scene.planets.len())
+        .filter(|&index| scene.planets[index].parent == Some(observer))
+        .map(|index| {
+            let to_moon = body_position(scene.planets, index, scene.time) - earth;
+            let elongation = (to_moon.z.atan2(to_moon.x) - to_sun.z.atan2(to_sun.x)).rem_euclid(2.0 * PI);
+            let quarter = (elongation / (PI / 2.0)).round();
+            let state = if (elongation - quarter * PI / 2.0).abs() <= band {
+                Some(MOON_PHASES[quarter as usize % MOON_PHASES.len()])
+            } else {
+                None
+            };
+            let name = scene.planets[index].name;
+            Reading {
+                key: format!("fase:{}", name),
+                state,
+                text: state.map(|phase| if name == "Luna" { phase.to_string() } else { format!("{}: {}", name, phase) }),
+            }
+        })
+        .collect()
+}
+
+// Pares de cuerpos del sistema solar (no los sintéticos de `--stress`) a pocos grados en el cielo
+// de la Tierra
+fn conjunctions(scene: &SceneState) -> Vec<Reading> {
+    let Some(observer) = scene.planets.iter().position(|planet| planet.name == OBSERVER) else {
+        return Vec::new();
+    };
+    let earth = body_position(scene.planets, observer, scene.time);
+    let bodies: Vec<(&str, Vec3)> = scene
+        .planets
+        .iter()
+        .enumerate()
+        .filter(|(index, planet)| *index != observer && planet.parent.is_none() && naif_id(planet.name).is_some())
+        .map(|(index, planet)| (planet.name, (body_position(scene.planets, index, scene.time) - earth).normalize()))
+        .collect();
+
+    let mut readings = Vec::new();
+    for (i, (first, first_direction)) in bodies.iter().enumerate() {
+        for (second, second_direction) in &bodies[i + 1..] {
+            let separation = first_direction.dot(second_direction).clamp(-1.0, 1.0).acos().to_degrees();
+            let state = if separation < CONJUNCTION_DEGREES {
+                Some("juntos")
+            } else if separation > CONJUNCTION_RELEASE_DEGREES {
+                Some("separados")
+            } else {
+                None
+            };
+            readings.push(Reading {
+                key: format!("conjuncion:{}:{}", first, second),
+                state,
+                text: (state == Some("juntos")).then(|| format!("Conjuncion de {} y {}", first, second)),
+            });
+        }
+    }
+    readings
+}
+
+// Registro de eventos de la simulación: corre los detectores cada cuadro, guarda los últimos
+// EVENT_LOG_CAPACITY y, con `--event-log <archivo>`, los agrega también a ese archivo. La primera
+// vez que se ve un sujeto solo se anota su estado, así al arrancar no sale una ráfaga de eventos.
+pub struct EventLog {
+    pub events: VecDeque<SimEvent>,
+    states: HashMap<String, &'static str>,
+    file: Option<String>,
+}
+
+impl EventLog {
+    pub fn from_args(args: &[String]) -> Self {
+        let file = args
+            .iter()
+            .position(|arg| arg == "--event-log")
+            .and_then(|position| args.get(position + 1).cloned());
+        EventLog { events: VecDeque::new(), states: HashMap::new(), file }
+    }
+
+    // Corre los detectores sobre la escena y devuelve cuántos eventos nuevos hubo
+    pub fn update(&mut self, scene: &SceneState, now: Instant) -> usize {
+        let mut logged = 0;
+        for (kind, detector) in DETECTORS {
+            for reading in detector(scene) {
+                let Some(state) = reading.state else {
+                    continue;
+                };
+                let previous = self.states.insert(reading.key, state);
+                if let (Some(previous), Some(text)) = (previous, reading.text) {
+                    if previous != state {
+                        self.push(SimEvent { kind, time: scene.time, text, logged_at: now });
+                        logged += 1;
+                    }
+                }
+            }
+        }
+        logged
+    }
+
+    fn push(&mut self, event: SimEvent) {
+        if let Some(path) = &self.file {
+            let line = format!("dia {:.1}  {}", event.day(), event.text);
+            let written = OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| writeln!(file, "{}", line));
+            if let Err(error) = written {
+                // Se avisa una vez y se sigue solo con el registro en memoria
+                eprintln!("No se pudo escribir el registro de eventos en {}: {}", path, error);
+                self.file = None;
+            }
+        }
+        if self.events.len() == EVENT_LOG_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    // Los últimos TICKER_LINES eventos que todavía se ven, del más viejo al más nuevo, con su opacidad
+    pub fn ticker(&self, now: Instant) -> Vec<(&SimEvent, f32)> {
+        let mut visible: Vec<(&SimEvent, f32)> = self
+            .events
+            .iter()
+            .rev()
+            .take(TICKER_LINES)
+            .filter_map(|event| {
+                let age = now.saturating_duration_since(event.logged_at);
+                let faded = age.saturating_sub(TICKER_HOLD).as_secs_f32() / TICKER_FADE.as_secs_f32();
+                (faded < 1.0).then_some((event, 1.0 - faded))
+            })
+            .collect();
+        visible.reverse();
+        visible
+    }
+
+    // Ticker al pie de la pantalla, con su borde inferior en `bottom`; devuelve su borde superior
+    // si quedaba algún evento por mostrar
+    pub fn render_ticker(&self, overlay: &mut Overlay, x: usize, bottom: usize, contrast: Contrast, now: Instant) -> Option<usize> {
+        let width = if contrast.is_high() { overlay.width().saturating_sub(2 * x) } else { TICKER_WIDTH.min(overlay.width().saturating_sub(2 * x)) };
+        let panel = |y: usize, event: &SimEvent, opacity: f32| {
+            Panel::new(x, y, width)
+                .contrast(contrast)
+                .fade(opacity)
+                .row(&format!("DIA {:.0}", event.day()), &event.text.to_uppercase())
+        };
+
+        // Un panel por evento para apagarlos por separado; todos tienen una fila y el mismo alto
+        let entries = self.ticker(now);
+        let (first, _) = entries.first()?;
+        let height = panel(0, first, 1.0).height();
+        let top = bottom.saturating_sub(entries.len() * height);
+        for (i, (event, opacity)) in entries.into_iter().enumerate() {
+            overlay.draw_panel(&panel(top + i * height, event, opacity));
+        }
+        Some(top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::rotate_x_vec3;
+    use crate::solar_system;
+
+    // Un cruce del plano de los anillos se anuncia una sola vez aunque la cámara se quede rondando el
+    // plano; las fases de la Luna salen en orden, y el ticker muestra los tres últimos y los apaga
+    #[test]
+    fn event_log() -> Result<(), String> {
+        let planets = solar_system();
+        let ring_systems = vec![("Saturno", RingSystem::saturn(), Vec::new())];
+        let saturn = planets.iter().position(|planet| planet.name == "Saturno").ok_or("no está Saturno")?;
+        let center = body_position(&planets, saturn, 0.0);
+        let normal = rotate_x_vec3(&Vec3::new(0.0, 1.0, 0.0), 26.7f32.to_radians());
+
+        let path = std::env::temp_dir().join(format!("spacetravel_selftest_{}.events.txt", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&path);
+        let mut log = EventLog::from_args(&["--event-log".to_string(), path.clone()]);
+        let now = Instant::now();
+
+        // Alturas de la cámara sobre el plano: solo los dos cruces completos cuentan
+        let heights = [2.0, 0.2, -0.2, 0.3, -0.4, -2.0, -0.1, 0.1, -0.1, 2.0];
+        let mut crossings = Vec::new();
+        for height in heights {
+            let scene = SceneState { planets: &planets, time: 0.0, camera_eye: center + normal * height, ring_systems: &ring_systems };
+            let before = log.events.len();
+            log.update(&scene, now);
+            crossings.extend(log.events.iter().skip(before).map(|event| event.text.clone()));
+        }
+        let expected = [
+            "La camara cruza el plano de los anillos de Saturno hacia el sur",
+            "La camara cruza el plano de los anillos de Saturno hacia el norte",
+        ];
+        if crossings != expected {
+            let _ = std::fs::remove_file(&path);
+            return Err(format!("cruces {:?}, se esperaban {:?}", crossings, expected));
+        }
+
+        // Dos meses sinódicos (la Luna da la vuelta respecto del Sol en 2π / (0.03 - 0.007) ticks)
+        let synodic = 2.0 * std::f32::consts::PI / (0.03 - 0.007);
+        let steps = (2.0 * synodic / 0.5) as usize;
+        let far = center + normal * 50.0;
+        for step in 0..steps {
+            let scene = SceneState { planets: &planets, time: step as f64 * 0.5, camera_eye: far, ring_systems: &ring_systems };
+            log.update(&scene, now);
+        }
+        let written = std::fs::read_to_string(&path).unwrap_or_default();
+        let _ = std::fs::remove_file(&path);
+
+        let phases: Vec<&str> = log.events.iter().filter(|event| event.kind == EventKind::MoonPhase).map(|event| event.text.as_str()).collect();
+        let cycle = ["Luna nueva", "Cuarto creciente", "Luna llena", "Cuarto menguante"];
+        let start = cycle.iter().position(|phase| Some(phase) == phases.first()).ok_or("no hubo fases de la Luna")?;
+        if phases.len() < 7 || phases.iter().enumerate().any(|(i, phase)| *phase != cycle[(start + i) % 4]) {
+            return Err(format!("fases {:?}", phases));
+        }
+        if written.lines().count() != log.events.len() || !written.lines().all(|line| line.starts_with("dia ")) {
+            return Err(format!("{} eventos en memoria y {} líneas en el archivo", log.events.len(), written.lines().count()));
+        }
+
+        let ticker = log.ticker(now);
+        if ticker.len() != 3 || ticker.last().map(|(event, _)| event) != log.events.back().as_ref() || ticker.iter().any(|(_, opacity)| *opacity != 1.0) {
+            return Err(format!("el ticker muestra {} eventos", ticker.len()));
+        }
+        let later = log.ticker(now + Duration::from_secs(8));
+        if later.len() != 3 || later.iter().any(|(_, opacity)| (opacity - 0.5).abs() > 1e-3) || !log.ticker(now + Duration::from_secs(11)).is_empty() {
+            return Err(format!("opacidades a los 8 s: {:?}", later.iter().map(|(_, opacity)| opacity).collect::<Vec<_>>()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use rayon::prelude::*;
+
+use crate::color::{Color, srgb_to_linear};
+use crate::framebuffer::{Framebuffer, BAND_HEIGHT};
+
+// Rejilla con que se estima la luminancia media del frame (una muestra por celda)
+const GRID_WIDTH: usize = 32;
+const GRID_HEIGHT: usize = 24;
+
+// Luminancia media que se considera "bien expuesta", en luz lineal (un gris sRGB de 0.08); la
+// escena es sobre todo espacio oscuro
+const TARGET_LUMINANCE: f32 = 0.007;
+
+// Constante de tiempo del suavizado: tapar el Sol un instante no hace parpadear la imagen
+const ADAPTATION_TIME: f32 = 0.5;
+
+// Rango por defecto de la exposición, en pasos (EV = log2 del multiplicador)
+pub const DEFAULT_MIN_EV: f32 = -1.5;
+pub const DEFAULT_MAX_EV: f32 = 1.0;
+
+// Exposición automática: mirar al Sol oscurece la escena y apartar la vista la aclara.
+// Cada frame se aplica la exposición calculada con los frames anteriores.
+pub struct AutoExposure {
+    pub min_ev: f32,
+    pub max_ev: f32,
+    // Con la exposición fija no se adapta (útil para capturas)
+    pub locked: bool,
+    smoothed_luminance: f32,
+    ev: f32,
+}
+
+impl AutoExposure {
+    pub fn new(min_ev: f32, max_ev: f32) -> Self {
+        AutoExposure {
+            min_ev,
+            max_ev,
+            locked: false,
+            smoothed_luminance: TARGET_LUMINANCE,
+            ev: 0.0,
+        }
+    }
+
+    pub fn toggle_lock(&mut self) {
+        self.locked = !self.locked;
+    }
+
+    pub fn ev(&self) -> f32 {
+        self.ev
+    }
+
+    // Mide el frame recién dibujado (antes de exponerlo) y ajusta la exposición del siguiente
+    pub fn update(&mut self, framebuffer: &Framebuffer, delta_seconds: f32) {
+        if self.locked {
+            return;
+        }
+
+        let luminance = average_luminance(framebuffer);
+        let blend = 1.0 - (-delta_seconds / ADAPTATION_TIME).exp();
+        self.smoothed_luminance += (luminance - self.smoothed_luminance) * blend;
+
+        let ev = (TARGET_LUMINANCE / self.smoothed_luminance.max(1e-4)).log2();
+        self.ev = ev.clamp(self.min_ev, self.max_ev);
+    }
+
+    // Etapa de tono: expone el color de la escena `stops` pasos con `Color::exposure`. Cada canal
+    // se expone por separado, así que basta una tabla de los 256 niveles por frame.
+    pub fn scale_framebuffer(framebuffer: &mut Framebuffer, stops: f32) {
+        if stops.abs() < 1e-3 {
+            return;
+        }
+
+        let mut table = [0u32; 256];
+        for (level, entry) in table.iter_mut().enumerate() {
+            *entry = Color::new(level as u8, 0, 0).exposure(stops).r as u32;
+        }
+        framebuffer.split_rows_mut(BAND_HEIGHT).into_par_iter().for_each(|band| {
+            for pixel in band.buffer.iter_mut() {
+                let scale = |channel: u32| table[(channel & 0xFF) as usize];
+                *pixel = scale(*pixel >> 16) << 16 | scale(*pixel >> 8) << 8 | scale(*pixel);
+            }
+        });
+    }
+}
+
+// Luminancia media en luz lineal (0.0 a 1.0) muestreando el centro de cada celda de la rejilla
+fn average_luminance(framebuffer: &Framebuffer) -> f32 {
+    if framebuffer.width == 0 || framebuffer.height == 0 {
+        return TARGET_LUMINANCE;
+    }
+
+    let mut total = 0.0;
+    for gy in 0..GRID_HEIGHT {
+        for gx in 0..GRID_WIDTH {
+            let x = (gx * 2 + 1) * framebuffer.width / (GRID_WIDTH * 2);
+            let y = (gy * 2 + 1) * framebuffer.height / (GRID_HEIGHT * 2);
+            let pixel = framebuffer.buffer[y * framebuffer.width + x];
+
+            let r = srgb_to_linear((pixel >> 16) as u8);
+            let g = srgb_to_linear((pixel >> 8) as u8);
+            let b = srgb_to_linear(pixel as u8);
+            total += 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        }
+    }
+
+    total / (GRID_WIDTH * GRID_HEIGHT) as f32
+}
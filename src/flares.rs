@@ -0,0 +1,145 @@
+use nalgebra_glm::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::color::Color;
+use crate::effects::EffectsBuffer;
+use crate::uniforms::Uniforms;
+use crate::project_point;
+
+// Intervalo (en ticks de simulación) entre dos fulguraciones consecutivas
+const MIN_FLARE_PERIOD: u32 = 90;
+const MAX_FLARE_PERIOD: u32 = 300;
+
+// Duración de cada fulguración, en ticks
+const MIN_FLARE_DURATION: u32 = 60;
+const MAX_FLARE_DURATION: u32 = 150;
+
+// Partículas por arco; cada una sale con un alcance distinto a lo largo de la superficie
+const PARTICLES_PER_FLARE: usize = 48;
+
+// Ángulo máximo (sobre la superficie) que recorre el arco y altura máxima, en radios solares
+const ARC_SPAN: f32 = 0.6;
+const PEAK_HEIGHT: f32 = 0.45;
+
+// Radio (en radios solares) de la esfera que contiene cualquier fulguración
+pub const FLARE_EXTENT: f32 = 1.0 + PEAK_HEIGHT;
+
+const WHITE_HOT: u32 = 0xFFFFF2;
+const ORANGE: u32 = 0xFF8C1A;
+
+// Una fulguración: un arco de partículas que sale de la superficie del Sol en `start_angle`
+// (sobre el plano orbital) y vuelve a caer en `duration` ticks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flare {
+    pub start_angle: f32,
+    pub duration: u32,
+    pub start_time: f64,
+}
+
+impl Flare {
+    // Avance normalizado de la fulguración: 0 al lanzarse, 1 al terminar
+    pub fn progress(&self, time: f64) -> f32 {
+        ((time - self.start_time).max(0.0) / self.duration.max(1) as f64) as f32
+    }
+
+    pub fn is_expired(&self, time: f64) -> bool {
+        time >= self.start_time + self.duration as f64
+    }
+}
+
+// Fulguraciones activas del Sol. El momento, el ángulo y la duración de cada una salen de un
+// RNG con semilla, así que una misma semilla repite la misma secuencia.
+pub struct SolarFlares {
+    pub flares: Vec<Flare>,
+    rng: StdRng,
+    solar_flare_period: u32,
+    last_launch: f64,
+}
+
+impl SolarFlares {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let solar_flare_period = rng.gen_range(MIN_FLARE_PERIOD..=MAX_FLARE_PERIOD);
+        SolarFlares { flares: Vec::new(), rng, solar_flare_period, last_launch: 0.0 }
+    }
+
+    // Lanza una fulguración cuando se cumple el intervalo actual y descarta las terminadas. Si el
+    // tiempo volvió atrás (la barra, el buscador), se olvidan las que todavía no habían salido y
+    // el intervalo cuenta desde ahí.
+    pub fn update(&mut self, time: f64) {
+        if time < self.last_launch {
+            self.flares.retain(|flare| flare.start_time <= time);
+            self.last_launch = time;
+        }
+        self.flares.retain(|flare| !flare.is_expired(time));
+
+        if time - self.last_launch >= self.solar_flare_period as f64 {
+            self.flares.push(Flare {
+                start_angle: self.rng.gen_range(0.0..2.0 * PI),
+                duration: self.rng.gen_range(MIN_FLARE_DURATION..=MAX_FLARE_DURATION),
+                start_time: time,
+            });
+            self.last_launch = time;
+            self.solar_flare_period = self.rng.gen_range(MIN_FLARE_PERIOD..=MAX_FLARE_PERIOD);
+        }
+    }
+
+    // Suma las partículas al buffer de efectos; respetan el z-buffer del frame (el Sol y los
+    // planetas las tapan)
+    pub fn render(&self, effects: &mut EffectsBuffer, framebuffer: &Framebuffer, uniforms: &Uniforms, sun_center: Vec3, sun_radius: f32, time: f64) {
+        for flare in &self.flares {
+            let t = flare.progress(time).clamp(0.0, 1.0);
+            let color = flare_color(t);
+            if color.is_black() {
+                continue;
+            }
+
+            for particle in 0..PARTICLES_PER_FLARE {
+                let reach = (particle + 1) as f32 / PARTICLES_PER_FLARE as f32;
+                let point = sun_center + particle_position(flare.start_angle, reach, t) * sun_radius;
+                if let Some(screen) = project_point(uniforms, point) {
+                    if screen.z > 0.0 {
+                        add_particle(effects, framebuffer, screen, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Posición (en radios solares, relativa al centro) de una partícula con alcance `reach` en el
+// instante `t`. Avanza a velocidad constante sobre la superficie y su altura sigue una
+// parábola que vale 0 al salir y al volver a caer.
+fn particle_position(start_angle: f32, reach: f32, t: f32) -> Vec3 {
+    let angle = start_angle + ARC_SPAN * reach * t;
+    let height = 4.0 * PEAK_HEIGHT * reach * t * (1.0 - t);
+    Vec3::new(angle.cos(), 0.0, angle.sin()) * (1.0 + height)
+}
+
+// Blanco incandescente al principio, naranja a mitad y negro (invisible al sumar) al final
+fn flare_color(t: f32) -> Color {
+    if t < 0.5 {
+        Color::from_hex(WHITE_HOT).lerp(&Color::from_hex(ORANGE), t * 2.0)
+    } else {
+        Color::from_hex(ORANGE).lerp(&Color::black(), (t - 0.5) * 2.0)
+    }
+}
+
+// Partícula de 2x2 píxeles
+fn add_particle(effects: &mut EffectsBuffer, framebuffer: &Framebuffer, screen: Vec3, color: Color) {
+    if screen.x < 0.0 || screen.y < 0.0 {
+        return;
+    }
+    let (x, y) = (screen.x as usize, screen.y as usize);
+    for py in y..(y + 2).min(framebuffer.height) {
+        for px in x..(x + 2).min(framebuffer.width) {
+            let index = py * framebuffer.width + px;
+            if framebuffer.zbuffer[index] > screen.z {
+                effects.add(index, color, 1.0);
+            }
+        }
+    }
+}
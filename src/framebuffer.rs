@@ -1,17 +1,112 @@
 // framebuffer.rs
 use crate::Vec3;
+use std::f32::consts::PI;
+use std::ops::Range;
+use crate::color::Color;
+use crate::render_stats::RenderStats;
 use font8x8::BASIC_FONTS;
 use font8x8::UnicodeFonts;
+use rayon::prelude::*;
 
+// Filas por franja en los pases que recorren toda la pantalla en paralelo (`split_rows_mut`)
+pub const BAND_HEIGHT: usize = 16;
+
+// FXAA: contraste local mínimo para tratar un píxel como borde, relativo al máximo de su
+// vecindad y absoluto (para no tocar las zonas oscuras casi uniformes)
+const FXAA_EDGE_THRESHOLD: f32 = 0.125;
+const FXAA_EDGE_THRESHOLD_MIN: f32 = 0.0312;
+// Peso del suavizado de detalles más finos que un píxel
+const FXAA_SUBPIXEL_QUALITY: f32 = 0.75;
+// Pasos (en píxeles) de la búsqueda de los extremos del borde, cada vez más largos
+const FXAA_SEARCH_STEPS: [f32; 10] = [1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 4.0, 8.0];
+
+// Segmentos de los arcos de `draw_arc`, según su largo en pantalla
+const ARC_MIN_SEGMENTS: usize = 8;
+const ARC_MAX_SEGMENTS: usize = 256;
+// Esferas de `draw_sphere_wireframe`: aplastamiento de los meridianos (como vistos 30° de
+// costado) y brillo de sus mitades escondidas
+const SPHERE_MERIDIAN_TILT: f32 = 0.5;
+const SPHERE_HIDDEN_BRIGHTNESS: f32 = 0.4;
+// Radio máximo de `fill_circle` y compañía
+const MAX_FILL_RADIUS: usize = 1 << 24;
+
+#[derive(Clone)]
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    // Fragmentos probados con `shade_if_visible`, cuántos llegaron al shader y triángulos
+    // enviados al rasterizador, desde el último `clear`
+    pub fragment_stats: FragmentStats,
+    // Dibujos y tramos del cuadro, también desde el último `clear`
+    pub render_stats: RenderStats,
     background_color: u32,
     current_color: u32,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FragmentStats {
+    pub tested: usize,
+    pub shaded: usize,
+    pub triangles: usize,
+}
+
+// Colores de `render_progress_bar`: la parte hecha y el resto
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BarColors {
+    pub fill: u32,
+    pub empty: u32,
+}
+
+// Patrón de `draw_dashed_line`: tramos de `dash` píxeles dibujados y otros tantos vacíos.
+// `phase` cuenta los píxeles recorridos, así una polilínea que usa el mismo patrón en todos sus
+// segmentos lo sigue sin cortes entre ellos.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DashPattern {
+    pub dash: usize,
+    pub phase: usize,
+}
+
+impl DashPattern {
+    pub fn new(dash: usize) -> Self {
+        DashPattern { dash: dash.max(1), phase: 0 }
+    }
+
+    fn is_drawn(&self) -> bool {
+        (self.phase / self.dash).is_multiple_of(2)
+    }
+}
+
+// Cómo escriben sus píxeles los círculos rellenos y los anillos: opacidad, mezcla aditiva (para
+// brillos) y, si hay profundidad, prueba de z-buffer. Solo lo opaco y no aditivo escribe la
+// profundidad, como en `blend_point`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillStyle {
+    pub alpha: f32,
+    pub additive: bool,
+    pub depth: Option<f32>,
+}
+
+impl FillStyle {
+    pub const OPAQUE: FillStyle = FillStyle { alpha: 1.0, additive: false, depth: None };
+
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn additive(mut self) -> Self {
+        self.additive = true;
+        self
+    }
+
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+}
+
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
         Framebuffer {
@@ -19,6 +114,8 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            fragment_stats: FragmentStats::default(),
+            render_stats: RenderStats::new(),
             background_color: 0x000000,
             current_color: 0xFFFFFF,
         }
@@ -31,6 +128,8 @@ impl Framebuffer {
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
+        self.fragment_stats = FragmentStats::default();
+        self.render_stats.reset();
     }
 
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
@@ -43,6 +142,85 @@ impl Framebuffer {
         }
     }
 
+    // Si un fragmento a `depth` ganaría ahora la prueba de profundidad en (x, y)
+    pub fn test_depth(&self, x: usize, y: usize, depth: f32) -> bool {
+        x < self.width && y < self.height && self.zbuffer[y * self.width + x] > depth
+    }
+
+    // Prueba de profundidad antes de sombrear: `shade` (lo caro: ruido, texturas) solo se llama
+    // si el fragmento ganaría, y entonces se escriben color y profundidad juntos. Prueba y
+    // escritura van con el mismo préstamo mutable, así nadie puede escribir en medio.
+    pub fn shade_if_visible(&mut self, x: usize, y: usize, depth: f32, shade: impl FnOnce() -> u32) -> bool {
+        self.fragment_stats.tested += 1;
+        if !self.test_depth(x, y, depth) {
+            return false;
+        }
+
+        self.fragment_stats.shaded += 1;
+        let index = y * self.width + x;
+        self.buffer[index] = shade();
+        self.zbuffer[index] = depth;
+        true
+    }
+
+    // Igual que `shade_if_visible` para fragmentos semitransparentes: `shade` devuelve
+    // (color, alpha) y el resultado se mezcla con `blend_point`
+    pub fn blend_if_visible(&mut self, x: usize, y: usize, depth: f32, shade: impl FnOnce() -> (u32, f32)) -> bool {
+        self.fragment_stats.tested += 1;
+        if !self.test_depth(x, y, depth) {
+            return false;
+        }
+
+        self.fragment_stats.shaded += 1;
+        let (color, alpha) = shade();
+        self.blend_point(x, y, depth, color, alpha);
+        true
+    }
+
+    pub fn read_pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x < self.width && y < self.height {
+            Some(self.buffer[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    // Escribe solo el color, sin mirar ni tocar el z-buffer
+    pub fn write_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = color;
+        }
+    }
+
+    pub fn read_depth(&self, x: usize, y: usize) -> Option<f32> {
+        if x < self.width && y < self.height {
+            Some(self.zbuffer[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    // Copia del frame actual (color y profundidad) para los pases que leen lo ya dibujado
+    pub fn clone_buffer(&self) -> Framebuffer {
+        self.clone()
+    }
+
+    // Mezcla `color` sobre el píxel existente según `alpha` (cobertura u opacidad).
+    // Solo los fragmentos totalmente opacos escriben en el z-buffer.
+    pub fn blend_point(&mut self, x: usize, y: usize, depth: f32, color: u32, alpha: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if self.zbuffer[index] > depth {
+                let alpha = alpha.clamp(0.0, 1.0);
+                let background = Color::from_hex(self.buffer[index]);
+                self.buffer[index] = background.lerp(&Color::from_hex(color), alpha).to_hex();
+                if alpha >= 1.0 {
+                    self.zbuffer[index] = depth;
+                }
+            }
+        }
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
@@ -77,6 +255,55 @@ impl Framebuffer {
         }
     }
 
+    // Texto centrado en (cx, cy), para mensajes de estado a mitad de pantalla
+    pub fn draw_text_centered(&mut self, cx: usize, cy: usize, text: &str, color: u32, scale: usize) {
+        let (width, height) = Self::measure_text(text, scale);
+        self.draw_text(cx.saturating_sub(width / 2), cy.saturating_sub(height / 2), text, color, scale);
+    }
+
+    // Texto que termina en la columna `rx` (su borde derecho), para números y etiquetas de esquina
+    pub fn draw_text_right_aligned(&mut self, rx: usize, y: usize, text: &str, color: u32, scale: usize) {
+        let width = Self::measure_text(text, scale).0;
+        self.draw_text(rx.saturating_sub(width), y, text, color, scale);
+    }
+
+    // Tamaño en píxeles (ancho, alto) que ocupa un texto con la fuente de 8x8
+    pub fn measure_text(text: &str, scale: usize) -> (usize, usize) {
+        (text.chars().count() * 8 * scale, 8 * scale)
+    }
+
+    pub fn draw_filled_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height);
+        for py in y..y_end {
+            for px in x..x_end {
+                self.buffer[py * self.width + px] = color;
+            }
+        }
+    }
+
+    // Barra de progreso: fondo `colors.empty` de todo el ancho y, encima, `colors.fill` en la
+    // fracción `progress`
+    pub fn render_progress_bar(&mut self, x: usize, y: usize, width: usize, height: usize, progress: f32, colors: BarColors) {
+        self.draw_filled_rect(x, y, width, height, colors.empty);
+        let filled = (width as f32 * progress.clamp(0.0, 1.0)) as usize;
+        self.draw_filled_rect(x, y, filled, height, colors.fill);
+    }
+
+    // Rectángulo semitransparente mezclado sobre lo que ya hay en pantalla (sin z-buffer)
+    pub fn blend_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32, alpha: f32) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let top = Color::from_hex(color);
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height);
+        for py in y..y_end {
+            for px in x..x_end {
+                let index = py * self.width + px;
+                self.buffer[index] = Color::from_hex(self.buffer[index]).lerp(&top, alpha).to_hex();
+            }
+        }
+    }
+
     pub fn draw_circle(&mut self, cx: usize, cy: usize, radius: usize, color: u32) {
         let mut x = radius as isize;
         let mut y = 0;
@@ -94,6 +321,21 @@ impl Framebuffer {
         }
     }
 
+    // Retícula centrada en (cx, cy): cuatro brazos que van de `gap` a `size` píxeles del centro y
+    // un círculo de radio `gap`, que marcan el punto sin taparlo. Lo que cae fuera se recorta.
+    pub fn draw_crosshair(&mut self, cx: isize, cy: isize, size: usize, gap: usize, color: u32) {
+        for distance in gap as isize..=size as isize {
+            for (px, py) in [(cx + distance, cy), (cx - distance, cy), (cx, cy + distance), (cx, cy - distance)] {
+                if px >= 0 && px < self.width as isize && py >= 0 && py < self.height as isize {
+                    self.buffer[py as usize * self.width + px as usize] = color;
+                }
+            }
+        }
+        if cx >= 0 && cy >= 0 {
+            self.draw_circle(cx as usize, cy as usize, gap, color);
+        }
+    }
+
     fn plot_circle_points(&mut self, cx: usize, cy: usize, x: isize, y: isize, color: u32) {
         let points = [
             (cx as isize + x, cy as isize + y),
@@ -112,6 +354,91 @@ impl Framebuffer {
             }
         }
     }
+
+    // Disco relleno: los píxeles a distancia `radius` o menos de (cx, cy), es decir con
+    // dx² + dy² <= radius². Con radio 0 es solo el centro, que puede caer fuera de la pantalla.
+    pub fn fill_circle(&mut self, cx: isize, cy: isize, radius: usize, color: u32, style: FillStyle) {
+        self.fill_annulus(cx, cy, 0, radius, style, |_| color);
+    }
+
+    // Disco con el color yendo de `center_color` a `edge_color` según la distancia al centro
+    // dividida por el radio
+    pub fn fill_circle_gradient(&mut self, cx: isize, cy: isize, radius: usize, center_color: u32, edge_color: u32, style: FillStyle) {
+        let (center, edge) = (Color::from_hex(center_color), Color::from_hex(edge_color));
+        let scale = 1.0 / radius.max(1) as f32;
+        self.fill_annulus(cx, cy, 0, radius, style, |distance_squared| {
+            center.lerp(&edge, (distance_squared as f32).sqrt() * scale).to_hex()
+        });
+    }
+
+    // Anillo: los píxeles del disco de `outer_radius` que no están a menos de `inner_radius`
+    // del centro (inner_radius² <= dx² + dy² <= outer_radius²)
+    pub fn draw_ring(&mut self, cx: isize, cy: isize, inner_radius: usize, outer_radius: usize, color: u32, style: FillStyle) {
+        self.fill_annulus(cx, cy, inner_radius, outer_radius, style, |_| color);
+    }
+
+    // Segmento de `start` a `end` en píxeles (puede salirse de la pantalla: se recorta) con la
+    // opacidad, la mezcla y la profundidad de `style`. Como `draw_line`, no pinta el último
+    // píxel, así que una polilínea no suma dos veces sus vértices.
+    pub fn blend_line(&mut self, start: (f32, f32), end: (f32, f32), color: u32, style: FillStyle) {
+        let Some((start, end)) = self.clip_line(start, end) else {
+            return;
+        };
+        let width = self.width;
+        bresenham(start.0 as usize, start.1 as usize, end.0 as usize, end.1 as usize, |x, y| {
+            self.fill_pixel(y as usize * width + x as usize, color, style);
+        });
+    }
+
+    // Recorre fila por fila los tramos del anillo (un disco con `inner` 0), recortados a la
+    // pantalla, y pinta cada píxel con `shade(dx² + dy²)`
+    fn fill_annulus(&mut self, cx: isize, cy: isize, inner: usize, outer: usize, style: FillStyle, shade: impl Fn(usize) -> u32) {
+        if inner > outer || self.width == 0 || self.height == 0 {
+            return;
+        }
+        // Radios acotados para que los cuadrados no desborden
+        let (inner, outer) = (inner.min(MAX_FILL_RADIUS), outer.min(MAX_FILL_RADIUS));
+        let (width, height) = (self.width as isize, self.height as isize);
+        let reach = outer as isize;
+        let (top, bottom) = (cy.saturating_sub(reach).max(0), cy.saturating_add(reach).min(height - 1));
+
+        for y in top..=bottom {
+            let dy = (y - cy).unsigned_abs();
+            let outer_half = (outer * outer - dy * dy).isqrt() as isize;
+            // Tramo hueco del centro: dx² < inner² - dy²
+            let hole_half = (inner * inner).checked_sub(dy * dy + 1).map(|rest| rest.isqrt() as isize);
+            let spans = match hole_half {
+                Some(hole) => [(-outer_half, -hole - 1), (hole + 1, outer_half)],
+                None => [(-outer_half, outer_half), (1, 0)],
+            };
+            for (from, to) in spans {
+                let (left, right) = (cx.saturating_add(from).max(0), cx.saturating_add(to).min(width - 1));
+                for x in left..=right {
+                    let dx = (x - cx).unsigned_abs();
+                    let index = y as usize * self.width + x as usize;
+                    self.fill_pixel(index, shade(dx * dx + dy * dy), style);
+                }
+            }
+        }
+    }
+
+    fn fill_pixel(&mut self, index: usize, color: u32, style: FillStyle) {
+        if style.depth.is_some_and(|depth| self.zbuffer[index] <= depth) {
+            return;
+        }
+        let alpha = style.alpha.clamp(0.0, 1.0);
+        let (current, top) = (Color::from_hex(self.buffer[index]), Color::from_hex(color));
+        self.buffer[index] = if style.additive {
+            (current + top * alpha).to_hex()
+        } else if alpha >= 1.0 {
+            color
+        } else {
+            current.lerp(&top, alpha).to_hex()
+        };
+        if let (Some(depth), false, true) = (style.depth, style.additive, alpha >= 1.0) {
+            self.zbuffer[index] = depth;
+        }
+    }
 }    
 
 impl Framebuffer {
@@ -139,38 +466,773 @@ impl Framebuffer {
         self.draw_line(screen_v2.0, screen_v2.1, screen_v0.0, screen_v0.1, self.current_color);
     }
 
-    pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u32) {
-        // Implementación del algoritmo de Bresenham para líneas
-        let mut x0 = x0 as isize;
-        let mut y0 = y0 as isize;
-        let x1 = x1 as isize;
-        let y1 = y1 as isize;
-
-        let dx = (x1 - x0).abs();
-        let dy = -(y1 - y0).abs();
-        let mut sx = if x0 < x1 { 1 } else { -1 };
-        let mut sy = if y0 < y1 { 1 } else { -1 };
-        let mut err = dx + dy;
-
-        while x0 != x1 || y0 != y1 {
-            if x0 >= 0 && x0 < self.width as isize && y0 >= 0 && y0 < self.height as isize {
-                self.buffer[y0 as usize * self.width + x0 as usize] = color;
-            }
-            let e2 = 2 * err;
-            if e2 >= dy {
-                if x0 == x1 {
-                    break;
+    pub fn draw_polygon(&mut self, points: &[(f32, f32)], color: u32) {
+        if points.len() < 2 {
+            return;
+        }
+
+        // Conectar cada punto con el siguiente, cerrando el polígono al final. Las aristas que
+        // salen de la pantalla se recortan; solo se saltan las que quedan enteras afuera.
+        for i in 0..points.len() {
+            let Some(((x0, y0), (x1, y1))) = self.clip_line(points[i], points[(i + 1) % points.len()]) else {
+                continue;
+            };
+            self.draw_line(x0 as usize, y0 as usize, x1 as usize, y1 as usize, color);
+        }
+    }
+
+    pub fn fill_polygon(&mut self, points: &[(f32, f32)], color: u32) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let min_y = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+        let max_y = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max).ceil();
+        if max_y < 0.0 {
+            return;
+        }
+        let max_y = (max_y as usize).min(self.height.saturating_sub(1));
+
+        let mut intersections: Vec<f32> = Vec::with_capacity(points.len());
+
+        // Algoritmo de scanline: intersectar cada fila (en el centro del píxel) con las aristas
+        for y in min_y..=max_y {
+            let scan_y = y as f32 + 0.5;
+            intersections.clear();
+
+            for i in 0..points.len() {
+                let (x0, y0) = points[i];
+                let (x1, y1) = points[(i + 1) % points.len()];
+
+                // Regla semiabierta para no contar dos veces los vértices compartidos
+                if (y0 <= scan_y && y1 > scan_y) || (y1 <= scan_y && y0 > scan_y) {
+                    let t = (scan_y - y0) / (y1 - y0);
+                    intersections.push(x0 + t * (x1 - x0));
                 }
-                err += dy;
-                x0 += sx;
             }
-            if e2 <= dx {
-                if y0 == y1 {
+
+            intersections.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+            // Rellenar entre pares de intersecciones (regla par-impar)
+            for pair in intersections.chunks(2) {
+                if pair.len() < 2 {
                     break;
                 }
-                err += dx;
-                y0 += sy;
+                let start = (pair[0] - 0.5).ceil().max(0.0) as usize;
+                let end = (pair[1] - 0.5).floor();
+                if end < 0.0 {
+                    continue;
+                }
+                let end = (end as usize).min(self.width.saturating_sub(1));
+                for x in start..=end {
+                    if x < self.width {
+                        self.buffer[y * self.width + x] = color;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u32) {
+        let (width, height) = (self.width as isize, self.height as isize);
+        bresenham(x0, y0, x1, y1, |x, y| {
+            if x >= 0 && x < width && y >= 0 && y < height {
+                self.buffer[y as usize * self.width + x as usize] = color;
             }
+        });
+    }
+
+    // Recorta el segmento de `start` a `end` (en píxeles, pueden estar fuera de la pantalla) al
+    // rectángulo del framebuffer con Liang–Barsky. Devuelve la parte visible, o `None` si el
+    // segmento queda entero afuera; los extremos devueltos siempre son píxeles válidos.
+    pub fn clip_line(&self, start: (f32, f32), end: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+        let finite = [start.0, start.1, end.0, end.1].iter().all(|c| c.is_finite());
+        if !finite || self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let (max_x, max_y) = ((self.width - 1) as f32, (self.height - 1) as f32);
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let (mut t_enter, mut t_exit) = (0.0f32, 1.0f32);
+
+        // Cada borde como p·t <= q: izquierdo, derecho, superior e inferior
+        for (p, q) in [(-dx, start.0), (dx, max_x - start.0), (-dy, start.1), (dy, max_y - start.1)] {
+            if p == 0.0 {
+                // Paralelo a este borde: o está entero del lado de adentro o no se ve
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t_enter = t_enter.max(t);
+                } else {
+                    t_exit = t_exit.min(t);
+                }
+            }
+        }
+        if t_enter > t_exit || !t_enter.is_finite() || !t_exit.is_finite() {
+            return None;
+        }
+
+        let at = |t: f32| ((start.0 + dx * t).clamp(0.0, max_x), (start.1 + dy * t).clamp(0.0, max_y));
+        Some((at(t_enter), at(t_exit)))
+    }
+
+    // Arco de la elipse de centro (cx, cy) y semiejes rx, ry entre los ángulos de `angles`
+    // (radianes, desde +X y hacia abajo en pantalla), como polilínea recortada a la pantalla
+    pub fn draw_arc(&mut self, cx: f32, cy: f32, rx: f32, ry: f32, angles: Range<f32>, color: u32) {
+        let Range { start, end } = angles;
+        // Un segmento cada ~4 píxeles de recorrido, entre ARC_MIN_SEGMENTS y ARC_MAX_SEGMENTS
+        let length = rx.abs().max(ry.abs()) * (end - start).abs();
+        if !length.is_finite() {
+            return;
+        }
+        let segments = ((length / 4.0).ceil() as usize).clamp(ARC_MIN_SEGMENTS, ARC_MAX_SEGMENTS);
+        let point = |i: usize| {
+            let angle = start + (end - start) * i as f32 / segments as f32;
+            (cx + rx * angle.cos(), cy + ry * angle.sin())
+        };
+        for i in 0..segments {
+            if let Some((a, b)) = self.clip_line(point(i), point(i + 1)) {
+                self.draw_line(a.0.round() as usize, a.1.round() as usize, b.0.round() as usize, b.1.round() as usize, color);
+            }
+        }
+    }
+
+    // Esfera envolvente en pantalla para depurar el descarte y los niveles de detalle: su
+    // contorno (el círculo máximo perpendicular a la vista) completo y dos meridianos, uno
+    // vertical y otro horizontal, vistos con una inclinación fija. De los meridianos la mitad
+    // cercana va en `color` y la escondida detrás de la esfera, más apagada.
+    pub fn draw_sphere_wireframe(&mut self, cx: f32, cy: f32, rx: f32, ry: f32, color: u32) {
+        let hidden = (Color::from_hex(color) * SPHERE_HIDDEN_BRIGHTNESS).to_hex();
+        self.draw_arc(cx, cy, rx, ry, 0.0..2.0 * PI, color);
+
+        // Meridiano vertical: la mitad derecha mira hacia la cámara
+        let (meridian_rx, parallel_ry) = (rx * SPHERE_MERIDIAN_TILT, ry * SPHERE_MERIDIAN_TILT);
+        self.draw_arc(cx, cy, meridian_rx, ry, -PI / 2.0..PI / 2.0, color);
+        self.draw_arc(cx, cy, meridian_rx, ry, PI / 2.0..3.0 * PI / 2.0, hidden);
+        // Meridiano horizontal: vista desde arriba, la mitad de abajo es la cercana
+        self.draw_arc(cx, cy, rx, parallel_ry, 0.0..PI, color);
+        self.draw_arc(cx, cy, rx, parallel_ry, PI..2.0 * PI, hidden);
+    }
+
+    // Línea gruesa para el modo de alto contraste: la misma línea repetida un píxel hacia
+    // cada lado (arriba, abajo, izquierda y derecha)
+    pub fn draw_thick_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u32) {
+        self.draw_line(x0, y0, x1, y1, color);
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let offset = |value: usize, delta: isize| value.checked_add_signed(delta);
+            if let (Some(x0), Some(y0), Some(x1), Some(y1)) = (offset(x0, dx), offset(y0, dy), offset(x1, dx), offset(y1, dy)) {
+                self.draw_line(x0, y0, x1, y1, color);
+            }
+        }
+    }
+
+    // Línea punteada con el patrón de `dashes`, que avanza con los píxeles recorridos
+    pub fn draw_dashed_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u32, dashes: &mut DashPattern) {
+        let (width, height) = (self.width as isize, self.height as isize);
+        bresenham(x0, y0, x1, y1, |x, y| {
+            if dashes.is_drawn() && x >= 0 && x < width && y >= 0 && y < height {
+                self.buffer[y as usize * self.width + x as usize] = color;
+            }
+            dashes.phase += 1;
+        });
+    }
+
+    // Divide el framebuffer en franjas horizontales de `band_height` filas que se pueden
+    // escribir en paralelo: cada una toma prestada su parte del buffer y del z-buffer
+    pub fn split_rows_mut(&mut self, band_height: usize) -> Vec<FramebufferBand<'_>> {
+        let width = self.width.max(1);
+        let band_height = band_height.max(1);
+        self.buffer
+            .chunks_mut(band_height * width)
+            .zip(self.zbuffer.chunks_mut(band_height * width))
+            .enumerate()
+            .map(|(index, (buffer, zbuffer))| FramebufferBand {
+                y_offset: index * band_height,
+                width,
+                buffer,
+                zbuffer,
+                current_color: self.current_color,
+            })
+            .collect()
+    }
+
+    // Antialiasing FXAA (la versión simplificada de FXAA 3.11), como último post-proceso de la
+    // escena. En cada píxel con contraste suficiente se decide si el borde es horizontal o
+    // vertical, se buscan sus extremos a lo largo de él y se mezcla el píxel con el vecino del
+    // otro lado según su posición en el borde. Las zonas uniformes (el centro de los planetas)
+    // quedan igual. Solo lee el color: el z-buffer no cambia.
+    pub fn apply_fxaa(&mut self) {
+        let (width, height) = (self.width, self.height);
+        if width < 3 || height < 3 {
+            return;
+        }
+
+        let luma: Vec<f32> = self.buffer.par_iter().map(|&color| luma(color)).collect();
+        let source = self.buffer.clone();
+        self.buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                if let Some((dx, dy, blend)) = fxaa_offset(&luma, width, height, x, y) {
+                    let nx = (x as isize + dx).clamp(0, width as isize - 1) as usize;
+                    let ny = (y as isize + dy).clamp(0, height as isize - 1) as usize;
+                    let neighbor = Color::from_hex(source[ny * width + nx]);
+                    *pixel = Color::from_hex(source[y * width + x]).lerp(&neighbor, blend).to_hex();
+                }
+            }
+        });
+    }
+
+    // Aberración cromática, el defecto de una lente que no enfoca igual todos los colores: sobre
+    // una copia del cuadro, cada píxel toma el rojo de un punto corrido hacia el centro de la
+    // pantalla, el verde de su lugar y el azul de un punto corrido hacia afuera. El corrimiento es
+    // `strength` veces la distancia al centro, así que el centro queda nítido.
+    pub fn apply_chromatic_aberration(&mut self, strength: f32) {
+        let (width, height) = (self.width, self.height);
+        if strength <= 0.0 || width == 0 || height == 0 {
+            return;
+        }
+
+        let source = self.buffer.clone();
+        let (cx, cy) = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+        self.buffer.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+                let sample = |scale: f32, shift: u32| {
+                    channel_bilinear(&source, width, height, cx + dx * scale, cy + dy * scale, shift)
+                };
+                let (red, blue) = (sample(1.0 - strength, 16), sample(1.0 + strength, 0));
+                *pixel = red << 16 | (*pixel & 0x00FF00) | blue;
+            }
+        });
+    }
+}
+
+// Un canal (el byte en `shift`) de una imagen 0xRRGGBB interpolado en una posición fraccionaria,
+// repitiendo el borde
+fn channel_bilinear(buffer: &[u32], width: usize, height: usize, x: f32, y: f32, shift: u32) -> u32 {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let at = |px: usize, py: usize| ((buffer[py * width + px] >> shift) & 0xFF) as f32;
+    let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+    let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+    (top * (1.0 - ty) + bottom * ty).round() as u32
+}
+
+// Luminancia perceptual de un color 0xRRGGBB, de 0.0 a 1.0
+fn luma(color: u32) -> f32 {
+    let channel = |shift: u32| ((color >> shift) & 0xFF) as f32 / 255.0;
+    0.299 * channel(16) + 0.587 * channel(8) + 0.114 * channel(0)
+}
+
+// Luminancia en coordenadas enteras, repitiendo el borde de la imagen
+fn luma_at(luma: &[f32], width: usize, height: usize, x: isize, y: isize) -> f32 {
+    let x = x.clamp(0, width as isize - 1) as usize;
+    let y = y.clamp(0, height as isize - 1) as usize;
+    luma[y * width + x]
+}
+
+// Luminancia interpolada en una posición fraccionaria (en unidades de píxel, sin el 0.5 del centro)
+fn luma_bilinear(luma: &[f32], width: usize, height: usize, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as isize, y0 as isize);
+    let top = luma_at(luma, width, height, x0, y0) * (1.0 - tx) + luma_at(luma, width, height, x0 + 1, y0) * tx;
+    let bottom = luma_at(luma, width, height, x0, y0 + 1) * (1.0 - tx) + luma_at(luma, width, height, x0 + 1, y0 + 1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+// Vecino (dx, dy) con el que mezclar el píxel y cuánto, o `None` si no está en un borde
+fn fxaa_offset(luma: &[f32], width: usize, height: usize, x: usize, y: usize) -> Option<(isize, isize, f32)> {
+    let (x, y) = (x as isize, y as isize);
+    let at = |dx: isize, dy: isize| luma_at(luma, width, height, x + dx, y + dy);
+    let (center, north, south, west, east) = (at(0, 0), at(0, -1), at(0, 1), at(-1, 0), at(1, 0));
+
+    let max = center.max(north).max(south).max(west).max(east);
+    let min = center.min(north).min(south).min(west).min(east);
+    let range = max - min;
+    if range < FXAA_EDGE_THRESHOLD_MIN.max(max * FXAA_EDGE_THRESHOLD) {
+        return None;
+    }
+
+    let (north_west, north_east, south_west, south_east) = (at(-1, -1), at(1, -1), at(-1, 1), at(1, 1));
+    let edge_horizontal = (north_west + south_west - 2.0 * west).abs()
+        + 2.0 * (north + south - 2.0 * center).abs()
+        + (north_east + south_east - 2.0 * east).abs();
+    let edge_vertical = (north_west + north_east - 2.0 * north).abs()
+        + 2.0 * (west + east - 2.0 * center).abs()
+        + (south_west + south_east - 2.0 * south).abs();
+    let horizontal = edge_horizontal >= edge_vertical;
+
+    // Lado del borde con el cambio más fuerte: el vecino de ese lado es el que se mezcla
+    let (before, after) = if horizontal { (north, south) } else { (west, east) };
+    let (gradient_before, gradient_after) = (before - center, after - center);
+    let toward_before = gradient_before.abs() >= gradient_after.abs();
+    let step: isize = if toward_before { -1 } else { 1 };
+    let gradient_scaled = 0.25 * gradient_before.abs().max(gradient_after.abs());
+    let local_average = 0.5 * (center + if toward_before { before } else { after });
+
+    // Se camina por la línea entre los dos lados en ambos sentidos hasta que la luminancia se
+    // aparta del promedio local: ahí termina el borde
+    let (along_x, along_y) = if horizontal { (1.0, 0.0) } else { (0.0, 1.0) };
+    let (start_x, start_y) = if horizontal {
+        (x as f32, y as f32 + 0.5 * step as f32)
+    } else {
+        (x as f32 + 0.5 * step as f32, y as f32)
+    };
+    let search = |direction: f32| {
+        let mut distance = 0.0;
+        let mut luma_end = 0.0;
+        for step_length in FXAA_SEARCH_STEPS {
+            distance += step_length;
+            let sample_x = start_x + along_x * direction * distance;
+            let sample_y = start_y + along_y * direction * distance;
+            luma_end = luma_bilinear(luma, width, height, sample_x, sample_y) - local_average;
+            if luma_end.abs() >= gradient_scaled {
+                break;
+            }
+        }
+        (distance, luma_end)
+    };
+    let (distance_negative, end_negative) = search(-1.0);
+    let (distance_positive, end_positive) = search(1.0);
+
+    // Cuanto más cerca del extremo del borde, más se mezcla; solo si en ese extremo la
+    // luminancia va en el sentido contrario a la del centro
+    let (distance, luma_end) = if distance_negative < distance_positive {
+        (distance_negative, end_negative)
+    } else {
+        (distance_positive, end_positive)
+    };
+    let edge_length = distance_negative + distance_positive;
+    let edge_blend = if (luma_end < 0.0) != (center < local_average) {
+        0.5 - distance / edge_length
+    } else {
+        0.0
+    };
+
+    // Detalles menores que un píxel: cuánto se aparta el centro del promedio de su vecindad
+    let neighborhood = (2.0 * (north + south + west + east) + north_west + north_east + south_west + south_east) / 12.0;
+    let subpixel = ((neighborhood - center).abs() / range).clamp(0.0, 1.0);
+    let subpixel = (-2.0 * subpixel + 3.0) * subpixel * subpixel;
+    let subpixel_blend = subpixel * subpixel * FXAA_SUBPIXEL_QUALITY;
+
+    let blend = edge_blend.max(subpixel_blend);
+    if blend <= 0.0 {
+        return None;
+    }
+    if horizontal {
+        Some((0, step, blend))
+    } else {
+        Some((step, 0, blend))
+    }
+}
+
+// Implementación del algoritmo de Bresenham para líneas (sin el último punto),
+// compartida por el framebuffer, sus franjas y el buffer de efectos
+pub fn bresenham(x0: usize, y0: usize, x1: usize, y1: usize, mut plot: impl FnMut(isize, isize)) {
+    let mut x0 = x0 as isize;
+    let mut y0 = y0 as isize;
+    let x1 = x1 as isize;
+    let y1 = y1 as isize;
+
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    while x0 != x1 || y0 != y1 {
+        plot(x0, y0);
+        let e2 = 2 * err;
+        if e2 >= dy {
+            if x0 == x1 {
+                break;
+            }
+            err += dy;
+            x0 += sx;
+        }
+        if e2 <= dx {
+            if y0 == y1 {
+                break;
+            }
+            err += dx;
+            y0 += sy;
+        }
+    }
+}
+
+// Franja de filas de un framebuffer, obtenida con `split_rows_mut`. Las coordenadas son las
+// del framebuffer completo; lo que cae fuera de la franja se recorta.
+pub struct FramebufferBand<'a> {
+    pub y_offset: usize,
+    pub width: usize,
+    pub buffer: &'a mut [u32],
+    pub zbuffer: &'a mut [f32],
+    current_color: u32,
+}
+
+impl<'a> FramebufferBand<'a> {
+    pub fn height(&self) -> usize {
+        self.buffer.len() / self.width
+    }
+
+    // Rango de filas (del framebuffer completo) que cubre la franja
+    pub fn rows(&self) -> std::ops::Range<usize> {
+        self.y_offset..self.y_offset + self.height()
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && self.rows().contains(&y) {
+            Some((y - self.y_offset) * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_current_color(&mut self, color: u32) {
+        self.current_color = color;
+    }
+
+    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
+        if let Some(index) = self.index(x, y) {
+            if self.zbuffer[index] > depth {
+                self.buffer[index] = self.current_color;
+                self.zbuffer[index] = depth;
+            }
+        }
+    }
+
+    // Escribe color y profundidad sin prueba de profundidad, como los fondos que se pintan por
+    // dirección
+    pub fn paint(&mut self, x: usize, y: usize, color: u32, depth: f32) {
+        if let Some(index) = self.index(x, y) {
+            self.buffer[index] = color;
+            self.zbuffer[index] = depth;
+        }
+    }
+
+    pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u32) {
+        bresenham(x0, y0, x1, y1, |x, y| {
+            if x >= 0 && y >= 0 {
+                if let Some(index) = self.index(x as usize, y as usize) {
+                    self.buffer[index] = color;
+                }
+            }
+        });
+    }
+
+    pub fn draw_filled_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let x_end = (x + width).min(self.width);
+        let rows = self.rows();
+        for py in y.max(rows.start)..(y + height).min(rows.end) {
+            let row = (py - self.y_offset) * self.width;
+            for px in x..x_end {
+                self.buffer[row + px] = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Mat4};
+    use crate::{Obj, SPHERE_MODEL, render_body, projected_radius};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+    use crate::uniforms::create_viewport_matrix;
+
+    // Escena de líneas, rectángulos y puntos que cruza los bordes de las franjas a propósito
+    fn draw_band_test_scene(
+        mut line: impl FnMut(usize, usize, usize, usize, u32),
+        mut rect: impl FnMut(usize, usize, usize, usize, u32),
+        mut point: impl FnMut(usize, usize, f32, u32),
+    ) {
+        for i in 0..40 {
+            line(i * 7 % 97, i * 13 % 71, 96 - i * 5 % 97, 70 - i * 3 % 71, 0x100000 * (i as u32 % 15 + 1));
+            rect(i * 11 % 90, i * 17 % 65, 3 + i % 9, 2 + i % 11, 0x001000 * (i as u32 % 15 + 1));
+        }
+        for i in 0..500 {
+            point(i * 31 % 101, i * 37 % 75, (i * 53 % 19) as f32, 0x000010 * (i as u32 % 15 + 1));
+        }
+    }
+
+    // La misma escena dibujada de forma secuencial y por franjas en paralelo (con un alto de
+    // franja que no divide la altura) debe dar buffers idénticos: ningún píxel del borde de una
+    // franja se pierde ni se duplica
+    #[test]
+    fn framebuffer_bands() -> Result<(), String> {
+        let (width, height) = (97, 71);
+        let mut sequential = Framebuffer::new(width, height);
+        {
+            let framebuffer = std::cell::RefCell::new(&mut sequential);
+            draw_band_test_scene(
+                |x0, y0, x1, y1, color| framebuffer.borrow_mut().draw_line(x0, y0, x1, y1, color),
+                |x, y, w, h, color| framebuffer.borrow_mut().draw_filled_rect(x, y, w, h, color),
+                |x, y, depth, color| {
+                    let mut framebuffer = framebuffer.borrow_mut();
+                    framebuffer.set_current_color(color);
+                    framebuffer.point(x, y, depth);
+                },
+            );
+        }
+
+        let mut banded = Framebuffer::new(width, height);
+        banded.split_rows_mut(7).into_par_iter().for_each(|band| {
+            let band = std::cell::RefCell::new(band);
+            draw_band_test_scene(
+                |x0, y0, x1, y1, color| band.borrow_mut().draw_line(x0, y0, x1, y1, color),
+                |x, y, w, h, color| band.borrow_mut().draw_filled_rect(x, y, w, h, color),
+                |x, y, depth, color| {
+                    let mut band = band.borrow_mut();
+                    band.set_current_color(color);
+                    band.point(x, y, depth);
+                },
+            );
+        });
+
+        if sequential.buffer != banded.buffer {
+            let first = sequential.buffer.iter().zip(&banded.buffer).position(|(a, b)| a != b).unwrap_or(0);
+            return Err(format!("el color difiere en ({}, {})", first % width, first / width));
+        }
+        if sequential.zbuffer != banded.zbuffer {
+            return Err("el z-buffer difiere".to_string());
+        }
+        Ok(())
+    }
+
+    // Contorno completo, mitades cercanas de los meridianos en el color pedido y las escondidas más
+    // apagadas; fuera de la pantalla o con radios inválidos no dibuja nada ni falla
+    #[test]
+    fn sphere_wireframe() -> Result<(), String> {
+        let (color, hidden) = (0xFFFFFF, (Color::from_hex(0xFFFFFF) * 0.4).to_hex());
+        let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        framebuffer.draw_sphere_wireframe(400.0, 300.0, 100.0, 100.0, color);
+        let expected = [
+            ("contorno a la derecha", (500, 300), color),
+            ("contorno arriba", (400, 200), color),
+            ("meridiano vertical cercano", (450, 300), color),
+            ("meridiano vertical escondido", (350, 300), hidden),
+            ("meridiano horizontal cercano", (400, 350), color),
+            ("meridiano horizontal escondido", (400, 250), hidden),
+            ("centro", (400, 300), 0),
+        ];
+        for (name, (x, y), expected) in expected {
+            let found = framebuffer.read_pixel(x, y).unwrap_or(0);
+            if found != expected {
+                return Err(format!("{} ({}, {}): {:06X}, se esperaba {:06X}", name, x, y, found, expected));
+            }
+        }
+
+        let mut offscreen = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        offscreen.draw_sphere_wireframe(-5000.0, 300.0, 100.0, 100.0, color);
+        offscreen.draw_sphere_wireframe(400.0, 300.0, f32::NAN, f32::INFINITY, color);
+        if offscreen.buffer.iter().any(|&pixel| pixel != 0) {
+            return Err("se dibujó una esfera fuera de la pantalla o con radio inválido".to_string());
+        }
+        Ok(())
+    }
+
+    // Píxeles exactos de los discos y anillos chicos (donde se notan los errores de uno), comparados
+    // también con la distancia de cada píxel para radios y centros que salen de la pantalla; el
+    // degradado, la mezcla, lo aditivo y la prueba de profundidad
+    #[test]
+    fn circle_fills() -> Result<(), String> {
+        let mask = |framebuffer: &Framebuffer| -> Vec<String> {
+            framebuffer
+                .buffer
+                .chunks(framebuffer.width)
+                .map(|row| row.iter().map(|&pixel| if pixel == 0 { '.' } else { '#' }).collect())
+                .collect()
+        };
+        let cases: [(usize, usize, &[&str]); 5] = [
+            (0, 0, &[".....", ".....", "..#..", ".....", "....."]),
+            (0, 1, &[".....", "..#..", ".###.", "..#..", "....."]),
+            (0, 2, &["..#..", ".###.", "#####", ".###.", "..#.."]),
+            (1, 2, &["..#..", ".###.", "##.##", ".###.", "..#.."]),
+            (2, 2, &["..#..", ".....", "#...#", ".....", "..#.."]),
+        ];
+        for (inner, outer, expected) in cases {
+            let mut framebuffer = Framebuffer::new(5, 5);
+            framebuffer.draw_ring(2, 2, inner, outer, 0xFFFFFF, FillStyle::OPAQUE);
+            if mask(&framebuffer) != expected {
+                return Err(format!("anillo {}..{}: {:?} en vez de {:?}", inner, outer, mask(&framebuffer), expected));
+            }
+        }
+
+        let (width, height) = (12, 9);
+        for radius in 0..8usize {
+            for (cx, cy) in [(5, 4), (0, 0), (-3, 4), (11, 10), (6, -2)] {
+                for inner in [0, radius / 2, radius] {
+                    let mut framebuffer = Framebuffer::new(width, height);
+                    framebuffer.draw_ring(cx, cy, inner, radius, 0xFFFFFF, FillStyle::OPAQUE);
+                    for y in 0..height {
+                        for x in 0..width {
+                            let distance = (x as isize - cx).pow(2) + (y as isize - cy).pow(2);
+                            let inside = distance >= (inner * inner) as isize && distance <= (radius * radius) as isize;
+                            if (framebuffer.buffer[y * width + x] != 0) != inside {
+                                return Err(format!("anillo {}..{} en ({}, {}): el píxel ({}, {}) no corresponde", inner, radius, cx, cy, x, y));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.fill_circle(-100, 4, 50, 0xFFFFFF, FillStyle::OPAQUE);
+        framebuffer.fill_circle(40, -40, 3, 0xFFFFFF, FillStyle::OPAQUE);
+        if framebuffer.buffer.iter().any(|&pixel| pixel != 0) {
+            return Err("un círculo fuera de la pantalla dibujó algo".to_string());
+        }
+
+        framebuffer.fill_circle_gradient(5, 4, 4, 0xFF0000, 0x0000FF, FillStyle::OPAQUE);
+        if framebuffer.read_pixel(5, 4) != Some(0xFF0000) || framebuffer.read_pixel(9, 4) != Some(0x0000FF) || framebuffer.read_pixel(7, 4) != Some(0x800080) {
+            return Err(format!("degradado: centro {:06X?}, mitad {:06X?}, borde {:06X?}", framebuffer.read_pixel(5, 4), framebuffer.read_pixel(7, 4), framebuffer.read_pixel(9, 4)));
+        }
+        framebuffer.fill_circle(5, 4, 0, 0x204060, FillStyle::OPAQUE);
+        framebuffer.fill_circle(5, 4, 0, 0x204060, FillStyle::OPAQUE.additive());
+        framebuffer.fill_circle(0, 0, 0, 0xFFFFFF, FillStyle::OPAQUE.alpha(0.5));
+        if framebuffer.read_pixel(5, 4) != Some(0x4080C0) || framebuffer.read_pixel(0, 0) != Some(0x808080) {
+            return Err(format!("mezcla: aditiva {:06X?}, al 50% {:06X?}", framebuffer.read_pixel(5, 4), framebuffer.read_pixel(0, 0)));
+        }
+
+        let mut framebuffer = Framebuffer::new(3, 1);
+        framebuffer.zbuffer[0] = 1.0;
+        framebuffer.fill_circle(1, 0, 1, 0xFFFFFF, FillStyle::OPAQUE.depth(2.0));
+        if framebuffer.buffer != [0, 0xFFFFFF, 0xFFFFFF] || framebuffer.zbuffer != [1.0, 2.0, 2.0] {
+            return Err(format!("con profundidad: {:06X?} {:?}", framebuffer.buffer, framebuffer.zbuffer));
+        }
+        Ok(())
+    }
+
+    // Un punto blanco a la derecha del centro: el verde queda en su lugar, el rojo sale hacia afuera
+    // (cada píxel lo toma de más adentro) y el azul hacia adentro; una imagen pareja no cambia
+    #[test]
+    fn chromatic_aberration() -> Result<(), String> {
+        let (width, height) = (101, 101);
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.buffer[50 * width + 90] = 0xFFFFFF;
+        let original = framebuffer.buffer.clone();
+        framebuffer.apply_chromatic_aberration(0.0);
+        if framebuffer.buffer != original {
+            return Err("con intensidad 0 el cuadro cambió".to_string());
+        }
+
+        framebuffer.apply_chromatic_aberration(0.1);
+        let row = &framebuffer.buffer[50 * width..51 * width];
+        let peak = |shift: u32| (0..width).max_by_key(|&x| (row[x] >> shift) & 0xFF).unwrap_or(0);
+        let (red, green, blue) = (peak(16), peak(8), peak(0));
+        if green != 90 || red <= 90 || blue >= 90 {
+            return Err(format!("el rojo quedó en x={}, el verde en x={} y el azul en x={}", red, green, blue));
+        }
+
+        let mut flat = Framebuffer::new(width, height);
+        flat.buffer.fill(0x336699);
+        flat.apply_chromatic_aberration(0.05);
+        if flat.buffer.iter().any(|&pixel| pixel != 0x336699) {
+            return Err("una imagen pareja cambió".to_string());
+        }
+        Ok(())
+    }
+
+    // FXAA sobre un disco liso: los píxeles del borde se mezclan con el fondo y los de adentro y
+    // afuera no cambian. Con un planeta real, su centro tampoco se desenfoca.
+    #[test]
+    fn fxaa() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let (size, radius, color) = (64usize, 20.0f32, 0xC08040);
+        let mut disc = Framebuffer::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let distance = Vec2::new(x as f32 + 0.5 - 32.0, y as f32 + 0.5 - 32.0).magnitude();
+                if distance < radius {
+                    disc.write_pixel(x, y, color);
+                }
+            }
+        }
+        let original = disc.buffer.clone();
+        disc.apply_fxaa();
+
+        let mut blended = 0;
+        for y in 0..size {
+            for x in 0..size {
+                let index = y * size + x;
+                let distance = Vec2::new(x as f32 + 0.5 - 32.0, y as f32 + 0.5 - 32.0).magnitude();
+                if (distance < radius - 2.0 || distance > radius + 2.0) && disc.buffer[index] != original[index] {
+                    return Err(format!("cambió el píxel ({}, {}), lejos del borde", x, y));
+                }
+                let pixel = Color::from_hex(disc.buffer[index]);
+                if disc.buffer[index] != original[index] {
+                    if pixel.r > 0xC0 || pixel.g > 0x80 || pixel.b > 0x40 {
+                        return Err(format!("el píxel ({}, {}) quedó fuera de la mezcla: {}", x, y, pixel));
+                    }
+                    blended += 1;
+                }
+            }
+        }
+        if blended < 16 {
+            return Err(format!("solo se suavizaron {} píxeles del borde", blended));
+        }
+
+        // Un planeta con su shader: el centro del disco no debe cambiar apenas
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let world_radius = obj.bounding_radius();
+        let mut framebuffer = Framebuffer::new(WIDTH as usize / 2, HEIGHT as usize / 2);
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+        let center = Vec3::new(0.0, 0.0, 0.0);
+        render_body(&mut framebuffer, &uniforms, &vertices, 5, center, world_radius);
+        let original = framebuffer.buffer.clone();
+        framebuffer.apply_fxaa();
+
+        let screen_radius = projected_radius(&uniforms, center, world_radius);
+        let (cx, cy) = (framebuffer.width as f32 * 0.5, framebuffer.height as f32 * 0.5);
+        let (mut inside, mut change) = (0, 0.0);
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                if Vec2::new(x as f32 + 0.5 - cx, y as f32 + 0.5 - cy).magnitude() < screen_radius * 0.5 {
+                    let (a, b) = (Color::from_hex(original[y * framebuffer.width + x]), Color::from_hex(framebuffer.buffer[y * framebuffer.width + x]));
+                    change += (a.r as f32 - b.r as f32).abs() + (a.g as f32 - b.g as f32).abs() + (a.b as f32 - b.b as f32).abs();
+                    inside += 1;
+                }
+            }
+        }
+        let average = change / (3.0 * inside.max(1) as f32);
+        if inside == 0 || average > 1.0 {
+            return Err(format!("el centro del planeta cambió {:.2} niveles en promedio", average));
+        }
+        Ok(())
+    }
+
+    // Las aristas que salen de la pantalla se dibujan recortadas, incluidas las que tienen
+    // coordenadas negativas; solo desaparecen las que quedan enteras afuera
+    #[test]
+    fn polygon_outline_clipping() -> Result<(), String> {
+        let mut framebuffer = Framebuffer::new(20, 20);
+        framebuffer.draw_polygon(&[(-10.0, 5.0), (10.0, 5.0), (10.0, 15.0), (-10.0, 15.0)], 0xFFFFFF);
+        let lit = |framebuffer: &Framebuffer, x: usize, y: usize| framebuffer.buffer[y * 20 + x] == 0xFFFFFF;
+        for (x, y) in [(0, 5), (9, 5), (10, 5), (10, 14), (1, 15), (10, 15)] {
+            if !lit(&framebuffer, x, y) {
+                return Err(format!("falta el píxel ({}, {}) del contorno", x, y));
+            }
+        }
+        let drawn = framebuffer.buffer.iter().filter(|&&pixel| pixel == 0xFFFFFF).count();
+        if drawn != 10 + 10 + 10 || lit(&framebuffer, 0, 10) || lit(&framebuffer, 5, 10) {
+            return Err(format!("el contorno tiene {} píxeles", drawn));
+        }
+
+        let mut outside = Framebuffer::new(20, 20);
+        outside.draw_polygon(&[(-10.0, -10.0), (-2.0, -10.0), (-2.0, 30.0)], 0xFFFFFF);
+        if outside.buffer.contains(&0xFFFFFF) {
+            return Err("un polígono fuera de la pantalla dibujó píxeles".to_string());
         }
+        Ok(())
     }
 }
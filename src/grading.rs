@@ -0,0 +1,242 @@
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+
+use crate::framebuffer::{Framebuffer, BAND_HEIGHT};
+
+// Cuánto se muestra el nombre del preset al cambiarlo
+const FLASH_DURATION: Duration = Duration::from_millis(1500);
+
+// Pesos de la luminancia (Rec. 709)
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradePreset {
+    Neutral,
+    Warm,
+    Cool,
+    // Solo rojo y más tenue, para mirar de noche sin perder la adaptación a la oscuridad
+    Night,
+    Grayscale,
+}
+
+impl GradePreset {
+    pub const ALL: [GradePreset; 5] = [GradePreset::Neutral, GradePreset::Warm, GradePreset::Cool, GradePreset::Night, GradePreset::Grayscale];
+
+    pub fn next(self) -> Self {
+        let index = GradePreset::ALL.iter().position(|&preset| preset == self).unwrap_or(0);
+        GradePreset::ALL[(index + 1) % GradePreset::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GradePreset::Neutral => "NEUTRO",
+            GradePreset::Warm => "CALIDO",
+            GradePreset::Cool => "FRIO",
+            GradePreset::Night => "NOCTURNO",
+            GradePreset::Grayscale => "GRISES",
+        }
+    }
+
+    // Nombre en inglés o en español, como en la línea de comandos
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "neutral" | "neutro" => Some(GradePreset::Neutral),
+            "warm" | "calido" | "cálido" => Some(GradePreset::Warm),
+            "cool" | "frio" | "frío" => Some(GradePreset::Cool),
+            "night" | "nocturno" => Some(GradePreset::Night),
+            "grayscale" | "grises" => Some(GradePreset::Grayscale),
+            _ => None,
+        }
+    }
+
+    // Matriz de color (filas: rojo, verde y azul de salida) y gamma de cada canal de salida
+    pub fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            GradePreset::Neutral => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            GradePreset::Warm => [[1.08, 0.04, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.86]],
+            GradePreset::Cool => [[0.9, 0.0, 0.0], [0.0, 1.0, 0.02], [0.0, 0.04, 1.1]],
+            GradePreset::Night => [[LUMA[0] * 0.8, LUMA[1] * 0.8, LUMA[2] * 0.8], [0.0; 3], [0.0; 3]],
+            GradePreset::Grayscale => [LUMA, LUMA, LUMA],
+        }
+    }
+
+    pub fn gamma(self) -> [f32; 3] {
+        match self {
+            GradePreset::Neutral | GradePreset::Grayscale => [1.0, 1.0, 1.0],
+            GradePreset::Warm => [0.95, 1.0, 1.05],
+            GradePreset::Cool => [1.05, 1.0, 0.95],
+            GradePreset::Night => [1.2, 1.0, 1.0],
+        }
+    }
+}
+
+// Etapa final de color, después de la exposición: la matriz del preset y una gamma por canal.
+// Se precalcula en tablas que solo se rehacen al cambiar de preset: por cada canal de entrada su
+// aporte a los tres de salida (en punto fijo, 8 bits de fracción), y por cada canal de salida su
+// gamma. Por píxel son tres búsquedas para la matriz y tres para la gamma.
+// `--grade <preset>` elige el inicial y `--grade-skip-hud` deja el HUD fuera.
+pub struct ColorGrading {
+    preset: GradePreset,
+    pub include_hud: bool,
+    mix: Box<[[[i32; 3]; 256]; 3]>,
+    gamma: Box<[[u8; 256]; 3]>,
+    changed_at: Option<Instant>,
+}
+
+impl ColorGrading {
+    pub fn new(preset: GradePreset) -> Self {
+        let mut grading = ColorGrading {
+            preset,
+            include_hud: true,
+            mix: Box::new([[[0; 3]; 256]; 3]),
+            gamma: Box::new([[0; 256]; 3]),
+            changed_at: None,
+        };
+        grading.rebuild();
+        grading
+    }
+
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let preset = match args.iter().position(|arg| arg == "--grade") {
+            None => GradePreset::Neutral,
+            Some(position) => args
+                .get(position + 1)
+                .and_then(|name| GradePreset::parse(name))
+                .ok_or("--grade espera neutral, warm, cool, night o grayscale")?,
+        };
+        let mut grading = ColorGrading::new(preset);
+        grading.include_hud = !args.iter().any(|arg| arg == "--grade-skip-hud");
+        Ok(grading)
+    }
+
+    pub fn set_preset(&mut self, preset: GradePreset, now: Instant) {
+        self.preset = preset;
+        self.changed_at = Some(now);
+        self.rebuild();
+    }
+
+    pub fn cycle(&mut self, now: Instant) {
+        self.set_preset(self.preset.next(), now);
+    }
+
+    // Nombre del preset mientras dura el aviso del último cambio
+    pub fn flash_label(&self, now: Instant) -> Option<&'static str> {
+        self.changed_at.filter(|&changed| now.duration_since(changed) < FLASH_DURATION).map(|_| self.preset.name())
+    }
+
+    fn rebuild(&mut self) {
+        let matrix = self.preset.matrix();
+        for (input, table) in self.mix.iter_mut().enumerate() {
+            for (value, contributions) in table.iter_mut().enumerate() {
+                *contributions = matrix.map(|row| (row[input] * value as f32 * 256.0).round() as i32);
+            }
+        }
+        for (table, exponent) in self.gamma.iter_mut().zip(self.preset.gamma()) {
+            for (value, entry) in table.iter_mut().enumerate() {
+                *entry = ((value as f32 / 255.0).powf(exponent) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    pub fn grade_pixel(&self, pixel: u32) -> u32 {
+        let (r, g, b) = (((pixel >> 16) & 0xFF) as usize, ((pixel >> 8) & 0xFF) as usize, (pixel & 0xFF) as usize);
+        let (mr, mg, mb) = (&self.mix[0][r], &self.mix[1][g], &self.mix[2][b]);
+        let channel = |output: usize| {
+            let mixed = ((mr[output] + mg[output] + mb[output] + 128) >> 8).clamp(0, 255) as usize;
+            self.gamma[output][mixed] as u32
+        };
+        channel(0) << 16 | channel(1) << 8 | channel(2)
+    }
+
+    // Aplica el preset a todo el frame; el neutro no lo toca
+    pub fn apply(&self, framebuffer: &mut Framebuffer) {
+        if self.preset == GradePreset::Neutral {
+            return;
+        }
+        framebuffer.split_rows_mut(BAND_HEIGHT).into_par_iter().for_each(|band| {
+            for pixel in band.buffer.iter_mut() {
+                *pixel = self.grade_pixel(*pixel);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_grading() -> Result<(), String> {
+        let samples = [0x000000, 0xFFFFFF, 0x808080, 0xFF0000, 0x00FF00, 0x0000FF, 0x3A7FC1, 0xE0A040];
+        let channels = |pixel: u32| ((pixel >> 16) & 0xFF, (pixel >> 8) & 0xFF, pixel & 0xFF);
+
+        let neutral = ColorGrading::new(GradePreset::Neutral);
+        if let Some(&pixel) = samples.iter().find(|&&pixel| neutral.grade_pixel(pixel) != pixel) {
+            return Err(format!("el neutro cambió {:06X} a {:06X}", pixel, neutral.grade_pixel(pixel)));
+        }
+
+        let night = ColorGrading::new(GradePreset::Night);
+        for &pixel in &samples {
+            let (red, green, blue) = channels(night.grade_pixel(pixel));
+            let (r, g, b) = channels(pixel);
+            if green != 0 || blue != 0 || red > r.max(g).max(b) {
+                return Err(format!("el nocturno dio {:06X} para {:06X}", night.grade_pixel(pixel), pixel));
+            }
+        }
+        if channels(night.grade_pixel(0xFFFFFF)).0 < 128 {
+            return Err("en el nocturno el blanco quedó casi negro".to_string());
+        }
+
+        let gray = ColorGrading::new(GradePreset::Grayscale);
+        if let Some(&pixel) = samples.iter().find(|&&pixel| {
+            let (red, green, blue) = channels(gray.grade_pixel(pixel));
+            red != green || green != blue
+        }) {
+            return Err(format!("en grises {:06X} quedó {:06X}", pixel, gray.grade_pixel(pixel)));
+        }
+
+        let (red, _, blue) = channels(ColorGrading::new(GradePreset::Warm).grade_pixel(0x808080));
+        let (cool_red, _, cool_blue) = channels(ColorGrading::new(GradePreset::Cool).grade_pixel(0x808080));
+        if red <= blue || cool_red >= cool_blue {
+            return Err(format!("el gris quedó {:02X}/{:02X} en cálido y {:02X}/{:02X} en frío (rojo/azul)", red, blue, cool_red, cool_blue));
+        }
+
+        let mut framebuffer = Framebuffer::new(8, 40);
+        framebuffer.buffer = (0..320).map(|index| samples[index % samples.len()]).collect();
+        gray.apply(&mut framebuffer);
+        if framebuffer.buffer.iter().enumerate().any(|(index, &pixel)| pixel != gray.grade_pixel(samples[index % samples.len()])) {
+            return Err("aplicar al frame no coincide con el píxel a píxel".to_string());
+        }
+
+        if GradePreset::parse("Nocturno") != Some(GradePreset::Night) || GradePreset::parse("frío") != Some(GradePreset::Cool) || GradePreset::parse("sepia").is_some() {
+            return Err("no se reconocen bien los nombres de los presets".to_string());
+        }
+        let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<String>>();
+        if ColorGrading::from_args(&args("--grade sepia")).is_ok() || ColorGrading::from_args(&args("--grade")).is_ok() {
+            return Err("--grade aceptó un preset que no existe".to_string());
+        }
+        let configured = ColorGrading::from_args(&args("--grade night --grade-skip-hud"))?;
+        if configured.preset != GradePreset::Night || configured.include_hud || !ColorGrading::from_args(&[])?.include_hud {
+            return Err("--grade night --grade-skip-hud no quedó en nocturno sin el HUD".to_string());
+        }
+
+        let start = Instant::now();
+        let mut grading = ColorGrading::new(GradePreset::Neutral);
+        if grading.flash_label(start).is_some() {
+            return Err("se anunció el preset sin haberlo cambiado".to_string());
+        }
+        let names: Vec<&str> = (0..GradePreset::ALL.len())
+            .map(|_| {
+                grading.cycle(start);
+                grading.preset.name()
+            })
+            .collect();
+        if names != ["CALIDO", "FRIO", "NOCTURNO", "GRISES", "NEUTRO"] {
+            return Err(format!("los presets se recorren en el orden {:?}", names));
+        }
+        if grading.flash_label(start) != Some("NEUTRO") || grading.flash_label(start + Duration::from_secs(2)).is_some() {
+            return Err("el nombre del preset no se muestra solo un momento".to_string());
+        }
+        Ok(())
+    }
+}
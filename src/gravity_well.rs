@@ -0,0 +1,161 @@
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+
+use crate::framebuffer::{Framebuffer, FramebufferBand, BAND_HEIGHT};
+use crate::uniforms::Uniforms;
+use crate::{Planet, body_position, project_point};
+
+// Líneas por lado de la malla; el coste es proporcional a GRID_LINES²
+const GRID_LINES: usize = 60;
+
+// Extensión (mitad del lado) de la malla según la altura de la cámara, para que no
+// desaparezca al alejarse ni tape la vista al acercarse
+const EXTENT_PER_HEIGHT: f32 = 0.45;
+const MIN_EXTENT: f32 = 40.0;
+const MAX_EXTENT: f32 = 320.0;
+
+// Escala del hundimiento; la masa se aproxima como radio³ (densidad uniforme)
+const WELL_STRENGTH: f32 = 1.5;
+
+const GRID_COLOR: (f32, f32, f32) = (70.0, 130.0, 200.0);
+const MIN_BRIGHTNESS: f32 = 0.35;
+
+// Segmentos más largos que esto (en píxeles) se descartan: son puntos casi detrás de la cámara
+const MAX_SEGMENT_LENGTH: f32 = 2048.0;
+
+// Visualización de "lámina elástica": una malla en el plano orbital hundida cerca de cada
+// cuerpo. Solo se dibuja en la vista bird eye; se activa con la tecla G.
+pub struct GravityWellGrid {
+    pub enabled: bool,
+    // Puntos proyectados (x, y de pantalla y profundidad de vista), reutilizados entre frames
+    projected: Vec<Option<Vec3>>,
+    // Segmentos visibles del frame, que cada franja del framebuffer recorta a sus filas
+    segments: Vec<GridSegment>,
+}
+
+// Tramo de la malla entre dos puntos proyectados, con el color que le da su profundidad
+struct GridSegment {
+    start: Vec3,
+    end: Vec3,
+    color: u32,
+}
+
+impl GravityWellGrid {
+    pub fn new() -> Self {
+        GravityWellGrid {
+            enabled: false,
+            projected: Vec::with_capacity((GRID_LINES + 1) * (GRID_LINES + 1)),
+            segments: Vec::with_capacity(2 * GRID_LINES * (GRID_LINES + 1)),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn render(&mut self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, planets: &[Planet], time: f64) {
+        let extent = (uniforms.view_pos.y.abs() * EXTENT_PER_HEIGHT).clamp(MIN_EXTENT, MAX_EXTENT);
+        let spacing = 2.0 * extent / GRID_LINES as f32;
+
+        let bodies: Vec<(Vec3, f32)> = (0..planets.len())
+            .map(|index| (body_position(planets, index, time), planets[index].radius))
+            .collect();
+
+        self.projected.clear();
+        let (mut nearest, mut farthest) = (f32::INFINITY, 0.0f32);
+        for row in 0..=GRID_LINES {
+            for column in 0..=GRID_LINES {
+                let x = -extent + column as f32 * spacing;
+                let z = -extent + row as f32 * spacing;
+                let point = Vec3::new(x, -well_depth(&bodies, x, z), z);
+
+                let screen = project_point(uniforms, point).filter(|p| p.z > 0.0 && p.z.is_finite());
+                if let Some(p) = screen {
+                    nearest = nearest.min(p.z);
+                    farthest = farthest.max(p.z);
+                }
+                self.projected.push(screen);
+            }
+        }
+
+        let depth_range = (farthest - nearest).max(f32::EPSILON);
+        let stride = GRID_LINES + 1;
+        self.segments.clear();
+        for row in 0..=GRID_LINES {
+            for column in 0..=GRID_LINES {
+                let index = row * stride + column;
+                if column < GRID_LINES {
+                    self.push_segment(index, index + 1, nearest, depth_range);
+                }
+                if row < GRID_LINES {
+                    self.push_segment(index, index + stride, nearest, depth_range);
+                }
+            }
+        }
+
+        // Cada franja dibuja la parte de los segmentos que cae en sus filas
+        let segments = &self.segments;
+        framebuffer.split_rows_mut(BAND_HEIGHT).into_par_iter().for_each(|mut band| {
+            for segment in segments {
+                segment.draw(&mut band);
+            }
+        });
+    }
+
+    fn push_segment(&mut self, a: usize, b: usize, nearest: f32, depth_range: f32) {
+        let (start, end) = match (self.projected[a], self.projected[b]) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return,
+        };
+
+        let length = (end.x - start.x).abs().max((end.y - start.y).abs());
+        if length > MAX_SEGMENT_LENGTH {
+            return;
+        }
+
+        // Lo más lejano se ve más tenue para dar sensación de profundidad
+        let t = (((start.z + end.z) * 0.5 - nearest) / depth_range).clamp(0.0, 1.0);
+        let brightness = 1.0 - (1.0 - MIN_BRIGHTNESS) * t;
+        let color = ((GRID_COLOR.0 * brightness) as u32) << 16
+            | ((GRID_COLOR.1 * brightness) as u32) << 8
+            | (GRID_COLOR.2 * brightness) as u32;
+        self.segments.push(GridSegment { start, end, color });
+    }
+}
+
+impl GridSegment {
+    fn draw(&self, band: &mut FramebufferBand) {
+        let (start, end) = (self.start, self.end);
+        let rows = band.rows();
+        if start.y.max(end.y) < rows.start as f32 || start.y.min(end.y) >= rows.end as f32 {
+            return;
+        }
+        band.set_current_color(self.color);
+
+        let length = (end.x - start.x).abs().max((end.y - start.y).abs());
+        let steps = length.ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let x = start.x + (end.x - start.x) * t;
+            let y = start.y + (end.y - start.y) * t;
+            if x < 0.0 || y < 0.0 {
+                continue;
+            }
+            // La profundidad es distancia de vista: se interpola su inverso
+            let depth = 1.0 / ((1.0 - t) / start.z + t / end.z);
+            band.point(x as usize, y as usize, depth);
+        }
+    }
+}
+
+// Hundimiento en (x, z): suma de masa / distancia de cada cuerpo; dentro del radio del cuerpo
+// la distancia se limita al radio para que el pozo no diverja
+fn well_depth(bodies: &[(Vec3, f32)], x: f32, z: f32) -> f32 {
+    bodies
+        .iter()
+        .map(|(position, radius)| {
+            let distance = ((x - position.x).powi(2) + (z - position.z).powi(2)).sqrt();
+            WELL_STRENGTH * radius.powi(3) / distance.max(*radius)
+        })
+        .sum()
+}
@@ -0,0 +1,315 @@
+use crate::Framebuffer;
+use crate::framebuffer::BarColors;
+use crate::overlay::Overlay;
+
+// Medidas de la fuente 8x8 y del espaciado interno de los paneles
+const GLYPH_SIZE: usize = 8;
+const PADDING: usize = 8;
+const ROW_GAP: usize = 6;
+const SEPARATOR_HEIGHT: usize = 7;
+
+const BACKGROUND_COLOR: u32 = 0x000000;
+const BACKGROUND_ALPHA: f32 = 0.6;
+const TITLE_COLOR: u32 = 0xFFD700;
+const LABEL_COLOR: u32 = 0xAAAAAA;
+const VALUE_COLOR: u32 = 0xFFFFFF;
+const BAR_COLOR: u32 = 0x4682B4;
+const BAR_BACKGROUND: u32 = 0x303030;
+const SEPARATOR_COLOR: u32 = 0x606060;
+
+// Alto contraste: escala mínima del texto y color único de todo lo que se lee
+const HIGH_CONTRAST_MIN_SCALE: usize = 4;
+const HIGH_CONTRAST_COLOR: u32 = 0xFFFFFF;
+
+// Estilo del HUD, para quien necesita más legibilidad (`--high-contrast` o F8). En alto
+// contraste el texto es blanco puro y de escala 4 o más, los fondos son negros y opacos, las
+// órbitas se dibujan más gruesas y se apagan los efectos de poco contraste (la niebla).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Contrast {
+    Normal,
+    High,
+}
+
+impl Contrast {
+    pub fn from_args(args: &[String]) -> Self {
+        if args.iter().any(|arg| arg == "--high-contrast") { Contrast::High } else { Contrast::Normal }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Contrast::Normal => Contrast::High,
+            Contrast::High => Contrast::Normal,
+        }
+    }
+
+    pub fn is_high(self) -> bool {
+        self == Contrast::High
+    }
+
+    // Escala con que se dibuja un texto pensado para `scale`
+    pub fn text_scale(self, scale: usize) -> usize {
+        match self {
+            Contrast::Normal => scale.max(1),
+            Contrast::High => scale.max(HIGH_CONTRAST_MIN_SCALE),
+        }
+    }
+
+    pub fn text_color(self, color: u32) -> u32 {
+        match self {
+            Contrast::Normal => color,
+            Contrast::High => HIGH_CONTRAST_COLOR,
+        }
+    }
+
+    // Opacidad de fondos y textos compuestos sobre la escena: en alto contraste no se transparentan
+    pub fn opacity(self, alpha: f32) -> f32 {
+        match self {
+            Contrast::Normal => alpha,
+            Contrast::High => 1.0,
+        }
+    }
+
+    // Niebla y demás efectos que bajan el contraste de la escena
+    pub fn low_contrast_effects(self) -> bool {
+        self == Contrast::Normal
+    }
+}
+
+enum Item {
+    Title(String),
+    Row(String, String),
+    Bar(String, f32),
+    Separator,
+}
+
+// Panel de HUD en modo inmediato: se arma cada frame con `row`, `bar`, `separator`...
+// y se dibuja con `draw`. El alto se calcula a partir del contenido, el fondo oscurece
+// lo que hay detrás y todo se recorta al framebuffer.
+pub struct Panel {
+    x: usize,
+    y: usize,
+    width: usize,
+    scale: usize,
+    contrast: Contrast,
+    // Opacidad de todo el panel (fondo y texto), para apagarlo de a poco
+    fade: f32,
+    items: Vec<Item>,
+}
+
+impl Panel {
+    pub fn new(x: usize, y: usize, width: usize) -> Self {
+        Panel { x, y, width, scale: 1, contrast: Contrast::Normal, fade: 1.0, items: Vec::new() }
+    }
+
+    // Escala del texto de las filas; los títulos usan una más
+    pub fn scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    // En alto contraste la escala sube al mínimo legible y los colores pasan a blanco sobre negro
+    pub fn contrast(mut self, contrast: Contrast) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    // Apaga el panel hacia el fondo (1.0 es opaco); en alto contraste no se apaga
+    pub fn fade(mut self, opacity: f32) -> Self {
+        self.fade = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn title(mut self, text: &str) -> Self {
+        self.items.push(Item::Title(text.to_string()));
+        self
+    }
+
+    // Etiqueta a la izquierda y valor alineado a la derecha
+    pub fn row(mut self, label: &str, value: &str) -> Self {
+        self.items.push(Item::Row(label.to_string(), value.to_string()));
+        self
+    }
+
+    // Barra de progreso con `fraction` entre 0.0 y 1.0
+    pub fn bar(mut self, label: &str, fraction: f32) -> Self {
+        self.items.push(Item::Bar(label.to_string(), fraction.clamp(0.0, 1.0)));
+        self
+    }
+
+    pub fn separator(mut self) -> Self {
+        self.items.push(Item::Separator);
+        self
+    }
+
+    // Ancho justo para que `text` quepa como título con el relleno del panel
+    pub fn title_width(text: &str, scale: usize) -> usize {
+        Framebuffer::measure_text(text, scale.max(1) + 1).0 + 2 * PADDING
+    }
+
+    // Escala de las filas con el contraste aplicado
+    fn text_scale(&self) -> usize {
+        self.contrast.text_scale(self.scale)
+    }
+
+    fn item_height(&self, item: &Item) -> usize {
+        match item {
+            Item::Title(_) => GLYPH_SIZE * (self.text_scale() + 1) + ROW_GAP,
+            Item::Row(_, _) | Item::Bar(_, _) => GLYPH_SIZE * self.text_scale() + ROW_GAP,
+            Item::Separator => SEPARATOR_HEIGHT,
+        }
+    }
+
+    // Posición y (absoluta) de cada elemento, en el orden en que se agregaron
+    pub fn item_offsets(&self) -> Vec<usize> {
+        let mut offsets = Vec::with_capacity(self.items.len());
+        let mut y = self.y + PADDING;
+        for item in &self.items {
+            offsets.push(y);
+            y += self.item_height(item);
+        }
+        offsets
+    }
+
+    // Alto total: relleno arriba y abajo más los elementos (sin el espacio sobrante de la última fila)
+    pub fn height(&self) -> usize {
+        let content: usize = self.items.iter().map(|item| self.item_height(item)).sum();
+        let trailing_gap = match self.items.last() {
+            Some(Item::Separator) | None => 0,
+            Some(_) => ROW_GAP,
+        };
+        2 * PADDING + content - trailing_gap
+    }
+
+    // Rectángulo (x, y, ancho, alto) que ocupa, sin recortar
+    pub fn bounds(&self) -> (usize, usize, usize, usize) {
+        (self.x, self.y, self.width, self.height())
+    }
+
+    // Se dibuja en la capa del HUD, que es la que sabe mezclar el fondo translúcido
+    pub fn draw(&self, overlay: &mut Overlay) {
+        let (x, y, width, height) = self.bounds();
+        if x >= overlay.width() || y >= overlay.height() {
+            return;
+        }
+        let (width, height) = (width.min(overlay.width() - x), height.min(overlay.height() - y));
+        let fade = if self.contrast.is_high() { 1.0 } else { self.fade };
+        overlay.blend_rect(self.x, self.y, width, height, BACKGROUND_COLOR, self.contrast.opacity(BACKGROUND_ALPHA) * fade);
+        let framebuffer = overlay.region(self.x, self.y, width, height);
+
+        let left = self.x + PADDING;
+        let right = self.x + width.saturating_sub(PADDING);
+        let inner_width = right.saturating_sub(left);
+        let scale = self.text_scale();
+        let color = |color: u32| dim(self.contrast.text_color(color), fade);
+
+        for (item, y) in self.items.iter().zip(self.item_offsets()) {
+            match item {
+                Item::Title(text) => {
+                    let text = fit(text, inner_width, scale + 1);
+                    framebuffer.draw_text(left, y, &text, color(TITLE_COLOR), scale + 1);
+                }
+                Item::Row(label, value) => {
+                    let value = fit(value, inner_width, scale);
+                    let value_width = Framebuffer::measure_text(&value, scale).0;
+                    let label = fit(label, inner_width.saturating_sub(value_width + GLYPH_SIZE * scale), scale);
+                    framebuffer.draw_text(left, y, &label, color(LABEL_COLOR), scale);
+                    framebuffer.draw_text_right_aligned(right, y, &value, color(VALUE_COLOR), scale);
+                }
+                Item::Bar(label, fraction) => {
+                    // La etiqueta ocupa la mitad izquierda y la barra la derecha
+                    let half = inner_width / 2;
+                    let label = fit(label, half.saturating_sub(GLYPH_SIZE * scale), scale);
+                    framebuffer.draw_text(left, y, &label, color(LABEL_COLOR), scale);
+
+                    let bar_height = GLYPH_SIZE * scale;
+                    framebuffer.render_progress_bar(left + half, y, inner_width - half, bar_height, *fraction, BarColors { fill: color(BAR_COLOR), empty: dim(BAR_BACKGROUND, fade) });
+                }
+                Item::Separator => {
+                    framebuffer.draw_filled_rect(left, y + SEPARATOR_HEIGHT / 2, inner_width, 1, color(SEPARATOR_COLOR));
+                }
+            }
+        }
+    }
+}
+
+// Color multiplicado por `amount` por canal: sobre el fondo negro equivale a transparentarlo
+fn dim(color: u32, amount: f32) -> u32 {
+    let channel = |shift: u32| (((color >> shift) & 0xFF) as f32 * amount).round() as u32;
+    (channel(16) << 16) | (channel(8) << 8) | channel(0)
+}
+
+// Recorta `text` a los caracteres que caben en `max_width` píxeles (la fuente es monoespaciada)
+fn fit(text: &str, max_width: usize, scale: usize) -> String {
+    let max_chars = max_width / (GLYPH_SIZE * scale);
+    text.chars().take(max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // En alto contraste el panel es opaco y solo tiene blanco puro sobre negro, con texto de
+    // escala 4 o más (el título, una más)
+    #[test]
+    fn high_contrast_panel() -> Result<(), String> {
+        let mut framebuffer = Framebuffer::new(400, 200);
+        framebuffer.buffer.iter_mut().for_each(|pixel| *pixel = 0x3366AA);
+        let panel = Panel::new(0, 0, 400)
+            .contrast(Contrast::High)
+            .title("MARTE")
+            .separator()
+            .row("LUNAS", "2");
+        let mut overlay = Overlay::new(400, 200);
+        panel.draw(&mut overlay);
+        overlay.composite(&mut framebuffer, 1.0);
+
+        let height = panel.height();
+        if height < 2 * 8 + 8 * 5 + 8 * 4 {
+            return Err(format!("alto {} px: el texto no crece a escala 4", height));
+        }
+        let inside = framebuffer.buffer[..height * 400].iter();
+        if let Some(pixel) = inside.clone().find(|&&pixel| pixel != 0x000000 && pixel != 0xFFFFFF) {
+            return Err(format!("color {:06X} dentro del panel", pixel));
+        }
+        if !inside.clone().any(|&pixel| pixel == 0xFFFFFF) {
+            return Err("el panel no tiene texto".to_string());
+        }
+        Ok(())
+    }
+
+    // Cada elemento empieza donde termina el anterior, y el alto cierra con el mismo relleno de
+    // arriba bajo el texto de la última fila (sin su espacio entre filas)
+    #[test]
+    fn panel_layout() -> Result<(), String> {
+        // Escala 2: el título mide 8·3 + 6, las filas y las barras 8·2 + 6
+        let panel = Panel::new(10, 20, 200).scale(2).title("MARTE").row("LUNAS", "2").bar("ORBITA", 0.5).separator().row("RADIO", "1");
+        let offsets = panel.item_offsets();
+        if offsets != vec![28, 58, 80, 102, 109] {
+            return Err(format!("posiciones {:?}", offsets));
+        }
+        let last_text_bottom = offsets[4] + 8 * 2;
+        if panel.height() != 113 || panel.y + panel.height() != last_text_bottom + PADDING {
+            return Err(format!("alto {} px, el texto termina en {}", panel.height(), last_text_bottom));
+        }
+        if panel.bounds() != (10, 20, 200, 113) {
+            return Err(format!("rectángulo {:?}", panel.bounds()));
+        }
+
+        // Un separador al final no deja espacio entre filas; vacío es solo el relleno
+        let closed = Panel::new(0, 0, 100).row("LUNAS", "2").separator();
+        if closed.height() != 2 * PADDING + 8 + ROW_GAP + SEPARATOR_HEIGHT {
+            return Err(format!("con un separador al final mide {} px", closed.height()));
+        }
+        let empty = Panel::new(0, 0, 100);
+        if empty.height() != 2 * PADDING || !empty.item_offsets().is_empty() {
+            return Err(format!("vacío mide {} px", empty.height()));
+        }
+
+        // En alto contraste las filas crecen a escala 4 aunque se pida menos
+        let high = Panel::new(0, 0, 100).contrast(Contrast::High).row("LUNAS", "2").row("RADIO", "1");
+        if high.item_offsets() != vec![PADDING, PADDING + 8 * 4 + ROW_GAP] {
+            return Err(format!("en alto contraste las posiciones son {:?}", high.item_offsets()));
+        }
+        Ok(())
+    }
+}
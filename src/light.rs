@@ -1,10 +1,19 @@
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{Vec2, Vec3};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::f32::consts::PI;
+
 use crate::color::Color;
+use crate::texture::spherical_uv;
+
+// Lado en píxeles de cada cara del cubo de la sonda de luz
+const PROBE_FACE_SIZE: u32 = 128;
 
 pub struct Light {
     pub position: Vec3,
     pub color: Color,
     pub intensity: f32,
+    // Fracción del disco de la luz que ve el fragmento (menos de 1 en un eclipse)
+    pub visibility: f32,
 }
 
 impl Light {
@@ -13,6 +22,7 @@ impl Light {
             position,
             color,
             intensity,
+            visibility: 1.0,
         }
     }
 }
@@ -23,6 +33,269 @@ impl Light {
             position: Vec3::new(0.0, 0.0, 0.0), // Posición en el centro del sistema
             color: Color::new(255, 229, 179),   // Color cálido del Sol en formato RGB
             intensity: 1.5,                     // Intensidad alta para simular la luz solar
+            visibility: 1.0,
+        }
+    }
+}
+
+// Sonda de luz: la radiancia del fondo (el skybox) en un cubo de seis caras, para que los
+// shaders puedan consultar qué se ve en cualquier dirección (reflejos del entorno).
+// Orden de las caras: +X, -X, +Y, -Y, +Z, -Z.
+pub struct LightProbe {
+    pub faces: [DynamicImage; 6],
+}
+
+impl LightProbe {
+    // Proyecta el skybox equirectangular sobre las seis caras
+    pub fn capture(skybox: &DynamicImage) -> LightProbe {
+        LightProbe::from_fn(|direction| sample_bilinear(skybox, spherical_uv(direction)))
+    }
+
+    // Llena las caras con la radiancia que da `radiance` en cada dirección (cubemaps y cielos
+    // sin imagen)
+    pub fn from_fn(radiance: impl Fn(Vec3) -> Color) -> LightProbe {
+        let face = |index: usize| {
+            let image = RgbaImage::from_fn(PROBE_FACE_SIZE, PROBE_FACE_SIZE, |x, y| {
+                let u = (x as f32 + 0.5) / PROBE_FACE_SIZE as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / PROBE_FACE_SIZE as f32 * 2.0 - 1.0;
+                let color = radiance(face_direction(index, u, v));
+                Rgba([color.r, color.g, color.b, 255])
+            });
+            DynamicImage::ImageRgba8(image)
+        };
+
+        LightProbe { faces: [face(0), face(1), face(2), face(3), face(4), face(5)] }
+    }
+
+    // Radiancia que llega desde `direction` (no hace falta que sea unitaria)
+    pub fn sample(&self, direction: Vec3) -> Color {
+        sample_cube(&self.faces, direction)
+    }
+}
+
+// Color de un cubo de seis caras (en el orden de la sonda) en la dirección `direction`: la cara
+// del eje dominante, muestreada en el punto donde la atraviesa
+pub fn sample_cube(faces: &[DynamicImage; 6], direction: Vec3) -> Color {
+    let (index, u, v) = face_coordinates(direction);
+    sample_bilinear(&faces[index], Vec2::new((u + 1.0) * 0.5, (v + 1.0) * 0.5))
+}
+
+// Dirección que corresponde al punto (u, v) en [-1, 1] de una cara
+fn face_direction(index: usize, u: f32, v: f32) -> Vec3 {
+    match index {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    }
+}
+
+// Inversa de `face_direction`: la cara del eje dominante y el punto (u, v) en ella
+fn face_coordinates(direction: Vec3) -> (usize, f32, f32) {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+    let (index, major, sc, tc) = if ax >= ay && ax >= az {
+        if x > 0.0 { (0, ax, -z, -y) } else { (1, ax, z, -y) }
+    } else if ay >= az {
+        if y > 0.0 { (2, ay, x, z) } else { (3, ay, x, -z) }
+    } else if z > 0.0 {
+        (4, az, x, -y)
+    } else {
+        (5, az, -x, -y)
+    };
+
+    let major = major.max(f32::EPSILON);
+    (index, sc / major, tc / major)
+}
+
+// Muestreo bilineal con las coordenadas (0.0 a 1.0) recortadas al borde de la imagen
+pub fn sample_bilinear(image: &DynamicImage, uv: Vec2) -> Color {
+    let (width, height) = image.dimensions();
+    let x = (uv.x.clamp(0.0, 1.0) * width as f32 - 0.5).clamp(0.0, (width - 1) as f32);
+    let y = (uv.y.clamp(0.0, 1.0) * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let pixel = |px: u32, py: u32| {
+        let p = image.get_pixel(px, py);
+        Color::new(p[0], p[1], p[2])
+    };
+    let top = pixel(x0, y0).lerp(&pixel(x1, y0), tx);
+    let bottom = pixel(x0, y1).lerp(&pixel(x1, y1), tx);
+    top.lerp(&bottom, ty)
+}
+
+// Esfera que puede tapar el Sol a otro cuerpo, en coordenadas de mundo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Occluder {
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+// Por debajo de esta fracción de luz el eclipse se considera total
+pub const TOTAL_ECLIPSE_FACTOR: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Eclipse {
+    Total,
+    // El que tapa se ve más chico que la luz y queda entero dentro de su disco
+    Annular,
+    Partial,
+}
+
+// Radios angulares de la luz y del que la tapa, y separación entre sus centros, vistos desde
+// `point`. `None` si el que tapa no está entre el punto y la luz.
+fn eclipse_geometry(point: Vec3, light_pos: Vec3, light_radius: f32, blocker_pos: Vec3, blocker_radius: f32) -> Option<(f32, f32, f32)> {
+    let (to_light, to_blocker) = (light_pos - point, blocker_pos - point);
+    let (light_distance, blocker_distance) = (to_light.magnitude(), to_blocker.magnitude());
+    if blocker_distance <= blocker_radius || blocker_distance >= light_distance {
+        return None;
+    }
+    let light = (light_radius / light_distance).min(1.0).asin();
+    let blocker = (blocker_radius / blocker_distance).min(1.0).asin();
+    let separation = (to_light.dot(&to_blocker) / (light_distance * blocker_distance)).clamp(-1.0, 1.0).acos();
+    Some((light, blocker, separation))
+}
+
+// Área que comparten dos discos de radios `a` y `b` con centros a distancia `d`, cuando se cortan
+fn lens_area(a: f32, b: f32, d: f32) -> f32 {
+    let alpha = ((d * d + a * a - b * b) / (2.0 * d * a)).clamp(-1.0, 1.0).acos();
+    let beta = ((d * d + b * b - a * a) / (2.0 * d * b)).clamp(-1.0, 1.0).acos();
+    let kite = ((-d + a + b) * (d + a - b) * (d - a + b) * (d + a + b)).max(0.0).sqrt();
+    a * a * alpha + b * b * beta - 0.5 * kite
+}
+
+// Luz que le llega a `point` de una fuente esférica tapada por otra esfera: compara sus tamaños
+// angulares y devuelve la fracción visible del disco de la luz. 0 en la umbra, 1 a plena luz y
+// en medio en la penumbra (o en un eclipse anular, con el que tapa entero dentro del disco).
+pub fn shadow_factor(fragment_pos: Vec3, light_pos: Vec3, light_radius: f32, blocker_pos: Vec3, blocker_radius: f32) -> f32 {
+    let Some((light, blocker, separation)) = eclipse_geometry(fragment_pos, light_pos, light_radius, blocker_pos, blocker_radius) else {
+        return 1.0;
+    };
+    if separation >= light + blocker || light <= 0.0 {
+        1.0
+    } else if separation <= blocker - light {
+        0.0
+    } else if separation <= light - blocker {
+        1.0 - (blocker * blocker) / (light * light)
+    } else {
+        (1.0 - lens_area(light, blocker, separation) / (PI * light * light)).clamp(0.0, 1.0)
+    }
+}
+
+// Qué eclipse se ve desde `point`, si hay alguno
+pub fn eclipse_at(point: Vec3, light_pos: Vec3, light_radius: f32, blocker_pos: Vec3, blocker_radius: f32) -> Option<Eclipse> {
+    let (light, blocker, separation) = eclipse_geometry(point, light_pos, light_radius, blocker_pos, blocker_radius)?;
+    if separation >= light + blocker {
+        None
+    } else if shadow_factor(point, light_pos, light_radius, blocker_pos, blocker_radius) < TOTAL_ECLIPSE_FACTOR {
+        Some(Eclipse::Total)
+    } else if separation <= light - blocker {
+        Some(Eclipse::Annular)
+    } else {
+        Some(Eclipse::Partial)
+    }
+}
+
+// Punto de la superficie de la esfera (`center`, `radius`) más cerca del eje de la sombra que
+// proyecta `blocker_pos` desde la luz: donde el eclipse es más profundo
+pub fn deepest_shadow_point(center: Vec3, radius: f32, light_pos: Vec3, blocker_pos: Vec3) -> Vec3 {
+    let axis = (blocker_pos - light_pos).try_normalize(1e-6).unwrap_or(Vec3::new(1.0, 0.0, 0.0));
+    let along = (center - blocker_pos).dot(&axis).max(0.0);
+    let on_axis = blocker_pos + axis * along;
+    center + (on_axis - center).try_normalize(1e-6).unwrap_or(-axis) * radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fragment::Fragment;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::test_uniforms;
+    use crate::shaders::select_shader;
+
+    // Con un fondo sintético cuyo color codifica las coordenadas equirectangulares, la sonda debe
+    // devolver en cada dirección el color que le corresponde (lejos de la costura y los polos)
+    #[test]
+    fn light_probe() -> Result<(), String> {
+        let skybox = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(512, 256, |x, y| {
+            image::Rgba([(x * 255 / 511) as u8, y as u8, 0, 255])
+        }));
+        let probe = LightProbe::capture(&skybox);
+
+        let directions = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.3, 0.5, 1.0),
+            Vec3::new(0.2, -0.7, -1.0),
+            Vec3::new(1.0, 0.9, -0.4),
+            Vec3::new(-0.5, 0.2, 0.8),
+            Vec3::new(0.1, 1.0, 0.3),
+        ];
+        for direction in directions {
+            let uv = spherical_uv(direction);
+            let color = probe.sample(direction);
+            let (expected_r, expected_g) = (uv.x * 255.0, uv.y * 255.0);
+            if (color.r as f32 - expected_r).abs() > 6.0 || (color.g as f32 - expected_g).abs() > 6.0 {
+                return Err(format!("en {:?} se obtuvo ({}, {}), se esperaba ({:.0}, {:.0})", direction, color.r, color.g, expected_r, expected_g));
+            }
+        }
+        Ok(())
+    }
+
+    // Desde el origen, con el Sol (radio angular 0.01) en +X: la umbra, la penumbra que crece hacia
+    // afuera sin saltos, el eclipse anular y lo que no se interpone
+    #[test]
+    fn eclipse_shadows() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let (point, sun) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(100.0, 0.0, 0.0));
+        let factor = |blocker: Vec3, radius: f32| shadow_factor(point, sun, 1.0, blocker, radius);
+
+        if factor(Vec3::new(10.0, 0.0, 0.0), 0.2) != 0.0 || eclipse_at(point, sun, 1.0, Vec3::new(10.0, 0.0, 0.0), 0.2) != Some(Eclipse::Total) {
+            return Err(format!("con el Sol tapado entero queda {}", factor(Vec3::new(10.0, 0.0, 0.0), 0.2)));
+        }
+        let mut previous = 0.0;
+        for step in 0..=40 {
+            let offset = 0.05 + 0.3 * step as f32 / 40.0;
+            let value = factor(Vec3::new(10.0, offset, 0.0), 0.2);
+            if value < previous - 1e-4 || value - previous > 0.2 {
+                return Err(format!("corriendo el que tapa a {} la luz pasó de {} a {}", offset, previous, value));
+            }
+            previous = value;
+        }
+        if previous != 1.0 || eclipse_at(point, sun, 1.0, Vec3::new(10.0, 0.35, 0.0), 0.2).is_some() {
+            return Err(format!("fuera de la penumbra llega {} de la luz", previous));
+        }
+        let partial = factor(Vec3::new(10.0, 0.2, 0.0), 0.2);
+        if !(0.05..0.95).contains(&partial) || eclipse_at(point, sun, 1.0, Vec3::new(10.0, 0.2, 0.0), 0.2) != Some(Eclipse::Partial) {
+            return Err(format!("en la penumbra llega {} de la luz", partial));
+        }
+        let annular = factor(Vec3::new(10.0, 0.0, 0.0), 0.05);
+        if (annular - 0.75).abs() > 0.01 || eclipse_at(point, sun, 1.0, Vec3::new(10.0, 0.0, 0.0), 0.05) != Some(Eclipse::Annular) {
+            return Err(format!("en el eclipse anular llega {} de la luz", annular));
+        }
+        if factor(Vec3::new(150.0, 0.0, 0.0), 5.0) != 1.0 || factor(Vec3::new(-10.0, 0.0, 0.0), 0.2) != 1.0 {
+            return Err("tapa la luz un cuerpo que no está entre el punto y el Sol".to_string());
+        }
+        let deepest = deepest_shadow_point(Vec3::new(-10.0, 0.5, 0.0), 1.0, sun, Vec3::new(10.0, 0.0, 0.0));
+        if (deepest - Vec3::new(-10.0, -0.5, 0.0)).magnitude() > 1e-4 {
+            return Err(format!("el punto más oscuro quedó en {:?}", deepest));
+        }
+
+        // En el shader la luz del Sol baja con la fracción visible del disco
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.sun_radius = 1.0;
+        let fragment = Fragment::new(Vec2::new(400.0, 300.0), Color::new(100, 100, 100), 7.0, Vec3::new(-1.0, 0.0, 0.0), 1.0, Vec3::new(0.3, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0));
+        let brightness = |color: Color| color.r as u32 + color.g as u32 + color.b as u32;
+        let lit = select_shader(4, &fragment, &uniforms);
+        uniforms.occluders = vec![Occluder { position: Vec3::new(5.0, 0.0, 0.0), radius: 1.0 }];
+        let eclipsed = select_shader(4, &fragment, &uniforms);
+        if brightness(eclipsed) >= brightness(lit) {
+            return Err(format!("en la umbra el fragmento se ve {} y a plena luz {}", eclipsed, lit));
         }
+        Ok(())
     }
 }
@@ -0,0 +1,152 @@
+use nalgebra_glm::{DVec3, Vec3};
+
+use crate::physics::{SPEED_OF_LIGHT, lorentz_factor};
+use crate::trajectory::real_orbit;
+use crate::{Planet, body_position};
+
+// Ángulo máximo entre el rumbo de la nave y un cuerpo para tomarlo como destino (radianes)
+const MAX_HEADING_ANGLE: f32 = 0.2;
+
+// Distancia real al Sol (m) de un punto a `distance` unidades de él en la escena. Las órbitas de
+// la simulación están espaciadas de a 20 unidades: entre dos órbitas se interpola la distancia
+// real de ambas, y más allá de la última se sigue con su proporción. `None` sin órbitas reales.
+pub fn real_solar_distance(planets: &[Planet], distance: f32) -> Option<f64> {
+    let mut anchors = vec![(0.0, 0.0)];
+    anchors.extend(
+        planets
+            .iter()
+            .filter(|planet| planet.parent.is_none())
+            .filter_map(|planet| real_orbit(planet).map(|real| (planet.distance_from_sun as f64, real))),
+    );
+    anchors.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let distance = distance as f64;
+    for pair in anchors.windows(2) {
+        let ((near, near_real), (far, far_real)) = (pair[0], pair[1]);
+        if distance <= far {
+            return Some(near_real + (far_real - near_real) * (distance - near) / (far - near));
+        }
+    }
+    let &(last, last_real) = anchors.last()?;
+    (last > 0.0).then(|| distance * last_real / last)
+}
+
+// Posición real (m, con el Sol en el origen) de un punto de la escena, en la misma dirección
+pub fn real_position(planets: &[Planet], position: Vec3) -> Option<DVec3> {
+    let distance = position.magnitude();
+    if distance == 0.0 {
+        return Some(DVec3::zeros());
+    }
+    Some(position.cast::<f64>() / distance as f64 * real_solar_distance(planets, distance)?)
+}
+
+// Viaje de la nave hasta el cuerpo `target`, en segundos: lo que tarda la luz, lo que tarda la
+// nave a su velocidad vista desde el Sol, y lo que pasa en el reloj de a bordo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Voyage {
+    pub target: usize,
+    pub light_seconds: f64,
+    pub seconds: f64,
+    pub proper_seconds: f64,
+}
+
+// Nave del modo warp: la cámara, con su posición en la escena y su velocidad en m/s
+pub struct Spacecraft {
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+impl Spacecraft {
+    // Planeta (o el Sol) más cerca del rumbo, dentro de MAX_HEADING_ANGLE; las lunas no cuentan
+    pub fn heading_target(&self, planets: &[Planet], time: f64) -> Option<usize> {
+        let heading = self.velocity.try_normalize(1e-6)?;
+        (0..planets.len())
+            .filter(|&index| planets[index].parent.is_none())
+            .filter_map(|index| {
+                let direction = (body_position(planets, index, time) - self.position).try_normalize(1e-6)?;
+                let angle = heading.dot(&direction).clamp(-1.0, 1.0).acos();
+                (angle < MAX_HEADING_ANGLE).then_some((index, angle))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    // Segundos que tarda la luz de la nave hasta `target`, con las distancias reales
+    pub fn light_travel_time(&self, planets: &[Planet], target: Vec3) -> Option<f64> {
+        let distance = (real_position(planets, target)? - real_position(planets, self.position)?).magnitude();
+        Some(distance / SPEED_OF_LIGHT as f64)
+    }
+
+    // Viaje hasta el cuerpo hacia el que va la nave; `None` si está quieta o no apunta a ninguno
+    pub fn voyage(&self, planets: &[Planet], time: f64) -> Option<Voyage> {
+        let target = self.heading_target(planets, time)?;
+        let light_seconds = self.light_travel_time(planets, body_position(planets, target, time))?;
+        let speed = self.velocity.magnitude();
+        let seconds = light_seconds * SPEED_OF_LIGHT as f64 / speed as f64;
+        Some(Voyage { target, light_seconds, seconds, proper_seconds: seconds / lorentz_factor(speed) as f64 })
+    }
+}
+
+// Duración para el HUD: en segundos hasta un minuto y después en minutos
+pub fn format_duration(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.1} S", seconds)
+    } else {
+        format!("{:.1} MIN", seconds / 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solar_system;
+    use crate::physics::ASTRONOMICAL_UNIT;
+
+    // La luz tarda unos 8.3 minutos del Sol a la Tierra y 43 hasta Júpiter; a 0.9c la nave tarda
+    // 1/0.9 de eso y a bordo pasa γ ≈ 2.29 veces menos
+    #[test]
+    fn light_travel() -> Result<(), String> {
+        let planets = solar_system();
+        let index = |name: &str| planets.iter().position(|planet| planet.name == name).ok_or(format!("falta {}", name));
+        let (earth, jupiter) = (index("Tierra")?, index("Júpiter")?);
+
+        let halfway = real_solar_distance(&planets, (planets[earth].distance_from_sun + planets[index("Marte")?].distance_from_sun) / 2.0).ok_or("sin distancias reales")?;
+        if (halfway / ASTRONOMICAL_UNIT - 1.262).abs() > 1e-3 {
+            return Err(format!("entre la Tierra y Marte la distancia real es {:.3} UA", halfway / ASTRONOMICAL_UNIT));
+        }
+
+        let at_sun = Spacecraft { position: Vec3::new(0.0, 0.0, 0.0), velocity: Vec3::new(0.0, 0.0, 0.0) };
+        for (target, minutes) in [(earth, 8.317), (jupiter, 43.27)] {
+            let light = at_sun.light_travel_time(&planets, body_position(&planets, target, 0.0)).ok_or("sin tiempo de luz")? / 60.0;
+            if (light - minutes).abs() > 0.01 {
+                return Err(format!("la luz tarda {:.3} min hasta {}, se esperaban {}", light, planets[target].name, minutes));
+            }
+        }
+
+        // A t = 0 los planetas están alineados; más tarde cada uno está en otra dirección
+        let time = 100.0;
+        let jupiter_position = body_position(&planets, jupiter, time);
+        let speed = 0.9 * SPEED_OF_LIGHT;
+        let ship = Spacecraft { position: Vec3::new(0.0, 0.0, 0.0), velocity: jupiter_position.normalize() * speed };
+        let voyage = ship.voyage(&planets, time).ok_or("la nave no encontró su rumbo")?;
+        if voyage.target != jupiter {
+            return Err(format!("la nave apunta a Júpiter y el rumbo dio {}", planets[voyage.target].name));
+        }
+        if (voyage.seconds / voyage.light_seconds - 1.0 / 0.9).abs() > 1e-3 || (voyage.seconds / voyage.proper_seconds - 2.294).abs() > 1e-3 {
+            return Err(format!("el viaje dio {:?}", voyage));
+        }
+        let away = Spacecraft { position: Vec3::new(0.0, 0.0, 0.0), velocity: -jupiter_position.normalize().cross(&Vec3::new(0.0, 1.0, 0.0)) * speed };
+        if away.heading_target(&planets, time).is_some_and(|target| target == jupiter) {
+            return Err("de costado a Júpiter se lo tomó como destino".to_string());
+        }
+        let parked = Spacecraft { velocity: Vec3::new(0.0, 0.0, 0.0), ..ship };
+        if parked.voyage(&planets, time).is_some() {
+            return Err("una nave quieta tiene viaje".to_string());
+        }
+
+        if format_duration(42.0) != "42.0 S" || format_duration(2596.0) != "43.3 MIN" {
+            return Err(format!("formato: {} y {}", format_duration(42.0), format_duration(2596.0)));
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,19 @@
+use nalgebra_glm::{Vec2, Vec3};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
 use crate::color::Color;
 
+// Longitud máxima (en píxeles) que se rasteriza; evita bucles enormes con vértices muy fuera de pantalla
+const MAX_LINE_LENGTH: i32 = 8192;
+
 pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
     let start = a.transformed_position;
     let end = b.transformed_position;
+    if !start.iter().chain(end.iter()).all(|c| c.is_finite()) {
+        return fragments;
+    }
 
     let mut x0 = start.x as i32;
     let mut y0 = start.y as i32;
@@ -15,15 +22,36 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
 
     let dx = (x1 - x0).abs();
     let dy = (y1 - y0).abs();
+    if dx.max(dy) > MAX_LINE_LENGTH {
+        return fragments;
+    }
 
     let sx = if x0 < x1 { 1 } else { -1 };
     let sy = if y0 < y1 { 1 } else { -1 };
 
     let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
+    let steps = dx.max(dy).max(1) as f32;
+    let mut step = 0;
 
     loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
-        fragments.push(Fragment::new(x0 as f32, y0 as f32, Color::new(255, 255, 255), z));
+        // La profundidad es distancia en espacio de vista: se interpola su inverso
+        let t = step as f32 / steps;
+        let z = if start.z > 0.0 && end.z > 0.0 {
+            1.0 / ((1.0 - t) / start.z + t / end.z)
+        } else {
+            start.z + (end.z - start.z) * t
+        };
+        let world_position = a.world_position.lerp(&b.world_position, t);
+
+        fragments.push(Fragment::new(
+            Vec2::new(x0 as f32, y0 as f32),
+            Color::new(255, 255, 255),
+            z,
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            a.position.lerp(&b.position, t),
+            world_position,
+        ));
 
         if x0 == x1 && y0 == y1 { break; }
 
@@ -36,7 +64,8 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             err += dx;
             y0 += sy;
         }
+        step += 1;
     }
 
     fragments
-}
\ No newline at end of file
+}
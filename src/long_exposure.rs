@@ -0,0 +1,146 @@
+use nalgebra_glm::Vec3;
+use image::{ImageResult, Rgb, RgbImage};
+use rayon::prelude::*;
+use std::path::Path;
+
+use crate::Framebuffer;
+
+// Opacidad por defecto de la acumulación sobre el frame
+pub const DEFAULT_LONG_EXPOSURE_OPACITY: f32 = 0.85;
+
+// Carpeta donde se guardan las exposiciones exportadas
+pub const CAPTURE_DIR: &str = "captures";
+
+// Exposición larga: como una foto de estelas de estrellas, cada frame los píxeles de los
+// cuerpos se acumulan (máximo por canal) en un buffer persistente que se compone sobre la
+// escena, así sus órbitas quedan pintadas en pantalla. Los cuerpos se reconocen por tener
+// profundidad finita en el z-buffer (el skybox queda en infinito); el HUD se dibuja después.
+// Las posiciones acumuladas solo tienen sentido con la cámara quieta: si se mueve, se borra.
+pub struct LongExposure {
+    pub active: bool,
+    pub opacity: f32,
+    accumulated: Vec<u32>,
+    last_camera: Option<(Vec3, Vec3)>,
+}
+
+impl LongExposure {
+    pub fn new() -> Self {
+        LongExposure {
+            active: false,
+            opacity: DEFAULT_LONG_EXPOSURE_OPACITY,
+            accumulated: Vec::new(),
+            last_camera: None,
+        }
+    }
+
+    // Empieza o detiene la acumulación; lo acumulado sigue visible hasta borrarlo
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    pub fn clear(&mut self) {
+        self.accumulated.iter_mut().for_each(|pixel| *pixel = 0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accumulated.iter().all(|&pixel| pixel == 0)
+    }
+
+    // Suma el frame actual (ya dibujados los cuerpos, antes del HUD) y compone lo acumulado
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, eye: Vec3, center: Vec3) {
+        if self.accumulated.len() != framebuffer.buffer.len() {
+            self.accumulated = vec![0; framebuffer.buffer.len()];
+        }
+
+        // Cualquier movimiento de la cámara invalida las posiciones de pantalla acumuladas
+        if self.last_camera.replace((eye, center)) != Some((eye, center)) {
+            self.clear();
+        }
+
+        if self.active {
+            self.accumulated
+                .par_iter_mut()
+                .zip(framebuffer.buffer.par_iter().zip(framebuffer.zbuffer.par_iter()))
+                .for_each(|(stored, (pixel, depth))| {
+                    if depth.is_finite() {
+                        *stored = lighten(*stored, *pixel);
+                    }
+                });
+        }
+
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        framebuffer
+            .buffer
+            .par_iter_mut()
+            .zip(self.accumulated.par_iter())
+            .for_each(|(pixel, &stored)| {
+                if stored != 0 {
+                    *pixel = mix(*pixel, lighten(*pixel, stored), opacity);
+                }
+            });
+    }
+
+    // Guarda solo lo acumulado (sin la escena) como PNG en CAPTURE_DIR y devuelve la ruta
+    pub fn export(&self, width: usize, height: usize, name: &str) -> ImageResult<String> {
+        let image = RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            let pixel = self.accumulated.get(y as usize * width + x as usize).copied().unwrap_or(0);
+            Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+        });
+
+        std::fs::create_dir_all(CAPTURE_DIR)?;
+        let path = Path::new(CAPTURE_DIR).join(name).to_string_lossy().into_owned();
+        image.save(&path)?;
+        Ok(path)
+    }
+}
+
+// Máximo por canal: cada punto del buffer guarda lo más brillante que pasó por él
+fn lighten(a: u32, b: u32) -> u32 {
+    let channel = |shift: u32| ((a >> shift) & 0xFF).max((b >> shift) & 0xFF) << shift;
+    channel(16) | channel(8) | channel(0)
+}
+
+fn mix(a: u32, b: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        ((ca + (cb - ca) * t).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Un cuerpo que cruza la pantalla deja estela; el fondo (profundidad infinita) no se acumula
+    // y mover la cámara borra lo acumulado
+    #[test]
+    fn long_exposure() -> Result<(), String> {
+        let (eye, center) = (Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0));
+        let mut exposure = LongExposure::new();
+        exposure.opacity = 1.0;
+        exposure.toggle();
+
+        for x in 0..4 {
+            let mut frame = Framebuffer::new(4, 1);
+            frame.buffer.iter_mut().for_each(|pixel| *pixel = 0x101010);
+            frame.buffer[x] = 0xC08040;
+            frame.zbuffer[x] = 5.0;
+            exposure.apply(&mut frame, eye, center);
+            if frame.buffer[..=x].iter().any(|&pixel| pixel != 0xC08040) {
+                return Err(format!("falta la estela tras {} frames: {:06X?}", x + 1, frame.buffer));
+            }
+            if frame.buffer[x + 1..].iter().any(|&pixel| pixel != 0x101010) {
+                return Err("se acumuló el fondo".to_string());
+            }
+        }
+
+        let mut frame = Framebuffer::new(4, 1);
+        exposure.apply(&mut frame, eye + Vec3::new(1.0, 0.0, 0.0), center);
+        if !exposure.is_empty() || frame.buffer.iter().any(|&pixel| pixel != 0) {
+            return Err("mover la cámara no borró la exposición".to_string());
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,16 @@
-use nalgebra_glm::{Vec3, Mat4, Vec4};
+use nalgebra_glm::{Vec2, Vec3, Mat4, Vec4};
 use nalgebra::{Vector4};
 use minifb::{Key, Window, WindowOptions};
 use std::f32::consts::PI;
 use std::sync::Arc;
 use std::path::Path;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
 use image::{open, DynamicImage, GenericImageView};
+use rayon::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
 mod framebuffer;
 mod triangle;
@@ -17,13 +22,110 @@ mod shaders;
 mod camera;
 mod uniforms;
 mod light;
-
-use framebuffer::Framebuffer;
+mod texture;
+mod planet_table;
+mod line;
+mod physics;
+mod debug_view;
+mod gravity_well;
+mod selftest;
+mod aabb;
+mod volumetric;
+mod hud;
+mod rings;
+mod procedural_mesh;
+mod exposure;
+mod seed;
+mod flares;
+mod depth_of_field;
+mod units;
+mod motion_blur;
+mod orbit_markers;
+mod long_exposure;
+mod ecliptic_grid;
+mod trajectory;
+mod status_line;
+mod render_order;
+mod stress;
+mod presenter;
+mod vertex_cache;
+mod command;
+mod render_stats;
+mod spice;
+mod events;
+mod sizes;
+mod navigation;
+mod skybox;
+mod timeline;
+mod cutaway;
+mod astronomy;
+mod night_sky;
+mod meteors;
+mod replay;
+mod grading;
+mod reference_frame;
+mod light_travel;
+mod time_slider;
+mod time_scrubber;
+mod sim_clock;
+mod roche;
+mod scene_graph;
+mod compare;
+mod effects;
+mod overlay;
+
+use framebuffer::{Framebuffer, BarColors, BAND_HEIGHT};
 use vertex::Vertex;
+use fragment::Fragment;
 use obj::Obj;
-use camera::Camera;
-use shaders::{vertex_shader, select_shader};
-use uniforms::{Uniforms, create_noise, create_model_matrix, create_view_matrix, create_perspective_matrix, create_viewport_matrix};
+use camera::{Camera, CameraConstraints};
+use color::Color;
+use shaders::{vertex_shader, select_shader, select_shader_with_alpha};
+use texture::{Texture, generate_heightmap_png, generate_spherical_normal_map};
+use planet_table::PlanetTable;
+use gravity_well::GravityWellGrid;
+use aabb::Aabb;
+use hud::{Panel, Contrast};
+use rings::RingSystem;
+use seed::SeedSource;
+use skybox::{Skybox, SkyboxConfig};
+use timeline::TimelinePlayer;
+use cutaway::{Cutaway, INTERIOR_COLOR};
+use night_sky::{NightSky, SUBSOLAR_COLOR};
+use light::{Occluder, Eclipse, eclipse_at, deepest_shadow_point};
+use meteors::MeteorShower;
+use replay::{ReplayRecorder, ReplayScrubber};
+use grading::ColorGrading;
+use light_travel::{Spacecraft, format_duration};
+use time_slider::{SliderLayout, TimeSlider};
+use time_scrubber::TimeScrubber;
+use sim_clock::SimClock;
+use roche::RocheOverlay;
+use scene_graph::{NodeMesh, SceneGraph, SceneNode};
+use compare::AbReference;
+use effects::EffectsBuffer;
+use overlay::Overlay;
+use flares::{SolarFlares, FLARE_EXTENT};
+use depth_of_field::DepthOfField;
+use units::{Unit, format_quantity};
+use motion_blur::MotionBlur;
+use orbit_markers::{OrbitMarkers, MarkedBody, draw_label};
+use procedural_mesh::ProceduralMesh;
+use long_exposure::LongExposure;
+use ecliptic_grid::EclipticGrid;
+use trajectory::{SpacecraftTrajectory, FlybyPlanner, TRAJECTORY_COLOR};
+use status_line::StatusLine;
+use render_order::{RenderOrder, view_depth, sort_back_to_front};
+use stress::{StressTest, stress_bodies};
+use presenter::{PresentRecovery, PresentOutcome};
+use vertex_cache::VertexCache;
+use command::{CommandPalette, Command, Toggle};
+use render_stats::DrawStats;
+use spice::{SpkFile, BodyEphemeris, ephemeris_time};
+use events::{EventLog, SceneState};
+use sizes::SizeScale;
+use exposure::{AutoExposure, DEFAULT_MIN_EV, DEFAULT_MAX_EV};
+use uniforms::{Uniforms, Displacement, RenderMode, ColorBlindMode, create_noise, create_ground_noise, create_model_matrix, create_oblate_model_matrix, create_view_matrix, create_perspective_matrix, create_viewport_matrix, adaptive_clip_planes, clip_planes_from_args, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE};
 
 #[derive(PartialEq)]
 struct Planet {
@@ -31,46 +133,620 @@ struct Planet {
     distance_from_sun: f32,
     radius: f32,
     orbit_speed: f32,
+    // Ángulo de la órbita en la época (el tiempo 0, J2000), en radianes; sin él lo elige
+    // `seed_start_angles` con la semilla, para que los planetas no arranquen todos alineados
+    start_angle: Option<f32>,
     color_index: usize,
+    displacement: Displacement,
+    // Achatamiento por rotación: cuánto más ancho es el ecuador que el eje polar
+    oblateness: f32,
+    // Contraste del color base en su shader (`Color::contrast`; 1 lo deja igual). Realza las
+    // bandas de Saturno.
+    contrast: f32,
+    // Índice del cuerpo alrededor del cual orbita (las lunas); `distance_from_sun`
+    // es entonces la distancia a ese cuerpo
+    parent: Option<usize>,
+    // Posiciones leídas de un archivo SPK (`--spk`); sin ellas, o fuera de su cobertura, el
+    // cuerpo sigue su órbita de la simulación
+    ephemeris: Option<Arc<BodyEphemeris>>,
+}
+
+impl Planet {
+    // Posición respecto del centro de su órbita (el Sol o el cuerpo padre) en el tiempo dado
+    fn position_at(&self, time: f64) -> Vec3 {
+        self.ephemeris
+            .as_ref()
+            .and_then(|ephemeris| ephemeris.offset(time))
+            .unwrap_or_else(|| orbit_offset(self, self.orbit_angle(time)))
+    }
+
+    // Fase de la órbita (0 a 2π) en el tiempo dado: la de la época más lo recorrido desde ella.
+    // Se calcula en f64 para que a tiempos grandes el producto no se coma la fase.
+    fn orbit_angle(&self, time: f64) -> f32 {
+        (self.start_angle.unwrap_or(0.0) as f64 + self.orbit_speed as f64 * time).rem_euclid(2.0 * std::f64::consts::PI) as f32
+    }
+
+    // Límite de Roche de `moon` alrededor de este cuerpo, en radios de este cuerpo (así vale con
+    // cualquier escala de tamaños); `None` si no se conoce la densidad de alguno de los dos
+    fn roche_limit(&self, moon: &Planet) -> Option<f32> {
+        Some(astronomy::roche_limit_radii(astronomy::mean_density(self)?, astronomy::mean_density(moon)?))
+    }
+
+    // Diámetro aparente en píxeles con el radio de `sizes`, visto a `camera_distance` (en las
+    // mismas unidades) con un campo de visión vertical de `fov_deg` grados y `screen_height` filas
+    fn apparent_size_pixels(&self, sizes: &SizeScale, camera_distance: f32, fov_deg: f32, screen_height: usize) -> f32 {
+        (2.0 * sizes.radius(self) / camera_distance) / (fov_deg.to_radians() / 2.0).tan() * screen_height as f32 / 2.0
+    }
+
+    // Tamaño aparente en `position` con la cámara en `eye`, el que eligen el nivel de detalle y los
+    // nombres. Las mallas miden `sphere_radius`, así que la distancia se pasa a esas unidades.
+    fn apparent_size(&self, sizes: &SizeScale, position: Vec3, eye: Vec3, sphere_radius: f32, screen_height: usize) -> f32 {
+        let distance = (position - eye).magnitude() / sphere_radius;
+        self.apparent_size_pixels(sizes, distance, DEFAULT_FOV_Y.to_degrees(), screen_height)
+    }
+}
+
+const SPHERE_MODEL: &str = "assets/model/sphere.obj";
+const RING_SEGMENTS: usize = 128;
+// Material de los nodos de anillos en el grafo de escena (su shader)
+const RING_MATERIAL: usize = 8;
+const SKYBOX_TEXTURE: &str = "assets/space.png";
+
+// Lente gravitatoria sobre el skybox: intensidad (por unidad de radio³) y alcance en radios en pantalla
+const LENSING_STRENGTH: f32 = 0.02;
+const LENSING_EXTENT: f32 = 4.0;
+
+// Aberración cromática: corrimiento de los canales por unidad de distancia al centro de la
+// pantalla, el de omisión y el máximo que acepta --aberration
+const DEFAULT_ABERRATION: f32 = 0.004;
+const MAX_ABERRATION: f32 = 0.05;
+
+// Velocidad de la nave en el modo warp, como fracción de la de la luz
+const WARP_BETA: f32 = 0.9;
+
+// Color de las órbitas y de sus marcas de fase
+const ORBIT_COLOR: u32 = 0xAAAAAA;
+
+// Umbrales de tamaño aparente (diámetro en píxeles): por debajo del primero un cuerpo no tiene
+// malla ni anillos (queda su impostor de un píxel), desde el segundo lleva su nombre y desde el
+// tercero se dibuja con la esfera de alta resolución
+const MIN_MESH_SIZE: f32 = 1.0;
+const MIN_LABEL_SIZE: f32 = 5.0;
+const HIGH_POLY_SIZE: f32 = 50.0;
+const HIGH_POLY_STACKS: usize = 48;
+const HIGH_POLY_SLICES: usize = 96;
+const BODY_LABEL_COLOR: u32 = 0xCCCCCC;
+
+// Nebulosa de fondo, detrás del Sol visto desde la posición inicial de la cámara
+const NEBULA_MIN: Vec3 = Vec3::new(-260.0, -60.0, -520.0);
+const NEBULA_MAX: Vec3 = Vec3::new(140.0, 120.0, -300.0);
+// Líneas de emisión de la nebulosa (longitud de onda en nm, peso): Hα domina en rojo, OIII
+// aporta el verde azulado y SII un rojo más profundo
+const NEBULA_EMISSION_LINES: [(f32, f32); 3] = [(656.3, 0.5), (500.7, 0.35), (672.4, 0.15)];
+const NEBULA_DENSITY: f32 = 0.006;
+
+// Datos reales de cada cuerpo para el panel de información (no afectan la simulación)
+struct PlanetStats {
+    diameter_km: f32,
+    surface_temp_k: f32,
+    moon_count: u32,
+    atmosphere: &'static str,
+}
+
+fn planet_stats() -> HashMap<&'static str, PlanetStats> {
+    HashMap::from([
+        ("Sol", PlanetStats { diameter_km: 1_392_700.0, surface_temp_k: 5772.0, moon_count: 0, atmosphere: "H, He" }),
+        ("Mercurio", PlanetStats { diameter_km: 4_879.0, surface_temp_k: 440.0, moon_count: 0, atmosphere: "Casi nula" }),
+        ("Venus", PlanetStats { diameter_km: 12_104.0, surface_temp_k: 737.0, moon_count: 0, atmosphere: "CO2, N2" }),
+        ("Tierra", PlanetStats { diameter_km: 12_742.0, surface_temp_k: 288.0, moon_count: 1, atmosphere: "N2, O2" }),
+        ("Marte", PlanetStats { diameter_km: 6_779.0, surface_temp_k: 210.0, moon_count: 2, atmosphere: "CO2, N2, Ar" }),
+        ("Júpiter", PlanetStats { diameter_km: 139_820.0, surface_temp_k: 165.0, moon_count: 95, atmosphere: "H2, He" }),
+        ("Saturno", PlanetStats { diameter_km: 116_460.0, surface_temp_k: 134.0, moon_count: 146, atmosphere: "H2, He" }),
+        ("Urano", PlanetStats { diameter_km: 50_724.0, surface_temp_k: 76.0, moon_count: 28, atmosphere: "H2, He, CH4" }),
+        ("Neptuno", PlanetStats { diameter_km: 49_244.0, surface_temp_k: 72.0, moon_count: 16, atmosphere: "H2, He, CH4" }),
+        ("Luna", PlanetStats { diameter_km: 3_474.0, surface_temp_k: 250.0, moon_count: 0, atmosphere: "Casi nula" }),
+    ])
+}
+
+// Opacidad de los textos del HUD compuestos sobre la escena
+const HUD_TEXT_OPACITY: f32 = 0.9;
+
+const INFO_PANEL_WIDTH: usize = 300;
+const INFO_PANEL_MARGIN: usize = 10;
+const STRESS_PANEL_WIDTH: usize = 220;
+const PROFILE_TOP_COUNT: usize = 5;
+
+// Límites de la escala de tiempo (ticks de simulación por frame), que se dobla o divide con + y -
+const MIN_TIME_SCALE: f32 = 0.125;
+const MAX_TIME_SCALE: f32 = 512.0;
+const TIME_SCALE_BAR_WIDTH: usize = 96;
+
+// Separación vertical entre los elementos del HUD apilados
+const HUD_GAP: usize = 6;
+
+// Panel con los datos del cuerpo enfocado, en la esquina superior derecha. En alto contraste
+// el texto no cabe en el ancho normal: ocupa todo el ancho a partir de `high_contrast_y`.
+fn planet_info_panel(screen_width: usize, planet: &Planet, stats: &PlanetStats, time: f64, contrast: Contrast, high_contrast_y: usize, sizes: &SizeScale) -> Panel {
+    let (x, y, width) = if contrast.is_high() {
+        (INFO_PANEL_MARGIN, high_contrast_y, screen_width.saturating_sub(2 * INFO_PANEL_MARGIN))
+    } else {
+        (screen_width.saturating_sub(INFO_PANEL_WIDTH + INFO_PANEL_MARGIN), INFO_PANEL_MARGIN, INFO_PANEL_WIDTH)
+    };
+    let mut diameter = String::new();
+    let mut temperature = String::new();
+    format_quantity(&mut diameter, stats.diameter_km as f64, Unit::Kilometers, 5);
+    format_quantity(&mut temperature, stats.surface_temp_k as f64, Unit::Kelvin, 4);
+    let mut panel = Panel::new(x, y, width)
+        .contrast(contrast)
+        .title(&planet.name.to_uppercase())
+        .separator()
+        .row("DIAMETRO", &diameter)
+        // Radios terrestres: el que se dibuja y el real
+        .row("RADIO", &format!("{:.2} RT", sizes.radius(planet)))
+        .row("RADIO REAL", &sizes::true_ratio(planet).map_or("-".to_string(), |ratio| format!("{:.2} RT", ratio)))
+        .row("TEMP", &temperature)
+        .row("LUNAS", &stats.moon_count.to_string())
+        .row("ATMOSFERA", stats.atmosphere);
+
+    // Fracción de la órbita recorrida en la vuelta actual (el Sol no orbita)
+    if planet.orbit_speed != 0.0 {
+        panel = panel.bar("ORBITA", planet.orbit_angle(time) / (2.0 * PI));
+    }
+    panel
+}
+
+// Con Marte enfocado, su panel suma los días hasta su próxima oposición y su próxima conjunción
+// vistas desde la Tierra, y su período sinódico, que es lo que separa dos oposiciones
+fn with_opposition(panel: Panel, planets: &[Planet], planet: &Planet, time: f64) -> Panel {
+    let Some(earth) = planets.iter().find(|body| body.name == "Tierra").filter(|_| planet.name == "Marte") else {
+        return panel;
+    };
+    let opposition = astronomy::next_opposition_time(earth, planet, time);
+    if !opposition.is_finite() {
+        return panel;
+    }
+    let conjunction = astronomy::next_conjunction_time(earth, planet, time);
+    let days = |duration: f64| {
+        let mut days = String::new();
+        format_quantity(&mut days, ephemeris_time(duration) / 86_400.0, Unit::Plain, 4);
+        format!("{} DIAS", days)
+    };
+    panel
+        .row("OPOSICION", &days(opposition - time))
+        .row("CONJUNCION", &days(conjunction - time))
+        .row("SINODICO", &days(astronomy::synodic_period(earth, planet) as f64))
+}
+
+// Cuerpos de la simulación; el índice de cada uno es el que usan `parent` y las teclas de enfoque.
+// El relieve va con cada cuerpo: Mercurio y Marte, con más cráteres, usan un ruido más fino.
+fn solar_system() -> Vec<Planet> {
+    vec![
+        Planet { name: "Sol", distance_from_sun: 0.0, radius: 3.0, orbit_speed: 0.0, start_angle: None, color_index: 0, displacement: Displacement::none(), oblateness: 0.0, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Mercurio", distance_from_sun: 20.0, radius: 0.5, orbit_speed: 0.003, start_angle: None, color_index: 1, displacement: Displacement::terrain(0.5, 9.0), oblateness: 0.0, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Venus", distance_from_sun: 40.0, radius: 0.8, orbit_speed: 0.005, start_angle: None, color_index: 2, displacement: Displacement::terrain(0.2, 5.0), oblateness: 0.0, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Tierra", distance_from_sun: 60.0, radius: 1.0, orbit_speed: 0.007, start_angle: None, color_index: 3, displacement: Displacement::terrain(0.3, 5.0), oblateness: 0.0034, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Marte", distance_from_sun: 80.0, radius: 0.7, orbit_speed: 0.009, start_angle: None, color_index: 4, displacement: Displacement::terrain(0.5, 9.0), oblateness: 0.0059, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Júpiter", distance_from_sun: 100.0, radius: 2.0, orbit_speed: 0.001, start_angle: None, color_index: 5, displacement: Displacement::none(), oblateness: 0.065, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Saturno", distance_from_sun: 120.0, radius: 1.8, orbit_speed: 0.003, start_angle: None, color_index: 6, displacement: Displacement::none(), oblateness: 0.098, contrast: 1.4, parent: None, ephemeris: None },
+        Planet { name: "Urano", distance_from_sun: 140.0, radius: 1.5, orbit_speed: 0.005, start_angle: None, color_index: 7, displacement: Displacement::none(), oblateness: 0.023, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Neptuno", distance_from_sun: 160.0, radius: 1.45, orbit_speed: 0.004, start_angle: None, color_index: 11, displacement: Displacement::none(), oblateness: 0.017, contrast: 1.0, parent: None, ephemeris: None },
+        Planet { name: "Luna", distance_from_sun: 8.0, radius: 0.3, orbit_speed: 0.03, start_angle: None, color_index: 10, displacement: Displacement::terrain(0.6, 0.0), oblateness: 0.0, contrast: 1.0, parent: Some(3), ephemeris: None },
+    ]
+}
+
+// Fase de la época de los cuerpos que no la traen en su configuración, al azar pero la misma para
+// la misma semilla
+fn seed_start_angles(planets: &mut [Planet], seeds: &SeedSource) {
+    for (index, planet) in planets.iter_mut().enumerate() {
+        if planet.start_angle.is_none() {
+            let mut rng = StdRng::seed_from_u64(seeds.derive("start_angle", index as u64));
+            planet.start_angle = Some(rng.gen_range(0.0..2.0 * PI));
+        }
+    }
+}
+
+// Punto de la órbita en la fase `angle`, relativo al centro de la órbita (el Sol o el
+// cuerpo padre). Es la parametrización que comparten la posición, la órbita dibujada y sus marcas.
+fn orbit_offset(planet: &Planet, angle: f32) -> Vec3 {
+    Vec3::new(
+        planet.distance_from_sun * angle.cos(),
+        0.0,
+        planet.distance_from_sun * angle.sin(),
+    )
+}
+
+// Posición de un cuerpo en el tiempo dado, sumando la de su cuerpo padre si lo tiene. El tiempo va
+// en f64 como el del reloj (`SimClock::elapsed`)
+fn body_position(planets: &[Planet], index: usize, time: f64) -> Vec3 {
+    let planet = &planets[index];
+    let offset = planet.position_at(time);
+
+    match planet.parent {
+        Some(parent) => body_position(planets, parent, time) + offset,
+        None => offset,
+    }
+}
+
+// Grafo de escena del sistema. Los nodos 0..n son los marcos de los cuerpos, con los mismos
+// índices que `planets`: solo llevan su posición y las lunas cuelgan del de su planeta. Cada marco
+// tiene un hijo con la esfera (escalada y achatada) y los cuerpos con anillos otro con los
+// anillos, que así no heredan el achatamiento ni pasan al marco de sus lunas.
+fn system_scene(planets: &[Planet], ring_systems: &[(&str, RingSystem, Vec<Vertex>)]) -> Result<SceneGraph, String> {
+    let mut nodes: Vec<SceneNode> = planets.iter().map(|planet| SceneNode::new(Mat4::identity(), planet.parent)).collect();
+    nodes.extend(
+        planets
+            .iter()
+            .enumerate()
+            .map(|(index, planet)| SceneNode::new(Mat4::identity(), Some(index)).with_drawable(NodeMesh::Sphere, planet.color_index)),
+    );
+    nodes.extend(
+        planets
+            .iter()
+            .enumerate()
+            .filter(|(_, planet)| ring_systems.iter().any(|(name, _, _)| *name == planet.name))
+            .map(|(index, _)| SceneNode::new(Mat4::identity(), Some(index)).with_drawable(NodeMesh::Rings, RING_MATERIAL)),
+    );
+    SceneGraph::new(nodes).map_err(|error| format!("grafo de escena: {}", error))
+}
+
+// Lleva los cuerpos del grafo a su posición en `time` y sus esferas y anillos al tamaño de `sizes`
+fn place_bodies(scene: &mut SceneGraph, planets: &[Planet], time: f64, sizes: &SizeScale, sphere_radius: f32) {
+    for (index, planet) in planets.iter().enumerate() {
+        scene.set_local(index, Mat4::new_translation(&planet.position_at(time)));
+    }
+    for item in scene.draw_list() {
+        let Some(body) = scene.parent(item.node) else {
+            continue;
+        };
+        let planet = &planets[body];
+        let local = match item.mesh {
+            NodeMesh::Sphere => create_oblate_model_matrix(Vec3::zeros(), sizes.radius(planet), planet.oblateness, Vec3::zeros()),
+            NodeMesh::Rings => create_model_matrix(Vec3::zeros(), sizes.radius(planet) * sphere_radius, Vec3::zeros()),
+        };
+        scene.set_local(item.node, local);
+    }
+    scene.update();
+}
+
+// Asigna a cada cuerpo los segmentos del archivo SPK que lo describen respecto del centro de su
+// órbita; devuelve cuántos cuerpos tienen efemérides
+fn attach_ephemerides(planets: &mut [Planet], file: &SpkFile) -> usize {
+    let ephemerides: Vec<Option<BodyEphemeris>> = planets
+        .iter()
+        .map(|planet| {
+            let target = spice::naif_id(planet.name)?;
+            let centers = spice::orbit_centers(planet.parent.map(|parent| planets[parent].name));
+            file.body_ephemeris(target, &centers, planet.distance_from_sun)
+        })
+        .collect();
+    let mut attached = 0;
+    for (planet, ephemeris) in planets.iter_mut().zip(ephemerides) {
+        attached += ephemeris.is_some() as usize;
+        planet.ephemeris = ephemeris.map(Arc::new);
+    }
+    attached
+}
+
+// Posición de la cámara (ojo, centro) para enfocar un cuerpo con su tamaño en `sizes`
+fn focus_targets(planets: &[Planet], index: usize, sizes: &SizeScale) -> (Vec3, Vec3) {
+    let position = body_position(planets, index, 0.0);
+    let eye = position + Vec3::new(20.0, sizes.radius(&planets[index]) * 2.0, 0.0);
+    (eye, position)
+}
+
+// Encuadres predefinidos: el sistema interior llega hasta Marte y el exterior empieza en Júpiter
+const INNER_SYSTEM_LIMIT: f32 = 90.0;
+const FRAMING_MARGIN: f32 = 1.15;
+const MIN_FRAMING_ELEVATION: f32 = 30.0 * PI / 180.0;
+
+// Distancia al Sol de la órbita de un cuerpo (para las lunas, la de su planeta)
+fn heliocentric_distance(planets: &[Planet], index: usize) -> f32 {
+    match planets[index].parent {
+        Some(parent) => heliocentric_distance(planets, parent),
+        None => planets[index].distance_from_sun,
+    }
+}
+
+// (ojo, centro) que encuadran los cuerpos `bodies` en sus posiciones actuales. Conserva el
+// rumbo de la cámara, pero con una elevación mínima para ver los planos orbitales desde arriba.
+fn frame_bodies(planets: &[Planet], bodies: &[usize], camera: &Camera, projection: &Mat4, time: f64, sphere_radius: f32, sizes: &SizeScale) -> (Vec3, Vec3) {
+    let spheres: Vec<(Vec3, f32)> = bodies
+        .iter()
+        .map(|&index| (body_position(planets, index, time), sizes.radius(&planets[index]) * sphere_radius))
+        .collect();
+
+    let offset = camera.eye - camera.center;
+    let horizontal = Vec3::new(offset.x, 0.0, offset.z);
+    let heading = if horizontal.magnitude() > 1e-3 { horizontal.normalize() } else { Vec3::new(0.0, 0.0, 1.0) };
+    let elevation = offset.y.atan2(horizontal.magnitude()).max(MIN_FRAMING_ELEVATION);
+    let direction = heading * elevation.cos() + Vec3::new(0.0, elevation.sin(), 0.0);
+
+    camera::frame_spheres(&spheres, direction, projection, FRAMING_MARGIN)
+}
+
+// Texturas de superficie generadas al arrancar
+struct SurfaceMaps {
+    // Relieve y color de la Luna
+    moon_terrain: Arc<Texture>,
+    // Normales del relieve de la Luna, del mismo ruido que su mapa de alturas
+    moon_normals: Arc<Texture>,
+    // Relieve de la Tierra, usado solo para el parallax de sus biomas
+    earth_relief: Arc<Texture>,
+}
+
+// Asocia las texturas de superficie del cuerpo (la Luna y la Tierra usan mapas)
+// y elige su nivel de mip-map según el tamaño del cuerpo en pantalla
+fn bind_surface_maps(uniforms: &mut Uniforms, planet: &Planet, maps: &SurfaceMaps, center: Vec3, world_radius: f32) {
+    if planet.color_index == 10 {
+        uniforms.texture_lod = maps.moon_terrain.mip_level_for_radius(projected_radius(uniforms, center, world_radius));
+        uniforms.height_map = Some(maps.moon_terrain.clone());
+        uniforms.color_map = Some(maps.moon_terrain.clone());
+        uniforms.normal_map = Some(maps.moon_normals.clone());
+    } else {
+        uniforms.height_map = None;
+        uniforms.color_map = None;
+        uniforms.normal_map = None;
+    }
+
+    uniforms.parallax_map = if planet.color_index == EARTH_COLOR_INDEX {
+        Some(maps.earth_relief.clone())
+    } else {
+        None
+    };
+}
+
+// Eclipse de Sol visto desde la Tierra, en el punto de su superficie donde la sombra de la Luna es
+// más profunda
+fn solar_eclipse(planets: &[Planet], time: f64, sphere_radius: f32, sizes: &SizeScale) -> Option<Eclipse> {
+    let earth = planets.iter().position(|planet| planet.name == "Tierra")?;
+    let moon = planets.iter().position(|planet| planet.parent == Some(earth))?;
+    let (sun, earth_center, moon_center) = (body_position(planets, 0, time), body_position(planets, earth, time), body_position(planets, moon, time));
+    let point = deepest_shadow_point(earth_center, sizes.radius(&planets[earth]) * sphere_radius, sun, moon_center);
+    eclipse_at(point, sun, sizes.radius(&planets[0]) * sphere_radius, moon_center, sizes.radius(&planets[moon]) * sphere_radius)
+}
+
+// Cuerpos que pueden eclipsar el Sol al cuerpo `index`: su planeta y sus lunas (el resto nunca
+// pasa tan cerca). Van con su posición relativa junto a `position`, donde se dibuja el cuerpo,
+// que con un cuerpo enfocado no es la de su órbita.
+fn eclipse_occluders(planets: &[Planet], index: usize, position: Vec3, time: f64, sphere_radius: f32, sizes: &SizeScale) -> Vec<Occluder> {
+    let own = body_position(planets, index, time);
+    planets
+        .iter()
+        .enumerate()
+        .filter(|&(other, planet)| other != 0 && (planet.parent == Some(index) || planets[index].parent == Some(other)))
+        .map(|(other, planet)| Occluder {
+            position: position + body_position(planets, other, time) - own,
+            radius: sizes.radius(planet) * sphere_radius,
+        })
+        .collect()
+}
+
+// El mapa de alturas de la Luna se genera una vez por semilla y queda guardado en disco
+fn moon_height_map_path(seeds: &SeedSource) -> String {
+    format!("assets/textures/moon_height_{:016x}.png", seeds.derive("moon_terrain", 0))
+}
+
+fn moon_terrain_noise(seeds: &SeedSource) -> FastNoiseLite {
+    create_ground_noise(seeds.noise_seed("moon_terrain", 0))
+}
+
+// Los mapas de normales precalculados se guardan aquí, uno por semilla
+const TEXTURE_CACHE_DIR: &str = "assets/textures/cache";
+
+fn moon_normal_map_path(seeds: &SeedSource) -> String {
+    format!("{}/moon_normal_{:016x}.png", TEXTURE_CACHE_DIR, seeds.derive("moon_terrain", 0))
+}
+
+// Mapa de normales del relieve; con caché se genera solo la primera vez y luego se lee del disco
+fn load_normal_map(noise: &FastNoiseLite, zoom: f32, path: &str, use_cache: bool) -> Texture {
+    if use_cache {
+        if let Ok(texture) = Texture::load(path) {
+            return texture;
+        }
+    }
+
+    let image = generate_spherical_normal_map(noise, zoom, 1024, 512);
+    if use_cache {
+        // Sin caché en disco la simulación sigue funcionando; solo tarda más en arrancar
+        let saved = std::fs::create_dir_all(TEXTURE_CACHE_DIR).and_then(|_| {
+            image.save(path).map_err(std::io::Error::other)
+        });
+        if let Err(error) = saved {
+            eprintln!("No se pudo guardar {}: {}", path, error);
+        }
+    }
+    Texture::from_image(&image)
+}
+
+fn load_terrain_texture(noise: &FastNoiseLite, path: &str) -> Texture {
+    if !Path::new(path).exists() {
+        generate_heightmap_png(noise, 100.0, 512, 256, path).expect("Failed to generate moon height map");
+    }
+    Texture::load(path).expect("Failed to load moon height map")
+}
+
+// Pasos de la carga inicial (modelos, tres mapas de superficie y el skybox)
+const LOADING_STEPS: usize = 5;
+
+// Pantalla de carga: el paso actual y una barra con lo que falta, mientras se generan las texturas
+fn show_loading(window: &mut Window, framebuffer: &mut Framebuffer, step: usize, total: usize, label: &str) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    framebuffer.clear();
+    framebuffer.draw_text_centered(width / 2, height / 2 - 24, label, 0xFFFFFF, 2);
+    framebuffer.render_progress_bar(width / 4, height / 2, width / 2, 12, step as f32 / total as f32, BarColors { fill: 0x4682B4, empty: 0x303030 });
+    // Si la ventana no acepta el frame se sigue cargando igual
+    let _ = window.update_with_buffer(&framebuffer.buffer, width, height);
+}
+
+const WINDOW_TITLE: &str = "Solar System Simulation";
+
+// Ventana principal; también sirve para recrearla si deja de aceptar cuadros
+fn create_window(width: usize, height: usize) -> Result<Window, String> {
+    Window::new(WINDOW_TITLE, width, height, WindowOptions::default()).map_err(|error| error.to_string())
 }
 
-fn load_texture(file_path: &str) -> DynamicImage {
-    image::open(Path::new(file_path)).expect("Failed to load texture")
+// Muestra el cuadro en la ventana, recreándola si deja de aceptarlos. Devuelve true si la ventana
+// es nueva; si no se puede mostrar nada, termina el programa.
+fn present_frame(window: &mut Window, present_recovery: &mut PresentRecovery, framebuffer: &Framebuffer, window_width: usize, window_height: usize) -> bool {
+    let outcome = present_recovery.present(
+        window,
+        || create_window(window_width, window_height),
+        &framebuffer.buffer,
+        framebuffer.width,
+        framebuffer.height,
+        Instant::now(),
+    );
+    match outcome {
+        PresentOutcome::Presented => false,
+        PresentOutcome::Retrying { error, first_failure } => {
+            if first_failure {
+                eprintln!("No se pudo actualizar la ventana ({}); se reintenta en los próximos cuadros", error);
+            }
+            false
+        }
+        PresentOutcome::Recreated { error } => {
+            eprintln!("La ventana siguió fallando ({}); se abrió una nueva", error);
+            true
+        }
+        PresentOutcome::Failed(error) => {
+            eprintln!("No se puede mostrar la simulación: {}", error);
+            std::process::exit(1);
+        }
+    }
 }
 
 
 fn render_skybox(framebuffer: &mut Framebuffer, skybox_texture: &DynamicImage) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+
+    framebuffer.split_rows_mut(BAND_HEIGHT).into_par_iter().for_each(|mut band| {
+        for y in band.rows() {
+            for x in 0..width {
+                // Escribir el color en el framebuffer con profundidad máxima
+                band.paint(x, y, skybox_color(skybox_texture, x as f32, y as f32, width, height), f32::INFINITY);
+            }
+        }
+    });
+}
+
+// Anillos del cuerpo, si tiene. Sus radios están en radios del planeta, así que `model` los
+// centra en el cuerpo y los escala por su radio en el mundo; la vista enfocada y la general pasan
+// por aquí.
+fn render_planet_rings(
+    framebuffer: &mut Framebuffer,
+    uniforms: &mut Uniforms,
+    ring_systems: &[(&str, RingSystem, Vec<Vertex>)],
+    planet: &Planet,
+    model: Mat4,
+) {
+    if let Some((_, rings, mesh)) = ring_systems.iter().find(|(name, _, _)| *name == planet.name) {
+        uniforms.model_matrix = model;
+        uniforms.displacement = Displacement::none();
+        rings.render(framebuffer, uniforms, mesh);
+    }
+}
+
+// Anillos de un cuerpo en su posición del frame, pendientes hasta que estén todos los cuerpos
+// opacos dibujados
+struct RingDraw<'a> {
+    planet: &'a Planet,
+    position: Vec3,
+    model: Mat4,
+}
+
+impl RenderOrder for RingDraw<'_> {
+    fn depth_from_camera(&self, camera: &Camera) -> f32 {
+        view_depth(camera, self.position)
+    }
+}
+
+// Color de la niebla de la nebulosa: suma de sus líneas de emisión según su peso
+fn nebula_color() -> Color {
+    NEBULA_EMISSION_LINES
+        .iter()
+        .fold(Color::black(), |color, &(nm, weight)| color + Color::from_wavelength(nm) * weight)
+}
+
+// Color del skybox en una posición de pantalla (que puede ser fraccionaria o salirse del borde)
+fn skybox_color(skybox_texture: &DynamicImage, x: f32, y: f32, width: usize, height: usize) -> u32 {
     let (texture_width, texture_height) = skybox_texture.dimensions();
 
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            // Mapear las coordenadas del framebuffer a las coordenadas de la textura
-            let tex_x = (x as f32 / (framebuffer.width - 1) as f32 * (texture_width - 1) as f32) as u32;
-            let tex_y = (y as f32 / (framebuffer.height - 1) as f32 * (texture_height - 1) as f32) as u32;
+    // Mapear las coordenadas del framebuffer a las coordenadas de la textura
+    let tex_x = (x / (width - 1) as f32 * (texture_width - 1) as f32).clamp(0.0, (texture_width - 1) as f32) as u32;
+    let tex_y = (y / (height - 1) as f32 * (texture_height - 1) as f32).clamp(0.0, (texture_height - 1) as f32) as u32;
 
-            // Obtener el color del píxel de la textura
-            let pixel = skybox_texture.get_pixel(tex_x, tex_y);
-            let color = (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | (pixel[2] as u32);
+    let pixel = skybox_texture.get_pixel(tex_x, tex_y);
+    (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | (pixel[2] as u32)
+}
+
+// Lente gravitatoria (solo visual): alrededor de un cuerpo, cada píxel del skybox se vuelve a
+// muestrear desplazado hacia el cuerpo en proporción a `gravity_strength * masa / distancia²`,
+// con la masa aproximada como radio³ y la distancia medida en radios en pantalla. Cerca del
+// borde el fondo se estira hacia afuera como un anillo de Einstein. Solo toca los píxeles con
+// profundidad infinita: lo ya dibujado encima del fondo no se deforma.
+fn distort_skybox_near_mass(
+    framebuffer: &mut Framebuffer,
+    skybox_texture: &DynamicImage,
+    planet: &Planet,
+    planet_screen_pos: Vec2,
+    planet_screen_radius: f32,
+    gravity_strength: f32,
+) {
+    if planet_screen_radius < 1.0 {
+        return;
+    }
 
-            // Escribir el color en el framebuffer con profundidad máxima
-            let index = y * framebuffer.width + x;
-            framebuffer.buffer[index] = color;
-            framebuffer.zbuffer[index] = std::f32::INFINITY; // Profundidad máxima
+    let mass = planet.radius.powi(3);
+    let reach = planet_screen_radius * LENSING_EXTENT;
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let x_start = (planet_screen_pos.x - reach).max(0.0) as usize;
+    let y_start = (planet_screen_pos.y - reach).max(0.0) as usize;
+    let x_end = ((planet_screen_pos.x + reach).max(0.0) as usize).min(width);
+    let y_end = ((planet_screen_pos.y + reach).max(0.0) as usize).min(height);
+
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            if framebuffer.read_depth(x, y).is_none_or(|depth| depth.is_finite()) {
+                continue;
+            }
+
+            let offset = planet_screen_pos - Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let distance = offset.magnitude() / planet_screen_radius;
+            if !(1.0..LENSING_EXTENT).contains(&distance) {
+                continue;
+            }
+
+            // Se apaga suavemente hacia el borde de la zona para que no se note el corte
+            let falloff = (1.0 - distance / LENSING_EXTENT).powi(2);
+            let shift = (gravity_strength * mass / (distance * distance) * falloff).min(distance * 0.9);
+            let sample = Vec2::new(x as f32, y as f32) + offset.normalize() * shift * planet_screen_radius;
+            framebuffer.write_pixel(x, y, skybox_color(skybox_texture, sample.x, sample.y, width, height));
         }
     }
 }
 
 
 fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize) {
+    match uniforms.render_mode {
+        RenderMode::Solid => {}
+        RenderMode::Wireframe => return render_wireframe(framebuffer, uniforms, vertex_array),
+        RenderMode::Normals => return render_normals(framebuffer, uniforms, vertex_array),
+    }
+
+    let draw_start = Instant::now();
+
+    // Los vértices compartidos entre triángulos vecinos se transforman una vez mientras sigan en la caché
+    let mut vertex_cache = VertexCache::new();
     let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
     for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+        transformed_vertices.push(vertex_cache.transform(vertex, uniforms));
     }
 
+    // Los triángulos con un vértice en el plano de la cámara no tienen proyección: se descartan
+    // (hasta que haya recorte contra el plano near, y también después, por las dudas)
     let mut triangles = Vec::new();
     for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
+        if i + 2 < transformed_vertices.len() && !transformed_vertices[i..i + 3].iter().any(|vertex| vertex.unprojected) {
             triangles.push([
                 transformed_vertices[i].clone(),
                 transformed_vertices[i + 1].clone(),
@@ -79,86 +755,311 @@ fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Ve
         }
     }
 
+    framebuffer.fragment_stats.triangles += triangles.len();
+    let count_rasterized = framebuffer.render_stats.count_rasterized;
+    let mut rasterized_triangles = 0;
     let mut fragments = Vec::new();
+    // En la vista en corte las caras traseras van aparte: son el relleno plano del hueco
+    let mut interior_fragments = Vec::new();
     for tri in &triangles {
-        fragments.extend(triangle::triangle(&tri[0], &tri[1], &tri[2]));
+        let interior = uniforms.cutaway.is_some() && triangle::is_backface(&tri[0], &tri[1], &tri[2]);
+        let target = if interior { &mut interior_fragments } else { &mut fragments };
+        let before = target.len();
+        target.extend(triangle::triangle_with_fill_rule(&tri[0], &tri[1], &tri[2], framebuffer.width, framebuffer.height, uniforms.fill_rule));
+        if count_rasterized && target.len() > before {
+            rasterized_triangles += 1;
+        }
     }
 
+    // Solo se sombrean los fragmentos que ganan la prueba de profundidad en ese momento. Con el
+    // corte, los del lado que apunta el plano se descartan antes.
+    let shading_start = Instant::now();
+    let fragment_count = fragments.len() + interior_fragments.len();
+    let clipped = |fragment: &Fragment| uniforms.cutaway.is_some_and(|plane| plane.clips(fragment.world_position));
+    let mut depth_passed = 0;
     for fragment in fragments {
         let x = fragment.position.x as usize;
         let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = select_shader(index, &fragment, &uniforms);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
+        if !clipped(&fragment) && framebuffer.shade_if_visible(x, y, fragment.depth, || select_shader(index, &fragment, uniforms).to_hex()) {
+            depth_passed += 1;
         }
     }
+    let interior_color = uniforms.color_blind_mode.apply(Color::from_hex(INTERIOR_COLOR)).to_hex();
+    for fragment in interior_fragments {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        if !clipped(&fragment) && framebuffer.shade_if_visible(x, y, fragment.depth, || interior_color) {
+            depth_passed += 1;
+        }
+    }
+
+    let stats = &mut framebuffer.render_stats;
+    let draw = DrawStats {
+        body: stats.current_body,
+        vertices: vertex_array.len(),
+        triangles: triangles.len(),
+        rasterized_triangles: count_rasterized.then_some(rasterized_triangles),
+        fragments: fragment_count,
+        depth_passed,
+        vertex_cache_hit_rate: vertex_cache.hit_rate(),
+        start: draw_start.saturating_duration_since(stats.frame_start),
+        duration: draw_start.elapsed(),
+        shading: shading_start.elapsed(),
+    };
+    stats.draws.push(draw);
+}
+
+// Color de la Tierra en `select_shader`: su océano usa reflejos en espacio de pantalla
+const EARTH_COLOR_INDEX: usize = 3;
+
+// Dibuja un objeto cuyos shaders leen el frame ya dibujado: primero guarda una copia
+// del framebuffer completo y luego rasteriza con esa copia como fuente de reflejos
+fn render_with_reflections(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize) {
+    let mut reflective_uniforms = uniforms.clone();
+    reflective_uniforms.reflection_source = Some(Arc::new(framebuffer.clone_buffer()));
+    render(framebuffer, &reflective_uniforms, vertex_array, index);
+}
+
+// Esfera del modelo y una versión de más triángulos para los cuerpos grandes en pantalla,
+// con el mismo radio
+struct SphereMeshes {
+    low: Vec<Vertex>,
+    high: Vec<Vertex>,
+}
+
+impl SphereMeshes {
+    fn for_size(&self, apparent_size: f32) -> &[Vertex] {
+        if apparent_size > HIGH_POLY_SIZE { &self.high } else { &self.low }
+    }
+}
+
+// Radio en pantalla (píxeles) por debajo del cual un cuerpo se dibuja como impostor:
+// su malla entera cabría en un píxel
+const IMPOSTOR_RADIUS: f32 = 0.5;
+
+// Dibuja la esfera de un cuerpo centrado en `center`: con su malla (y reflejos, la Tierra) o,
+// si en pantalla mide menos de un píxel, como un único píxel sombreado
+fn render_body(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize, center: Vec3, world_radius: f32) {
+    if uniforms.render_mode == RenderMode::Solid && projected_radius(uniforms, center, world_radius) < IMPOSTOR_RADIUS {
+        render_impostor(framebuffer, uniforms, index, center, world_radius);
+    } else if index == EARTH_COLOR_INDEX {
+        render_with_reflections(framebuffer, uniforms, vertex_array, index);
+    } else {
+        render(framebuffer, uniforms, vertex_array, index);
+    }
+}
+
+// Impostor de un cuerpo sub-píxel: se sombrea el punto de la superficie que mira a la cámara
+// y se escribe en el píxel del centro con la profundidad de ese punto
+fn render_impostor(framebuffer: &mut Framebuffer, uniforms: &Uniforms, index: usize, center: Vec3, world_radius: f32) {
+    let to_camera = uniforms.view_pos - center;
+    if to_camera.magnitude() <= world_radius {
+        return;
+    }
+    let normal = to_camera.normalize();
+    let world_position = center + normal * world_radius;
+
+    let screen = match project_point(uniforms, center) {
+        Some(screen) if screen.z > 0.0 && screen.x >= 0.0 && screen.y >= 0.0 => screen,
+        _ => return,
+    };
+    let object_position = uniforms
+        .model_matrix
+        .try_inverse()
+        .map(|inverse| inverse * Vec4::new(world_position.x, world_position.y, world_position.z, 1.0))
+        .map(|p| Vec3::new(p.x, p.y, p.z))
+        .unwrap_or(normal);
+
+    let fragment = Fragment::new(
+        Vec2::new(screen.x.floor(), screen.y.floor()),
+        Color::new(100, 100, 100),
+        screen.z - world_radius,
+        normal,
+        1.0,
+        object_position,
+        world_position,
+    );
+    framebuffer.shade_if_visible(screen.x as usize, screen.y as usize, fragment.depth, || {
+        select_shader(index, &fragment, uniforms).to_hex()
+    });
+}
+
+const WIREFRAME_COLOR: u32 = 0x00FF66;
+const NORMAL_LENGTH: f32 = 0.15;
+
+fn draw_depth_line(framebuffer: &mut Framebuffer, a: &Vertex, b: &Vertex, color: u32) {
+    framebuffer.set_current_color(color);
+    for fragment in line::line(a, b) {
+        if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+            framebuffer.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+        }
+    }
+}
+
+// Dibuja solo las aristas de las caras frontales, con prueba de profundidad y sin sombrear fragmentos
+fn render_wireframe(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) {
+    let transformed_vertices: Vec<Vertex> = vertex_array
+        .iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    for tri in transformed_vertices.chunks_exact(3) {
+        if tri.iter().any(|vertex| vertex.unprojected) || triangle::is_backface(&tri[0], &tri[1], &tri[2]) {
+            continue;
+        }
+        draw_depth_line(framebuffer, &tri[0], &tri[1], WIREFRAME_COLOR);
+        draw_depth_line(framebuffer, &tri[1], &tri[2], WIREFRAME_COLOR);
+        draw_depth_line(framebuffer, &tri[2], &tri[0], WIREFRAME_COLOR);
+    }
 }
 
-fn render_saturn_rings(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize) {
-    let num_rings = 50;
-    let radius = 10.0;
-    let y_offset = 3.0;
+// Dibuja un segmento corto desde cada vértice visible a lo largo de su normal, coloreado por dirección
+fn render_normals(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) {
+    let transformed_vertices: Vec<Vertex> = vertex_array
+        .iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    // Longitud proporcional a la escala del objeto
+    let scale = Vec3::new(uniforms.model_matrix[(0, 0)], uniforms.model_matrix[(1, 0)], uniforms.model_matrix[(2, 0)]).magnitude();
+    let length = NORMAL_LENGTH * scale;
+
+    for tri in transformed_vertices.chunks_exact(3) {
+        if tri.iter().any(|vertex| vertex.unprojected) || triangle::is_backface(&tri[0], &tri[1], &tri[2]) {
+            continue;
+        }
+
+        for vertex in tri {
+            let normal = vertex.transformed_normal.normalize();
+            let Some(tip_position) = project_point(uniforms, vertex.world_position + normal * length) else {
+                continue;
+            };
+
+            let mut tip = vertex.clone();
+            tip.transformed_position = tip_position;
+            tip.world_position = vertex.world_position + normal * length;
+
+            let color = Color::from_float(normal.x * 0.5 + 0.5, normal.y * 0.5 + 0.5, normal.z * 0.5 + 0.5);
+            draw_depth_line(framebuffer, vertex, &tip, color.to_hex());
+        }
+    }
+}
+
+// Radio aproximado en píxeles de una esfera del mundo vista desde la cámara actual
+fn projected_radius(uniforms: &Uniforms, center: Vec3, radius: f32) -> f32 {
+    let view_center = uniforms.view_matrix * Vec4::new(center.x, center.y, center.z, 1.0);
+    let depth = -view_center.z;
+    if depth <= 0.0 {
+        return 0.0;
+    }
+
+    radius * uniforms.projection_matrix[(1, 1)].abs() / depth * uniforms.viewport_matrix[(1, 1)].abs()
+}
+
+// Proyecta un punto del mundo a pantalla: (x, y) en píxeles y z como distancia en espacio de vista
+fn project_point(uniforms: &Uniforms, point: Vec3) -> Option<Vec3> {
+    let view_position = uniforms.view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+    let clip = uniforms.projection_matrix * view_position;
+    if clip.w.abs() < f32::EPSILON {
+        return None;
+    }
 
-    let saturn_position = Vec3::new(0.0, 0.0, 0.0);
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = uniforms.viewport_matrix * ndc;
+    Some(Vec3::new(screen.x, screen.y, -view_position.z))
+}
 
-    for i in 0..num_rings {
-        let angle = 2.0 * PI * i as f32 / num_rings as f32;
-        let ring_translation = Vec3::new(radius * angle.cos(), y_offset, radius * angle.sin()) + saturn_position;
+// Pase de los anillos: rasteriza con submuestras 2x2 y mezcla según la cobertura,
+// para que la geometría delgada (casi de canto) no se rompa en una línea punteada
+fn render_rings(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], index: usize) {
+    if uniforms.render_mode != RenderMode::Solid {
+        return render(framebuffer, uniforms, vertex_array, index);
+    }
 
-        let mut ring_uniforms = uniforms.clone();
-        ring_uniforms.model_matrix = create_model_matrix(ring_translation, 0.2, Vec3::new(0.0, 0.0, 0.0));
+    let transformed_vertices: Vec<Vertex> = vertex_array
+        .iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
 
-        render(framebuffer, &ring_uniforms, vertex_array, index);
+    for tri in transformed_vertices.chunks_exact(3) {
+        if tri.iter().any(|vertex| vertex.unprojected) {
+            continue;
+        }
+        for (fragment, coverage) in triangle::triangle_coverage(&tri[0], &tri[1], &tri[2], framebuffer.width, framebuffer.height) {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            // Lo tapado por el planeta no llega al shader
+            framebuffer.blend_if_visible(x, y, fragment.depth, || {
+                let (shaded_color, opacity) = select_shader_with_alpha(index, &fragment, uniforms);
+                (shaded_color.to_hex(), coverage * opacity)
+            });
+        }
     }
 }
 
+// Las órbitas se recortan contra este w de recorte (la distancia de vista) antes de proyectar,
+// para que los puntos detrás de la cámara no se reflejen al otro lado de la pantalla
+const ORBIT_NEAR_W: f32 = 1e-3;
+
+// Cada segmento se recorta primero contra el plano cercano y después, ya en pantalla, contra
+// los bordes del framebuffer, así la órbita no se corta donde sale de la vista
 fn draw_orbit(
     framebuffer: &mut Framebuffer,
     planet: &Planet,
+    center: Vec3,
     uniforms: &Uniforms,
     segments: usize,
     color: u32,
+    contrast: Contrast,
 ) {
-    let mut previous_screen_point = None;
-
-    for i in 0..=segments {
-        let angle = 2.0 * PI * (i as f32 / segments as f32);
-
-        // Calcular la posición 3D del punto en la órbita
-        let orbit_point = Vec3::new(
-            planet.distance_from_sun * angle.cos(),
-            0.0,
-            planet.distance_from_sun * angle.sin(),
-        );
-
-
-        // Transformar el punto
-        let transformed_point = uniforms.viewport_matrix
-            * uniforms.projection_matrix
-            * uniforms.view_matrix
-            * Vec4::new(orbit_point.x, orbit_point.y, orbit_point.z, 1.0);
-
-        // Convertir a coordenadas de pantalla
-        if transformed_point.w != 0.0 {
-            let screen_x = ((transformed_point.x / transformed_point.w + 1.0) * 0.5 * framebuffer.width as f32) as isize;
-            let screen_y = ((1.0 - (transformed_point.y / transformed_point.w + 1.0) * 0.5) * framebuffer.height as f32) as isize;
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    let to_clip = |angle: f32| {
+        let orbit_point = center + orbit_offset(planet, angle);
+        view_projection * Vec4::new(orbit_point.x, orbit_point.y, orbit_point.z, 1.0)
+    };
+    let to_screen = |clip: Vec4| {
+        let screen = uniforms.viewport_matrix * (clip / clip.w);
+        (screen.x, screen.y)
+    };
 
+    let mut lines = Vec::with_capacity(segments);
+    let mut previous = to_clip(0.0);
+    for i in 1..=segments {
+        let current = to_clip(2.0 * PI * (i as f32 / segments as f32));
+        let (mut start, mut end) = (previous, current);
+        previous = current;
 
-            // Validar y dibujar
-            if screen_x >= 0 && screen_y >= 0 {
-                let screen_x = screen_x as usize;
-                let screen_y = screen_y as usize;
+        // Recorte contra el plano cercano
+        if start.w < ORBIT_NEAR_W && end.w < ORBIT_NEAR_W {
+            continue;
+        }
+        if start.w < ORBIT_NEAR_W {
+            start = start.lerp(&end, (ORBIT_NEAR_W - start.w) / (end.w - start.w));
+        } else if end.w < ORBIT_NEAR_W {
+            end = end.lerp(&start, (ORBIT_NEAR_W - end.w) / (start.w - end.w));
+        }
 
-                if let Some((prev_x, prev_y)) = previous_screen_point {
-                    framebuffer.draw_line(prev_x, prev_y, screen_x, screen_y, color);
-                }
+        if let Some(((x0, y0), (x1, y1))) = framebuffer.clip_line(to_screen(start), to_screen(end)) {
+            lines.push((x0 as usize, y0 as usize, x1 as usize, y1 as usize));
+        }
+    }
 
-                previous_screen_point = Some((screen_x, screen_y));
-            }
+    // `draw_line` deja el último píxel para el segmento siguiente, que no se dibuja si este salió
+    // de la pantalla: se pinta aparte
+    if contrast.is_high() {
+        for &(x0, y0, x1, y1) in &lines {
+            framebuffer.draw_thick_line(x0, y0, x1, y1, color);
+            framebuffer.write_pixel(x1, y1, color);
         }
+        return;
     }
+    framebuffer.split_rows_mut(BAND_HEIGHT).into_par_iter().for_each(|mut band| {
+        let rows = band.rows();
+        for &(x0, y0, x1, y1) in lines.iter().filter(|&&(_, y0, _, y1)| y0.min(y1) < rows.end && y0.max(y1) >= rows.start) {
+            band.draw_line(x0, y0, x1, y1, color);
+            band.draw_filled_rect(x1, y1, 1, 1, color);
+        }
+    });
 }
 
 fn lerp(start: Vec3, end: Vec3, t: f32) -> Vec3 {
@@ -171,111 +1072,838 @@ fn is_in_camera_view(camera: &Camera, object_position: Vec3, object_radius: f32)
     let camera_forward = (camera.center - camera.eye).normalize();
     let dot_product = view_vector.dot(&camera_forward);
 
-    // Convertir el FOV de grados a radianes y calcular el coseno del ángulo
-    let fov_radians = camera.fov.to_radians() / 2.0;
-    dot_product > fov_radians.cos()
-}
+    // Convertir el FOV de grados a radianes y calcular el coseno del ángulo
+    let fov_radians = camera.fov.to_radians() / 2.0;
+    dot_product > fov_radians.cos()
+}
+
+// Modelo de la esfera (en sus dos resoluciones), anillos, ruido de cada cuerpo y texturas de
+// superficie: todo lo que se prepara al arrancar para dibujar los cuerpos de `planets`
+struct SceneAssets {
+    sphere_radius: f32,
+    sphere_meshes: SphereMeshes,
+    ring_systems: Vec<(&'static str, RingSystem, Vec<Vertex>)>,
+    planet_noises: Vec<Arc<FastNoiseLite>>,
+    surface_maps: SurfaceMaps,
+}
+
+impl SceneAssets {
+    // `progress` recibe el paso de la carga (de 0 a LOADING_STEPS) y su nombre
+    fn load(seeds: &SeedSource, planets: &[Planet], use_texture_cache: bool, mut progress: impl FnMut(usize, &str)) -> Self {
+        progress(0, "CARGANDO MODELOS");
+
+        // El modelo de la esfera viene apoyado sobre el plano y = 0; se centra en el origen al cargarlo
+        let sphere_obj = Obj::load(SPHERE_MODEL).expect("Failed to load sphere.obj");
+        let sphere_obj = sphere_obj.transform(Mat4::new_translation(&-sphere_obj.bounding_center()));
+        let sphere_radius = sphere_obj.bounding_radius();
+        let sphere_meshes = SphereMeshes {
+            low: sphere_obj.get_vertex_array(),
+            high: ProceduralMesh::uv_sphere(sphere_radius, HIGH_POLY_STACKS, HIGH_POLY_SLICES),
+        };
+
+        // Planetas con anillos; todos usan el mismo shader con su propio perfil de densidad
+        let ring_systems = [("Saturno", RingSystem::saturn()), ("Urano", RingSystem::uranus()), ("Neptuno", RingSystem::neptune())]
+            .into_iter()
+            .map(|(name, rings)| {
+                let mesh = rings.mesh(RING_SEGMENTS);
+                (name, rings, mesh)
+            })
+            .collect();
+
+        // Cada cuerpo tiene su propio ruido, derivado de la semilla global
+        let planet_noises: Vec<Arc<FastNoiseLite>> = (0..planets.len())
+            .map(|index| Arc::new(create_noise(seeds.noise_seed("planet", index as u64))))
+            .collect();
+
+        let earth_index = planets.iter().position(|p| p.color_index == EARTH_COLOR_INDEX).unwrap_or(0);
+        progress(1, "RELIEVE DE LA LUNA");
+        let moon_terrain = Arc::new(load_terrain_texture(&moon_terrain_noise(seeds), &moon_height_map_path(seeds)));
+        progress(2, "NORMALES DE LA LUNA");
+        let moon_normals = Arc::new(load_normal_map(&moon_terrain_noise(seeds), 100.0, &moon_normal_map_path(seeds), use_texture_cache));
+        // El relieve de la Tierra sale del mismo ruido que sus biomas (zoom 15 sobre la esfera del modelo)
+        progress(3, "RELIEVE DE LA TIERRA");
+        let earth_relief = Arc::new(Texture::from_heightmap(&planet_noises[earth_index], 15.0 * sphere_radius, 512, 256));
+
+        SceneAssets {
+            sphere_radius,
+            sphere_meshes,
+            ring_systems,
+            planet_noises,
+            surface_maps: SurfaceMaps { moon_terrain, moon_normals, earth_relief },
+        }
+    }
+}
+
+// Uniforms al arrancar, para un framebuffer de `width` x `height` con la cámara en `eye`; el
+// modelo, el ruido y los mapas se fijan después en cada cuerpo
+fn initial_uniforms(noise: Arc<FastNoiseLite>, width: usize, height: usize, eye: Vec3) -> Uniforms {
+    Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix: Mat4::identity(),
+        projection_matrix: create_perspective_matrix(width as f32, height as f32, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE),
+        viewport_matrix: create_viewport_matrix(width as f32, height as f32),
+        time: 0.0,
+        noise,
+        displacement: Displacement::none(),
+        view_pos: eye,
+        height_map: None,
+        color_map: None,
+        parallax_map: None,
+        normal_map: None,
+        render_mode: RenderMode::Solid,
+        color_blind_mode: ColorBlindMode::None,
+        spacecraft_velocity: Vec3::new(0.0, 0.0, 0.0),
+        texture_lod: 0,
+        reflection_source: None,
+        ring_profile: None,
+        ring_arcs: None,
+        light_probe: None,
+        cutaway: None,
+        sun_radius: 0.0,
+        occluders: Vec::new(),
+        contrast: 1.0,
+        fill_rule: triangle::FillRule::TopLeft,
+    }
+}
+
+// Vista y proyección del cuadro, con los planos de recorte de `clip_planes` o, sin ellos, adaptados
+// a la distancia de la cámara a su objetivo
+fn set_camera_matrices(uniforms: &mut Uniforms, camera: &Camera, width: usize, height: usize, clip_planes: Option<(f32, f32)>) {
+    uniforms.view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+    uniforms.view_pos = camera.eye;
+    let (near, far) = clip_planes.unwrap_or_else(|| adaptive_clip_planes((camera.center - camera.eye).magnitude()));
+    uniforms.projection_matrix = create_perspective_matrix(width as f32, height as f32, camera.fov_y, near, far);
+}
+
+// Lo que cambia de un cuadro a otro de la vista general: la cámara, la escena ya colocada en
+// `time` con `place_bodies`, la escala de tiempo efectiva (para el desenfoque de movimiento) y
+// las opciones de dibujo
+struct OverviewFrame<'a> {
+    camera: &'a Camera,
+    scene: &'a SceneGraph,
+    time: f64,
+    effective_time_scale: f32,
+    contrast: Contrast,
+    show_orbits: bool,
+    sizes: &'a SizeScale,
+}
+
+// Vista general sin enfoque: órbitas (si `show_orbits`), cuerpos, anillos y nombres, con las
+// posiciones y matrices de la escena del cuadro. Devuelve cuántos cuerpos estaban dentro del
+// campo de visión.
+fn render_overview(
+    framebuffer: &mut Framebuffer,
+    overlay: &mut Overlay,
+    uniforms: &mut Uniforms,
+    planets: &[Planet],
+    assets: &SceneAssets,
+    motion_blur: &mut MotionBlur,
+    frame: OverviewFrame,
+) -> usize {
+    let OverviewFrame { camera, scene, time, effective_time_scale, contrast, show_orbits, sizes } = frame;
+    // Renderizar todo el sistema solar. La Tierra se deja para el final,
+    // para que sus reflejos vean el resto del frame ya dibujado.
+    let sphere_radius = assets.sphere_radius;
+    let mut deferred_earth = None;
+    let mut ring_draws = Vec::new();
+    let mut visible = 0;
+    for item in scene.draw_list() {
+        let Some(index) = scene.parent(item.node) else {
+            continue;
+        };
+        let planet = &planets[index];
+        let translation = scene.world_position(index);
+        let radius = sizes.radius(planet);
+
+        // Los anillos quedan pendientes si su cuerpo se ve y tiene malla
+        if item.mesh == NodeMesh::Rings {
+            if is_in_camera_view(camera, translation, radius) && planet.apparent_size(sizes, translation, camera.eye, sphere_radius, framebuffer.height) >= MIN_MESH_SIZE {
+                ring_draws.push(RingDraw { planet, position: translation, model: item.world });
+            }
+            continue;
+        }
+
+        if show_orbits {
+            let orbit_center = planet.parent.map(|parent| scene.world_position(parent)).unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+            draw_orbit(framebuffer, planet, orbit_center, uniforms, 100, ORBIT_COLOR, contrast);
+        }
+
+        if planet.color_index == EARTH_COLOR_INDEX {
+            deferred_earth = Some((index, planet, translation, item.world));
+            continue;
+        }
+
+        if is_in_camera_view(camera, translation, radius) {
+            uniforms.model_matrix = item.world;
+            uniforms.displacement = planet.displacement;
+            uniforms.contrast = planet.contrast;
+            uniforms.noise = assets.planet_noises[index].clone();
+            bind_surface_maps(uniforms, planet, &assets.surface_maps, translation, radius * sphere_radius);
+            uniforms.occluders = eclipse_occluders(planets, index, translation, time, sphere_radius, sizes);
+            let size = planet.apparent_size(sizes, translation, camera.eye, sphere_radius, framebuffer.height);
+            framebuffer.render_stats.current_body = planet.name;
+            render_body(framebuffer, uniforms, assets.sphere_meshes.for_size(size), planet.color_index, translation, radius * sphere_radius);
+            motion_blur.render_trail(framebuffer, uniforms, index, translation, radius * sphere_radius, effective_time_scale);
+            visible += 1;
+        } else {
+            motion_blur.forget(index);
+        }
+    }
+
+    if let Some((index, planet, translation, world)) = deferred_earth {
+        let radius = sizes.radius(planet);
+        if is_in_camera_view(camera, translation, radius) {
+            uniforms.model_matrix = world;
+            uniforms.displacement = planet.displacement;
+            uniforms.contrast = planet.contrast;
+            uniforms.noise = assets.planet_noises[index].clone();
+            bind_surface_maps(uniforms, planet, &assets.surface_maps, translation, radius * sphere_radius);
+            uniforms.occluders = eclipse_occluders(planets, index, translation, time, sphere_radius, sizes);
+            let size = planet.apparent_size(sizes, translation, camera.eye, sphere_radius, framebuffer.height);
+            framebuffer.render_stats.current_body = planet.name;
+            render_body(framebuffer, uniforms, assets.sphere_meshes.for_size(size), planet.color_index, translation, radius * sphere_radius);
+            motion_blur.render_trail(framebuffer, uniforms, index, translation, radius * sphere_radius, effective_time_scale);
+            visible += 1;
+        } else {
+            motion_blur.forget(index);
+        }
+    }
+
+    // Los anillos se mezclan con lo que ya está dibujado: después de todos los cuerpos
+    // opacos (un cuerpo detrás de unos anillos no los tapa) y de atrás hacia adelante
+    sort_back_to_front(&mut ring_draws, camera);
+    for draw in &ring_draws {
+        render_planet_rings(framebuffer, uniforms, &assets.ring_systems, draw.planet, draw.model);
+    }
+
+    // Nombres de los cuerpos que se ven de más de MIN_LABEL_SIZE píxeles, a su derecha en pantalla
+    let camera_right = Vec3::new(uniforms.view_matrix[(0, 0)], uniforms.view_matrix[(0, 1)], uniforms.view_matrix[(0, 2)]);
+    for (index, planet) in planets.iter().enumerate() {
+        let position = scene.world_position(index);
+        let radius = sizes.radius(planet);
+        if planet.apparent_size(sizes, position, camera.eye, sphere_radius, framebuffer.height) > MIN_LABEL_SIZE {
+            let anchor = position + camera_right * (radius * sphere_radius * 1.2);
+            draw_label(framebuffer, overlay, uniforms, anchor, &planet.name.to_uppercase(), BODY_LABEL_COLOR);
+        }
+    }
+
+    visible
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let seeds = SeedSource::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+
+    // Comprobaciones del pipeline sin abrir ventana, para diagnosticar problemas de render
+    if args.iter().any(|arg| arg == "--self-test") {
+        let passed = selftest::run(&seeds);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    let stress = StressTest::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let skybox_config = SkyboxConfig::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let night_sky = NightSky::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let mut replay_recorder = ReplayRecorder::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let mut color_grading = ColorGrading::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let mut ab_reference = AbReference::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+
+    // Prueba de carga sin ventana: mide el tiempo por cuadro con los cuerpos extra
+    if let Some(stress) = stress.as_ref().filter(|stress| stress.headless) {
+        let use_texture_cache = !args.iter().any(|arg| arg == "--no-texture-cache");
+        let passed = stress::run_headless(stress, &seeds, use_texture_cache);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // Cuadro de referencia sin ventana: lo guarda o lo compara con uno de otra plataforma
+    if let Some(position) = args.iter().position(|arg| arg == "--reference-frame") {
+        let Some(path) = args.get(position + 1) else {
+            eprintln!("--reference-frame espera la ruta de un PNG");
+            std::process::exit(2);
+        };
+        let use_texture_cache = !args.iter().any(|arg| arg == "--no-texture-cache");
+        match reference_frame::run_reference_check(&seeds, path, use_texture_cache) {
+            Ok(passed) => std::process::exit(if passed { 0 } else { 1 }),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Comparación A/B sin ventana de las escenas de referencia con el código actual
+    if args.iter().any(|arg| arg == "--compare") {
+        let use_texture_cache = !args.iter().any(|arg| arg == "--no-texture-cache");
+        if let Err(error) = compare::run_compare(&seeds, &args, use_texture_cache) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    let window_width = 800;
+    let window_height = 600;
+    let framebuffer_width = 800;
+    let framebuffer_height = 600;
+
+    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    let mut window = create_window(window_width, window_height).unwrap_or_else(|error| {
+        eprintln!("No se pudo abrir la ventana: {}", error);
+        std::process::exit(1);
+    });
+
+    framebuffer.set_background_color(0x000000);
+
+    let mut camera = Camera::new(
+        Vec3::new(50.0, 100.0, 250.0), // Posición inicial
+        Vec3::new(0.0, 0.0, 0.0),     // Centro (sol)
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+
+    let noise = Arc::new(create_noise(seeds.noise_seed("default", 0)));
+    let nebula_noise = Arc::new(create_noise(seeds.noise_seed("nebula", 0)));
+    let mut uniforms = initial_uniforms(noise.clone(), framebuffer_width, framebuffer_height, camera.eye);
+    uniforms.fill_rule = triangle::FillRule::from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let clip_planes = clip_planes_from_args(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+
+    let mut planets = solar_system();
+    if let Some(stress) = &stress {
+        planets.extend(stress_bodies(&seeds, stress.bodies));
+    }
+    seed_start_angles(&mut planets, &seeds);
+    // Efemérides de JPL en texto: los cuerpos que cubren siguen sus posiciones reales
+    if let Some(position) = args.iter().position(|arg| arg == "--spk") {
+        let path = args.get(position + 1).unwrap_or_else(|| {
+            eprintln!("--spk espera la ruta de un archivo SPK en texto");
+            std::process::exit(2);
+        });
+        let file = SpkFile::load(path).unwrap_or_else(|error| {
+            eprintln!("No se pudo leer el archivo SPK {}", error);
+            std::process::exit(2);
+        });
+        let attached = attach_ephemerides(&mut planets, &file);
+        println!("{}: efemérides para {} cuerpos", path, attached);
+    }
+    let use_texture_cache = !args.iter().any(|arg| arg == "--no-texture-cache");
+    let assets = SceneAssets::load(&seeds, &planets, use_texture_cache, |step, label| {
+        show_loading(&mut window, &mut framebuffer, step, LOADING_STEPS, label)
+    });
+    let sphere_radius = assets.sphere_radius;
+    let mut scene = system_scene(&planets, &assets.ring_systems).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    // Demo guionizada: los nombres de sus cuerpos se comprueban con la lista ya armada
+    let mut timeline = TimelinePlayer::from_args(&args, &planets).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let mut timeline_caption: Option<String> = None;
+    // Barra de tiempo, con la ventana en años de la Tierra
+    let earth_year = planets.iter().find(|planet| planet.name == "Tierra").map_or(1.0, |earth| 2.0 * PI / earth.orbit_speed);
+    let mut time_scrubber = TimeScrubber::new(earth_year);
+    // `toggle aberration` en la paleta; --aberration <intensidad> la deja activa desde el arranque
+    let aberration_arg = args.iter().position(|arg| arg == "--aberration");
+    let aberration_strength = match aberration_arg {
+        None => DEFAULT_ABERRATION,
+        Some(position) => args
+            .get(position + 1)
+            .and_then(|value| value.parse::<f32>().ok())
+            .filter(|strength| *strength > 0.0 && *strength <= MAX_ABERRATION)
+            .unwrap_or_else(|| {
+                eprintln!("--aberration espera la intensidad de la aberracion cromatica (hasta {}), como {}", MAX_ABERRATION, DEFAULT_ABERRATION);
+                std::process::exit(2);
+            }),
+    };
+    let mut aberration_enabled = aberration_arg.is_some();
+    let mut time_slider = TimeSlider::from_args(&args, earth_year).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+
+    let mut focused_planet: Option<&Planet> = None;
+    let mut bird_eye_view = false;
+    show_loading(&mut window, &mut framebuffer, 4, LOADING_STEPS, "FONDO ESTELAR");
+    let skybox = skybox_config.load().unwrap_or_else(|error| {
+        eprintln!("No se pudo cargar el fondo {}", error);
+        std::process::exit(2);
+    });
+    uniforms.light_probe = Some(Arc::new(skybox.light_probe()));
+    show_loading(&mut window, &mut framebuffer, LOADING_STEPS, LOADING_STEPS, "LISTO");
+    let mut prev_mouse_x = None;
+    let mut mouse_active = false;
+    let mut transitioning = false;
+    let mut transition_target_eye = camera.eye;
+    let mut transition_target_center = camera.center;
+    let mut transition_speed = 0.05;
+    let mut clock = SimClock::from_args(&args, earth_year).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let mut planet_table = PlanetTable::new();
+    let mut show_debug_frustum = false;
+    let mut auto_exposure = AutoExposure::new(DEFAULT_MIN_EV, DEFAULT_MAX_EV);
+    let mut last_frame = Instant::now();
+    let mut gravity_grid = GravityWellGrid::new();
+    let mut pan_drag = PanDrag::new();
+    let mut solar_flares = SolarFlares::new(seeds.derive("flares", 0));
+    let mut meteor_shower = MeteorShower::new(seeds.derive("meteors", 0));
+    let mut depth_of_field = DepthOfField::new();
+    let mut motion_blur = MotionBlur::new(planets.len());
+    let mut orbit_markers = OrbitMarkers::new();
+    let mut roche_overlay = RocheOverlay::new();
+    let mut ecliptic_grid = EclipticGrid::new();
+    let mut trajectory = SpacecraftTrajectory::new();
+    let mut flyby_planner = FlybyPlanner::new();
+    let mut contrast = Contrast::from_args(&args);
+    let mut status_line = StatusLine::from_args(&args);
+    let mut overlay = Overlay::new(framebuffer_width, framebuffer_height);
+    let mut effects = EffectsBuffer::from_args(&args, framebuffer_width, framebuffer_height).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    });
+    let mut long_exposure = LongExposure::new();
+    let mut time_scale = 1.0;
+    let mut paused = false;
+    let mut present_recovery = PresentRecovery::new();
+    let mut command_palette = CommandPalette::new();
+    let mut show_orbits = true;
+    let mut show_profile = false;
+    let mut warp_mode = false;
+    let mut event_log = EventLog::from_args(&args);
+    // Con --true-sizes arranca ya con los tamaños relativos reales (Z vuelve a los didácticos)
+    let mut sizes = if args.iter().any(|arg| arg == "--true-sizes") { SizeScale::true_sizes() } else { SizeScale::new() };
+    let mut cutaway = Cutaway::new();
+    let mut night_sky_mode = false;
+    let mut replay: Option<ReplayScrubber> = None;
+    // Contar los triángulos que llegan a rasterizarse cuesta una comprobación por triángulo
+    framebuffer.render_stats.count_rasterized = args.iter().any(|arg| arg == "--profile-triangles");
+    let stats = planet_stats();
+    let mut timeline_clock = Instant::now();
+
+    while window.is_open() {
+        // Repetición instantánea: mientras se recorre el historial la simulación no avanza ni se
+        // dibuja. Esc o Retroceso vuelven al vivo, con los relojes puestos al día para que nada salte.
+        if let Some(scrubber) = replay.as_mut() {
+            if window.is_key_pressed(Key::Left, minifb::KeyRepeat::Yes) {
+                scrubber.step(-1);
+            }
+            if window.is_key_pressed(Key::Right, minifb::KeyRepeat::Yes) {
+                scrubber.step(1);
+            }
+            scrubber.render(&mut framebuffer, contrast);
+            // Los cuadros se guardaron sin el ajuste de color salvo con --grade-skip-hud
+            if color_grading.include_hud {
+                color_grading.apply(&mut framebuffer);
+            }
+            if window.is_key_pressed(Key::Escape, minifb::KeyRepeat::No) || window.is_key_pressed(Key::Backspace, minifb::KeyRepeat::No) {
+                replay = None;
+                last_frame = Instant::now();
+                timeline_clock = last_frame;
+            }
+            if present_frame(&mut window, &mut present_recovery, &framebuffer, window_width, window_height) {
+                prev_mouse_x = None;
+                mouse_active = false;
+                pan_drag = PanDrag::new();
+            }
+            continue;
+        }
+
+        // Paleta de comandos (Ctrl+P). Mientras está abierta se queda con todo el teclado, y Esc
+        // la cierra en vez de salir del programa.
+        let ctrl_down = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+        let command = if command_palette.visible {
+            command_palette.handle_input(&window, &planets)
+        } else {
+            // Con una línea de tiempo en curso, Esc solo la corta
+            if window.is_key_pressed(Key::Escape, minifb::KeyRepeat::No) {
+                match timeline.as_mut() {
+                    Some(player) => player.abort(),
+                    None => break,
+                }
+            }
+            if ctrl_down && window.is_key_pressed(Key::P, minifb::KeyRepeat::No) {
+                command_palette.open();
+            }
+            None
+        };
+        match command {
+            Some(Command::Focus(index)) => {
+                focused_planet = Some(&planets[index]);
+                let (eye, center) = focus_targets(&planets, index, &sizes);
+                transition_target_eye = eye;
+                transition_target_center = center;
+                transitioning = true;
+            }
+            Some(Command::SetTimeScale(scale)) => time_scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE),
+            Some(Command::Toggle(toggle)) => match toggle {
+                Toggle::Orbits => show_orbits = !show_orbits,
+                Toggle::Markers => orbit_markers.toggle(),
+                Toggle::Ecliptic => ecliptic_grid.toggle(),
+                Toggle::Gravity => gravity_grid.toggle(),
+                Toggle::Table => planet_table.toggle(),
+                Toggle::Contrast => contrast = contrast.toggled(),
+                Toggle::Pause => paused = !paused,
+                Toggle::Profile => show_profile = !show_profile,
+                Toggle::Sizes => sizes.toggle(),
+                Toggle::Warp => warp_mode = !warp_mode,
+                Toggle::Aberration => aberration_enabled = !aberration_enabled,
+                Toggle::Roche => roche_overlay.toggle(),
+            },
+            Some(Command::ResetCamera) => {
+                focused_planet = None;
+                bird_eye_view = false;
+                transition_target_eye = Vec3::new(50.0, 100.0, 250.0);
+                transition_target_center = Vec3::new(0.0, 0.0, 0.0);
+                transitioning = true;
+            }
+            Some(Command::DumpStats) => {
+                // Lo que hay en el framebuffer es todavía el cuadro anterior, completo
+                let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                match framebuffer.render_stats.export(&format!("frame_stats_{}.json", stamp)) {
+                    Ok(path) => println!("Estadísticas del cuadro guardadas en {}", path),
+                    Err(error) => eprintln!("No se pudieron guardar las estadísticas: {}", error),
+                }
+            }
+            None => {}
+        }
+        let shortcut_pressed = |key: Key| !command_palette.visible && window.is_key_pressed(key, minifb::KeyRepeat::No);
+
+        // Abrir la repetición con los últimos segundos; empieza en el cuadro siguiente
+        if shortcut_pressed(Key::Backspace) {
+            replay = ReplayScrubber::open(replay_recorder.frames());
+        }
+
+        // Alternar entre la vista normal y la "bird's eye view"
+        if shortcut_pressed(Key::B) && !ctrl_down {
+            bird_eye_view = !bird_eye_view;
+            if bird_eye_view {
+                transition_target_eye = Vec3::new(0.0, 500.0, 200.0);
+                transition_target_center = Vec3::new(0.0, 0.0, 0.0);
+                transitioning = true;
+            } else {
+                transition_target_eye = Vec3::new(50.0, 100.0, 250.0);
+                transition_target_center = Vec3::new(0.0, 0.0, 0.0);
+                transitioning = true;
+            }
+        }
+
+        // Encuadres: 0 todo el sistema, 9 el sistema interior, 8 de Júpiter hacia afuera
+        let framing_presets = [
+            (Key::Key0, 0.0, f32::INFINITY),
+            (Key::Key9, 0.0, INNER_SYSTEM_LIMIT),
+            (Key::Key8, INNER_SYSTEM_LIMIT, f32::INFINITY),
+        ];
+        for (key, min_distance, max_distance) in framing_presets {
+            if shortcut_pressed(key) {
+                let bodies: Vec<usize> = (0..planets.len())
+                    .filter(|&index| {
+                        let distance = heliocentric_distance(&planets, index);
+                        distance >= min_distance && distance <= max_distance
+                    })
+                    .collect();
+                let (eye, center) = frame_bodies(&planets, &bodies, &camera, &uniforms.projection_matrix, clock.elapsed(), sphere_radius, &sizes);
+                focused_planet = None;
+                bird_eye_view = false;
+                transition_target_eye = eye;
+                transition_target_center = center;
+                transitioning = true;
+            }
+        }
+
+        // Cambiar el modo de dibujo de depuración (sólido, wireframe, normales)
+        if shortcut_pressed(Key::F2) {
+            uniforms.render_mode = uniforms.render_mode.next();
+        }
+
+        // Simular un tipo de daltonismo tras otro sobre los cuerpos (ninguno, protanopia, deuteranopia, tritanopia)
+        if ctrl_down && shortcut_pressed(Key::D) {
+            uniforms.color_blind_mode = uniforms.color_blind_mode.next();
+        }
+
+        // Ver solo el buffer de los efectos aditivos, sobre negro
+        if ctrl_down && shortcut_pressed(Key::E) {
+            effects.toggle_view();
+        }
+
+        // Alternar con el cuadro de --ab-reference; al mostrarlo la cámara y el tiempo pasan a los
+        // suyos y la simulación se pausa, para comparar con el render en vivo de la misma vista
+        if let Some(reference) = ab_reference.as_mut().filter(|_| shortcut_pressed(Key::F) && !ctrl_down) {
+            reference.toggle();
+            if reference.showing {
+                camera = Camera::new(reference.view.eye, reference.view.center, Vec3::new(0.0, 1.0, 0.0));
+                clock.set(reference.view.time as f64);
+                paused = true;
+                focused_planet = None;
+                bird_eye_view = false;
+                transitioning = false;
+            }
+        }
+
+        // Vista en corte del cuerpo enfocado. Mientras está activa, Shift con las flechas gira el
+        // plano e Inicio/Fin lo corren por su normal.
+        if shortcut_pressed(Key::H) {
+            cutaway.toggle();
+        }
+        if cutaway.enabled && !command_palette.visible {
+            let axis = |negative: Key, positive: Key| {
+                window.is_key_down(positive) as i32 as f32 - window.is_key_down(negative) as i32 as f32
+            };
+            if window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift) {
+                cutaway.rotate(axis(Key::Left, Key::Right), axis(Key::Down, Key::Up));
+            }
+            cutaway.slide(axis(Key::End, Key::Home));
+        }
+
+        // Mostrar u ocultar la vista cenital con el frustum de la cámara
+        if shortcut_pressed(Key::F3) {
+            show_debug_frustum = !show_debug_frustum;
+        }
+
+        // Malla de pozos gravitatorios (solo se dibuja en la vista bird eye)
+        if shortcut_pressed(Key::G) {
+            gravity_grid.toggle();
+        }
+
+        // Marcas de fase y sentido de movimiento sobre las órbitas
+        if shortcut_pressed(Key::O) {
+            orbit_markers.toggle();
+        }
+
+        // Rejilla de distancias sobre el plano de la eclíptica
+        if shortcut_pressed(Key::K) {
+            ecliptic_grid.toggle();
+        }
+
+        // T mantenida muestra la barra para buscar un momento con las flechas (una línea de tiempo
+        // manda sobre el tiempo y no deja moverlo). Una pulsación corta pasa al siguiente destino
+        // de la transferencia de Hohmann desde la Tierra y al final la oculta.
+        let scrub_down = !command_palette.visible && window.is_key_down(Key::T);
+        let scrub_direction = if timeline.is_some() {
+            0.0
+        } else {
+            window.is_key_down(Key::Right) as i32 as f32 - window.is_key_down(Key::Left) as i32 as f32
+        };
+        if time_scrubber.update(scrub_down, scrub_direction, &mut clock) {
+            trajectory.cycle(&planets, clock.elapsed());
+        }
+
+        // Planificador de asistencias gravitatorias: mientras está abierto, las teclas de planetas
+        // eligen el origen y el planeta del sobrevuelo en vez de enfocarlos
+        if ctrl_down && shortcut_pressed(Key::F) {
+            flyby_planner.toggle();
+        }
+
+        // Cielo desde la superficie de la Tierra (--observer elige el lugar). Al salir se vuelve a
+        // la vista general.
+        if shortcut_pressed(Key::Q) {
+            night_sky_mode = !night_sky_mode;
+            if night_sky_mode {
+                focused_planet = None;
+                bird_eye_view = false;
+            } else {
+                camera.up = Vec3::new(0.0, 1.0, 0.0);
+                transition_target_eye = Vec3::new(50.0, 100.0, 250.0);
+                transition_target_center = Vec3::new(0.0, 0.0, 0.0);
+                transitioning = true;
+            }
+        }
+
+        // Exposición larga: X empieza o detiene la acumulación, Supr la borra y F7 la exporta
+        if shortcut_pressed(Key::X) {
+            long_exposure.toggle();
+        }
+        if shortcut_pressed(Key::Delete) {
+            long_exposure.clear();
+        }
+        if shortcut_pressed(Key::F7) && !long_exposure.is_empty() {
+            let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            match long_exposure.export(framebuffer_width, framebuffer_height, &format!("long_exposure_{}.png", stamp)) {
+                Ok(path) => println!("Exposición larga guardada en {}", path),
+                Err(error) => eprintln!("No se pudo guardar la exposición larga: {}", error),
+            }
+        }
+
+        // Fijar la exposición actual (para capturas) o volver a la automática
+        if shortcut_pressed(Key::F4) {
+            auto_exposure.toggle_lock();
+        }
 
-fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+        // Profundidad de campo para capturas (cuesta unos milisegundos, por eso va aparte)
+        if shortcut_pressed(Key::F5) {
+            depth_of_field.toggle();
+        }
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-    let mut window = Window::new(
-        "Solar System Simulation",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    )
-    .unwrap();
+        // Escala de tiempo y pausa
+        if shortcut_pressed(Key::Equal) || shortcut_pressed(Key::NumPadPlus) {
+            time_scale = (time_scale * 2.0f32).min(MAX_TIME_SCALE);
+        }
+        if shortcut_pressed(Key::Minus) || shortcut_pressed(Key::NumPadMinus) {
+            time_scale = (time_scale * 0.5f32).max(MIN_TIME_SCALE);
+        }
+        if shortcut_pressed(Key::P) {
+            paused = !paused;
+        }
 
-    framebuffer.set_background_color(0x000000);
+        // Desenfoque de movimiento de los cuerpos a escalas de tiempo altas
+        if shortcut_pressed(Key::F6) {
+            motion_blur.toggle();
+        }
 
-    let mut camera = Camera::new(
-        Vec3::new(50.0, 100.0, 250.0), // Posición inicial
-        Vec3::new(0.0, 0.0, 0.0),     // Centro (sol)
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+        // Cuerpos que más tardan en sombrearse
+        if shortcut_pressed(Key::F9) {
+            show_profile = !show_profile;
+        }
 
-    let sphere_obj = Obj::load("assets/model/sphere.obj").expect("Failed to load sphere.obj");
-    let sphere_vertex_arrays = sphere_obj.get_vertex_array();
+        // Modo warp: la nave va hacia donde mira la cámara a WARP_BETA de la velocidad de la luz
+        if shortcut_pressed(Key::F10) {
+            warp_mode = !warp_mode;
+        }
 
-    let rings_obj = Obj::load("assets/model/rings.obj").expect("Failed to load rings.obj");
-    let rings_vertex_arrays = rings_obj.get_vertex_array();
+        // Mostrar u ocultar la barra de tiempo
+        if shortcut_pressed(Key::F12) {
+            time_slider.toggle();
+        }
 
-    let noise = Arc::new(create_noise());
-    let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-    let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
+        // Siguiente preset de color (--grade elige el inicial)
+        if shortcut_pressed(Key::F11) {
+            color_grading.cycle(Instant::now());
+        }
 
-    let mut uniforms = Uniforms {
-        model_matrix: Mat4::identity(),
-        view_matrix: Mat4::identity(),
-        projection_matrix,
-        viewport_matrix,
-        time: 0,
-        noise: noise.clone(),
-    };
+        // Tamaños relativos reales o los didácticos de la configuración
+        if shortcut_pressed(Key::Z) {
+            sizes.toggle();
+        }
 
-    let planets = vec![
-        Planet { name: "Sol", distance_from_sun: 0.0, radius: 3.0, orbit_speed: 0.0, color_index: 0 },
-        Planet { name: "Mercurio", distance_from_sun: 20.0, radius: 0.5, orbit_speed: 0.003, color_index: 1 },
-        Planet { name: "Venus", distance_from_sun: 40.0, radius: 0.8, orbit_speed: 0.005, color_index: 2 },
-        Planet { name: "Tierra", distance_from_sun: 60.0, radius: 1.0, orbit_speed: 0.007, color_index: 3 },
-        Planet { name: "Marte", distance_from_sun: 80.0, radius: 0.7, orbit_speed: 0.009, color_index: 4 },
-        Planet { name: "Júpiter", distance_from_sun: 100.0, radius: 2.0, orbit_speed: 0.001, color_index: 5 },
-        Planet { name: "Saturno", distance_from_sun: 120.0, radius: 1.8, orbit_speed: 0.003, color_index: 6 },
-        Planet { name: "Urano", distance_from_sun: 140.0, radius: 1.5, orbit_speed: 0.005, color_index: 7 },
-    ];
+        // Modo de alto contraste (también con --high-contrast al arrancar)
+        if shortcut_pressed(Key::F8) {
+            contrast = contrast.toggled();
+        }
 
-    let mut focused_planet: Option<&Planet> = None;
-    let mut bird_eye_view = false;
-    let skybox_texture = load_texture("assets/space.png");
-    let mut prev_mouse_x = None;
-    let mut mouse_active = false;
-    let mut transitioning = false;
-    let mut transition_target_eye = camera.eye;
-    let mut transition_target_center = camera.center;
-    let mut transition_speed = 0.05;
-    let mut time = 0.0;
+        // Abrir o cerrar la tabla comparativa de cuerpos
+        if shortcut_pressed(Key::I) {
+            planet_table.toggle();
+        }
 
-    while window.is_open() {
-        if window.is_key_down(Key::Escape) {
-            break;
+        // Barra de tiempo al pie, encima de la línea de la escala de tiempo. Mientras se arrastra
+        // el ratón es suyo; una línea de tiempo manda sobre el tiempo y la deja quieta.
+        let slider_layout = SliderLayout::new(framebuffer_width, framebuffer_height.saturating_sub(10 + Framebuffer::measure_text("", contrast.text_scale(2)).1 + HUD_GAP));
+        let mouse_position = window.get_mouse_pos(minifb::MouseMode::Discard);
+        let slider_captured = timeline.is_none()
+            && time_slider.update(&slider_layout, mouse_position, window.get_mouse_down(minifb::MouseButton::Left), &mut clock);
+        if slider_captured {
+            mouse_active = false;
+            prev_mouse_x = None;
         }
 
-        // Alternar entre la vista normal y la "bird's eye view"
-        if window.is_key_pressed(Key::B, minifb::KeyRepeat::No) {
-            bird_eye_view = !bird_eye_view;
-            if bird_eye_view {
-                transition_target_eye = Vec3::new(0.0, 500.0, 200.0);
-                transition_target_center = Vec3::new(0.0, 0.0, 0.0);
-                transitioning = true;
-            } else {
-                transition_target_eye = Vec3::new(50.0, 100.0, 250.0);
-                transition_target_center = Vec3::new(0.0, 0.0, 0.0);
+        // Mientras la tabla está abierta captura el teclado: la cámara y las teclas de planetas no reaccionan
+        let mut planet_key_map = Vec::new();
+        if command_palette.visible {
+            // Tampoco mientras se escribe un comando
+        } else if planet_table.visible {
+            if let Some(index) = planet_table.handle_input(&window, planets.len()) {
+                focused_planet = Some(&planets[index]);
+                let (eye, center) = focus_targets(&planets, index, &sizes);
+                transition_target_eye = eye;
+                transition_target_center = center;
                 transitioning = true;
             }
+        } else {
+            if !bird_eye_view && !transitioning {
+                // Permitir el control de la cámara solo si no estamos en "bird's eye view" y no estamos en transición
+                let constraints = camera_constraints(&planets, focused_planet, camera.eye, clock.elapsed(), sphere_radius, &sizes);
+                handle_input(&window, &mut camera, &planets, &mut prev_mouse_x, &mut mouse_active, &constraints, !slider_captured);
+
+                // Volver a centrar en el Sol (doble clic derecho o C) conservando la posición relativa del ojo
+                let recenter = handle_pan(&window, &mut camera, &mut pan_drag);
+                if recenter || window.is_key_pressed(Key::C, minifb::KeyRepeat::No) {
+                    focused_planet = None;
+                    transition_target_center = Vec3::new(0.0, 0.0, 0.0);
+                    transition_target_eye = camera.eye - camera.center;
+                    transitioning = true;
+                }
+            }
+
+            // Detectar teclas para enfoque en un planeta
+            planet_key_map = vec![
+                (Key::M, 1), // Mercurio
+                (Key::V, 2), // Venus
+                (Key::E, 3), // Tierra
+                (Key::R, 4), // Marte
+                (Key::J, 5), // Júpiter
+                (Key::N, 6), // Saturno
+                (Key::U, 7), // Urano
+                (Key::L, 9), // Luna
+            ];
+
+            // Con un cuerpo enfocado, [ y ] (o RePág y AvPág) pasan al planeta vecino hacia adentro
+            // o hacia afuera; con Shift, a su luna más grande o de vuelta a su planeta
+            if let Some(planet) = focused_planet {
+                let step = if shortcut_pressed(Key::LeftBracket) || shortcut_pressed(Key::PageUp) {
+                    -1
+                } else if shortcut_pressed(Key::RightBracket) || shortcut_pressed(Key::PageDown) {
+                    1
+                } else {
+                    0
+                };
+                let shift_down = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+                let current = planets.iter().position(|p| p == planet).unwrap_or(0);
+                let next = match (step, shift_down) {
+                    (0, _) => None,
+                    (_, true) => navigation::moon_or_parent(&planets, current),
+                    (_, false) => navigation::adjacent_planet(&planets, current, step, false),
+                };
+                if let Some(index) = next.filter(|&index| index != current) {
+                    focused_planet = Some(&planets[index]);
+                    let (eye, center) = focus_targets(&planets, index, &sizes);
+                    transition_target_eye = eye;
+                    transition_target_center = center;
+                    transitioning = true;
+                }
+            }
         }
 
-        if !bird_eye_view && !transitioning {
-            // Permitir el control de la cámara solo si no estamos en "bird's eye view" y no estamos en transición
-            handle_input(&window, &mut camera, &planets, &mut prev_mouse_x, &mut mouse_active);
+        // Con Ctrl las letras son atajos (Ctrl+E), no cuerpos
+        if ctrl_down {
+            planet_key_map.clear();
         }
 
-        // Detectar teclas para enfoque en un planeta
-        let planet_key_map = vec![
-            (Key::M, &planets[1]), // Mercurio
-            (Key::V, &planets[2]), // Venus
-            (Key::E, &planets[3]), // Tierra
-            (Key::R, &planets[4]), // Marte
-            (Key::J, &planets[5]), // Júpiter
-            (Key::N, &planets[6]), // Saturno
-            (Key::U, &planets[7]), // Urano
-        ];
+        if flyby_planner.active {
+            for (key, index) in planet_key_map.drain(..) {
+                if window.is_key_pressed(key, minifb::KeyRepeat::No) {
+                    flyby_planner.select(&planets, index, clock.elapsed(), &sizes, sphere_radius);
+                }
+            }
+        }
 
-        for (key, planet) in planet_key_map {
+        for (key, index) in planet_key_map {
+            let planet = &planets[index];
             if window.is_key_pressed(key, minifb::KeyRepeat::No) {
                 if focused_planet == Some(planet) {
                     // Si ya está enfocado, volver a la vista general
@@ -286,21 +1914,23 @@ fn main() {
                 } else {
                     // Enfocar en el planeta seleccionado
                     focused_planet = Some(planet);
-                    transition_target_eye = Vec3::new(
-                        planet.distance_from_sun + 20.0,
-                        planet.radius * 2.0,
-                        0.0,
-                    );
-                    transition_target_center = Vec3::new(
-                        planet.distance_from_sun,
-                        0.0,
-                        0.0,
-                    );
+                    let (eye, center) = focus_targets(&planets, index, &sizes);
+                    transition_target_eye = eye;
+                    transition_target_center = center;
                     transitioning = true;
                 }
             }
         }
 
+        // Mientras cambian los tamaños, la cámara que sigue al cuerpo enfocado acompaña su radio
+        if let (Some(planet), true) = (focused_planet, sizes.is_transitioning()) {
+            let index = planets.iter().position(|p| p == planet).unwrap_or(0);
+            let (eye, center) = focus_targets(&planets, index, &sizes);
+            transition_target_eye = eye;
+            transition_target_center = center;
+            transitioning = true;
+        }
+
         // Interpolar la posición de la cámara durante la transición
         if transitioning {
             camera.eye = lerp(camera.eye, transition_target_eye, transition_speed);
@@ -313,63 +1943,320 @@ fn main() {
             }
         }
 
+        // Línea de tiempo (--timeline): hasta su último keyframe manda sobre la cámara, el campo de
+        // visión, la escala de tiempo y el cuerpo enfocado
+        let now = Instant::now();
+        let timeline_delta = now.duration_since(timeline_clock).as_secs_f32();
+        timeline_clock = now;
+        if let Some(player) = timeline.as_mut() {
+            match player.step(timeline_delta, &planets, &sizes) {
+                Some(frame) => {
+                    camera.eye = frame.eye;
+                    camera.center = frame.center;
+                    camera.set_vertical_fov(frame.fov);
+                    time_scale = frame.time_scale;
+                    focused_planet = frame.focus.map(|index| &planets[index]);
+                    timeline_caption = frame.caption;
+                    transitioning = false;
+                }
+                None => {
+                    timeline = None;
+                    timeline_caption = None;
+                    camera.set_vertical_fov(DEFAULT_FOV_Y.to_degrees());
+                }
+            }
+        }
+
+        // En el cielo nocturno la cámara va con el observador. Enfocar un cuerpo, la vista de pájaro
+        // o una línea de tiempo lo sacan de ahí.
+        if night_sky_mode && (focused_planet.is_some() || bird_eye_view || timeline.is_some()) {
+            night_sky_mode = false;
+            camera.up = Vec3::new(0.0, 1.0, 0.0);
+        }
+        // El tiempo del cuadro, ya movido por la barra o el buscador. El reloj avanza antes del HUD,
+        // que muestra el de la escena dibujada.
+        let sim_time = clock.elapsed();
+        uniforms.time = clock.shader_time();
+        if night_sky_mode {
+            let earth = planets.iter().position(|planet| planet.name == "Tierra").unwrap_or(0);
+            let radius = sizes.radius(&planets[earth]) * sphere_radius;
+            night_sky.place(&mut camera, body_position(&planets, earth, sim_time), radius, sim_time);
+            transitioning = false;
+        }
+
         framebuffer.clear();
-        uniforms.view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
-        render_skybox(&mut framebuffer, &skybox_texture);
+        effects.clear();
+        set_camera_matrices(&mut uniforms, &camera, window_width, window_height, clip_planes);
+        if night_sky_mode {
+            uniforms.view_matrix = night_sky.view_matrix(camera.eye, sim_time);
+        }
+        // Caja que envuelve el frustum, para descartar de entrada lo que no puede verse
+        let (frustum_min, frustum_max) = Camera::compute_frustum_aabb(&uniforms.projection_matrix, &uniforms.view_matrix);
+        let view_bounds = Aabb::new(frustum_min, frustum_max);
+        // Tramos del cuadro para la traza de `dump stats`
+        let mut pass_start = Instant::now();
+        place_bodies(&mut scene, &planets, sim_time, &sizes, sphere_radius);
+        uniforms.spacecraft_velocity = if warp_mode {
+            (camera.center - camera.eye).normalize() * WARP_BETA * physics::SPEED_OF_LIGHT
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        };
+        match &skybox {
+            // Desde la Tierra las estrellas quedan en su dirección y el día aclara el cielo
+            _ if night_sky_mode => {
+                let sun_direction = body_position(&planets, 0, sim_time) - camera.eye;
+                night_sky::render_sky(&mut framebuffer, &skybox, &camera, &uniforms.projection_matrix, sun_direction);
+            }
+            // En el modo warp cada píxel busca en reposo la dirección de la que viene su luz (la
+            // aberración con la velocidad opuesta). Así entra también lo que en reposo queda
+            // detrás de la cámara, y las estrellas se juntan hacia adelante.
+            _ if warp_mode => {
+                let velocity = uniforms.spacecraft_velocity;
+                skybox::render_by_direction(&mut framebuffer, &camera, &uniforms.projection_matrix, |direction| {
+                    skybox.sample(physics::relativistic_aberration(-velocity, direction))
+                });
+            }
+            Skybox::Image(image) => render_skybox(&mut framebuffer, image),
+            Skybox::Cubemap(faces) => skybox::render_skybox_cubemap(&mut framebuffer, faces, &camera, &uniforms.projection_matrix),
+            Skybox::Gradient(top, bottom) => skybox::render_skybox_gradient(&mut framebuffer, *top, *bottom, &camera, &uniforms.projection_matrix),
+        }
+
+        // Lente gravitatoria alrededor de los cuerpos que se van a dibujar (antes de la
+        // nebulosa, que se compone sobre el fondo ya deformado). Muestrea la imagen de fondo por
+        // posición en pantalla, así que solo se aplica a ella y no en el modo warp ni desde la
+        // Tierra.
+        let lensed_bodies: Vec<(usize, Vec3)> = match focused_planet {
+            _ if warp_mode || night_sky_mode => Vec::new(),
+            Some(planet) => {
+                let index = planets.iter().position(|p| p == planet).unwrap_or(0);
+                vec![(index, body_position(&planets, index, 0.0))]
+            }
+            None => (0..planets.len()).map(|index| (index, scene.world_position(index))).collect(),
+        };
+        for (index, center) in lensed_bodies {
+            let (planet, Skybox::Image(image)) = (&planets[index], &skybox) else {
+                continue;
+            };
+            if let Some(screen) = project_point(&uniforms, center).filter(|p| p.z > 0.0) {
+                let screen_radius = projected_radius(&uniforms, center, sizes.radius(planet) * sphere_radius);
+                distort_skybox_near_mass(&mut framebuffer, image, planet, Vec2::new(screen.x, screen.y), screen_radius, LENSING_STRENGTH);
+            }
+        }
+
+        framebuffer.render_stats.record_pass("fondo", pass_start);
+        pass_start = Instant::now();
+
+        // La nebulosa baja el contraste de lo que queda detrás: no se dibuja en alto contraste
+        if contrast.low_contrast_effects() {
+            uniforms.noise = nebula_noise.clone();
+            volumetric::render_volumetric_fog(
+                &mut effects,
+                &camera,
+                &uniforms,
+                Aabb::new(NEBULA_MIN, NEBULA_MAX),
+                nebula_color(),
+                NEBULA_DENSITY,
+            );
+        }
+
+        framebuffer.render_stats.record_pass("nebulosa", pass_start);
+        pass_start = Instant::now();
+
+        // En pausa nada se mueve: la escala efectiva es 0 y no hay desenfoque de movimiento. Lo
+        // mismo mientras se arrastra la barra de tiempo, que ya fija el instante.
+        let effective_time_scale = if paused || time_slider.is_dragging() || time_scrubber.is_active() { 0.0 } else { time_scale };
+        uniforms.sun_radius = sizes.radius(&planets[0]) * sphere_radius;
 
+        // Con un cuerpo enfocado solo se dibuja ese
+        let mut visible_bodies = 1;
         if let Some(planet) = focused_planet {
+            motion_blur.reset();
+
             // Renderizar solo el planeta enfocado
-            uniforms.model_matrix = create_model_matrix(
-                Vec3::new(planet.distance_from_sun, 0.0, 0.0),
-                planet.radius,
+            let index = planets.iter().position(|p| p == planet).unwrap_or(0);
+            let position = body_position(&planets, index, 0.0);
+            let radius = sizes.radius(planet);
+            uniforms.model_matrix = create_oblate_model_matrix(
+                position,
+                radius,
+                planet.oblateness,
                 Vec3::new(0.0, 0.0, 0.0),
             );
+            uniforms.displacement = planet.displacement;
+            uniforms.contrast = planet.contrast;
+            uniforms.noise = assets.planet_noises[index].clone();
+            bind_surface_maps(&mut uniforms, planet, &assets.surface_maps, position, radius * sphere_radius);
+            uniforms.occluders = eclipse_occluders(&planets, index, position, sim_time, sphere_radius, &sizes);
+
+            let size = planet.apparent_size(&sizes, position, camera.eye, sphere_radius, framebuffer_height);
+            framebuffer.render_stats.current_body = planet.name;
+            uniforms.cutaway = cutaway.plane(position, radius * sphere_radius, camera.eye);
+            render_body(&mut framebuffer, &uniforms, assets.sphere_meshes.for_size(size), planet.color_index, position, radius * sphere_radius);
+            uniforms.cutaway = None;
+
+            if size >= MIN_MESH_SIZE {
+                let model = create_model_matrix(position, radius * sphere_radius, Vec3::new(0.0, 0.0, 0.0));
+                render_planet_rings(&mut framebuffer, &mut uniforms, &assets.ring_systems, planet, model);
+            }
+        } else {
+            if bird_eye_view && gravity_grid.enabled {
+                gravity_grid.render(&mut framebuffer, &uniforms, &planets, sim_time);
+            }
 
-            render(&mut framebuffer, &uniforms, &sphere_vertex_arrays, planet.color_index);
+            // La trayectoria va con las órbitas, antes de los cuerpos que pasan por delante
+            if trajectory.is_visible() {
+                trajectory.render(&mut framebuffer, &uniforms);
+            }
+            if flyby_planner.active {
+                flyby_planner.render(&mut framebuffer, &uniforms);
+            }
 
-            // Renderizar anillos si es Saturno
-            if planet.name == "Saturno" {
-                uniforms.model_matrix = create_model_matrix(
-                    Vec3::new(planet.distance_from_sun, 0.0, 0.0),
-                    3.5, // Tamaño de los anillos
-                    Vec3::new(0.0, 0.0, 0.0),
-                );
-                render_saturn_rings(&mut framebuffer, &uniforms, &rings_vertex_arrays, 8);
+            let frame = OverviewFrame { camera: &camera, scene: &scene, time: sim_time, effective_time_scale, contrast, show_orbits, sizes: &sizes };
+            visible_bodies = render_overview(&mut framebuffer, &mut overlay, &mut uniforms, &planets, &assets, &mut motion_blur, frame);
+
+            // Rejilla y marcas de las órbitas, después de todos los cuerpos para que el z-buffer las tape
+            if ecliptic_grid.enabled {
+                ecliptic_grid.render(&mut framebuffer, &mut overlay, &uniforms, &planets);
             }
-        } else {
-            // Renderizar todo el sistema solar
-            for planet in &planets {
-                draw_orbit(&mut framebuffer, planet, &uniforms, 100, 0xAAAAAA);
-
-                let angle = planet.orbit_speed * time;
-                let translation = Vec3::new(
-                    planet.distance_from_sun * angle.cos(),
-                    0.0,
-                    planet.distance_from_sun * angle.sin(),
-                );
-
-                if is_in_camera_view(&camera, translation, planet.radius) {
-                    uniforms.model_matrix = create_model_matrix(translation, planet.radius, Vec3::new(0.0, 0.0, 0.0));
-                    render(&mut framebuffer, &uniforms, &sphere_vertex_arrays, planet.color_index);
-
-                    // Renderizar los anillos de Saturno si el planeta es visible
-                    if planet.name == "Saturno" {
-                        let y_offset = 6.0;
-                        let rings_translation = Vec3::new(
-                            translation.x,
-                            translation.y + y_offset,
-                            translation.z,
-                        );
-                        let rings_scale = 3.5;
-
-                        uniforms.model_matrix = create_model_matrix(rings_translation, rings_scale, Vec3::new(0.0, 0.0, 0.0));
-                        render(&mut framebuffer, &uniforms, &rings_vertex_arrays, 8);
-                    }
+            if orbit_markers.enabled {
+                for planet in planets.iter() {
+                    let orbit_center = planet.parent.map(|parent| scene.world_position(parent)).unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+                    let world_radius = sizes.radius(planet) * sphere_radius;
+                    let body = MarkedBody { planet, orbit_center, angle: planet.orbit_angle(sim_time), world_radius };
+                    orbit_markers.render(&mut framebuffer, &mut overlay, &uniforms, &body, ORBIT_COLOR);
+                }
+            }
+        }
+
+        // Límite de Roche de los cuerpos con lunas, después de ellos para que el z-buffer los respete
+        if roche_overlay.enabled {
+            let bodies: Vec<(usize, Vec3)> = match focused_planet {
+                Some(planet) => {
+                    let index = planets.iter().position(|p| p == planet).unwrap_or(0);
+                    vec![(index, body_position(&planets, index, 0.0))]
                 }
+                None => (0..planets.len()).map(|index| (index, scene.world_position(index))).collect(),
+            };
+            roche_overlay.render(&mut framebuffer, &uniforms, &planets, &bodies, sphere_radius, &sizes);
+        }
+
+        // El suelo del cielo nocturno tapa lo que cae debajo del horizonte, también los efectos
+        if night_sky_mode {
+            night_sky::render_ground(&mut framebuffer, &camera, &uniforms.projection_matrix);
+        }
+        effects.hide_behind(&framebuffer.zbuffer);
+
+        framebuffer.render_stats.record_pass("cuerpos", pass_start);
+        pass_start = Instant::now();
+
+        // Fulguraciones del Sol (después de los planetas, para que estos las tapen). Si se
+        // enfoca otro planeta el Sol no se dibuja, así que tampoco sus fulguraciones.
+        solar_flares.update(sim_time);
+        let sun_center = body_position(&planets, 0, sim_time);
+        let sun_radius = sizes.radius(&planets[0]) * sphere_radius;
+        let flare_bounds = Aabb::new(
+            sun_center - Vec3::repeat(sun_radius * FLARE_EXTENT),
+            sun_center + Vec3::repeat(sun_radius * FLARE_EXTENT),
+        );
+        if focused_planet.is_none_or(|planet| planet.name == "Sol") && view_bounds.intersects(&flare_bounds) {
+            solar_flares.render(&mut effects, &framebuffer, &uniforms, sun_center, sun_radius, sim_time);
+        }
+
+        // Meteoros que entran en la atmósfera de la Tierra, cuando la Tierra está en escena
+        meteor_shower.update(effective_time_scale);
+        if let Some(earth) = planets.iter().position(|planet| planet.name == "Tierra") {
+            let earth_center = match focused_planet {
+                None => Some(body_position(&planets, earth, sim_time)),
+                Some(planet) if planet.name == "Tierra" => Some(body_position(&planets, earth, 0.0)),
+                Some(_) => None,
+            };
+            if let Some(center) = earth_center {
+                meteor_shower.render(&mut effects, &framebuffer, &uniforms, center, sizes.radius(&planets[earth]) * sphere_radius);
             }
         }
 
-        time += 1.0;
+        // Nebulosa, fulguraciones y meteoros se agregan juntos, dentro del margen de los efectos
+        effects.combine(&mut framebuffer);
+
+        framebuffer.render_stats.record_pass("fulguraciones", pass_start);
+        pass_start = Instant::now();
+
+        // Exposición: se mide la escena sin exponer y se aplica lo calculado hasta el frame anterior
+        let now = Instant::now();
+        let delta_seconds = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+        // El cambio de tamaños avanza con el tiempo real (se ve en el cuadro siguiente)
+        sizes.update(delta_seconds);
+        let exposure_for_frame = auto_exposure.ev();
+        auto_exposure.update(&framebuffer, delta_seconds);
+        AutoExposure::scale_framebuffer(&mut framebuffer, exposure_for_frame);
+
+        // Estelas de la exposición larga, sobre la escena ya expuesta y antes del HUD
+        long_exposure.apply(&mut framebuffer, camera.eye, camera.center);
+
+        // Profundidad de campo: con un planeta enfocado el foco está en su superficie; con la
+        // cámara libre se enfoca el centro de la órbita, o lo fijado a mano con [ y ]
+        if depth_of_field.enabled {
+            let focus = match focused_planet {
+                Some(planet) => {
+                    let index = planets.iter().position(|p| p == planet).unwrap_or(0);
+                    let center = body_position(&planets, index, 0.0);
+                    ((center - camera.eye).magnitude() - sizes.radius(planet) * sphere_radius).max(0.0)
+                }
+                None => {
+                    let automatic = (camera.center - camera.eye).magnitude();
+                    if !command_palette.visible && window.is_key_down(Key::LeftBracket) {
+                        depth_of_field.adjust_focus(automatic, -1.0);
+                    }
+                    if !command_palette.visible && window.is_key_down(Key::RightBracket) {
+                        depth_of_field.adjust_focus(automatic, 1.0);
+                    }
+                    if shortcut_pressed(Key::Backslash) {
+                        depth_of_field.manual_focus = None;
+                    }
+                    depth_of_field.focus_distance(automatic)
+                }
+            };
+            depth_of_field.apply(&mut framebuffer, focus);
+        }
+
+        if aberration_enabled {
+            framebuffer.apply_chromatic_aberration(aberration_strength);
+        }
+
+        // Antialiasing de los bordes, el último post-proceso: el HUD se dibuja después para no suavizar el texto
+        framebuffer.apply_fxaa();
+        framebuffer.render_stats.record_pass("post-proceso", pass_start);
+        pass_start = Instant::now();
+
+        // Esferas envolventes de los cuerpos mientras se mantiene Ctrl+B, sobre la escena ya
+        // suavizada para que las líneas queden nítidas
+        if ctrl_down && !command_palette.visible && window.is_key_down(Key::B) {
+            let bodies: Vec<(usize, Vec3)> = match focused_planet {
+                Some(planet) => {
+                    let index = planets.iter().position(|p| p == planet).unwrap_or(0);
+                    vec![(index, body_position(&planets, index, 0.0))]
+                }
+                None => (0..planets.len()).map(|index| (index, scene.world_position(index))).collect(),
+            };
+            debug_view::render_bounding_spheres(&mut framebuffer, &uniforms, &planets, &bodies, sphere_radius, &sizes);
+        }
+
+        // Con --grade-skip-hud el color se ajusta aquí, antes de todo el HUD
+        if !color_grading.include_hud {
+            color_grading.apply(&mut framebuffer);
+        }
+
+        clock.advance(effective_time_scale);
+
+        // Fases de la Luna, cruces del plano de los anillos, conjunciones...
+        let scene_state = SceneState { planets: &planets, time: sim_time, camera_eye: camera.eye, ring_systems: &assets.ring_systems };
+        event_log.update(&scene_state, Instant::now());
+
+        // Historial para la repetición, sin el HUD y sin los cuadros en pausa
+        if !paused {
+            replay_recorder.capture(&framebuffer);
+        }
 
         // Determinar la vista actual
         let current_view = if let Some(planet) = focused_planet {
@@ -380,27 +2267,335 @@ fn main() {
             "NAVE".to_string()
         };
 
-        // Dibujar el texto en la esquina superior izquierda
-        let text_color = 0xFFFFFF; // Blanco
-        framebuffer.draw_text(10, 10, &current_view, text_color, 3);
+        status_line.update(&status_line::describe(&current_view, focused_planet, time_scale, paused));
+
+        // Dibujar el nombre de la vista en la esquina superior izquierda
+        let view_panel = Panel::new(10, 10, Panel::title_width(&current_view, contrast.text_scale(2)))
+            .scale(2)
+            .contrast(contrast)
+            .title(&current_view);
+        overlay.draw_panel(&view_panel);
+
+        // En alto contraste el panel del cuerpo enfocado va debajo del de la vista, y los textos
+        // de la izquierda debajo de ambos
+        let mut hud_y = 10 + view_panel.height() + HUD_GAP;
+        let info_panel = focused_planet.and_then(|planet| {
+            stats.get(planet.name).map(|planet_stats| {
+                let panel = planet_info_panel(framebuffer_width, planet, planet_stats, sim_time, contrast, hud_y, &sizes);
+                with_opposition(panel, &planets, planet, sim_time)
+            })
+        });
+        if let (Some(panel), true) = (&info_panel, contrast.is_high()) {
+            hud_y += panel.height() + HUD_GAP;
+        }
+
+        // Contadores de la prueba de carga (--stress), debajo de los paneles de la izquierda
+        let mut stress_panel = None;
+        if stress.is_some() {
+            let frame_stats = framebuffer.fragment_stats;
+            let width = if contrast.is_high() { framebuffer_width.saturating_sub(2 * INFO_PANEL_MARGIN) } else { STRESS_PANEL_WIDTH };
+            let panel = Panel::new(INFO_PANEL_MARGIN, hud_y, width)
+                .contrast(contrast)
+                .row("CUERPOS", &planets.len().to_string())
+                .row("VISIBLES", &visible_bodies.to_string())
+                .row("TRIANGULOS", &frame_stats.triangles.to_string())
+                .row("FRAGMENTOS", &frame_stats.shaded.to_string())
+                .row("CUADRO", &format!("{:.1} MS", delta_seconds * 1000.0));
+            hud_y += panel.height() + HUD_GAP;
+            stress_panel = Some(panel);
+        }
+
+        // Los PROFILE_TOP_COUNT cuerpos que más tiempo de sombreado llevaron en este cuadro (F9)
+        let mut profile_panel = None;
+        if show_profile {
+            let width = if contrast.is_high() { framebuffer_width.saturating_sub(2 * INFO_PANEL_MARGIN) } else { STRESS_PANEL_WIDTH };
+            let mut panel = Panel::new(INFO_PANEL_MARGIN, hud_y, width).contrast(contrast).title("SOMBREADO");
+            for (body, shading) in framebuffer.render_stats.top_by_shading(PROFILE_TOP_COUNT) {
+                panel = panel.row(&body.to_uppercase(), &format!("{:.2} MS", shading.as_secs_f32() * 1000.0));
+            }
+            if let Some(hit_rate) = framebuffer.render_stats.vertex_cache_hit_rate() {
+                panel = panel.row("CACHE VERTICES", &format!("{:.0}%", hit_rate * 100.0));
+            }
+            hud_y += panel.height() + HUD_GAP;
+            profile_panel = Some(panel);
+        }
+
+        let hud_scale = contrast.text_scale(2);
+        let line_height = Framebuffer::measure_text("", hud_scale).1;
+        let bottom_y = framebuffer_height.saturating_sub(10 + line_height);
+
+        // Últimos eventos al pie, encima de la línea del EV y la escala de tiempo y de la barra de
+        // tiempo si está a la vista (la paleta va en su lugar)
+        let hud_bottom = if time_slider.visible || time_scrubber.is_active() { slider_layout.label_y(contrast) } else { bottom_y };
+        let ticker_top = if command_palette.visible {
+            None
+        } else {
+            event_log.render_ticker(&mut overlay, INFO_PANEL_MARGIN, hud_bottom.saturating_sub(HUD_GAP), contrast, Instant::now())
+        };
+
+        // Todo el HUD va en su propia capa, compuesta sobre el cuadro ya post-procesado, así la
+        // escena (ni su z-buffer) no se mezcla con él. Primero los nombres de los cuerpos, el
+        // panel de la vista y los eventos; encima los textos sueltos, algo transparentes, y
+        // después los paneles, la tabla y la paleta.
+        overlay.composite(&mut framebuffer, 1.0);
+        if sizes.true_sizes || sizes.is_transitioning() {
+            let label = if sizes.true_sizes { "TAMANOS REALES" } else { "TAMANOS DIDACTICOS" };
+            overlay.draw_text(10, hud_y, label, contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+        if uniforms.render_mode != RenderMode::Solid {
+            overlay.draw_text(10, hud_y, &format!("MODO: {}", uniforms.render_mode.name()), contrast.text_color(WIREFRAME_COLOR), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+        if warp_mode {
+            let beta = uniforms.spacecraft_velocity.magnitude() / physics::SPEED_OF_LIGHT;
+            overlay.draw_text(10, hud_y, &format!("WARP: {:.2}C", beta), contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+            // Cuerpo hacia el que va la nave: cuánto tarda la luz en llegar y cuánto la nave, en
+            // el reloj del Sol y en el de a bordo
+            let ship = Spacecraft { position: camera.eye, velocity: uniforms.spacecraft_velocity };
+            if let Some(voyage) = ship.voyage(&planets, sim_time) {
+                let name = planets[voyage.target].name.to_uppercase();
+                overlay.draw_text(10, hud_y, &format!("RUMBO: {}  LUZ: {}", name, format_duration(voyage.light_seconds)), contrast.text_color(0xFFFFFF), hud_scale);
+                hud_y += line_height + HUD_GAP;
+                let times = format!("A {:.2}C: {} ({} A BORDO)", beta, format_duration(voyage.seconds), format_duration(voyage.proper_seconds));
+                overlay.draw_text(10, hud_y, &times, contrast.text_color(0xFFFFFF), hud_scale);
+                hud_y += line_height + HUD_GAP;
+            }
+        }
+        if uniforms.color_blind_mode != ColorBlindMode::None {
+            overlay.draw_text(10, hud_y, &format!("DALTONISMO: {}", uniforms.color_blind_mode.name()), contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+        if flyby_planner.active {
+            overlay.draw_text(10, hud_y, flyby_planner.label(), contrast.text_color(TRAJECTORY_COLOR), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+        if cutaway.enabled && focused_planet.is_some() {
+            overlay.draw_text(10, hud_y, "CORTE", contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+        // Desde la superficie, las coordenadas de la cámara y las del punto subsolar, que queda bajo
+        // el horizonte; con la Tierra enfocada se marca con una retícula
+        let earth = planets.iter().position(|planet| planet.name == "Tierra");
+        if let Some(earth) = earth.filter(|_| night_sky_mode) {
+            overlay.draw_text(10, hud_y, &format!("CIELO DESDE {}", night_sky.label()), contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+            let solar_day = night_sky::solar_day(planets[earth].orbit_speed);
+            overlay.draw_text(10, hud_y, &format!("DIA SOLAR {:.1}", solar_day), contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+            let earth_center = body_position(&planets, earth, sim_time);
+            let (latitude, longitude) = night_sky::geographic_coordinates(camera.eye - earth_center, sim_time);
+            overlay.draw_text(10, hud_y, &night_sky::format_coordinates(latitude, longitude), contrast.text_color(0xFFFFFF), hud_scale);
+            hud_y += line_height + HUD_GAP;
+            let radius = sizes.radius(&planets[earth]) * sphere_radius;
+            let subsolar = night_sky::subsolar_point(earth_center, body_position(&planets, 0, sim_time), radius);
+            let (latitude, longitude) = night_sky::geographic_coordinates(subsolar - earth_center, sim_time);
+            overlay.draw_text(10, hud_y, &format!("SUBSOLAR {}", night_sky::format_coordinates(latitude, longitude)), contrast.text_color(SUBSOLAR_COLOR), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+        // La Tierra enfocada se dibuja en su posición del tiempo 0
+        let subsolar_time = match focused_planet {
+            _ if night_sky_mode => Some(sim_time),
+            Some(planet) if planet.name == "Tierra" => Some(0.0),
+            _ => None,
+        };
+        if let (Some(earth), Some(subsolar_time)) = (earth, subsolar_time) {
+            let radius = sizes.radius(&planets[earth]) * sphere_radius;
+            let (earth_center, sun_center) = (body_position(&planets, earth, subsolar_time), body_position(&planets, 0, subsolar_time));
+            night_sky::render_subsolar_marker(&mut overlay, &uniforms, earth_center, sun_center, radius, contrast.text_color(SUBSOLAR_COLOR));
+        }
+        let eclipse_label = match solar_eclipse(&planets, sim_time, sphere_radius, &sizes) {
+            Some(Eclipse::Total) => Some("ECLIPSE SOLAR"),
+            Some(Eclipse::Annular) => Some("ECLIPSE SOLAR ANULAR"),
+            _ => None,
+        };
+        if let Some(label) = eclipse_label {
+            overlay.draw_text(10, hud_y, label, contrast.text_color(0xFFD54F), hud_scale);
+            hud_y += line_height + HUD_GAP;
+        }
+
+        if paused {
+            overlay.draw_text_centered(framebuffer_width / 2, framebuffer_height / 2, "PAUSA", contrast.text_color(0xFFFFFF), contrast.text_scale(3));
+        }
+        if long_exposure.active {
+            let y = if contrast.is_high() { hud_y } else { 20 };
+            overlay.draw_text_centered(framebuffer_width / 2, y, "EXPOSICION LARGA", contrast.text_color(0xFF6347), hud_scale);
+        }
+        if let Some(name) = color_grading.flash_label(Instant::now()) {
+            overlay.draw_text_centered(framebuffer_width / 2, framebuffer_height / 3, &format!("COLOR: {}", name), contrast.text_color(0xFFFFFF), contrast.text_scale(3));
+        }
+        if let Some(caption) = &timeline_caption {
+            let y = framebuffer_height * 3 / 4;
+            overlay.draw_text_centered(framebuffer_width / 2, y, &caption.to_uppercase(), contrast.text_color(0xFFFFFF), contrast.text_scale(2));
+        }
+        if trajectory.is_visible() && !trajectory.label().is_empty() {
+            let y = match ticker_top {
+                Some(top) => top.saturating_sub(HUD_GAP + line_height),
+                None => hud_bottom.saturating_sub(10 + line_height),
+            };
+            overlay.draw_text_centered(framebuffer_width / 2, y, trajectory.label(), contrast.text_color(TRAJECTORY_COLOR), hud_scale);
+        }
+        if time_scale != 1.0 {
+            overlay.draw_text_right_aligned(framebuffer_width - 10, bottom_y, &format!("x{}", time_scale), contrast.text_color(0xFFFFFF), hud_scale);
+            // Posición de la escala entre la mínima y la máxima, en pasos de x2
+            let progress = (time_scale / MIN_TIME_SCALE).log2() / (MAX_TIME_SCALE / MIN_TIME_SCALE).log2();
+            overlay.region(framebuffer_width - 10 - TIME_SCALE_BAR_WIDTH, framebuffer_height - 8, TIME_SCALE_BAR_WIDTH, 4).render_progress_bar(
+                framebuffer_width - 10 - TIME_SCALE_BAR_WIDTH,
+                framebuffer_height - 8,
+                TIME_SCALE_BAR_WIDTH,
+                4,
+                progress,
+                BarColors { fill: 0xFFFFFF, empty: 0x404040 },
+            );
+        }
+        // Las barras de tiempo ocupan el pie, con sus fechas encima
+        let slider_top = slider_layout.label_y(contrast).saturating_sub(2 * line_height);
+        if time_scrubber.is_active() {
+            time_scrubber.render(overlay.region(0, slider_top, framebuffer_width, framebuffer_height - slider_top), &slider_layout, sim_time as f32, contrast);
+        } else if time_slider.visible {
+            time_slider.render(overlay.region(0, slider_top, framebuffer_width, framebuffer_height - slider_top), &slider_layout, sim_time as f32, contrast);
+        }
+
+        if show_debug_frustum {
+            let lock = if auto_exposure.locked { " (FIJA)" } else { "" };
+            overlay.draw_text(10, bottom_y, &format!("EV {:+.2}{}", auto_exposure.ev(), lock), contrast.text_color(0xFFFFFF), hud_scale);
+            debug_view::render_debug_frustum(&mut framebuffer, &camera, &planets, &uniforms.projection_matrix, sim_time);
+        }
+        overlay.composite(&mut framebuffer, contrast.opacity(HUD_TEXT_OPACITY));
+
+        if let Some(panel) = info_panel {
+            overlay.draw_panel(&panel);
+        }
+        if let Some(panel) = stress_panel {
+            overlay.draw_panel(&panel);
+        }
+        if let Some(panel) = profile_panel {
+            overlay.draw_panel(&panel);
+        }
+
+        // La tabla y la paleta ocupan toda la pantalla o todo su ancho
+        if planet_table.visible {
+            planet_table.render(&mut overlay, &planets, &camera, sim_time);
+        }
+
+        if command_palette.visible {
+            command_palette.render(&mut overlay, contrast);
+        }
+        overlay.composite(&mut framebuffer, 1.0);
+
+        framebuffer.render_stats.record_pass("hud", pass_start);
+
+        // Ajuste de color final, con el HUD incluido
+        if color_grading.include_hud {
+            color_grading.apply(&mut framebuffer);
+        }
+        // La referencia del A/B va tal cual se guardó, sin ajuste de color
+        if let Some(reference) = &ab_reference {
+            reference.render(&mut framebuffer, contrast);
+        }
+
+        if present_frame(&mut window, &mut present_recovery, &framebuffer, window_width, window_height) {
+            // El estado del ratón era de la ventana anterior
+            prev_mouse_x = None;
+            mouse_active = false;
+            pan_drag = PanDrag::new();
+        }
+    }
+
+}
+
+
+// Estado del arrastre con el botón derecho, que desplaza el punto de giro de la cámara
+struct PanDrag {
+    prev_mouse_pos: Option<(f32, f32)>,
+    was_down: bool,
+    last_press: Option<Instant>,
+}
+
+impl PanDrag {
+    fn new() -> Self {
+        PanDrag { prev_mouse_pos: None, was_down: false, last_press: None }
+    }
+}
+
+// Fracción de la distancia al objetivo que se desplaza por cada píxel arrastrado
+const PAN_SPEED: f32 = 0.0015;
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(350);
+
+// Arrastrar con el botón derecho mueve el centro de la órbita; devuelve true con un
+// doble clic derecho, para volver a centrar la cámara en el Sol
+fn handle_pan(window: &Window, camera: &mut Camera, pan: &mut PanDrag) -> bool {
+    let down = window.get_mouse_down(minifb::MouseButton::Right);
+    let mut double_click = false;
 
-        window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
-            .unwrap();
+    if down && !pan.was_down {
+        let now = Instant::now();
+        double_click = pan.last_press.is_some_and(|last| now.duration_since(last) < DOUBLE_CLICK_TIME);
+        pan.last_press = if double_click { None } else { Some(now) };
     }
+    pan.was_down = down;
 
+    if !down {
+        pan.prev_mouse_pos = None;
+        return double_click;
+    }
+
+    if let Some((mouse_x, mouse_y)) = window.get_mouse_pos(minifb::MouseMode::Discard) {
+        if let Some((prev_x, prev_y)) = pan.prev_mouse_pos {
+            camera.pan(mouse_x - prev_x, mouse_y - prev_y, PAN_SPEED);
+        }
+        pan.prev_mouse_pos = Some((mouse_x, mouse_y));
+    } else {
+        pan.prev_mouse_pos = None;
+    }
+
+    double_click
 }
 
+// Límites de la cámara libre. Enfocando un cuerpo el ojo no se acerca a menos de
+// FOCUS_MIN_DISTANCE radios de su centro. Sin enfoque el punto de giro puede haber quedado en
+// el vacío después de arrastrar la vista: el ojo se mantiene a esos radios del cuerpo más
+// cercano y del punto de giro solo a FREE_PIVOT_MIN_DISTANCE.
+const FOCUS_MIN_DISTANCE: f32 = 1.1;
+const FREE_PIVOT_MIN_DISTANCE: f32 = 0.5;
+const MAX_CAMERA_DISTANCE: f32 = 1000.0;
+// La misma inclinación máxima que permite `Camera::orbit`
+const MIN_CAMERA_ELEVATION: f32 = -(PI / 2.0 - 0.1);
+
+fn camera_constraints(planets: &[Planet], focused_planet: Option<&Planet>, eye: Vec3, time: f64, sphere_radius: f32, sizes: &SizeScale) -> CameraConstraints {
+    let (min_distance_from_center, keep_out) = match focused_planet {
+        Some(planet) => (sizes.radius(planet) * sphere_radius * FOCUS_MIN_DISTANCE, None),
+        None => {
+            let nearest = (0..planets.len())
+                .map(|index| {
+                    let radius = sizes.radius(&planets[index]) * sphere_radius * FOCUS_MIN_DISTANCE;
+                    (body_position(planets, index, time), radius)
+                })
+                .min_by(|a, b| ((eye - a.0).magnitude() - a.1).total_cmp(&((eye - b.0).magnitude() - b.1)));
+            (FREE_PIVOT_MIN_DISTANCE, nearest)
+        }
+    };
+    CameraConstraints {
+        min_distance_from_center,
+        max_distance_from_center: MAX_CAMERA_DISTANCE,
+        min_elevation: MIN_CAMERA_ELEVATION,
+        keep_out,
+    }
+}
 
-fn handle_input(window: &Window, camera: &mut Camera, planets: &[Planet],  prev_mouse_pos: &mut Option<(f32, f32)>, mouse_active: &mut bool) {
+// `mouse_free` es falso mientras el ratón lo usa la barra de tiempo
+fn handle_input(window: &Window, camera: &mut Camera, planets: &[Planet],  prev_mouse_pos: &mut Option<(f32, f32)>, mouse_active: &mut bool, constraints: &CameraConstraints, mouse_free: bool) {
     let movement_speed = 0.022;
-    let zoom_speed = 0.5;
+    let zoom_speed: f32 = 0.5;
     let rotation_speed = PI / 200.0;
 
-    if window.is_key_down(Key::Left) {
+    // Shift con las flechas gira el plano de la vista en corte y con T mueve el tiempo, no la cámara
+    let arrows_taken = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift) || window.is_key_down(Key::T);
+    if window.is_key_down(Key::Left) && !arrows_taken {
         camera.orbit(-rotation_speed, 0.0);
     }
-    if window.is_key_down(Key::Right) {
+    if window.is_key_down(Key::Right) && !arrows_taken {
         camera.orbit(rotation_speed, 0.0);
     }
 
@@ -411,7 +2606,7 @@ fn handle_input(window: &Window, camera: &mut Camera, planets: &[Planet],  prev_
     let mut movement = Vec3::new(0.0, 0.0, 0.0);
 
     // Alternar el estado de `mouse_active` al hacer clic
-    if window.get_mouse_down(minifb::MouseButton::Left) {
+    if mouse_free && window.get_mouse_down(minifb::MouseButton::Left) {
         if let Some((mouse_x, mouse_y)) = window.get_mouse_pos(minifb::MouseMode::Clamp) {
             if mouse_x >= 0.0 && mouse_x <= window.get_size().0 as f32
                 && mouse_y >= 0.0 && mouse_y <= window.get_size().1 as f32
@@ -460,9 +2655,358 @@ fn handle_input(window: &Window, camera: &mut Camera, planets: &[Planet],  prev_
     }
 
     if window.is_key_down(Key::W) {
-        camera.zoom(zoom_speed);
+        camera.zoom(zoom_speed.min(constraints.zoom_room(camera)));
     }
     if window.is_key_down(Key::S) {
         camera.zoom(-zoom_speed);
     }
+
+    camera.apply_constraints(constraints);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use crate::seed::DEFAULT_SEED;
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    // El diámetro aparente de un cuerpo frente a la cámara coincide con el de su esfera proyectada
+    #[test]
+    fn apparent_size_matches_projection() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let uniforms = test_uniforms(seeds);
+        let planets = solar_system();
+        let sizes = SizeScale::new();
+        for (planet, distance) in planets.iter().zip([12.0, 40.0, 150.0, 600.0]) {
+            let center = Vec3::new(0.0, 0.0, 10.0 - distance);
+            let apparent = planet.apparent_size_pixels(&sizes, distance, DEFAULT_FOV_Y.to_degrees(), HEIGHT as usize);
+            let projected = 2.0 * projected_radius(&uniforms, center, sizes.radius(planet));
+            if (apparent - projected).abs() > 1e-3 * projected.max(1.0) {
+                return Err(format!("{} a {}: {:.3} px, proyectado {:.3} px", planet.name, distance, apparent, projected));
+            }
+        }
+        Ok(())
+    }
+
+    // Dos frames con la misma semilla deben ser idénticos byte a byte, y con otra semilla distintos
+    #[test]
+    fn determinism() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+
+        let first = render_test_frame(seeds, &vertices);
+        let second = render_test_frame(seeds, &vertices);
+        if first != second {
+            return Err("dos frames con la misma semilla difieren".to_string());
+        }
+
+        let other = SeedSource::new(seeds.base.wrapping_add(1));
+        if render_test_frame(&other, &vertices) == first {
+            return Err("con otra semilla se obtuvo el mismo frame".to_string());
+        }
+        Ok(())
+    }
+
+    // La Tierra (su shader usa el ruido del planeta) llenando un framebuffer pequeño
+    fn render_test_frame(seeds: &SeedSource, vertices: &[Vertex]) -> Vec<u32> {
+        let mut framebuffer = Framebuffer::new(WIDTH as usize / 8, HEIGHT as usize / 8);
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+        uniforms.model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, Vec3::new(0.0, 0.0, 0.0));
+
+        render(&mut framebuffer, &uniforms, vertices, 3);
+        framebuffer.buffer
+    }
+
+    // Una órbita vista desde arriba con el centro sobre cada borde de la pantalla: todo punto de la
+    // circunferencia que cae dentro del framebuffer tiene la línea a menos de 2 px, también donde
+    // cruza el borde
+    #[test]
+    fn orbit_clipping() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let mut uniforms = test_uniforms(seeds);
+        let eye = Vec3::new(0.0, 20.0, 0.0);
+        uniforms.view_matrix = create_view_matrix(eye, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        uniforms.view_pos = eye;
+
+        // Unidades del mundo por píxel en cada eje de la pantalla, sobre el plano y = 0
+        let origin = project_point(&uniforms, Vec3::new(0.0, 0.0, 0.0)).ok_or("el origen no se proyecta")?;
+        let unit_x = project_point(&uniforms, Vec3::new(1.0, 0.0, 0.0)).ok_or("no se proyecta")?;
+        let unit_z = project_point(&uniforms, Vec3::new(0.0, 0.0, 1.0)).ok_or("no se proyecta")?;
+        let (pixels_x, pixels_z) = ((unit_x.x - origin.x).abs(), (unit_z.y - origin.y).abs());
+        let (half_x, half_z) = (0.5 * WIDTH / pixels_x, 0.5 * HEIGHT / pixels_z);
+
+        let mut planets = solar_system();
+        let planet = &mut planets[3];
+        planet.distance_from_sun = 0.4 * half_x.min(half_z);
+        let planet = &planets[3];
+
+        let edges = [("izquierdo", -half_x, 0.0), ("derecho", half_x, 0.0), ("superior", 0.0, -half_z), ("inferior", 0.0, half_z)];
+        for (edge, x, z) in edges {
+            let center = Vec3::new(x, 0.0, z);
+            let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+            framebuffer.clear();
+            draw_orbit(&mut framebuffer, planet, center, &uniforms, 100, 0xFFFFFF, Contrast::Normal);
+
+            let drawn = |x: isize, y: isize| {
+                (-2..=2).any(|dy| (-2..=2).any(|dx| {
+                    let (px, py) = (x + dx, y + dy);
+                    px >= 0 && py >= 0 && (px as usize) < framebuffer.width && (py as usize) < framebuffer.height
+                        && framebuffer.buffer[py as usize * framebuffer.width + px as usize] == 0xFFFFFF
+                }))
+            };
+            let mut inside = 0;
+            for i in 0..2000 {
+                let angle = 2.0 * std::f32::consts::PI * i as f32 / 2000.0;
+                let point = center + Vec3::new(angle.cos(), 0.0, angle.sin()) * planet.distance_from_sun;
+                let screen = project_point(&uniforms, point).ok_or("un punto de la órbita no se proyecta")?;
+                if screen.x < 0.0 || screen.y < 0.0 || screen.x > WIDTH - 1.0 || screen.y > HEIGHT - 1.0 {
+                    continue;
+                }
+                inside += 1;
+                if !drawn(screen.x as isize, screen.y as isize) {
+                    return Err(format!("borde {}: hueco en ({:.1}, {:.1})", edge, screen.x, screen.y));
+                }
+            }
+            if inside == 0 || inside == 2000 {
+                return Err(format!("borde {}: la órbita no cruza el borde ({} de 2000 puntos dentro)", edge, inside));
+            }
+        }
+        Ok(())
+    }
+
+    // Escena de Saturno enfocado: los anillos se dibujan alrededor del planeta (no del Sol) y a
+    // su escala. Con la cámara en la dirección del enfoque, pero más lejos para que quepan, los
+    // píxeles cubiertos deben quedar centrados en el disco de Saturno y ser tantas veces más
+    // anchos que él como el radio exterior del anillo A.
+    #[test]
+    fn focused_saturn_rings() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let sphere_radius = obj.bounding_radius();
+
+        let rings = RingSystem::saturn();
+        let outer_radius = rings.profile.iter().map(|band| band.outer).fold(0.0, f32::max);
+        let mesh = rings.mesh(RING_SEGMENTS);
+        let ring_systems = [("Saturno", rings, mesh)];
+
+        let planets = solar_system();
+        let index = planets.iter().position(|planet| planet.name == "Saturno").ok_or("no hay Saturno")?;
+        let planet = &planets[index];
+        let (eye, position) = focus_targets(&planets, index, &SizeScale::new());
+        let eye = position + (eye - position) * 3.0;
+        let world_radius = planet.radius * sphere_radius;
+
+        let mut framebuffer = Framebuffer::new(WIDTH as usize / 2, HEIGHT as usize / 2);
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.view_matrix = create_view_matrix(eye, position, Vec3::new(0.0, 1.0, 0.0));
+        uniforms.view_pos = eye;
+        uniforms.viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+        uniforms.model_matrix = create_model_matrix(position, planet.radius, Vec3::new(0.0, 0.0, 0.0));
+
+        // Columnas extremas de los píxeles que cumplen `covered`
+        let column_range = |framebuffer: &Framebuffer, covered: &dyn Fn(usize) -> bool| {
+            (0..framebuffer.buffer.len())
+                .filter(|&i| covered(i))
+                .map(|i| (i % framebuffer.width) as f32)
+                .fold(None, |range: Option<(f32, f32)>, x| match range {
+                    Some((min, max)) => Some((min.min(x), max.max(x))),
+                    None => Some((x, x)),
+                })
+        };
+
+        render_body(&mut framebuffer, &uniforms, &vertices, planet.color_index, position, world_radius);
+        let planet_only = framebuffer.buffer.clone();
+        let (planet_min, planet_max) = column_range(&framebuffer, &|i| framebuffer.zbuffer[i].is_finite()).ok_or("Saturno no dejó píxeles")?;
+
+        // Los anillos se mezclan con lo que hay debajo sin escribir el z-buffer: se buscan los píxeles que cambiaron
+        render_planet_rings(&mut framebuffer, &mut uniforms, &ring_systems, planet, create_model_matrix(position, world_radius, Vec3::new(0.0, 0.0, 0.0)));
+        let (min_x, max_x) = column_range(&framebuffer, &|i| framebuffer.buffer[i] != planet_only[i]).ok_or("los anillos no dejaron píxeles")?;
+
+        if min_x == 0.0 || max_x as usize == framebuffer.width - 1 {
+            return Err("los anillos se salen del cuadro".to_string());
+        }
+        let planet_center = (planet_min + planet_max) * 0.5;
+        if ((min_x + max_x) * 0.5 - planet_center).abs() > 3.0 {
+            return Err(format!("los píxeles van de x={} a x={}, Saturno está en x={}", min_x, max_x, planet_center));
+        }
+        let ratio = (max_x - min_x + 1.0) / (planet_max - planet_min + 1.0);
+        if (ratio - outer_radius).abs() > outer_radius * 0.1 {
+            return Err(format!("los anillos miden {:.2} veces el planeta, se esperaban {:.2}", ratio, outer_radius));
+        }
+        Ok(())
+    }
+
+    // Los anillos se ordenan del más lejano al más cercano, y dibujados después de los opacos
+    // siguen viéndose sobre un cuerpo que está detrás de ellos
+    #[test]
+    fn ring_draw_order() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let sphere_radius = obj.bounding_radius();
+        let ring_systems = [("Saturno", RingSystem::saturn(), RingSystem::saturn().mesh(RING_SEGMENTS))];
+
+        let planets = solar_system();
+        let index = planets.iter().position(|planet| planet.name == "Saturno").ok_or("no hay Saturno")?;
+        let saturn = &planets[index];
+        let world_radius = saturn.radius * sphere_radius;
+        let (eye, position) = focus_targets(&planets, index, &SizeScale::new());
+        let eye = position + (eye - position) * 3.0;
+        let camera = Camera::new(eye, position, Vec3::new(0.0, 1.0, 0.0));
+
+        let forward = (position - eye).normalize();
+        let mut draws: Vec<RingDraw> = [0.0, 50.0, -5.0, 20.0]
+            .iter()
+            .map(|depth| RingDraw { planet: saturn, position: position + forward * *depth, model: create_model_matrix(position + forward * *depth, world_radius, Vec3::new(0.0, 0.0, 0.0)) })
+            .collect();
+        sort_back_to_front(&mut draws, &camera);
+        let depths: Vec<f32> = draws.iter().map(|draw| (draw.position - position).dot(&forward)).collect();
+        if depths.windows(2).any(|pair| pair[0] < pair[1]) {
+            return Err(format!("orden {:?}, se esperaba de atrás hacia adelante", depths));
+        }
+
+        // Un cuerpo bien detrás de Saturno que en pantalla se ve más grande que sus anillos
+        let distance = (position - eye).magnitude();
+        let behind_distance = 4.0 * distance;
+        let behind = eye + forward * behind_distance;
+        let behind_radius = 3.0 * saturn.radius * behind_distance / distance;
+
+        let mut framebuffer = Framebuffer::new(WIDTH as usize / 2, HEIGHT as usize / 2);
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.view_matrix = create_view_matrix(eye, position, Vec3::new(0.0, 1.0, 0.0));
+        uniforms.view_pos = eye;
+        uniforms.viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+
+        uniforms.model_matrix = create_model_matrix(position, saturn.radius, Vec3::new(0.0, 0.0, 0.0));
+        render_body(&mut framebuffer, &uniforms, &vertices, saturn.color_index, position, world_radius);
+        uniforms.model_matrix = create_model_matrix(behind, behind_radius, Vec3::new(0.0, 0.0, 0.0));
+        render_body(&mut framebuffer, &uniforms, &vertices, 5, behind, behind_radius * sphere_radius);
+        let opaque = framebuffer.buffer.clone();
+        let behind_depth = (behind - eye).magnitude() - behind_radius * sphere_radius * 1.5;
+        let behind_pixels = |framebuffer: &Framebuffer| {
+            (0..framebuffer.buffer.len()).filter(|&i| framebuffer.zbuffer[i].is_finite() && framebuffer.zbuffer[i] > behind_depth).collect::<Vec<_>>()
+        };
+        let covered = behind_pixels(&framebuffer);
+        if covered.is_empty() {
+            return Err("el cuerpo de atrás no dejó píxeles".to_string());
+        }
+
+        render_planet_rings(&mut framebuffer, &mut uniforms, &ring_systems, saturn, create_model_matrix(position, world_radius, Vec3::new(0.0, 0.0, 0.0)));
+        if !covered.iter().any(|&i| framebuffer.buffer[i] != opaque[i]) {
+            return Err("los anillos no se ven sobre el cuerpo que está detrás".to_string());
+        }
+        Ok(())
+    }
+
+    // En la escena de Saturno enfocado (a la distancia real del enfoque) los anillos tapan y son
+    // tapados por el planeta, y la cara trasera de la esfera queda detrás de la delantera: buena
+    // parte de los fragmentos pierde la prueba de profundidad y no debe llegar al shader. El
+    // resultado tiene que ser el mismo que sombreando todo y probando la profundidad después.
+    #[test]
+    fn early_depth_test() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let sphere_radius = obj.bounding_radius();
+        let rings = RingSystem::saturn();
+        let mesh = rings.mesh(RING_SEGMENTS);
+        let ring_systems = [("Saturno", rings, mesh)];
+
+        let planets = solar_system();
+        let index = planets.iter().position(|planet| planet.name == "Saturno").ok_or("no hay Saturno")?;
+        let planet = &planets[index];
+        let (eye, position) = focus_targets(&planets, index, &SizeScale::new());
+        let world_radius = planet.radius * sphere_radius;
+
+        let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.view_matrix = create_view_matrix(eye, position, Vec3::new(0.0, 1.0, 0.0));
+        uniforms.view_pos = eye;
+        uniforms.model_matrix = create_model_matrix(position, planet.radius, Vec3::new(0.0, 0.0, 0.0));
+        render_body(&mut framebuffer, &uniforms, &vertices, planet.color_index, position, world_radius);
+        let body_uniforms = uniforms.clone();
+        render_planet_rings(&mut framebuffer, &mut uniforms, &ring_systems, planet, create_model_matrix(position, world_radius, Vec3::new(0.0, 0.0, 0.0)));
+        let stats = framebuffer.fragment_stats;
+
+        if stats.tested == 0 || stats.shaded * 10 > stats.tested * 9 {
+            return Err(format!("se sombrearon {} de {} fragmentos", stats.shaded, stats.tested));
+        }
+
+        // Referencia: sombrear cada fragmento del planeta y recién entonces probar la profundidad
+        let mut reference = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        let transformed: Vec<Vertex> = vertices.iter().map(|vertex| vertex_shader(vertex, &body_uniforms)).collect();
+        for tri in transformed.chunks_exact(3) {
+            for fragment in triangle::triangle(&tri[0], &tri[1], &tri[2], reference.width, reference.height) {
+                reference.set_current_color(select_shader(planet.color_index, &fragment, &body_uniforms).to_hex());
+                reference.point(fragment.position.x as usize, fragment.position.y as usize, fragment.depth);
+            }
+        }
+        let mut planet_only = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        render_body(&mut planet_only, &body_uniforms, &vertices, planet.color_index, position, world_radius);
+        if reference.buffer != planet_only.buffer || reference.zbuffer != planet_only.zbuffer {
+            return Err("el planeta no queda igual que sombreando todos los fragmentos".to_string());
+        }
+        Ok(())
+    }
+
+    // Esferas de radio 1e-3 a 1e3 a varias distancias: ni pánicos ni NaN en el z-buffer, al menos
+    // un píxel mientras la esfera está delante de la cámara (regla sub-píxel o impostor) y una
+    // cantidad de píxeles que crece con el radio sin pasarse del disco proyectado
+    #[test]
+    fn extreme_radii() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let vertices = obj.get_vertex_array();
+        let sphere_radius = obj.bounding_radius();
+        let eye = Vec3::new(0.0, 0.0, 10.0);
+
+        for distance in [5.0, 50.0, 500.0] {
+            let mut previous_pixels = 0;
+            for exponent in -3..=3 {
+                let scale = 10f32.powi(exponent);
+                let world_radius = scale * sphere_radius;
+                let center = eye - Vec3::new(0.0, 0.0, distance);
+
+                let mut framebuffer = Framebuffer::new(WIDTH as usize / 4, HEIGHT as usize / 4);
+                let mut uniforms = test_uniforms(seeds);
+                uniforms.viewport_matrix = create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+                uniforms.model_matrix = create_model_matrix(center, scale, Vec3::new(0.0, 0.0, 0.0));
+
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    render_body(&mut framebuffer, &uniforms, &vertices, 4, center, world_radius)
+                }))
+                .map_err(|_| format!("pánico con radio {} a distancia {}", world_radius, distance))?;
+
+                if framebuffer.zbuffer.iter().any(|depth| depth.is_nan()) {
+                    return Err(format!("profundidad NaN con radio {} a distancia {}", world_radius, distance));
+                }
+
+                // Con la cámara dentro de la esfera o rozándola no hay disco que comparar
+                if world_radius * 2.0 >= distance {
+                    continue;
+                }
+                let pixels = framebuffer.zbuffer.iter().filter(|depth| depth.is_finite()).count();
+                let disc = std::f32::consts::PI * projected_radius(&uniforms, center, world_radius).powi(2);
+                if pixels == 0 {
+                    return Err(format!("radio {} a distancia {} no dejó ningún píxel", world_radius, distance));
+                }
+                if pixels < previous_pixels || pixels as f32 > disc * 1.3 + 8.0 {
+                    return Err(format!(
+                        "radio {} a distancia {}: {} píxeles (antes {}, disco de {:.0})",
+                        world_radius, distance, pixels, previous_pixels, disc
+                    ));
+                }
+                previous_pixels = pixels;
+            }
+        }
+        Ok(())
+    }
 }
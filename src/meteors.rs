@@ -0,0 +1,238 @@
+use nalgebra_glm::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::color::Color;
+use crate::effects::EffectsBuffer;
+use crate::uniforms::Uniforms;
+use crate::project_point;
+
+// Todo va relativo al centro de la Tierra y en radios terrestres, así que la lluvia la acompaña
+// en su órbita y con cualquier escala de tamaños. La esfera de Hill real mide unos 235 radios,
+// mucho más allá de la órbita didáctica de la Luna: los meteoroides aparecen en una esfera más
+// chica, con la misma idea de ser el borde desde el que manda la gravedad de la Tierra.
+pub const HILL_RADIUS: f32 = 4.0;
+// Cuadros entre dos meteoroides nuevos
+const SPAWN_INTERVAL: u32 = 12;
+// Velocidad de llegada (radios por tick) y parámetro de impacto máximo, en radios
+const MIN_ENTRY_SPEED: f32 = 0.04;
+const MAX_ENTRY_SPEED: f32 = 0.07;
+const MAX_IMPACT_PARAMETER: f32 = 1.3;
+// Tamaños, en radios terrestres; solo cambian el frenado y el brillo
+const MIN_METEOROID_RADIUS: f32 = 0.002;
+const MAX_METEOROID_RADIUS: f32 = 0.01;
+// GM de la Tierra en radios³/tick²
+const EARTH_GM: f32 = 0.0005;
+// Atmósfera: densidad exponencial desde la superficie y techo por encima del cual no frena ni brilla
+const ATMOSPHERE_TOP: f32 = 0.6;
+const SCALE_HEIGHT: f32 = 0.15;
+// Frenado por tick a densidad 1 para un meteoroide del tamaño mínimo (los grandes frenan menos)
+const DRAG: f32 = 1.0;
+// Altura mínima: por debajo el meteoroide se da por consumido, igual que si casi se detiene
+pub const MIN_ALTITUDE: f32 = 0.1;
+const BURNOUT_SPEED: f32 = 0.005;
+// Paso máximo de la integración, en ticks (con la simulación acelerada se parte en varios)
+const MAX_STEP: f32 = 0.5;
+// La estela mide lo que el meteoroide recorre en estos ticks
+const TRAIL_TICKS: f32 = 6.0;
+
+// Colores de la estela según la velocidad: blanco incandescente al entrar, naranja al frenar y
+// rojo al final
+const WHITE_HOT: u32 = 0xFFFFF2;
+const ORANGE: u32 = 0xFF8C1A;
+const RED: u32 = 0xD02010;
+const HOT_SPEED: f32 = 0.04;
+const COOL_SPEED: f32 = 0.01;
+
+// Posición y velocidad relativas a la Tierra, en radios terrestres (y por tick)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Meteoroid {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub radius: f32,
+}
+
+impl Meteoroid {
+    pub fn altitude(&self) -> f32 {
+        self.position.magnitude() - 1.0
+    }
+
+    // Avanza `delta` ticks con la gravedad de la Tierra y el rozamiento de la atmósfera
+    pub fn step(&mut self, delta: f32) {
+        let steps = (delta / MAX_STEP).ceil().max(1.0);
+        let dt = delta / steps;
+        for _ in 0..steps as usize {
+            let distance = self.position.magnitude().max(1.0);
+            let gravity = -self.position * (EARTH_GM / (distance * distance * distance));
+            self.velocity += gravity * dt;
+            let altitude = distance - 1.0;
+            if altitude < ATMOSPHERE_TOP {
+                let drag = DRAG * (-altitude / SCALE_HEIGHT).exp() * MIN_METEOROID_RADIUS / self.radius;
+                self.velocity *= (-drag * dt).exp();
+            }
+            self.position += self.velocity * dt;
+        }
+    }
+
+    // Dentro de la atmósfera brilla; afuera es una roca oscura
+    pub fn is_burning(&self) -> bool {
+        self.altitude() < ATMOSPHERE_TOP
+    }
+
+    // Consumido: bajó de la altura mínima o casi se detuvo dentro de la atmósfera. También se
+    // descarta el que sale de la esfera de Hill sin haber caído.
+    pub fn is_spent(&self) -> bool {
+        let escaping = self.position.magnitude() > HILL_RADIUS && self.position.dot(&self.velocity) > 0.0;
+        self.altitude() < MIN_ALTITUDE || (self.is_burning() && self.velocity.magnitude() < BURNOUT_SPEED) || escaping
+    }
+
+    // Cola de la estela: hacia atrás por la trayectoria, más corta cuanto más frena
+    pub fn trail_tail(&self) -> Vec3 {
+        self.position - self.velocity * TRAIL_TICKS
+    }
+}
+
+// Color de la estela a una velocidad dada
+pub fn entry_color(speed: f32) -> Color {
+    let t = ((HOT_SPEED - speed) / (HOT_SPEED - COOL_SPEED)).clamp(0.0, 1.0);
+    if t < 0.5 {
+        Color::from_hex(WHITE_HOT).lerp(&Color::from_hex(ORANGE), t * 2.0)
+    } else {
+        Color::from_hex(ORANGE).lerp(&Color::from_hex(RED), (t - 0.5) * 2.0)
+    }
+}
+
+// Meteoroides que caen hacia la Tierra. Cada SPAWN_INTERVAL cuadros aparece uno en el borde de la
+// esfera de Hill apuntando más o menos a la Tierra; el RNG lleva semilla, como las fulguraciones.
+pub struct MeteorShower {
+    pub meteoroids: Vec<Meteoroid>,
+    rng: StdRng,
+    frames_until_spawn: u32,
+}
+
+impl MeteorShower {
+    pub fn new(seed: u64) -> Self {
+        MeteorShower { meteoroids: Vec::new(), rng: StdRng::seed_from_u64(seed), frames_until_spawn: SPAWN_INTERVAL }
+    }
+
+    // Avanza `delta` ticks (0 en pausa: entonces tampoco aparecen nuevos)
+    pub fn update(&mut self, delta: f32) {
+        if delta <= 0.0 {
+            return;
+        }
+        for meteoroid in self.meteoroids.iter_mut() {
+            meteoroid.step(delta);
+        }
+        self.meteoroids.retain(|meteoroid| !meteoroid.is_spent());
+
+        self.frames_until_spawn = self.frames_until_spawn.saturating_sub(1);
+        if self.frames_until_spawn == 0 {
+            let meteoroid = self.spawn();
+            self.meteoroids.push(meteoroid);
+            self.frames_until_spawn = SPAWN_INTERVAL;
+        }
+    }
+
+    fn spawn(&mut self) -> Meteoroid {
+        let azimuth = self.rng.gen_range(0.0..2.0 * PI);
+        let height: f32 = self.rng.gen_range(-1.0..1.0);
+        let ring = (1.0 - height * height).sqrt();
+        let outward = Vec3::new(ring * azimuth.cos(), height, ring * azimuth.sin());
+
+        // Desvío perpendicular a la dirección de llegada, dentro del parámetro de impacto
+        let side = outward.cross(&Vec3::new(0.0, 1.0, 0.0)).try_normalize(1e-6).unwrap_or(Vec3::new(1.0, 0.0, 0.0));
+        let lift = outward.cross(&side);
+        let angle = self.rng.gen_range(0.0..2.0 * PI);
+        let offset = (side * angle.cos() + lift * angle.sin()) * self.rng.gen_range(0.0..MAX_IMPACT_PARAMETER);
+
+        let position = outward * HILL_RADIUS;
+        let direction = (offset - position).normalize();
+        Meteoroid {
+            position,
+            velocity: direction * self.rng.gen_range(MIN_ENTRY_SPEED..MAX_ENTRY_SPEED),
+            radius: self.rng.gen_range(MIN_METEOROID_RADIUS..MAX_METEOROID_RADIUS),
+        }
+    }
+
+    // Estelas de los que están entrando, sumadas al buffer de efectos para la Tierra en
+    // `earth_center` de radio `earth_radius` (en mundo). Respetan el z-buffer del frame.
+    pub fn render(&self, effects: &mut EffectsBuffer, framebuffer: &Framebuffer, uniforms: &Uniforms, earth_center: Vec3, earth_radius: f32) {
+        for meteoroid in self.meteoroids.iter().filter(|meteoroid| meteoroid.is_burning()) {
+            render_atmospheric_entry(effects, framebuffer, uniforms, meteoroid, earth_center, earth_radius);
+        }
+    }
+}
+
+// Estela de un meteoroide: una línea de la cabeza a la cola, más brillante cuanto más grande
+pub fn render_atmospheric_entry(effects: &mut EffectsBuffer, framebuffer: &Framebuffer, uniforms: &Uniforms, meteoroid: &Meteoroid, earth_center: Vec3, earth_radius: f32) {
+    let to_world = |point: Vec3| earth_center + point * earth_radius;
+    let (Some(head), Some(tail)) = (project_point(uniforms, to_world(meteoroid.position)), project_point(uniforms, to_world(meteoroid.trail_tail()))) else {
+        return;
+    };
+    if head.z <= 0.0 || tail.z <= 0.0 {
+        return;
+    }
+    let color = entry_color(meteoroid.velocity.magnitude());
+    let brightness = 0.5 + 0.5 * (meteoroid.radius - MIN_METEOROID_RADIUS) / (MAX_METEOROID_RADIUS - MIN_METEOROID_RADIUS);
+    effects.add_line(framebuffer, (head.x, head.y), (tail.x, tail.y), color, brightness, head.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::FillStyle;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+
+    // Un meteoroide que cae de frente frena en la atmósfera, su estela se acorta y pasa del blanco al
+    // rojo hasta consumirse; la lluvia aparece en el borde de la esfera de Hill
+    #[test]
+    fn meteor_entry_and_shower() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        if entry_color(0.07) != Color::from_hex(0xFFFFF2) || entry_color(0.025) != Color::from_hex(0xFF8C1A) || entry_color(0.005) != Color::from_hex(0xD02010) {
+            return Err("los colores de la estela no van del blanco al naranja y al rojo".to_string());
+        }
+
+        let mut meteoroid = Meteoroid { position: Vec3::new(HILL_RADIUS, 0.0, 0.0), velocity: Vec3::new(-0.06, 0.0, 0.0), radius: 0.004 };
+        let mut burning = Vec::new();
+        for _ in 0..1000 {
+            if meteoroid.is_spent() {
+                break;
+            }
+            meteoroid.step(1.0);
+            if meteoroid.is_burning() {
+                burning.push((meteoroid.velocity.magnitude(), (meteoroid.position - meteoroid.trail_tail()).magnitude()));
+            }
+        }
+        if !meteoroid.is_spent() || meteoroid.altitude() < MIN_ALTITUDE - 0.1 {
+            return Err(format!("el meteoroide no se consumió en la atmósfera (altura {})", meteoroid.altitude()));
+        }
+        let (&(first_speed, first_trail), &(last_speed, last_trail)) = (burning.first().ok_or("nunca entró en la atmósfera")?, burning.last().ok_or("nunca entró en la atmósfera")?);
+        if last_speed > first_speed * 0.8 || last_trail >= first_trail {
+            return Err(format!("en la atmósfera pasó de {} a {} (estela de {} a {})", first_speed, last_speed, first_trail, last_trail));
+        }
+
+        let mut shower = MeteorShower::new(seeds.derive("meteors", 0));
+        let mut twin = MeteorShower::new(seeds.derive("meteors", 0));
+        shower.update(0.0);
+        for _ in 0..12 {
+            shower.update(1.0);
+            twin.update(1.0);
+        }
+        let spawned = shower.meteoroids.first().ok_or("en 12 cuadros no apareció ningún meteoroide")?;
+        if shower.meteoroids != twin.meteoroids || (spawned.position.magnitude() - HILL_RADIUS).abs() > 1e-4 || spawned.position.dot(&spawned.velocity) >= 0.0 {
+            return Err(format!("el meteoroide apareció en {:?} con velocidad {:?}", spawned.position, spawned.velocity));
+        }
+
+        let mut framebuffer = Framebuffer::new(5, 1);
+        framebuffer.clear();
+        let style = FillStyle::OPAQUE.additive();
+        framebuffer.blend_line((-3.0, 0.0), (2.0, 0.0), 0x404040, style);
+        framebuffer.blend_line((0.0, 0.0), (9.0, 0.0), 0x404040, style);
+        if framebuffer.buffer != vec![0x808080, 0x808080, 0x404040, 0x404040, 0x000000] {
+            return Err(format!("las líneas sumadas quedaron {:06X?}", framebuffer.buffer));
+        }
+        Ok(())
+    }
+}
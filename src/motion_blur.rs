@@ -0,0 +1,93 @@
+use nalgebra_glm::Vec3;
+
+use crate::Framebuffer;
+use crate::framebuffer::FillStyle;
+use crate::uniforms::Uniforms;
+use crate::{project_point, projected_radius};
+
+// Copias "fantasma" detrás de cada cuerpo; el coste está acotado a GHOSTS discos por cuerpo
+const GHOSTS: usize = 4;
+const MAX_GHOST_ALPHA: f32 = 0.45;
+
+// Desplazamiento mínimo en pantalla (píxeles por frame) para que aparezca el desenfoque
+const MIN_DISPLACEMENT: f32 = 3.0;
+
+// Por debajo de esta escala de tiempo no hay desenfoque, aunque algo se mueva rápido en pantalla
+pub const MIN_BLUR_TIME_SCALE: f32 = 8.0;
+
+// Desenfoque de movimiento aproximado por objeto: se guarda la posición de cada cuerpo en el
+// frame anterior y, si su desplazamiento en pantalla supera el umbral, se dibujan discos
+// translúcidos de su color a lo largo del vector de movimiento. El movimiento se mide
+// proyectando ambas posiciones con la cámara actual, así que mover la cámara no lo activa.
+pub struct MotionBlur {
+    pub enabled: bool,
+    previous: Vec<Option<Vec3>>,
+}
+
+impl MotionBlur {
+    pub fn new(body_count: usize) -> Self {
+        MotionBlur { enabled: true, previous: vec![None; body_count] }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    // Olvida las posiciones anteriores (al cambiar de vista no hay continuidad)
+    pub fn reset(&mut self) {
+        self.previous.iter_mut().for_each(|position| *position = None);
+    }
+
+    // El cuerpo no se dibujó este frame (fuera de vista): al volver no debe dejar estela
+    pub fn forget(&mut self, index: usize) {
+        self.previous[index] = None;
+    }
+
+    // Se llama después de dibujar el cuerpo `index` en `center`
+    pub fn render_trail(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        uniforms: &Uniforms,
+        index: usize,
+        center: Vec3,
+        world_radius: f32,
+        time_scale: f32,
+    ) {
+        let previous = self.previous[index].replace(center);
+        if !self.enabled || time_scale < MIN_BLUR_TIME_SCALE {
+            return;
+        }
+
+        let (current, previous) = match (previous.and_then(|p| project_point(uniforms, p)), project_point(uniforms, center)) {
+            (Some(previous), Some(current)) if current.z > 0.0 && previous.z > 0.0 => (current, previous),
+            _ => return,
+        };
+        let (dx, dy) = (previous.x - current.x, previous.y - current.y);
+        if (dx * dx + dy * dy).sqrt() < MIN_DISPLACEMENT {
+            return;
+        }
+
+        // El color del impostor es el del centro del cuerpo ya dibujado; si algo lo tapa, no hay estela
+        if current.x < 0.0 || current.y < 0.0 {
+            return;
+        }
+        let (cx, cy) = (current.x as usize, current.y as usize);
+        let color = match (framebuffer.read_pixel(cx, cy), framebuffer.read_depth(cx, cy)) {
+            (Some(color), Some(depth)) if depth >= current.z - world_radius - 1e-3 => color,
+            _ => return,
+        };
+
+        let radius = projected_radius(uniforms, center, world_radius).max(1.0);
+        for ghost in 1..=GHOSTS {
+            let t = ghost as f32 / GHOSTS as f32;
+            let alpha = MAX_GHOST_ALPHA * (1.0 - ghost as f32 / (GHOSTS + 1) as f32);
+            // Detrás del frente del cuerpo (profundidad de su centro): no tapa al cuerpo nítido
+            draw_disc(framebuffer, current.x + dx * t, current.y + dy * t, radius, current.z, color, alpha);
+        }
+    }
+}
+
+fn draw_disc(framebuffer: &mut Framebuffer, cx: f32, cy: f32, radius: f32, depth: f32, color: u32, alpha: f32) {
+    let style = FillStyle::OPAQUE.alpha(alpha).depth(depth);
+    framebuffer.fill_circle(cx.floor() as isize, cy.floor() as isize, radius.round() as usize, color, style);
+}
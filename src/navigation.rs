@@ -0,0 +1,100 @@
+use crate::Planet;
+
+// Cuerpo central del sistema: no orbita a nadie ni se mueve
+fn is_star(planet: &Planet) -> bool {
+    planet.parent.is_none() && planet.orbit_speed == 0.0
+}
+
+// Planetas (cuerpos que orbitan la estrella) ordenados por distancia, de adentro hacia afuera.
+// Se arma con la lista viva, así vale para los cuerpos de `--stress`; la estrella solo entra con
+// `include_star`.
+pub fn planets_by_distance(planets: &[Planet], include_star: bool) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..planets.len())
+        .filter(|&index| planets[index].parent.is_none() && (include_star || !is_star(&planets[index])))
+        .collect();
+    order.sort_by(|&a, &b| planets[a].distance_from_sun.total_cmp(&planets[b].distance_from_sun));
+    order
+}
+
+// Planeta vecino de `current` hacia afuera (`step` positivo) o hacia adentro, dando la vuelta en
+// los extremos. Una luna cuenta como su planeta; desde la estrella (si no está en el orden) se
+// entra por el extremo que corresponde.
+pub fn adjacent_planet(planets: &[Planet], current: usize, step: isize, include_star: bool) -> Option<usize> {
+    let order = planets_by_distance(planets, include_star);
+    if order.is_empty() {
+        return None;
+    }
+    let mut root = current;
+    while let Some(parent) = planets[root].parent {
+        root = parent;
+    }
+
+    let len = order.len() as isize;
+    let next = match order.iter().position(|&index| index == root) {
+        Some(position) => (position as isize + step).rem_euclid(len),
+        None if step > 0 => 0,
+        None => len - 1,
+    };
+    Some(order[next as usize])
+}
+
+// Desde un planeta, su luna más grande; desde una luna, su planeta. `None` si no hay adónde ir.
+pub fn moon_or_parent(planets: &[Planet], current: usize) -> Option<usize> {
+    if let Some(parent) = planets[current].parent {
+        return Some(parent);
+    }
+    (0..planets.len())
+        .filter(|&index| planets[index].parent == Some(current))
+        .max_by(|&a, &b| planets[a].radius.total_cmp(&planets[b].radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solar_system;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::stress::stress_bodies;
+
+    // [ y ] recorren los planetas por distancia dando la vuelta, sin el Sol; Shift va a la luna y vuelve
+    #[test]
+    fn planet_navigation() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let mut planets = solar_system();
+        let index = |name: &str| planets.iter().position(|planet| planet.name == name).ok_or(format!("no está {}", name));
+        let (sun, mercury, venus, earth, mars, uranus, neptune, moon) =
+            (index("Sol")?, index("Mercurio")?, index("Venus")?, index("Tierra")?, index("Marte")?, index("Urano")?, index("Neptuno")?, index("Luna")?);
+
+        let cases = [
+            (earth, 1, mars),
+            (earth, -1, venus),
+            (uranus, 1, neptune),
+            (neptune, 1, mercury),
+            (mercury, -1, neptune),
+            (moon, 1, mars),
+            (sun, 1, mercury),
+            (sun, -1, neptune),
+        ];
+        for (from, step, expected) in cases {
+            let next = adjacent_planet(&planets, from, step, false);
+            if next != Some(expected) {
+                return Err(format!("desde {} con {} llegó a {:?}", planets[from].name, step, next.map(|i| planets[i].name)));
+            }
+        }
+        if adjacent_planet(&planets, mercury, -1, true) != Some(sun) {
+            return Err("con el Sol incluido, Mercurio no pasa al Sol".to_string());
+        }
+        if moon_or_parent(&planets, earth) != Some(moon) || moon_or_parent(&planets, moon) != Some(earth) || moon_or_parent(&planets, mars).is_some() {
+            return Err("Shift no alterna entre la Tierra y la Luna".to_string());
+        }
+
+        // Los cuerpos agregados entran en el orden según su distancia
+        let count = planets.len();
+        planets.extend(stress_bodies(seeds, 4));
+        let order = planets_by_distance(&planets, false);
+        let sorted = order.windows(2).all(|pair| planets[pair[0]].distance_from_sun <= planets[pair[1]].distance_from_sun);
+        if !sorted || !(count..planets.len()).all(|i| order.contains(&i) == planets[i].parent.is_none()) {
+            return Err("el orden no incluye los cuerpos agregados".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use nalgebra_glm::{Mat4, Vec3};
+use std::f32::consts::PI;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::skybox::{self, Skybox};
+use crate::uniforms::{create_view_matrix, Uniforms};
+use crate::{Framebuffer, project_point};
+use crate::overlay::Overlay;
+
+// Día sidéreo de la Tierra en tiempo de la simulación. Es didáctico, como las distancias: con el
+// año de la simulación (2π / 0.007 ≈ 900) un día real duraría 2.5 y el cielo giraría demasiado
+// rápido para seguirlo.
+pub const SIDEREAL_DAY: f32 = 240.0;
+// El observador se para a esta altura sobre el centro, en radios de la Tierra, por encima del
+// relieve de la malla
+const OBSERVER_HEIGHT: f32 = 1.5;
+// Altura sobre el horizonte hacia la que mira (radianes)
+const VIEW_ALTITUDE: f32 = 0.35;
+// Distancia del punto al que mira, para que los planos de recorte abarquen el sistema
+const LOOK_DISTANCE: f32 = 100.0;
+const GROUND_COLOR: u32 = 0x0B0F08;
+const DAY_SKY_COLOR: u32 = 0x4A7FC1;
+// Seno de la altura del Sol entre los que el cielo pasa de la noche al día
+const TWILIGHT: (f32, f32) = (-0.1, 0.15);
+// Ciudad de Guatemala
+const DEFAULT_LATITUDE: f32 = 14.6;
+const DEFAULT_LONGITUDE: f32 = -90.5;
+pub const SUBSOLAR_COLOR: u32 = 0xFFD54F;
+// Retícula del punto subsolar: largo de los brazos y hueco del centro, en píxeles
+const CROSSHAIR_SIZE: usize = 12;
+const CROSSHAIR_GAP: usize = 4;
+
+// Ángulo que ha girado la Tierra sobre su eje en `time`. El eje es +Y, sin la inclinación real.
+// Las vueltas se cuentan en f64: a tiempos grandes un f32 ya no distingue las horas del día.
+pub fn earth_rotation(time: f64) -> f32 {
+    ((time / SIDEREAL_DAY as f64).rem_euclid(1.0) * 2.0 * std::f64::consts::PI) as f32
+}
+
+// Día solar para una órbita de velocidad angular `orbit_speed`: más largo que el sidéreo, porque
+// la Tierra avanza en su órbita en el mismo sentido en que gira
+pub fn solar_day(orbit_speed: f32) -> f32 {
+    1.0 / (1.0 / SIDEREAL_DAY - orbit_speed / (2.0 * PI))
+}
+
+// Cenit del punto de latitud `lat` y longitud `lon` (radianes) con la Tierra girada `rotation`
+pub fn zenith(lat: f32, lon: f32, rotation: f32) -> Vec3 {
+    let angle = lon + rotation;
+    Vec3::new(lat.cos() * angle.cos(), lat.sin(), lat.cos() * angle.sin())
+}
+
+// Dirección en que mira el observador: el horizonte del lado del ecuador (el sur en el hemisferio
+// norte), levantada VIEW_ALTITUDE
+fn view_direction(lat: f32, lon: f32, rotation: f32) -> Vec3 {
+    let angle = lon + rotation;
+    let north = Vec3::new(-lat.sin() * angle.cos(), lat.cos(), -lat.sin() * angle.sin());
+    let horizon = if lat >= 0.0 { -north } else { north };
+    horizon * VIEW_ALTITUDE.cos() + zenith(lat, lon, rotation) * VIEW_ALTITUDE.sin()
+}
+
+// Orientación del cielo visto desde la superficie, con el cenit hacia arriba en pantalla. Es una
+// matriz de vista con el ojo en el origen: se compone con la traslación al observador.
+pub fn local_sky_matrix(lat: f32, lon: f32, earth_rotation: f32) -> Mat4 {
+    let origin = Vec3::new(0.0, 0.0, 0.0);
+    create_view_matrix(origin, view_direction(lat, lon, earth_rotation), zenith(lat, lon, earth_rotation))
+}
+
+// Observador en la superficie de la Tierra (Q). La posición se da con `--observer <lat> <lon>`
+// en grados; por omisión, Guatemala.
+pub struct NightSky {
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+impl NightSky {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let Some(position) = args.iter().position(|arg| arg == "--observer") else {
+            return Ok(NightSky { latitude: DEFAULT_LATITUDE.to_radians(), longitude: DEFAULT_LONGITUDE.to_radians() });
+        };
+        let degrees = |index: usize| args.get(position + index).and_then(|value| value.parse::<f32>().ok());
+        match (degrees(1), degrees(2)) {
+            (Some(latitude), Some(longitude)) if latitude.abs() <= 90.0 && longitude.abs() <= 180.0 => {
+                Ok(NightSky { latitude: latitude.to_radians(), longitude: longitude.to_radians() })
+            }
+            _ => Err("--observer espera latitud y longitud en grados, como 14.6 -90.5".to_string()),
+        }
+    }
+
+    // Cenit del observador en `time`
+    pub fn zenith(&self, time: f64) -> Vec3 {
+        zenith(self.latitude, self.longitude, earth_rotation(time))
+    }
+
+    // Pone la cámara en el observador sobre la Tierra en `earth_center` (de radio `radius` en
+    // mundo), mirando al cielo local
+    pub fn place(&self, camera: &mut Camera, earth_center: Vec3, radius: f32, time: f64) {
+        let up = self.zenith(time);
+        camera.eye = earth_center + up * radius * OBSERVER_HEIGHT;
+        camera.center = camera.eye + view_direction(self.latitude, self.longitude, earth_rotation(time)) * LOOK_DISTANCE;
+        camera.up = up;
+    }
+
+    // Matriz de vista para el observador en `eye`
+    pub fn view_matrix(&self, eye: Vec3, time: f64) -> Mat4 {
+        local_sky_matrix(self.latitude, self.longitude, earth_rotation(time)) * Mat4::new_translation(&-eye)
+    }
+
+    // Coordenadas para el HUD, como "14.6N 90.5O"
+    pub fn label(&self) -> String {
+        let (latitude, longitude) = (self.latitude.to_degrees(), self.longitude.to_degrees());
+        format!(
+            "{:.1}{} {:.1}{}",
+            latitude.abs(),
+            if latitude >= 0.0 { 'N' } else { 'S' },
+            longitude.abs(),
+            if longitude >= 0.0 { 'E' } else { 'O' }
+        )
+    }
+}
+
+// Latitud y longitud geográficas (radianes) del punto en `offset` respecto del centro de la
+// Tierra en `time`. La longitud se cuenta sobre la Tierra girada, como la de `zenith`, y queda
+// entre -π y π.
+pub fn geographic_coordinates(offset: Vec3, time: f64) -> (f32, f32) {
+    let latitude = (offset.y / offset.magnitude()).clamp(-1.0, 1.0).asin();
+    let longitude = offset.z.atan2(offset.x) - earth_rotation(time);
+    (latitude, (longitude + PI).rem_euclid(2.0 * PI) - PI)
+}
+
+// Coordenadas para el HUD, como "LAT: +14.6 LON: -90.5" (la fuente no tiene el signo de grados)
+pub fn format_coordinates(latitude: f32, longitude: f32) -> String {
+    format!("LAT: {:+.1} LON: {:+.1}", latitude.to_degrees(), longitude.to_degrees())
+}
+
+// Punto de la superficie de la Tierra (centro `earth_center`, radio `radius`) que tiene al Sol en
+// el cenit
+pub fn subsolar_point(earth_center: Vec3, sun_center: Vec3, radius: f32) -> Vec3 {
+    earth_center + (sun_center - earth_center).normalize() * radius
+}
+
+// Retícula sobre el punto subsolar cuando cae en pantalla del lado de la Tierra que mira a la
+// cámara. Desde el observador queda bajo el horizonte; se ve con la Tierra enfocada.
+pub fn render_subsolar_marker(overlay: &mut Overlay, uniforms: &Uniforms, earth_center: Vec3, sun_center: Vec3, radius: f32, color: u32) {
+    let point = subsolar_point(earth_center, sun_center, radius);
+    if (point - earth_center).dot(&(uniforms.view_pos - point)) <= 0.0 {
+        return;
+    }
+    let Some(screen) = project_point(uniforms, point).filter(|p| p.z > 0.0 && p.x.is_finite() && p.y.is_finite()) else {
+        return;
+    };
+    let (x, y) = (screen.x.round() as isize, screen.y.round() as isize);
+    let reach = CROSSHAIR_SIZE as isize;
+    let (left, top) = ((x - reach).max(0) as usize, (y - reach).max(0) as usize);
+    let (right, bottom) = ((x + reach + 1).max(0) as usize, (y + reach + 1).max(0) as usize);
+    overlay.region(left, top, right.saturating_sub(left), bottom.saturating_sub(top)).draw_crosshair(x, y, CROSSHAIR_SIZE, CROSSHAIR_GAP, color);
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Cuánto es de día (0 a 1) con el Sol en `sun_direction` visto desde un lugar de cenit `zenith`
+pub fn daylight(zenith: Vec3, sun_direction: Vec3) -> f32 {
+    smoothstep(TWILIGHT.0, TWILIGHT.1, sun_direction.normalize().dot(&zenith))
+}
+
+// Estrellas del fondo en su dirección real, que se aclaran hacia el azul mientras el Sol está alto
+pub fn render_sky(framebuffer: &mut Framebuffer, skybox: &Skybox, camera: &Camera, projection: &Mat4, sun_direction: Vec3) {
+    let day = daylight(camera.up, sun_direction);
+    let day_sky = Color::from_hex(DAY_SKY_COLOR);
+    skybox::render_by_direction(framebuffer, camera, projection, |direction| skybox.sample(direction).lerp(&day_sky, day));
+}
+
+// Suelo: tapa lo que queda bajo el horizonte (la propia Tierra y el Sol cuando se pone). Va
+// después de los cuerpos y no deja dibujar nada encima.
+pub fn render_ground(framebuffer: &mut Framebuffer, camera: &Camera, projection: &Mat4) {
+    let ground = Color::from_hex(GROUND_COLOR);
+    skybox::paint_by_direction(framebuffer, camera, projection, |direction| {
+        (direction.dot(&camera.up) < 0.0).then_some((ground, 0.0))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selftest::close_to;
+    use nalgebra_glm::Vec4;
+    use crate::{solar_system, body_position};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    // Desde la superficie el cenit queda arriba en pantalla y el Sol sale y se pone una vez por día
+    #[test]
+    fn sky_from_surface() -> Result<(), String> {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        for bad in [&["--observer", "95", "0"][..], &["--observer", "10"], &["--observer", "norte", "0"]] {
+            if NightSky::from_args(&args(bad)).is_ok() {
+                return Err(format!("se aceptó {:?}", bad));
+            }
+        }
+        let night_sky = NightSky::from_args(&args(&["--observer", "-33.9", "18.4"]))?;
+        if night_sky.label() != "33.9S 18.4E" {
+            return Err(format!("el lugar se muestra como {}", night_sky.label()));
+        }
+
+        for (latitude, longitude, rotation) in [(14.6f32, -90.5f32, 0.0f32), (-33.9, 18.4, 2.0), (70.0, 120.0, 4.5)] {
+            let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+            let angle = longitude + rotation;
+            let zenith = Vec3::new(latitude.cos() * angle.cos(), latitude.sin(), latitude.cos() * angle.sin());
+            let view = local_sky_matrix(latitude, longitude, rotation) * Vec4::new(zenith.x, zenith.y, zenith.z, 0.0);
+            if view.x.abs() > 1e-5 || view.y < 0.9 {
+                return Err(format!("en ({}, {}) el cenit queda en {:?} de la vista", latitude, longitude, view));
+            }
+        }
+
+        let planets = solar_system();
+        let earth = planets.iter().position(|planet| planet.name == "Tierra").ok_or("no está la Tierra")?;
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let time = 40.0;
+        night_sky.place(&mut camera, body_position(&planets, earth, time), 1.0, time);
+        let expected = create_view_matrix(camera.eye, camera.center, camera.up);
+        if (night_sky.view_matrix(camera.eye, time) - expected).abs().max() > 1e-3 {
+            return Err("la matriz del cielo no coincide con la de la cámara".to_string());
+        }
+
+        // Altura del Sol a lo largo de un día solar: sale y se pone una vez
+        let (steps, day) = (72, solar_day(planets[earth].orbit_speed));
+        let altitudes: Vec<f32> = (0..steps)
+            .map(|step| {
+                let time = time + (day * step as f32 / steps as f32) as f64;
+                let sun = -body_position(&planets, earth, time).normalize();
+                night_sky.zenith(time).dot(&sun)
+            })
+            .collect();
+        let crossings = altitudes.windows(2).filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0)).count();
+        let back = (altitudes[steps - 1] < 0.0) != (altitudes[0] < 0.0);
+        if crossings + back as usize != 2 {
+            return Err(format!("en un día el Sol cruzó el horizonte {} veces", crossings + back as usize));
+        }
+        let (noon, midnight) = altitudes.iter().fold((f32::MIN, f32::MAX), |(high, low), &altitude| (high.max(altitude), low.min(altitude)));
+        if daylight(night_sky.zenith(0.0), night_sky.zenith(0.0)) != 1.0 || daylight(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)) != 0.0 || noon < 0.5 || midnight > -0.5 {
+            return Err(format!("el Sol llegó a {} de altura y bajó a {}", noon, midnight));
+        }
+        Ok(())
+    }
+
+    // La cámara del observador da de vuelta su latitud y longitud en cualquier momento del día, el
+    // punto subsolar está en el ecuador (la Tierra no tiene inclinación) y su retícula solo se dibuja
+    // del lado que mira a la cámara
+    #[test]
+    fn surface_coordinates() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        let earth_center = Vec3::new(60.0, 0.0, 0.0);
+        for (latitude, longitude, time) in [("23.4", "-122.3", 0.0f64), ("-33.9", "18.4", 97.0), ("70", "179.9", 1234.5)] {
+            let night_sky = NightSky::from_args(&args(&["--observer", latitude, longitude]))?;
+            let mut camera = Camera::new(Vec3::zeros(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+            night_sky.place(&mut camera, earth_center, 2.0, time);
+            let (found_latitude, found_longitude) = geographic_coordinates(camera.eye - earth_center, time);
+            let longitude_error = (found_longitude - night_sky.longitude).abs();
+            if (found_latitude - night_sky.latitude).abs() > 1e-3 || longitude_error.min(2.0 * std::f32::consts::PI - longitude_error) > 1e-3 {
+                return Err(format!("en {} {} a las {} se leyó {:?}", latitude, longitude, time, (found_latitude.to_degrees(), found_longitude.to_degrees())));
+            }
+        }
+        let label = format_coordinates(23.4f32.to_radians(), -122.3f32.to_radians());
+        if label != "LAT: +23.4 LON: -122.3" {
+            return Err(format!("las coordenadas se muestran como {}", label));
+        }
+
+        let subsolar = subsolar_point(earth_center, Vec3::zeros(), 2.0);
+        let (latitude, _) = geographic_coordinates(subsolar - earth_center, 0.0);
+        if !close_to(subsolar, Vec3::new(58.0, 0.0, 0.0)) || latitude.abs() > 1e-6 {
+            return Err(format!("punto subsolar en {:?}, latitud {}", subsolar, latitude));
+        }
+
+        // Vista desde (0, 0, 10): con el Sol del lado de la cámara la retícula rodea el centro de la
+        // pantalla sin taparlo; con el Sol detrás de la Tierra no se dibuja nada
+        let uniforms = test_uniforms(seeds);
+        let center = project_point(&uniforms, Vec3::new(0.0, 0.0, 2.0)).ok_or("el punto subsolar no se proyecta")?;
+        let (x, y) = (center.x.round() as usize, center.y.round() as usize);
+        let marker = |sun: Vec3| {
+            let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+            let mut overlay = Overlay::new(WIDTH as usize, HEIGHT as usize);
+            render_subsolar_marker(&mut overlay, &uniforms, Vec3::zeros(), sun, 2.0, 0xFFFFFF);
+            overlay.composite(&mut framebuffer, 1.0);
+            framebuffer
+        };
+        let front = marker(Vec3::new(0.0, 0.0, 100.0));
+        let pixel = |dx: isize, dy: isize| front.buffer[(y as isize + dy) as usize * front.width + (x as isize + dx) as usize];
+        if pixel(8, 0) != 0xFFFFFF || pixel(0, -8) != 0xFFFFFF || pixel(0, 0) != 0 || pixel(2, 2) != 0 {
+            return Err("la retícula no rodea el punto subsolar".to_string());
+        }
+        if marker(Vec3::new(0.0, 0.0, -100.0)).buffer.iter().any(|&pixel| pixel != 0) {
+            return Err("se marcó el punto subsolar del otro lado de la Tierra".to_string());
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use tobj;
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat3, Mat4, mat4_to_mat3};
 use crate::vertex::Vertex;
 
 pub struct Obj {
@@ -40,8 +40,57 @@ impl Obj {
         Ok(Obj { meshes })
     }
 
+    // Devuelve una copia con `m` aplicada a las posiciones y la transpuesta inversa a las normales,
+    // para corregir la orientación o el origen de un modelo una sola vez al cargarlo
+    pub fn transform(&self, m: Mat4) -> Self {
+        let normal_matrix = mat4_to_mat3(&m).transpose().try_inverse().unwrap_or(Mat3::identity());
+
+        let meshes = self.meshes.iter().map(|mesh| Mesh {
+            vertices: mesh.vertices.iter()
+                .map(|v| {
+                    let p = m * Vec4::new(v.x, v.y, v.z, 1.0);
+                    Vec3::new(p.x, p.y, p.z) / p.w
+                })
+                .collect(),
+            normals: mesh.normals.iter()
+                .map(|n| (normal_matrix * n).normalize())
+                .collect(),
+            texcoords: mesh.texcoords.clone(),
+            indices: mesh.indices.clone(),
+        }).collect();
+
+        Obj { meshes }
+    }
+
+    // Centro de la caja envolvente de todos los vértices
+    pub fn bounding_center(&self) -> Vec3 {
+        let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        for vertex in self.meshes.iter().flat_map(|mesh| mesh.vertices.iter()) {
+            min = min.inf(vertex);
+            max = max.sup(vertex);
+        }
+
+        if min.x > max.x {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        (min + max) * 0.5
+    }
+
+    // Distancia máxima de un vértice al origen del modelo
+    pub fn bounding_radius(&self) -> f32 {
+        self.meshes.iter()
+            .flat_map(|mesh| mesh.vertices.iter())
+            .map(|vertex| vertex.magnitude())
+            .fold(0.0, f32::max)
+    }
+
+    // Un vértice por cada índice de las caras; las copias de un mismo vértice llevan su índice
+    // (contando los de las mallas anteriores) para que `VertexCache` las reconozca
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
         let mut vertices = Vec::new();
+        let mut first_index = 0;
 
         for mesh in &self.meshes {
             for &index in &mesh.indices {
@@ -53,8 +102,9 @@ impl Obj {
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
-                vertices.push(Vertex::new(position, normal, tex_coords));
+                vertices.push(Vertex::new(position, normal, tex_coords).with_mesh_index(first_index + index as usize));
             }
+            first_index += mesh.vertices.len();
         }
 
         vertices
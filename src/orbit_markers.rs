@@ -0,0 +1,135 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::overlay::Overlay;
+use crate::uniforms::Uniforms;
+use crate::{Planet, orbit_offset, project_point};
+
+// Marcas de fase cada 90° con su etiqueta (el ángulo en grados)
+const TICKS: [(f32, &str); 4] = [(0.0, "0"), (0.5 * PI, "90"), (PI, "180"), (1.5 * PI, "270")];
+
+// Largo de cada marca hacia dentro y hacia fuera de la órbita, como fracción de su radio
+const TICK_LENGTH: f32 = 0.04;
+
+// La punta de la flecha está a esta distancia (en radios del cuerpo) por delante de su centro
+const ARROW_LEAD: f32 = 2.5;
+const ARROW_LENGTH: f32 = 1.5;
+
+// Brillo de las etiquetas tapadas por algo más cercano
+const OCCLUDED_LABEL_BRIGHTNESS: f32 = 0.3;
+
+// Segmentos más largos que esto (en píxeles) se descartan: son puntos casi detrás de la cámara
+const MAX_SEGMENT_LENGTH: f32 = 2048.0;
+
+// Cuerpo cuyas fases se marcan: el centro de su órbita, su fase actual y su radio en el mundo
+pub struct MarkedBody<'a> {
+    pub planet: &'a Planet,
+    pub orbit_center: Vec3,
+    pub angle: f32,
+    pub world_radius: f32,
+}
+
+// Referencias de fase sobre cada órbita para presentaciones: marcas a 0°, 90°, 180° y 270°
+// con su etiqueta y una flecha delante del cuerpo en el sentido en que se mueve. Se dibujan
+// después de los cuerpos para que el z-buffer las oculte detrás de ellos. Se activa con la tecla O.
+pub struct OrbitMarkers {
+    pub enabled: bool,
+}
+
+impl OrbitMarkers {
+    pub fn new() -> Self {
+        OrbitMarkers { enabled: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, overlay: &mut Overlay, uniforms: &Uniforms, body: &MarkedBody, color: u32) {
+        let MarkedBody { planet, orbit_center, angle, world_radius } = *body;
+        if planet.distance_from_sun <= 0.0 {
+            return;
+        }
+
+        for (tick_angle, label) in TICKS {
+            let point = orbit_center + orbit_offset(planet, tick_angle);
+            // Perpendicular a la tangente, dentro del plano de la órbita
+            let across = orbit_offset(planet, tick_angle).normalize() * planet.distance_from_sun * TICK_LENGTH;
+            draw_depth_tested_line(framebuffer, uniforms, point - across, point + across, color);
+            draw_label(framebuffer, overlay, uniforms, point + across, label, color);
+        }
+
+        if planet.orbit_speed == 0.0 {
+            return;
+        }
+
+        // Flecha en el borde delantero del cuerpo, apuntando en el sentido del movimiento
+        let direction = planet.orbit_speed.signum();
+        let lead = direction * ARROW_LEAD * world_radius / planet.distance_from_sun;
+        let tip = orbit_center + orbit_offset(planet, angle + lead);
+        let tangent = orbit_offset(planet, angle + lead + 0.5 * PI).normalize() * direction;
+        let across = orbit_offset(planet, angle + lead).normalize();
+        let length = ARROW_LENGTH * world_radius;
+        for side in [-1.0, 1.0] {
+            let tail = tip - tangent * length + across * (side * 0.6 * length);
+            draw_depth_tested_line(framebuffer, uniforms, tail, tip, color);
+        }
+    }
+}
+
+// Línea 3D que solo pinta donde no hay algo más cercano; no escribe en el z-buffer
+fn draw_depth_tested_line(framebuffer: &mut Framebuffer, uniforms: &Uniforms, start: Vec3, end: Vec3, color: u32) {
+    let visible = |p: &Vec3| p.z > 0.0 && p.z.is_finite();
+    if let (Some(start), Some(end)) = (project_point(uniforms, start).filter(visible), project_point(uniforms, end).filter(visible)) {
+        draw_depth_tested_segment(framebuffer, start, end, color);
+    }
+}
+
+// Igual que `draw_depth_tested_line` con los extremos ya proyectados (x, y de pantalla y
+// profundidad de vista), para quien proyecta cada punto una sola vez y lo comparte entre segmentos
+pub fn draw_depth_tested_segment(framebuffer: &mut Framebuffer, start: Vec3, end: Vec3, color: u32) {
+    let length = (end.x - start.x).abs().max((end.y - start.y).abs());
+    if length > MAX_SEGMENT_LENGTH {
+        return;
+    }
+
+    let steps = length.ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let x = start.x + (end.x - start.x) * t;
+        let y = start.y + (end.y - start.y) * t;
+        if x < 0.0 || y < 0.0 {
+            continue;
+        }
+        // La profundidad es distancia de vista: se interpola su inverso
+        let depth = 1.0 / ((1.0 - t) / start.z + t / end.z);
+        if let Some(stored) = framebuffer.read_depth(x as usize, y as usize) {
+            if depth < stored {
+                framebuffer.buffer[y as usize * framebuffer.width + x as usize] = color;
+            }
+        }
+    }
+}
+
+// Etiqueta junto a un punto 3D, en la capa del HUD; si algo más cercano en `framebuffer` la tapa
+// se atenúa en vez de desaparecer
+pub fn draw_label(framebuffer: &Framebuffer, overlay: &mut Overlay, uniforms: &Uniforms, anchor: Vec3, text: &str, color: u32) {
+    let screen = match project_point(uniforms, anchor) {
+        Some(screen) if screen.z > 0.0 && screen.x >= 0.0 && screen.y >= 0.0 => screen,
+        _ => return,
+    };
+    let (x, y) = (screen.x as usize, screen.y as usize);
+    let depth = match framebuffer.read_depth(x, y) {
+        Some(depth) => depth,
+        None => return,
+    };
+
+    let color = if screen.z < depth { color } else { scale_color(color, OCCLUDED_LABEL_BRIGHTNESS) };
+    overlay.draw_text(x + 2, y + 2, text, color, 1);
+}
+
+pub fn scale_color(color: u32, factor: f32) -> u32 {
+    let channel = |shift: u32| ((((color >> shift) & 0xFF) as f32 * factor) as u32) << shift;
+    channel(16) | channel(8) | channel(0)
+}
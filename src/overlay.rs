@@ -0,0 +1,243 @@
+use crate::Framebuffer;
+use crate::color::Color;
+use crate::hud::Panel;
+
+// Píxel vacío de la capa. El byte alto de cada píxel es cuánto deja ver de la escena (0 opaco,
+// 255 transparente): lo que se dibuja con las funciones de siempre (0x00RRGGBB) queda opaco y
+// `Overlay::blend_rect` guarda ahí la opacidad de los fondos translúcidos. Solo esta capa lee ese
+// byte; el `Framebuffer` no sabe nada de él.
+const TRANSPARENT: u32 = 0xFF00_0000;
+
+// Capa del HUD: los nombres de los cuerpos, los paneles y los textos se dibujan aquí y no en el
+// cuadro, y se componen sobre él después de todos los post-procesos (exposición, profundidad de
+// campo, aberración, FXAA), cada píxel con su propia opacidad. Solo se compone lo que se marcó con
+// `region` desde la última composición, y después eso queda transparente para el cuadro
+// siguiente: ni la composición ni el borrado recorren la capa entera.
+pub struct Overlay {
+    canvas: Framebuffer,
+    // Columnas [inicio, fin) de cada fila donde se dibujó; vacía si inicio >= fin
+    dirty: Vec<(usize, usize)>,
+}
+
+impl Overlay {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut canvas = Framebuffer::new(width, height);
+        canvas.buffer.fill(TRANSPARENT);
+        Overlay { canvas, dirty: vec![(width, 0); height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.canvas.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.canvas.height
+    }
+
+    // Marca el rectángulo donde se va a dibujar (se recorta a la capa) y devuelve la capa para
+    // hacerlo. Lo que se dibuje fuera de lo marcado no se compone ni se borra: queda en la capa
+    // hasta que se marque su zona.
+    pub fn region(&mut self, x: usize, y: usize, width: usize, height: usize) -> &mut Framebuffer {
+        let x_end = x.saturating_add(width).min(self.canvas.width);
+        if x < x_end {
+            let y_end = y.saturating_add(height).min(self.canvas.height);
+            for span in self.dirty.iter_mut().take(y_end).skip(y) {
+                *span = (span.0.min(x), span.1.max(x_end));
+            }
+        }
+        &mut self.canvas
+    }
+
+    // Como `Framebuffer::draw_text`; los glifos se corren hasta `scale - 1` píxeles a la izquierda
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: u32, scale: usize) {
+        let (width, height) = Framebuffer::measure_text(text, scale);
+        let left = x.saturating_sub(scale);
+        self.region(left, y, width + (x - left), height).draw_text(x, y, text, color, scale);
+    }
+
+    pub fn draw_text_centered(&mut self, cx: usize, cy: usize, text: &str, color: u32, scale: usize) {
+        let (width, height) = Framebuffer::measure_text(text, scale);
+        self.draw_text(cx.saturating_sub(width / 2), cy.saturating_sub(height / 2), text, color, scale);
+    }
+
+    pub fn draw_text_right_aligned(&mut self, rx: usize, y: usize, text: &str, color: u32, scale: usize) {
+        let width = Framebuffer::measure_text(text, scale).0;
+        self.draw_text(rx.saturating_sub(width), y, text, color, scale);
+    }
+
+    pub fn draw_panel(&mut self, panel: &Panel) {
+        panel.draw(self);
+    }
+
+    // Rectángulo de `color` con opacidad `alpha` sobre lo que ya hay en la capa: donde había algo
+    // opaco se mezclan los colores, y donde se deja ver la escena la opacidad se acumula
+    pub fn blend_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32, alpha: f32) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let top = Color::from_hex(color);
+        let canvas = self.region(x, y, width, height);
+        let x_end = x.saturating_add(width).min(canvas.width);
+        let y_end = y.saturating_add(height).min(canvas.height);
+        for py in y..y_end {
+            for px in x..x_end {
+                let index = py * canvas.width + px;
+                canvas.buffer[index] = blend_pixel(canvas.buffer[index], top, alpha);
+            }
+        }
+    }
+
+    // Compone lo marcado sobre `framebuffer`, con la opacidad de cada píxel multiplicada por
+    // `opacity`, y lo deja transparente. Si las medidas difieren se compone solo la zona común;
+    // los z-buffers no se tocan.
+    pub fn composite(&mut self, framebuffer: &mut Framebuffer, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        let width = self.canvas.width;
+        for (y, span) in self.dirty.iter_mut().enumerate() {
+            let (start, end) = std::mem::replace(span, (width, 0));
+            if start >= end {
+                continue;
+            }
+            // Solo el tramo marcado: fuera de él la capa ya está transparente
+            let row = &mut self.canvas.buffer[y * width + start..y * width + end];
+            for (x, &pixel) in (start..end).zip(row.iter()) {
+                let alpha = pixel_alpha(pixel) * opacity;
+                if alpha <= 0.0 || x >= framebuffer.width || y >= framebuffer.height {
+                    continue;
+                }
+                let index = y * framebuffer.width + x;
+                framebuffer.buffer[index] = if alpha >= 1.0 {
+                    pixel & 0xFFFFFF
+                } else {
+                    Color::from_hex(framebuffer.buffer[index]).lerp(&Color::from_hex(pixel), alpha).to_hex()
+                };
+            }
+            row.fill(TRANSPARENT);
+        }
+    }
+}
+
+// Opacidad (0 a 1) de un píxel de la capa
+fn pixel_alpha(pixel: u32) -> f32 {
+    1.0 - (pixel >> 24) as f32 / 255.0
+}
+
+// `top` con opacidad `alpha` sobre un píxel de la capa: el color queda promediado por la opacidad
+// de cada uno y la opacidad es la de los dos juntos. Sobre un píxel opaco es la mezcla de siempre.
+fn blend_pixel(pixel: u32, top: Color, alpha: f32) -> u32 {
+    let below = pixel_alpha(pixel) * (1.0 - alpha);
+    let combined = alpha + below;
+    if combined <= 0.0 {
+        return TRANSPARENT;
+    }
+    let base = Color::from_hex(pixel);
+    let channel = |top: u8, base: u8| ((top as f32 * alpha + base as f32 * below) / combined).round() as u32;
+    let transparency = ((1.0 - combined) * 255.0).round() as u32;
+    transparency << 24 | channel(top.r, base.r) << 16 | channel(top.g, base.g) << 8 | channel(top.b, base.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+    use crate::orbit_markers::draw_label;
+    use crate::reference_frame::pixel_delta;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    // La capa del HUD da lo mismo que dibujar directo sobre la escena (los fondos translúcidos, a lo
+    // sumo un nivel por el redondeo de su opacidad, y los textos mezclados con la opacidad de la
+    // capa), no toca la escena hasta componerse ni fuera de lo dibujado, y queda vacía
+    #[test]
+    fn hud_overlay() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let (width, height) = (160, 120);
+        let mut scene = Framebuffer::new(width, height);
+        for (index, pixel) in scene.buffer.iter_mut().enumerate() {
+            *pixel = ((index % width) as u32 * 0x010000) | ((index / width) as u32 * 0x000200) | 0x40;
+        }
+        scene.zbuffer.fill(5.0);
+        // El fondo de un panel vacío es el rectángulo translúcido de siempre
+        let panel = Panel::new(10, 10, 100);
+        let (x, y, panel_width, panel_height) = panel.bounds();
+
+        let mut direct = scene.clone_buffer();
+        direct.blend_rect(x, y, panel_width, panel_height, 0x000000, 0.6);
+        direct.draw_text(20, 90, "PAUSA", 0xFFFFFF, 1);
+        let mut overlay = Overlay::new(width, height);
+        let mut composed = scene.clone_buffer();
+        overlay.draw_panel(&panel);
+        if composed.buffer != scene.buffer {
+            return Err("el panel llegó a la escena antes de componer la capa".to_string());
+        }
+        overlay.composite(&mut composed, 1.0);
+        overlay.draw_text(20, 90, "PAUSA", 0xFFFFFF, 1);
+        overlay.composite(&mut composed, 1.0);
+        let worst = direct.buffer.iter().zip(&composed.buffer).map(|(&a, &b)| pixel_delta(a, b)).max().unwrap_or(0);
+        if worst > 1 {
+            return Err(format!("el HUD compuesto difiere en {} niveles del dibujado directo", worst));
+        }
+        if composed.buffer.iter().any(|&pixel| pixel >> 24 != 0) || composed.zbuffer.iter().any(|&depth| depth != 5.0) {
+            return Err("la composición dejó opacidad en la escena o tocó el z-buffer".to_string());
+        }
+
+        // Textos al 90%: cada píxel del texto mezclado al 90% con la escena y el resto intacto
+        let mut layer = Framebuffer::new(width, height);
+        layer.draw_text(30, 40, "TAMANOS REALES", 0xFFD54F, 2);
+        let mut expected = scene.clone_buffer();
+        for (pixel, &color) in expected.buffer.iter_mut().zip(&layer.buffer) {
+            if color != 0x000000 {
+                *pixel = Color::from_hex(*pixel).lerp(&Color::from_hex(color), 0.9).to_hex();
+            }
+        }
+        let mut faded = scene.clone_buffer();
+        overlay.draw_text(30, 40, "TAMANOS REALES", 0xFFD54F, 2);
+        overlay.composite(&mut faded, 0.9);
+        if faded.buffer != expected.buffer {
+            return Err("los textos al 90% no coinciden con la mezcla directa".to_string());
+        }
+
+        // Lo que se dibuja fuera de lo marcado no se compone; tampoco se borra, así que aparece
+        // cuando se marca su zona
+        overlay.region(0, 0, 1, 1).draw_filled_rect(50, 50, 2, 2, 0xFFFFFF);
+        let mut untouched = scene.clone_buffer();
+        overlay.composite(&mut untouched, 1.0);
+        if untouched.buffer != scene.buffer {
+            return Err("un dibujo fuera de la región marcada llegó a la escena".to_string());
+        }
+        overlay.region(50, 50, 2, 2);
+        overlay.composite(&mut untouched, 1.0);
+        if untouched.buffer[50 * width + 50] != 0xFFFFFF || overlay.canvas.buffer.iter().any(|&pixel| pixel != TRANSPARENT) {
+            return Err("el dibujo sin marcar se perdió o quedó en la capa después de marcarlo".to_string());
+        }
+
+        // Dos fondos al 50% dejan ver un cuarto de la escena; después la capa no tiene nada
+        overlay.blend_rect(0, 0, 4, 1, 0xFFFFFF, 0.5);
+        overlay.blend_rect(0, 0, 4, 1, 0xFFFFFF, 0.5);
+        let pixel = overlay.canvas.buffer[0];
+        if pixel == TRANSPARENT || (pixel_alpha(pixel) - 0.75).abs() > 0.01 {
+            return Err(format!("dos fondos al 50% dieron {:08X}", pixel));
+        }
+        let mut black = Framebuffer::new(width, height);
+        overlay.composite(&mut black, 1.0);
+        if Color::from_hex(black.buffer[0]).r.abs_diff(191) > 1 || black.buffer[4..].iter().any(|&pixel| pixel != 0) {
+            return Err(format!("sobre negro quedó {:06X}", black.buffer[0]));
+        }
+        overlay.composite(&mut black, 1.0);
+        if Color::from_hex(black.buffer[0]).r.abs_diff(191) > 1 {
+            return Err("la capa no quedó vacía después de componer".to_string());
+        }
+
+        // Los nombres de los cuerpos van a la capa: el post-proceso no los toca
+        let uniforms = test_uniforms(seeds);
+        let mut frame = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        let mut labels = Overlay::new(WIDTH as usize, HEIGHT as usize);
+        draw_label(&frame, &mut labels, &uniforms, Vec3::zeros(), "TIERRA", 0xFFFFFF);
+        if frame.buffer.iter().any(|&pixel| pixel != 0) {
+            return Err("la etiqueta se dibujó en el cuadro".to_string());
+        }
+        labels.composite(&mut frame, 1.0);
+        if !frame.buffer.contains(&0xFFFFFF) {
+            return Err("la etiqueta no llegó al cuadro al componer".to_string());
+        }
+        Ok(())
+    }
+}
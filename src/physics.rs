@@ -0,0 +1,73 @@
+use nalgebra_glm::Vec3;
+
+// Constantes físicas en unidades SI
+pub const SPEED_OF_LIGHT: f32 = 299_792_458.0; // m/s
+pub const SUN_GRAVITATIONAL_PARAMETER: f64 = 1.327_124_400_18e20; // m³/s² (G · M☉)
+pub const ASTRONOMICAL_UNIT: f64 = 1.495_978_707e11; // m
+pub const GRAVITATIONAL_CONSTANT: f32 = 6.674_30e-11; // m³/(kg·s²)
+
+// Tope de β para que γ siga siendo finito
+const MAX_BETA: f32 = 0.999_999;
+
+// Factor de Lorentz γ = 1 / √(1 - β²) a `speed` m/s: cuánto más lento corre un reloj que se mueve
+pub fn lorentz_factor(speed: f32) -> f32 {
+    let beta = (speed / SPEED_OF_LIGHT).min(MAX_BETA);
+    1.0 / (1.0 - beta * beta).sqrt()
+}
+
+// Aberración relativista exacta: dirección (unitaria) en que ve una fuente que en reposo está en
+// `d` un observador que se mueve con velocidad `v` (m/s). Con θ el ángulo entre `d` y `v` y
+// β = |v| / c: cos θ' = (cos θ + β) / (1 + β cos θ) y sen θ' = sen θ / (γ (1 + β cos θ)), así
+// que las fuentes se juntan hacia adelante. Con `-v` se deshace.
+pub fn relativistic_aberration(v: Vec3, d: Vec3) -> Vec3 {
+    let d = d.normalize();
+    let speed = v.magnitude();
+    if speed <= 0.0 {
+        return d;
+    }
+
+    let beta = (speed / SPEED_OF_LIGHT).min(MAX_BETA);
+    let gamma = lorentz_factor(speed);
+    let forward = v / speed;
+    let cos_theta = d.dot(&forward);
+    let perpendicular = d - forward * cos_theta;
+    ((forward * (cos_theta + beta) + perpendicular / gamma) / (1.0 + beta * cos_theta)).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 0.9c una fuente a 90° de la marcha se ve a acos(0.9) ≈ 25.8°; lo que está justo adelante o
+    // atrás no se mueve, todo lo demás se acerca al frente y la velocidad opuesta lo deshace
+    #[test]
+    fn aberration_at_point_nine_c() -> Result<(), String> {
+        let forward = Vec3::new(0.0, 0.0, -1.0);
+        let velocity = forward * 0.9 * SPEED_OF_LIGHT;
+        let side = Vec3::new(1.0, 0.0, 0.0);
+
+        if (relativistic_aberration(Vec3::new(0.0, 0.0, 0.0), side) - side).magnitude() > 1e-6 {
+            return Err("en reposo la dirección cambia".to_string());
+        }
+        let angle = relativistic_aberration(velocity, side).dot(&forward).clamp(-1.0, 1.0).acos().to_degrees();
+        if (angle - 0.9f32.acos().to_degrees()).abs() > 0.01 {
+            return Err(format!("a 90° de la marcha se ve a {:.2}°", angle));
+        }
+        for fixed in [forward, -forward] {
+            if (relativistic_aberration(velocity, fixed) - fixed).magnitude() > 1e-5 {
+                return Err(format!("la dirección {:?} sobre la marcha se movió", fixed));
+            }
+        }
+
+        for step in 1..36 {
+            let theta = (step as f32 * 5.0).to_radians();
+            let direction = forward * theta.cos() + side * theta.sin();
+            let observed = relativistic_aberration(velocity, direction);
+            let back = relativistic_aberration(-velocity, observed);
+            if (observed.magnitude() - 1.0).abs() > 1e-4 || observed.dot(&forward) <= direction.dot(&forward) || (back - direction).magnitude() > 1e-3 {
+                return Err(format!("a {}° de la marcha: {:?} y de vuelta {:?}", step * 5, observed, back));
+            }
+        }
+        Ok(())
+    }
+}
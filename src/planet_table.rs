@@ -0,0 +1,131 @@
+use minifb::{Key, KeyRepeat, Window};
+use std::f32::consts::PI;
+use std::fmt::Write;
+
+use crate::Framebuffer;
+use crate::Camera;
+use crate::overlay::Overlay;
+use crate::{Planet, body_position, is_in_camera_view};
+use crate::units::{Unit, DISTANCE_SCALE, format_quantity};
+
+const TEXT_SCALE: usize = 2;
+const ROW_PADDING: usize = 6;
+const MARGIN: usize = 20;
+
+// Columnas: título y posición x del borde derecho (los números se alinean a la derecha)
+const COLUMNS: [(&str, usize); 6] = [
+    ("CUERPO", 0),
+    ("DIST", 260),
+    ("RADIO", 360),
+    ("PERIODO", 500),
+    ("ANGULO", 620),
+    ("VISIBLE", 760),
+];
+
+// Tabla comparativa de todos los cuerpos, abierta con la tecla I.
+// Mientras está visible captura las flechas y Enter para que no muevan la cámara.
+pub struct PlanetTable {
+    pub visible: bool,
+    selected: usize,
+    scroll: usize,
+    // Texto de la celda que se está dibujando, reutilizado para no reservar memoria cada frame
+    cell: String,
+}
+
+impl PlanetTable {
+    pub fn new() -> Self {
+        PlanetTable {
+            visible: false,
+            selected: 0,
+            scroll: 0,
+            cell: String::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    // Procesa la navegación; devuelve el índice del cuerpo elegido con Enter
+    pub fn handle_input(&mut self, window: &Window, body_count: usize) -> Option<usize> {
+        if body_count == 0 {
+            return None;
+        }
+
+        if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+            self.selected = (self.selected + 1).min(body_count - 1);
+        }
+        if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            self.visible = false;
+            return Some(self.selected);
+        }
+
+        None
+    }
+
+    pub fn render(&mut self, overlay: &mut Overlay, planets: &[Planet], camera: &Camera, time: f64) {
+        // Oscurecer la escena 3D detrás de la tabla
+        let (width, height) = (overlay.width(), overlay.height());
+        overlay.blend_rect(0, 0, width, height, 0x000000, 0.65);
+
+        let row_height = Framebuffer::measure_text("X", TEXT_SCALE).1 + ROW_PADDING;
+        let visible_rows = (height.saturating_sub(2 * MARGIN) / row_height).saturating_sub(1).max(1);
+
+        // Desplazar la tabla para que la fila seleccionada siempre quede a la vista
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible_rows {
+            self.scroll = self.selected + 1 - visible_rows;
+        }
+        // La fila elegida se resalta antes de escribir la tabla encima
+        let row_y = |row: usize| MARGIN + (row + 1) * row_height;
+        overlay.blend_rect(MARGIN / 2, row_y(self.selected - self.scroll) - ROW_PADDING / 2, width - MARGIN, row_height, 0x4682B4, 0.5);
+
+        let framebuffer = overlay.region(0, 0, width, height);
+        for (column, (title, _)) in COLUMNS.iter().enumerate() {
+            draw_cell(framebuffer, column, MARGIN, title, 0xFFD700);
+        }
+
+        for (row, index) in (self.scroll..planets.len()).take(visible_rows).enumerate() {
+            let y = row_y(row);
+            let planet = &planets[index];
+            let position = body_position(planets, index, time);
+            draw_cell(framebuffer, 0, y, planet.name, 0xFFFFFF);
+
+            format_quantity(&mut self.cell, planet.distance_from_sun as f64, Unit::Distance(DISTANCE_SCALE), 3);
+            draw_cell(framebuffer, 1, y, &self.cell, 0xFFFFFF);
+
+            format_quantity(&mut self.cell, planet.radius as f64, Unit::Distance(DISTANCE_SCALE), 3);
+            draw_cell(framebuffer, 2, y, &self.cell, 0xFFFFFF);
+
+            if planet.orbit_speed > 0.0 {
+                format_quantity(&mut self.cell, (2.0 * PI / planet.orbit_speed) as f64, Unit::Plain, 4);
+            } else {
+                self.cell.clear();
+                self.cell.push('-');
+            }
+            draw_cell(framebuffer, 3, y, &self.cell, 0xFFFFFF);
+
+            let angle = planet.orbit_angle(time).to_degrees();
+            self.cell.clear();
+            let _ = write!(self.cell, "{:.0}", angle);
+            draw_cell(framebuffer, 4, y, &self.cell, 0xFFFFFF);
+
+            let visible = if is_in_camera_view(camera, position, planet.radius) { "SI" } else { "NO" };
+            draw_cell(framebuffer, 5, y, visible, 0xFFFFFF);
+        }
+    }
+}
+
+fn draw_cell(framebuffer: &mut Framebuffer, column: usize, y: usize, text: &str, color: u32) {
+    let right_edge = COLUMNS[column].1;
+    if right_edge == 0 {
+        framebuffer.draw_text(MARGIN, y, text, color, TEXT_SCALE);
+    } else {
+        framebuffer.draw_text_right_aligned(right_edge, y, text, color, TEXT_SCALE);
+    }
+}
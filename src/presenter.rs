@@ -0,0 +1,162 @@
+use minifb::Window;
+use std::time::{Duration, Instant};
+
+// Tiempo que se reintenta con la misma ventana antes de recrearla, y con la recreada antes de rendirse
+const RECREATE_AFTER: Duration = Duration::from_secs(1);
+
+// Lo único que el bucle principal necesita de la ventana para mostrar un cuadro. Las pruebas
+// lo implementan con ventanas falsas que fallan a propósito.
+pub trait PresentTarget {
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), String>;
+}
+
+impl PresentTarget for Window {
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), String> {
+        self.update_with_buffer(buffer, width, height).map_err(|error| error.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PresentOutcome {
+    Presented,
+    // El cuadro no se mostró; se vuelve a intentar con el siguiente. `first_failure` indica
+    // que es el primer fallo de la racha (para avisar una sola vez).
+    Retrying { error: String, first_failure: bool },
+    // La ventana se reemplazó por una nueva: el estado que dependía de ella hay que reiniciarlo
+    Recreated { error: String },
+    // Ni reintentar ni recrear la ventana funcionó
+    Failed(String),
+}
+
+// Fallos al actualizar la ventana (algunos compositores de X11/Wayland la rechazan al
+// redimensionarla bruscamente o al reiniciarse): se reintenta en cada cuadro, si sigue fallando
+// durante RECREATE_AFTER se recrea la ventana, y si la nueva tampoco funciona se abandona.
+pub struct PresentRecovery {
+    failing_since: Option<Instant>,
+    recreated: bool,
+}
+
+impl PresentRecovery {
+    pub fn new() -> Self {
+        PresentRecovery { failing_since: None, recreated: false }
+    }
+
+    // Muestra `buffer` en `target`; `recreate` construye una ventana nueva con la configuración actual
+    pub fn present<T: PresentTarget>(
+        &mut self,
+        target: &mut T,
+        recreate: impl FnOnce() -> Result<T, String>,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+        now: Instant,
+    ) -> PresentOutcome {
+        let error = match target.present(buffer, width, height) {
+            Ok(()) => {
+                self.failing_since = None;
+                self.recreated = false;
+                return PresentOutcome::Presented;
+            }
+            Err(error) => error,
+        };
+
+        let first_failure = self.failing_since.is_none();
+        let since = *self.failing_since.get_or_insert(now);
+        if now.duration_since(since) < RECREATE_AFTER {
+            return PresentOutcome::Retrying { error, first_failure };
+        }
+        if self.recreated {
+            return PresentOutcome::Failed(format!("la ventana recreada tampoco acepta cuadros: {}", error));
+        }
+
+        match recreate() {
+            Ok(new_target) => {
+                *target = new_target;
+                self.failing_since = None;
+                self.recreated = true;
+                PresentOutcome::Recreated { error }
+            }
+            Err(recreate_error) => PresentOutcome::Failed(format!(
+                "no se pudo recrear la ventana ({}) después de: {}",
+                recreate_error, error
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ventana falsa que rechaza los primeros `failures` cuadros
+    struct FlakyWindow {
+        failures: usize,
+    }
+
+    impl PresentTarget for FlakyWindow {
+        fn present(&mut self, _buffer: &[u32], _width: usize, _height: usize) -> Result<(), String> {
+            if self.failures == 0 {
+                return Ok(());
+            }
+            self.failures -= 1;
+            Err("fallo inyectado".to_string())
+        }
+    }
+
+    // Un caso de `window_recovery`: los fallos de la ventana y los de la recreada (None si no se
+    // puede crear), los momentos en ms en que se presenta y los resultados esperados
+    struct RecoveryCase {
+        failures: usize,
+        recreated_failures: Option<usize>,
+        times: &'static [u64],
+        expected: &'static [&'static str],
+    }
+
+    // Un fallo suelto se reintenta sin tocar la ventana; si siguen durante un segundo se recrea una
+    // vez, y si la nueva también falla (o no se puede crear) se abandona
+    #[test]
+    fn window_recovery() -> Result<(), String> {
+        let start = std::time::Instant::now();
+        let at = |millis: u64| start + std::time::Duration::from_millis(millis);
+        let buffer = [0u32; 4];
+        let unreachable = || -> Result<FlakyWindow, String> { Err("no debía recrearse".to_string()) };
+        let short = |outcome: &PresentOutcome| match outcome {
+            PresentOutcome::Presented => "mostrado",
+            PresentOutcome::Retrying { .. } => "reintento",
+            PresentOutcome::Recreated { .. } => "recreada",
+            PresentOutcome::Failed(_) => "abandono",
+        };
+
+        let cases = [
+            RecoveryCase { failures: 1, recreated_failures: None, times: &[0, 16, 32], expected: &["reintento", "mostrado", "mostrado"] },
+            RecoveryCase { failures: usize::MAX, recreated_failures: Some(0), times: &[0, 500, 1000, 1016], expected: &["reintento", "reintento", "recreada", "mostrado"] },
+            RecoveryCase { failures: usize::MAX, recreated_failures: Some(usize::MAX), times: &[0, 1000, 1500, 2500], expected: &["reintento", "recreada", "reintento", "abandono"] },
+            RecoveryCase { failures: usize::MAX, recreated_failures: None, times: &[0, 999, 1000], expected: &["reintento", "reintento", "abandono"] },
+        ];
+        for (case, RecoveryCase { failures, recreated_failures, times, expected }) in cases.into_iter().enumerate() {
+            let mut window = FlakyWindow { failures };
+            let mut recovery = PresentRecovery::new();
+            let mut outcomes = Vec::new();
+            for &millis in times {
+                let outcome = match recreated_failures {
+                    Some(failures) => recovery.present(&mut window, || Ok(FlakyWindow { failures }), &buffer, 2, 2, at(millis)),
+                    None => recovery.present(&mut window, unreachable, &buffer, 2, 2, at(millis)),
+                };
+                outcomes.push(short(&outcome));
+            }
+            if outcomes != expected {
+                return Err(format!("caso {}: {:?}, se esperaba {:?}", case, outcomes, expected));
+            }
+        }
+
+        // Solo el primer fallo de una racha se avisa
+        let mut window = FlakyWindow { failures: 2 };
+        let mut recovery = PresentRecovery::new();
+        let first = recovery.present(&mut window, unreachable, &buffer, 2, 2, at(0));
+        let second = recovery.present(&mut window, unreachable, &buffer, 2, 2, at(16));
+        match (first, second) {
+            (PresentOutcome::Retrying { first_failure: true, .. }, PresentOutcome::Retrying { first_failure: false, .. }) => Ok(()),
+            (first, second) => Err(format!("avisos {:?} y {:?}", first, second)),
+        }
+    }
+}
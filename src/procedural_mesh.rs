@@ -0,0 +1,81 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::PI;
+
+use crate::vertex::Vertex;
+use crate::texture::spherical_uv;
+
+// Mallas generadas en código, con el mismo formato que `Obj::get_vertex_array`
+// (tres vértices por triángulo, caras en sentido antihorario vistas desde afuera)
+pub struct ProceduralMesh;
+
+impl ProceduralMesh {
+    // Anillo plano (disco con agujero) en el plano XZ, mirando hacia +Y.
+    // Las coordenadas de textura son (ángulo / 2π, posición radial de 0.0 a 1.0).
+    pub fn ring_disc(inner: f32, outer: f32, segments: usize) -> Vec<Vertex> {
+        Self::ring_arc(inner, outer, 0.0, 2.0 * PI, segments.max(3))
+    }
+
+    // Tramo de `ring_disc` entre los ángulos `start` y `end` (radianes, de +X hacia +Z)
+    pub fn ring_arc(inner: f32, outer: f32, start: f32, end: f32, segments: usize) -> Vec<Vertex> {
+        let segments = segments.max(1);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let mut vertices = Vec::with_capacity(segments * 6);
+
+        let point = |i: usize, radius: f32, v: f32| {
+            let angle = start + (end - start) * i as f32 / segments as f32;
+            Vertex::new(
+                Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin()),
+                normal,
+                Vec2::new(angle / (2.0 * PI), v),
+            )
+        };
+
+        for i in 0..segments {
+            let inner_a = point(i, inner, 0.0);
+            let inner_b = point(i + 1, inner, 0.0);
+            let outer_a = point(i, outer, 1.0);
+            let outer_b = point(i + 1, outer, 1.0);
+
+            // El ángulo crece de +X hacia +Z; con este orden la normal de la cara apunta a +Y
+            vertices.extend([inner_a.clone(), inner_b.clone(), outer_b.clone()]);
+            vertices.extend([inner_a, outer_b, outer_a]);
+        }
+
+        vertices
+    }
+
+    // Esfera UV de radio `radius` centrada en el origen: `stacks` franjas de polo a polo y
+    // `slices` gajos alrededor del eje Y. Las coordenadas de textura son las de `spherical_uv`.
+    pub fn uv_sphere(radius: f32, stacks: usize, slices: usize) -> Vec<Vertex> {
+        let (stacks, slices) = (stacks.max(2), slices.max(3));
+        let mut vertices = Vec::with_capacity(stacks * slices * 6);
+
+        let point = |stack: usize, slice: usize| {
+            // Latitud de +π/2 (polo norte) a -π/2; longitud de +X hacia +Z
+            let latitude = 0.5 * PI - PI * stack as f32 / stacks as f32;
+            let longitude = 2.0 * PI * slice as f32 / slices as f32;
+            let normal = Vec3::new(latitude.cos() * longitude.cos(), latitude.sin(), latitude.cos() * longitude.sin());
+            Vertex::new(normal * radius, normal, spherical_uv(normal)).with_mesh_index(stack * (slices + 1) + slice)
+        };
+
+        for stack in 0..stacks {
+            for slice in 0..slices {
+                let top_a = point(stack, slice);
+                let top_b = point(stack, slice + 1);
+                let bottom_a = point(stack + 1, slice);
+                let bottom_b = point(stack + 1, slice + 1);
+
+                // Vistos desde afuera, +Z queda a la izquierda de +X: este orden es antihorario.
+                // En los polos uno de los dos triángulos es degenerado y se omite.
+                if stack > 0 {
+                    vertices.extend([top_a.clone(), top_b.clone(), bottom_b.clone()]);
+                }
+                if stack + 1 < stacks {
+                    vertices.extend([top_a, bottom_b, bottom_a]);
+                }
+            }
+        }
+
+        vertices
+    }
+}
@@ -0,0 +1,269 @@
+use fastnoise_lite::FastNoiseLite;
+use image::{DynamicImage, Rgb, RgbImage};
+use nalgebra_glm::Vec3;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::hud::Contrast;
+use crate::motion_blur::MotionBlur;
+use crate::overlay::Overlay;
+use crate::seed::SeedSource;
+use crate::sizes::SizeScale;
+use crate::uniforms::create_noise;
+use crate::scene_graph::SceneGraph;
+use crate::{Framebuffer, OverviewFrame, Planet, SceneAssets, SKYBOX_TEXTURE, initial_uniforms, place_bodies, render_overview, render_skybox, seed_start_angles, set_camera_matrices, solar_system, system_scene};
+
+// Cuadro de referencia: la vista general desde la cámara inicial en un instante fijo, sin
+// ventana ni reloj. Es la base de las comparaciones de imágenes.
+pub const REFERENCE_WIDTH: usize = 400;
+pub const REFERENCE_HEIGHT: usize = 300;
+
+// Cámara e instante de un cuadro de referencia. Se guardan junto al PNG, con el mismo nombre y
+// extensión .txt, para volver a dibujar la misma escena o poner la cámara en el mismo lugar.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReferenceView {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub time: f32,
+}
+
+// Escenas de referencia con su nombre de archivo: la vista general y el sistema interior de cerca
+pub const REFERENCE_SCENES: [(&str, ReferenceView); 2] = [
+    ("general", ReferenceView { eye: Vec3::new(50.0, 100.0, 250.0), center: Vec3::new(0.0, 0.0, 0.0), time: 240.0 }),
+    ("interior", ReferenceView { eye: Vec3::new(30.0, 45.0, 95.0), center: Vec3::new(0.0, 0.0, 0.0), time: 240.0 }),
+];
+
+impl ReferenceView {
+    pub fn to_text(self) -> String {
+        format!(
+            "eye {} {} {}\ncenter {} {} {}\ntime {}\n",
+            self.eye.x, self.eye.y, self.eye.z, self.center.x, self.center.y, self.center.z, self.time
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let field = |name: &str, count: usize| -> Result<Vec<f32>, String> {
+            let line = text.lines().find(|line| line.split_whitespace().next() == Some(name)).ok_or(format!("falta la línea {}", name))?;
+            let values: Vec<f32> = line.split_whitespace().skip(1).map(|value| value.parse::<f32>()).collect::<Result<_, _>>().map_err(|_| format!("línea {} inválida", name))?;
+            if values.len() != count {
+                return Err(format!("la línea {} espera {} números", name, count));
+            }
+            Ok(values)
+        };
+        let (eye, center, time) = (field("eye", 3)?, field("center", 3)?, field("time", 1)?);
+        Ok(ReferenceView { eye: Vec3::new(eye[0], eye[1], eye[2]), center: Vec3::new(center[0], center[1], center[2]), time: time[0] })
+    }
+
+    // Ruta de los datos de la vista junto a un PNG
+    pub fn metadata_path(png: &str) -> PathBuf {
+        Path::new(png).with_extension("txt")
+    }
+
+    pub fn save(&self, png: &str) -> Result<(), String> {
+        let path = ReferenceView::metadata_path(png);
+        std::fs::write(&path, self.to_text()).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn load(png: &str) -> Result<Self, String> {
+        let path = ReferenceView::metadata_path(png);
+        let text = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        ReferenceView::parse(&text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+}
+
+// Tolerancia entre plataformas. Dentro del mismo proceso el cuadro sale idéntico bit a bit: el
+// ruido y las partes aleatorias salen de la semilla, los pases en paralelo son por píxel (sin
+// sumas en coma flotante repartidas entre hilos) y nada recorre un HashMap. Entre sistemas las
+// funciones trigonométricas y exp/powf de la biblioteca matemática pueden diferir en el último
+// bit; eso mueve un nivel el color de algunos píxeles o cambia qué píxel del borde de una esfera
+// se rellena. Se acepta hasta CHANNEL_TOLERANCE niveles por canal en cualquier píxel, y que una
+// fracción MAX_DIFFERING_FRACTION de los píxeles (bordes) se pase de eso.
+pub const CHANNEL_TOLERANCE: u32 = 4;
+pub const MAX_DIFFERING_FRACTION: f32 = 0.005;
+
+// Lo que se carga una vez para dibujar cualquier número de escenas de referencia
+pub struct ReferenceRenderer {
+    skybox: DynamicImage,
+    planets: Vec<Planet>,
+    assets: SceneAssets,
+    scene: SceneGraph,
+    noise: Arc<FastNoiseLite>,
+}
+
+impl ReferenceRenderer {
+    pub fn new(seeds: &SeedSource, use_texture_cache: bool) -> Result<Self, String> {
+        let skybox = image::open(SKYBOX_TEXTURE).map_err(|error| format!("{}: {}", SKYBOX_TEXTURE, error))?;
+        let mut planets = solar_system();
+        seed_start_angles(&mut planets, seeds);
+        let assets = SceneAssets::load(seeds, &planets, use_texture_cache, |_, _| {});
+        let scene = system_scene(&planets, &assets.ring_systems)?;
+        let noise = Arc::new(create_noise(seeds.noise_seed("default", 0)));
+        Ok(ReferenceRenderer { skybox, planets, assets, scene, noise })
+    }
+
+    // Dibuja la vista general del sistema desde `view` en un cuadro de `width` x `height`
+    // (REFERENCE_WIDTH x REFERENCE_HEIGHT para las referencias), siempre igual para la misma vista
+    pub fn render(&mut self, view: &ReferenceView, width: usize, height: usize) -> Framebuffer {
+        let camera = Camera::new(view.eye, view.center, Vec3::new(0.0, 1.0, 0.0));
+        let mut uniforms = initial_uniforms(self.noise.clone(), width, height, camera.eye);
+        let mut framebuffer = Framebuffer::new(width, height);
+        let mut motion_blur = MotionBlur::new(self.planets.len());
+        let sizes = SizeScale::new();
+
+        set_camera_matrices(&mut uniforms, &camera, width, height, None);
+        place_bodies(&mut self.scene, &self.planets, view.time as f64, &sizes, self.assets.sphere_radius);
+        render_skybox(&mut framebuffer, &self.skybox);
+        let mut overlay = Overlay::new(width, height);
+        let frame = OverviewFrame { camera: &camera, scene: &self.scene, time: view.time as f64, effective_time_scale: 1.0, contrast: Contrast::Normal, show_orbits: true, sizes: &sizes };
+        render_overview(&mut framebuffer, &mut overlay, &mut uniforms, &self.planets, &self.assets, &mut motion_blur, frame);
+        framebuffer.apply_fxaa();
+        overlay.composite(&mut framebuffer, 1.0);
+        framebuffer
+    }
+}
+
+// Diferencia entre dos cuadros del mismo tamaño
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameDiff {
+    pub pixels: usize,
+    // Píxeles con algún canal distinto, y con alguno a más de CHANNEL_TOLERANCE
+    pub changed: usize,
+    pub beyond_tolerance: usize,
+    // Mayor diferencia de un canal, en niveles de 0 a 255, y la suma de esa diferencia en todos
+    // los píxeles
+    pub max_delta: u32,
+    pub total_delta: u64,
+}
+
+impl FrameDiff {
+    pub fn compare(expected: &[u32], actual: &[u32]) -> Self {
+        let mut diff = FrameDiff { pixels: expected.len(), changed: 0, beyond_tolerance: 0, max_delta: 0, total_delta: 0 };
+        for (&a, &b) in expected.iter().zip(actual) {
+            let delta = pixel_delta(a, b);
+            diff.total_delta += delta as u64;
+            if delta > 0 {
+                diff.changed += 1;
+            }
+            if delta > CHANNEL_TOLERANCE {
+                diff.beyond_tolerance += 1;
+            }
+            diff.max_delta = diff.max_delta.max(delta);
+        }
+        diff
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.changed == 0
+    }
+
+    pub fn within_tolerance(&self) -> bool {
+        self.beyond_tolerance as f32 <= self.pixels as f32 * MAX_DIFFERING_FRACTION
+    }
+
+    // Diferencia media por píxel, en niveles
+    pub fn mean_delta(&self) -> f32 {
+        self.total_delta as f32 / self.pixels.max(1) as f32
+    }
+
+    pub fn changed_percent(&self) -> f32 {
+        self.changed as f32 * 100.0 / self.pixels.max(1) as f32
+    }
+}
+
+// Mayor diferencia entre los canales de dos píxeles 0xRRGGBB
+pub fn pixel_delta(a: u32, b: u32) -> u32 {
+    [16, 8, 0].into_iter().map(|shift| ((a >> shift) & 0xFF).abs_diff((b >> shift) & 0xFF)).max().unwrap_or(0)
+}
+
+pub fn save_png(framebuffer: &Framebuffer, path: &str) -> Result<(), String> {
+    let image = RgbImage::from_fn(framebuffer.width as u32, framebuffer.height as u32, |x, y| {
+        let pixel = framebuffer.buffer[y as usize * framebuffer.width + x as usize];
+        Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+    });
+    image.save(path).map_err(|error| format!("{}: {}", path, error))
+}
+
+pub fn load_png(path: &str) -> Result<(usize, usize, Vec<u32>), String> {
+    let image = image::open(path).map_err(|error| format!("{}: {}", path, error))?.to_rgb8();
+    let pixels = image.pixels().map(|Rgb([r, g, b])| (*r as u32) << 16 | (*g as u32) << 8 | *b as u32).collect();
+    Ok((image.width() as usize, image.height() as usize, pixels))
+}
+
+// `--reference-frame <png>`: dibuja el cuadro de referencia sin abrir la ventana. Si el PNG no
+// existe lo guarda; si existe (por ejemplo, generado en otra plataforma) lo compara con la
+// tolerancia de arriba. Devuelve si pasó.
+pub fn run_reference_check(seeds: &SeedSource, path: &str, use_texture_cache: bool) -> Result<bool, String> {
+    let (_, view) = REFERENCE_SCENES[0];
+    let framebuffer = ReferenceRenderer::new(seeds, use_texture_cache)?.render(&view, REFERENCE_WIDTH, REFERENCE_HEIGHT);
+    if !Path::new(path).exists() {
+        save_png(&framebuffer, path)?;
+        view.save(path)?;
+        println!("Cuadro de referencia de {}x{} guardado en {}", framebuffer.width, framebuffer.height, path);
+        return Ok(true);
+    }
+
+    let (width, height, expected) = load_png(path)?;
+    if (width, height) != (framebuffer.width, framebuffer.height) {
+        return Err(format!("{} mide {}x{}; el cuadro de referencia, {}x{}", path, width, height, framebuffer.width, framebuffer.height));
+    }
+    let diff = FrameDiff::compare(&expected, &framebuffer.buffer);
+    println!(
+        "píxeles distintos: {} de {}  más de {} niveles: {}  mayor diferencia: {}",
+        diff.changed, diff.pixels, CHANNEL_TOLERANCE, diff.beyond_tolerance, diff.max_delta
+    );
+    if diff.is_identical() {
+        println!("PASS: idéntico bit a bit");
+    } else if diff.within_tolerance() {
+        println!("PASS: dentro de la tolerancia entre plataformas");
+    } else {
+        println!("FAIL: más del {:.1}% de los píxeles fuera de la tolerancia", MAX_DIFFERING_FRACTION * 100.0);
+    }
+    Ok(diff.within_tolerance())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::DEFAULT_SEED;
+
+    // El cuadro completo (fondo, órbitas, cuerpos, anillos, nombres y FXAA) dos veces con los
+    // mismos recursos, en chico: tiene que salir idéntico bit a bit. Corre con
+    // `cargo test -- --ignored`.
+    #[test]
+    #[ignore = "genera los mapas de relieve de la escena (casi un minuto sin --release)"]
+    fn reference_frame() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let mut renderer = ReferenceRenderer::new(seeds, false)?;
+        let view = &REFERENCE_SCENES[0].1;
+        let first = renderer.render(view, REFERENCE_WIDTH / 4, REFERENCE_HEIGHT / 4);
+        let second = renderer.render(view, REFERENCE_WIDTH / 4, REFERENCE_HEIGHT / 4);
+        let diff = FrameDiff::compare(&first.buffer, &second.buffer);
+        if !diff.is_identical() {
+            return Err(format!("el segundo cuadro difiere en {} píxeles (hasta {} niveles)", diff.changed, diff.max_delta));
+        }
+        let background = first.buffer[0];
+        if first.buffer.iter().filter(|&&pixel| pixel != background).count() < first.buffer.len() / 10 {
+            return Err("el cuadro de referencia está casi vacío".to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reference_view_text() -> Result<(), String> {
+        for (name, view) in REFERENCE_SCENES {
+            if ReferenceView::parse(&view.to_text()) != Ok(view) {
+                return Err(format!("la vista {} no vuelve igual de su texto:\n{}", name, view.to_text()));
+            }
+        }
+        for text in ["eye 1 2 3\ncenter 0 0 0\n", "eye 1 2\ncenter 0 0 0\ntime 5\n", "eye 1 2 x\ncenter 0 0 0\ntime 5\n"] {
+            if ReferenceView::parse(text).is_ok() {
+                return Err(format!("se aceptó {:?}", text));
+            }
+        }
+        if ReferenceView::metadata_path("base/general.png") != Path::new("base/general.txt") {
+            return Err(format!("datos de la vista en {:?}", ReferenceView::metadata_path("base/general.png")));
+        }
+        Ok(())
+    }
+}
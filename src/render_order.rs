@@ -0,0 +1,21 @@
+use nalgebra_glm::Vec3;
+
+use crate::camera::Camera;
+
+// Orden del algoritmo del pintor para lo transparente: lo que no escribe en el z-buffer
+// (los anillos) se mezcla con lo que ya hay en el frame, así que se dibuja después de los
+// cuerpos opacos y de atrás hacia adelante.
+pub trait RenderOrder {
+    fn depth_from_camera(&self, camera: &Camera) -> f32;
+}
+
+// Profundidad de `position` a lo largo de la dirección en que mira la cámara
+pub fn view_depth(camera: &Camera, position: Vec3) -> f32 {
+    let forward = (camera.center - camera.eye).normalize();
+    (position - camera.eye).dot(&forward)
+}
+
+// Ordena de la más lejana a la más cercana; a igual profundidad conserva el orden original
+pub fn sort_back_to_front<T: RenderOrder>(items: &mut [T], camera: &Camera) {
+    items.sort_by(|a, b| b.depth_from_camera(camera).total_cmp(&a.depth_from_camera(camera)));
+}
@@ -0,0 +1,224 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::long_exposure::CAPTURE_DIR;
+
+// Contadores de una llamada a `render`
+#[derive(Clone, Debug)]
+pub struct DrawStats {
+    pub body: &'static str,
+    pub vertices: usize,
+    pub triangles: usize,
+    // Triángulos que dieron algún fragmento (los demás quedaron detrás de la cámara o fuera de
+    // la pantalla). Contarlos cuesta una comprobación por triángulo: solo con `--profile-triangles`.
+    pub rasterized_triangles: Option<usize>,
+    pub fragments: usize,
+    pub depth_passed: usize,
+    // Fracción de los vértices que salieron de la caché de vértices en vez del vertex shader
+    pub vertex_cache_hit_rate: f32,
+    // Desde el comienzo del cuadro
+    pub start: Duration,
+    pub duration: Duration,
+    // Parte de `duration` en la prueba de profundidad y los fragment shaders
+    pub shading: Duration,
+}
+
+// Tramo del cuadro (fondo, cuerpos, post-proceso...) para la traza
+#[derive(Clone, Debug)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+// Estadísticas de un cuadro: cada dibujo de `render` con el cuerpo al que pertenece y los
+// tramos del cuadro. Son contadores y un `Instant` por dibujo, así que quedan siempre activas.
+#[derive(Clone, Debug)]
+pub struct RenderStats {
+    pub frame_start: Instant,
+    pub draws: Vec<DrawStats>,
+    pub passes: Vec<PassTiming>,
+    // Cuerpo al que se atribuyen los próximos dibujos
+    pub current_body: &'static str,
+    pub count_rasterized: bool,
+}
+
+impl RenderStats {
+    pub fn new() -> Self {
+        RenderStats {
+            frame_start: Instant::now(),
+            draws: Vec::new(),
+            passes: Vec::new(),
+            current_body: "",
+            count_rasterized: false,
+        }
+    }
+
+    // Empieza un cuadro nuevo; `count_rasterized` se conserva
+    pub fn reset(&mut self) {
+        self.frame_start = Instant::now();
+        self.draws.clear();
+        self.passes.clear();
+        self.current_body = "";
+    }
+
+    // Tramo que empezó en `start` y termina ahora
+    pub fn record_pass(&mut self, name: &'static str, start: Instant) {
+        self.passes.push(PassTiming {
+            name,
+            start: start.saturating_duration_since(self.frame_start),
+            duration: start.elapsed(),
+        });
+    }
+
+    // Los `count` cuerpos que más tiempo de sombreado llevaron, sumando sus dibujos
+    pub fn top_by_shading(&self, count: usize) -> Vec<(&'static str, Duration)> {
+        let mut totals: Vec<(&'static str, Duration)> = Vec::new();
+        for draw in &self.draws {
+            match totals.iter_mut().find(|(body, _)| *body == draw.body) {
+                Some((_, total)) => *total += draw.shading,
+                None => totals.push((draw.body, draw.shading)),
+            }
+        }
+        totals.sort_by_key(|total| Reverse(total.1));
+        totals.truncate(count);
+        totals
+    }
+
+    // Fracción de los vértices del cuadro que salieron de la caché de vértices: la de cada dibujo
+    // pesada por sus vértices. `None` si no hubo dibujos con vértices.
+    pub fn vertex_cache_hit_rate(&self) -> Option<f32> {
+        let vertices: usize = self.draws.iter().map(|draw| draw.vertices).sum();
+        let hits: f32 = self.draws.iter().map(|draw| draw.vertex_cache_hit_rate * draw.vertices as f32).sum();
+        (vertices > 0).then(|| hits / vertices as f32)
+    }
+
+    // Traza del cuadro en el formato de chrome://tracing (y de Perfetto): un evento por tramo y
+    // por dibujo, con los contadores del dibujo en `args`
+    pub fn to_trace_json(&self) -> String {
+        let micros = |duration: Duration| duration.as_secs_f64() * 1e6;
+        let mut events = Vec::new();
+        for pass in &self.passes {
+            events.push(format!(
+                "{{\"name\":{},\"cat\":\"pass\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":{:.1},\"dur\":{:.1}}}",
+                json_string(pass.name),
+                micros(pass.start),
+                micros(pass.duration),
+            ));
+        }
+        for draw in &self.draws {
+            let mut args = format!(
+                "\"vertices\":{},\"triangles\":{},\"fragments\":{},\"depth_passed\":{},\"vertex_cache_hit_rate\":{:.3},\"shading_us\":{:.1}",
+                draw.vertices,
+                draw.triangles,
+                draw.fragments,
+                draw.depth_passed,
+                draw.vertex_cache_hit_rate,
+                micros(draw.shading),
+            );
+            if let Some(rasterized) = draw.rasterized_triangles {
+                let _ = write!(args, ",\"rasterized_triangles\":{}", rasterized);
+            }
+            events.push(format!(
+                "{{\"name\":{},\"cat\":\"draw\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":{:.1},\"dur\":{:.1},\"args\":{{{}}}}}",
+                json_string(draw.body),
+                micros(draw.start),
+                micros(draw.duration),
+                args,
+            ));
+        }
+        format!("{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}\n", events.join(",\n"))
+    }
+
+    // Guarda la traza en la carpeta de capturas y devuelve la ruta
+    pub fn export(&self, name: &str) -> std::io::Result<String> {
+        std::fs::create_dir_all(CAPTURE_DIR)?;
+        let path = Path::new(CAPTURE_DIR).join(name).to_string_lossy().into_owned();
+        std::fs::write(&path, self.to_trace_json())?;
+        Ok(path)
+    }
+}
+
+// Cadena JSON entre comillas (los nombres de los cuerpos pueden llevar tildes, que JSON admite tal cual)
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    
+    use crate::{Obj, Framebuffer, SPHERE_MODEL, render};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    // Cada `render` deja sus contadores con el cuerpo que se estaba dibujando, coherentes con los
+    // del framebuffer, y la traza los incluye
+    #[test]
+    fn per_draw_stats_and_trace() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let vertices = obj.get_vertex_array();
+        let uniforms = test_uniforms(seeds);
+        let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+
+        framebuffer.render_stats.current_body = "Primero";
+        render(&mut framebuffer, &uniforms, &vertices, 1);
+        framebuffer.render_stats.count_rasterized = true;
+        framebuffer.render_stats.current_body = "Segundo \"B\"";
+        render(&mut framebuffer, &uniforms, &vertices, 2);
+
+        let stats = &framebuffer.render_stats;
+        let [first, second] = stats.draws.as_slice() else {
+            return Err(format!("{} dibujos, se esperaban 2", stats.draws.len()));
+        };
+        if first.vertices != vertices.len() || first.triangles != vertices.len() / 3 || first.rasterized_triangles.is_some() {
+            return Err(format!("primer dibujo: {:?}", first));
+        }
+        match second.rasterized_triangles {
+            Some(rasterized) if rasterized > 0 && rasterized < second.triangles => {}
+            other => return Err(format!("triángulos rasterizados {:?} de {}", other, second.triangles)),
+        }
+        // El segundo se dibuja en el mismo lugar: ninguno de sus fragmentos pasa la profundidad
+        // salvo empates, así que pasan menos que en el primero
+        let passed = first.depth_passed + second.depth_passed;
+        if passed != framebuffer.fragment_stats.shaded || first.fragments < first.depth_passed || second.depth_passed >= first.depth_passed {
+            return Err(format!("fragmentos {} / {} y {} / {}", first.depth_passed, first.fragments, second.depth_passed, second.fragments));
+        }
+        if stats.top_by_shading(5).len() != 2 || stats.top_by_shading(1).len() != 1 {
+            return Err("la lista por tiempo de sombreado no tiene un elemento por cuerpo".to_string());
+        }
+
+        // La esfera comparte vértices entre triángulos: la caché acierta en los dos dibujos
+        match stats.vertex_cache_hit_rate() {
+            Some(rate) if rate > 0.5 && rate < 1.0 && first.vertex_cache_hit_rate == second.vertex_cache_hit_rate => {}
+            other => return Err(format!("aciertos de la caché de vértices {:?}", other)),
+        }
+
+        let trace = stats.to_trace_json();
+        let balanced = trace.matches('{').count() == trace.matches('}').count() && trace.matches('[').count() == trace.matches(']').count();
+        if !balanced || !trace.contains("\"traceEvents\"") || !trace.contains("\"Segundo \\\"B\\\"\"") || !trace.contains("\"rasterized_triangles\"") || !trace.contains("\"vertex_cache_hit_rate\"") {
+            return Err(format!("traza inesperada: {}", trace));
+        }
+
+        framebuffer.clear();
+        if !framebuffer.render_stats.draws.is_empty() || !framebuffer.render_stats.count_rasterized {
+            return Err("clear no empezó un cuadro nuevo conservando la opción de conteo".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Framebuffer;
+use crate::framebuffer::BarColors;
+use crate::hud::Contrast;
+
+// Cuánto se guarda hacia atrás
+pub const HISTORY: Duration = Duration::from_secs(5);
+// Memoria para el historial si no se pasa --replay-budget, en MB
+const DEFAULT_BUDGET_MB: usize = 16;
+// Los cuadros se guardan a la mitad de ancho y de alto (un cuarto de los píxeles)
+const DOWNSAMPLE: usize = 2;
+// Con el presupuesto excedido se guarda uno de cada tantos cuadros, hasta este máximo
+const MAX_CAPTURE_INTERVAL: usize = 8;
+
+const REPLAY_COLOR: u32 = 0xE53935;
+const BORDER_WIDTH: usize = 4;
+const BAR_MARGIN: usize = 20;
+const BAR_HEIGHT: usize = 8;
+
+// Promedia cada bloque de DOWNSAMPLE x DOWNSAMPLE píxeles; devuelve ancho, alto y píxeles
+pub fn downsample(framebuffer: &Framebuffer) -> (usize, usize, Vec<u32>) {
+    let (width, height) = (framebuffer.width / DOWNSAMPLE, framebuffer.height / DOWNSAMPLE);
+    let count = (DOWNSAMPLE * DOWNSAMPLE) as u32;
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 3];
+            for sy in 0..DOWNSAMPLE {
+                let row = (y * DOWNSAMPLE + sy) * framebuffer.width;
+                for sx in 0..DOWNSAMPLE {
+                    let pixel = framebuffer.buffer[row + x * DOWNSAMPLE + sx];
+                    sum[0] += (pixel >> 16) & 0xFF;
+                    sum[1] += (pixel >> 8) & 0xFF;
+                    sum[2] += pixel & 0xFF;
+                }
+            }
+            pixels.push(((sum[0] / count) << 16) | ((sum[1] / count) << 8) | (sum[2] / count));
+        }
+    }
+    (width, height, pixels)
+}
+
+// Un cuadro del historial comprimido por tramos: cada tramo es un color y cuántas veces se repite
+pub struct ReplayFrame {
+    pub width: usize,
+    pub height: usize,
+    pub captured: Instant,
+    runs: Vec<(u32, u32)>,
+}
+
+impl ReplayFrame {
+    pub fn compress(width: usize, height: usize, pixels: &[u32], captured: Instant) -> Self {
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for &pixel in pixels {
+            match runs.last_mut() {
+                Some((color, count)) if *color == pixel => *count += 1,
+                _ => runs.push((pixel, 1)),
+            }
+        }
+        ReplayFrame { width, height, captured, runs }
+    }
+
+    pub fn decompress(&self) -> Vec<u32> {
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for &(color, count) in &self.runs {
+            pixels.extend(std::iter::repeat_n(color, count as usize));
+        }
+        pixels
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.runs.len() * std::mem::size_of::<(u32, u32)>()
+    }
+}
+
+// Historial de los últimos HISTORY. Si ocupa más que `budget` bytes se descartan los más viejos
+// y se baja la frecuencia de captura; con holgura de sobra se vuelve a subir.
+pub struct History {
+    frames: VecDeque<Arc<ReplayFrame>>,
+    bytes: usize,
+    budget: usize,
+    capture_interval: Arc<AtomicUsize>,
+}
+
+impl History {
+    pub fn new(budget: usize) -> Self {
+        History { frames: VecDeque::new(), bytes: 0, budget, capture_interval: Arc::new(AtomicUsize::new(1)) }
+    }
+
+    pub fn push(&mut self, frame: ReplayFrame) {
+        let newest = frame.captured;
+        self.bytes += frame.bytes();
+        self.frames.push_back(Arc::new(frame));
+        while self.frames.front().is_some_and(|frame| newest.duration_since(frame.captured) > HISTORY) {
+            self.pop_oldest();
+        }
+
+        let interval = self.capture_interval();
+        if self.bytes > self.budget {
+            self.capture_interval.store((interval + 1).min(MAX_CAPTURE_INTERVAL), Ordering::Relaxed);
+            while self.bytes > self.budget && self.frames.len() > 1 {
+                self.pop_oldest();
+            }
+        } else if interval > 1 && self.bytes * interval / (interval - 1) < self.budget * 3 / 4 {
+            // Capturando un cuadro más de cada `interval` seguiría entrando con margen
+            self.capture_interval.store(interval - 1, Ordering::Relaxed);
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some(frame) = self.frames.pop_front() {
+            self.bytes -= frame.bytes();
+        }
+    }
+
+    // Se guarda uno de cada tantos cuadros
+    pub fn capture_interval(&self) -> usize {
+        self.capture_interval.load(Ordering::Relaxed)
+    }
+
+    pub fn frames(&self) -> Vec<Arc<ReplayFrame>> {
+        self.frames.iter().cloned().collect()
+    }
+}
+
+// Captura del historial para la repetición instantánea. El hilo de dibujo solo reduce el cuadro;
+// un hilo aparte lo comprime y lo guarda. `--replay-budget <MB>` fija la memoria del historial.
+pub struct ReplayRecorder {
+    sender: Sender<(usize, usize, Vec<u32>, Instant)>,
+    history: Arc<Mutex<History>>,
+    capture_interval: Arc<AtomicUsize>,
+    frame: usize,
+}
+
+impl ReplayRecorder {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let budget = match args.iter().position(|arg| arg == "--replay-budget") {
+            None => DEFAULT_BUDGET_MB,
+            Some(position) => args
+                .get(position + 1)
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|&megabytes| megabytes > 0)
+                .ok_or("--replay-budget espera los MB para el historial de la repetición, como 16")?,
+        };
+        Ok(ReplayRecorder::new(budget * 1024 * 1024))
+    }
+
+    pub fn new(budget: usize) -> Self {
+        let history = History::new(budget);
+        let capture_interval = history.capture_interval.clone();
+        let history = Arc::new(Mutex::new(history));
+        let (sender, receiver) = mpsc::channel::<(usize, usize, Vec<u32>, Instant)>();
+        let worker_history = history.clone();
+        thread::spawn(move || {
+            for (width, height, pixels, captured) in receiver {
+                let frame = ReplayFrame::compress(width, height, &pixels, captured);
+                if let Ok(mut history) = worker_history.lock() {
+                    history.push(frame);
+                }
+            }
+        });
+        ReplayRecorder { sender, history, capture_interval, frame: 0 }
+    }
+
+    // Guarda el cuadro si le toca según la frecuencia de captura actual
+    pub fn capture(&mut self, framebuffer: &Framebuffer) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(self.capture_interval.load(Ordering::Relaxed).max(1)) {
+            return;
+        }
+        let (width, height, pixels) = downsample(framebuffer);
+        let _ = self.sender.send((width, height, pixels, Instant::now()));
+    }
+
+    pub fn frames(&self) -> Vec<Arc<ReplayFrame>> {
+        self.history.lock().map(|history| history.frames()).unwrap_or_default()
+    }
+}
+
+// Repetición (Retroceso): recorre el historial cuadro por cuadro mientras la simulación espera
+pub struct ReplayScrubber {
+    frames: Vec<Arc<ReplayFrame>>,
+    index: usize,
+    pixels: Vec<u32>,
+}
+
+impl ReplayScrubber {
+    // Empieza en el último cuadro; `None` si todavía no hay historial
+    pub fn open(frames: Vec<Arc<ReplayFrame>>) -> Option<Self> {
+        let index = frames.len().checked_sub(1)?;
+        let pixels = frames[index].decompress();
+        Some(ReplayScrubber { frames, index, pixels })
+    }
+
+    pub fn step(&mut self, delta: isize) {
+        let index = self.index.saturating_add_signed(delta).min(self.frames.len() - 1);
+        if index != self.index {
+            self.index = index;
+            self.pixels = self.frames[index].decompress();
+        }
+    }
+
+    // Segundos desde el cuadro mostrado hasta el último del historial
+    pub fn seconds_back(&self) -> f32 {
+        let newest = self.frames[self.frames.len() - 1].captured;
+        newest.duration_since(self.frames[self.index].captured).as_secs_f32()
+    }
+
+    // El cuadro guardado estirado a la pantalla, con un marco y un cartel que dejan claro que no
+    // es la simulación en vivo, y la barra de tiempo al pie
+    pub fn render(&self, framebuffer: &mut Framebuffer, contrast: Contrast) {
+        let frame = &self.frames[self.index];
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        for y in 0..height {
+            let source_row = (y * frame.height / height) * frame.width;
+            for x in 0..width {
+                framebuffer.buffer[y * width + x] = self.pixels[source_row + x * frame.width / width];
+            }
+        }
+
+        let color = contrast.text_color(REPLAY_COLOR);
+        framebuffer.draw_filled_rect(0, 0, width, BORDER_WIDTH, color);
+        framebuffer.draw_filled_rect(0, height.saturating_sub(BORDER_WIDTH), width, BORDER_WIDTH, color);
+        framebuffer.draw_filled_rect(0, 0, BORDER_WIDTH, height, color);
+        framebuffer.draw_filled_rect(width.saturating_sub(BORDER_WIDTH), 0, BORDER_WIDTH, height, color);
+
+        let scale = contrast.text_scale(2);
+        let line_height = Framebuffer::measure_text("", scale).1;
+        framebuffer.draw_text_centered(width / 2, 14, "REPETICION", color, contrast.text_scale(3));
+
+        let bar_y = height.saturating_sub(BAR_MARGIN + BAR_HEIGHT);
+        let progress = if self.frames.len() > 1 { self.index as f32 / (self.frames.len() - 1) as f32 } else { 1.0 };
+        framebuffer.render_progress_bar(BAR_MARGIN, bar_y, width.saturating_sub(2 * BAR_MARGIN), BAR_HEIGHT, progress, BarColors { fill: color, empty: 0x404040 });
+        let text_y = bar_y.saturating_sub(line_height + 6);
+        let white = contrast.text_color(0xFFFFFF);
+        framebuffer.draw_text(BAR_MARGIN, text_y, &format!("-{:.1} S  {}/{}", self.seconds_back(), self.index + 1, self.frames.len()), white, scale);
+        framebuffer.draw_text_right_aligned(width.saturating_sub(BAR_MARGIN), text_y, "IZQ/DER: CUADRO  ESC: EN VIVO", white, scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+
+    // El historial reduce y comprime sin perder nada, se queda con los últimos segundos, baja la
+    // frecuencia de captura al pasarse de memoria y la recupera con holgura
+    #[test]
+    fn replay_history() -> Result<(), String> {
+        let mut framebuffer = Framebuffer::new(4, 2);
+        framebuffer.buffer = vec![0x000000, 0x040404, 0x102030, 0x102030, 0x080808, 0x0C0C0C, 0x102030, 0x504030];
+        let (width, height, pixels) = downsample(&framebuffer);
+        if (width, height, pixels.clone()) != (2, 1, vec![0x060606, 0x202830]) {
+            return Err(format!("la reducción dio {}x{} {:06X?}", width, height, pixels));
+        }
+
+        let start = Instant::now();
+        let noisy = |seed: u32| (0..1000u32).map(|i| i.wrapping_mul(2654435761).wrapping_add(seed) & 0xFFFFFF).collect::<Vec<u32>>();
+        let frame = ReplayFrame::compress(40, 25, &noisy(7), start);
+        if frame.decompress() != noisy(7) {
+            return Err("comprimir y descomprimir cambió el cuadro".to_string());
+        }
+        let flat = ReplayFrame::compress(40, 25, &[0x101010; 1000], start);
+        if flat.decompress() != vec![0x101010; 1000] || flat.bytes() * 100 > frame.bytes() {
+            return Err(format!("un cuadro liso ocupa {} bytes y uno con ruido {}", flat.bytes(), frame.bytes()));
+        }
+
+        let frame_bytes = frame.bytes();
+        let mut history = History::new(frame_bytes * 3);
+        for index in 0..6 {
+            history.push(ReplayFrame::compress(40, 25, &noisy(index), start + Duration::from_millis(10 * index as u64)));
+        }
+        if history.bytes > frame_bytes * 3 || history.frames().len() != 3 || history.capture_interval() < 2 {
+            return Err(format!("pasado de memoria quedan {} cuadros en {} bytes, capturando 1 de cada {}", history.frames().len(), history.bytes, history.capture_interval()));
+        }
+        let late = start + Duration::from_secs(6);
+        for index in 0..8 {
+            history.push(ReplayFrame::compress(40, 25, &[0x202020; 1000], late + Duration::from_millis(10 * index)));
+        }
+        if history.frames().len() != 8 || history.capture_interval() != 1 {
+            return Err(format!("con holgura quedan {} cuadros, capturando 1 de cada {}", history.frames().len(), history.capture_interval()));
+        }
+
+        if ReplayScrubber::open(Vec::new()).is_some() {
+            return Err("se abrió la repetición sin historial".to_string());
+        }
+        let mut scrubber = ReplayScrubber::open(history.frames()).ok_or("no se abrió la repetición")?;
+        scrubber.step(3);
+        let at_end = scrubber.index;
+        scrubber.step(-100);
+        if at_end != 7 || scrubber.index != 0 || (scrubber.seconds_back() - 0.07).abs() > 1e-3 {
+            return Err(format!("la repetición quedó en {} y {} ({} s atrás)", at_end, scrubber.index, scrubber.seconds_back()));
+        }
+        let mut screen = Framebuffer::new(800, 600);
+        scrubber.render(&mut screen, Contrast::Normal);
+        if screen.buffer[300 * 800 + 400] != 0x202020 || screen.buffer[0] == 0x202020 {
+            return Err(format!("en el centro de la repetición se ve {:06X} y en el borde {:06X}", screen.buffer[300 * 800 + 400], screen.buffer[0]));
+        }
+
+        // La compresión va en otro hilo: los cuadros llegan al historial un poco después
+        let mut recorder = ReplayRecorder::new(1024 * 1024);
+        for _ in 0..3 {
+            recorder.capture(&framebuffer);
+        }
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while recorder.frames().len() < 3 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        if recorder.frames().len() != 3 {
+            return Err(format!("de 3 cuadros capturados llegaron {}", recorder.frames().len()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use nalgebra_glm::{Vec3, Quat, quat_angle_axis, quat_to_mat4};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::Framebuffer;
+use crate::render_rings;
+use crate::procedural_mesh::ProceduralMesh;
+use crate::uniforms::Uniforms;
+use crate::vertex::Vertex;
+
+// Índice de `ring_shader` en `select_shader`
+const RING_SHADER_INDEX: usize = 8;
+
+// Por debajo de esta densidad una banda no se malla: queda como un hueco real,
+// visible incluso con los anillos de canto
+const MIN_MESH_DENSITY: f32 = 0.05;
+
+// Tramo del perfil radial de densidad, en radios del planeta
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RingBand {
+    pub inner: f32,
+    pub outer: f32,
+    // Densidad óptica aproximada de 0.0 (vacío) a 1.0 (opaco); escala el brillo y la opacidad
+    pub density: f32,
+    pub color: u32,
+}
+
+// Tramo del perfil que contiene `radius` (en radios del planeta), si hay alguno
+pub fn sample_profile(profile: &[RingBand], radius: f32) -> Option<&RingBand> {
+    profile.iter().find(|band| radius >= band.inner && radius < band.outer)
+}
+
+// Arco de anillo incompleto (como los del anillo Adams de Neptuno): solo hay material entre
+// `start_angle` y `end_angle` (radianes en el plano del anillo, de +X hacia +Z). El radio está
+// en radios del planeta, y su aspecto sale de la banda del perfil que lo contiene, con la
+// opacidad multiplicada por `opacity`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RingArc {
+    pub start_angle: f32,
+    pub end_angle: f32,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub opacity: f32,
+}
+
+impl RingArc {
+    fn covers_radius(&self, radius: f32) -> bool {
+        radius >= self.inner_radius && radius < self.outer_radius
+    }
+
+    fn covers_angle(&self, angle: f32) -> bool {
+        (angle - self.start_angle).rem_euclid(2.0 * PI) <= self.span()
+    }
+
+    fn span(&self) -> f32 {
+        (self.end_angle - self.start_angle).rem_euclid(2.0 * PI)
+    }
+}
+
+// Opacidad del arco que contiene el punto (`radius`, `angle`); 1.0 fuera de todo arco
+pub fn arc_opacity(arcs: &[RingArc], radius: f32, angle: f32) -> f32 {
+    arcs.iter()
+        .find(|arc| arc.covers_radius(radius) && arc.covers_angle(angle))
+        .map_or(1.0, |arc| arc.opacity)
+}
+
+// Sistema de anillos plano alrededor de un planeta. El aspecto sale de un perfil radial
+// de densidad (los datos de cada planeta); el shader es el mismo para todos.
+pub struct RingSystem {
+    pub profile: Arc<Vec<RingBand>>,
+    // Tramos del perfil que no son círculos completos; las bandas que cubren solo se mallan ahí
+    pub arcs: Arc<Vec<RingArc>>,
+    pub tilt_quat: Quat,
+}
+
+impl RingSystem {
+    // Anillos de Saturno: C, B, división de Cassini, A con la división de Encke,
+    // inclinados como el eje del planeta (26.7°)
+    pub fn saturn() -> Self {
+        RingSystem {
+            profile: Arc::new(vec![
+                RingBand { inner: 1.24, outer: 1.53, density: 0.35, color: 0x8C8273 },  // Anillo C
+                RingBand { inner: 1.53, outer: 1.95, density: 1.0, color: 0xD8CBA8 },   // Anillo B
+                RingBand { inner: 1.95, outer: 2.03, density: 0.02, color: 0x3A3530 },  // División de Cassini
+                RingBand { inner: 2.03, outer: 2.21, density: 0.7, color: 0xBFB39A },   // Anillo A
+                RingBand { inner: 2.21, outer: 2.215, density: 0.0, color: 0x000000 },  // División de Encke
+                RingBand { inner: 2.215, outer: 2.27, density: 0.6, color: 0xBFB39A },  // Anillo A (exterior)
+            ]),
+            arcs: Arc::new(Vec::new()),
+            tilt_quat: quat_angle_axis(26.7f32.to_radians(), &Vec3::new(1.0, 0.0, 0.0)),
+        }
+    }
+
+    // Anillos de Urano: angostos, oscuros y muy separados (del 6 al ε, algo ensanchados para
+    // que se vean), con el eje casi tumbado (97.8°)
+    pub fn uranus() -> Self {
+        let narrow = |radius: f32, width: f32, density: f32| RingBand { inner: radius, outer: radius + width, density, color: 0x4A4A52 };
+        RingSystem {
+            profile: Arc::new(vec![
+                narrow(1.64, 0.006, 0.5),   // 6, 5, 4
+                narrow(1.75, 0.008, 0.6),   // α
+                narrow(1.79, 0.008, 0.6),   // β
+                narrow(1.85, 0.005, 0.4),   // η
+                narrow(1.86, 0.005, 0.5),   // γ
+                narrow(1.90, 0.006, 0.6),   // δ
+                narrow(2.00, 0.02, 0.9),    // ε
+            ]),
+            arcs: Arc::new(Vec::new()),
+            tilt_quat: quat_angle_axis(97.8f32.to_radians(), &Vec3::new(1.0, 0.0, 0.0)),
+        }
+    }
+
+    // Anillos de Neptuno: Galle, Le Verrier, la sábana tenue de Lassell, Arago y Adams, con el
+    // eje inclinado 28.3°. El anillo Adams solo se ve en sus cuatro arcos, que ocupan unos 40°
+    // de su circunferencia.
+    pub fn neptune() -> Self {
+        let narrow = |radius: f32, width: f32, density: f32| RingBand { inner: radius, outer: radius + width, density, color: 0x6A5E58 };
+        let adams = narrow(2.54, 0.012, 0.8);
+        let arc = |start_degrees: f32, end_degrees: f32, opacity: f32| RingArc {
+            start_angle: start_degrees.to_radians(),
+            end_angle: end_degrees.to_radians(),
+            inner_radius: adams.inner,
+            outer_radius: adams.outer,
+            opacity,
+        };
+        RingSystem {
+            profile: Arc::new(vec![
+                narrow(1.69, 0.06, 0.15),                                                  // Galle
+                narrow(2.148, 0.006, 0.5),                                                 // Le Verrier
+                RingBand { inner: 2.154, outer: 2.31, density: 0.06, color: 0x4A4440 },    // Lassell
+                narrow(2.31, 0.006, 0.3),                                                  // Arago
+                adams,                                                                     // Adams
+            ]),
+            arcs: Arc::new(vec![
+                arc(0.0, 10.0, 1.0),    // Fraternité
+                arc(13.0, 19.0, 0.85),  // Egalité
+                arc(24.0, 28.0, 0.7),   // Liberté
+                arc(33.0, 35.0, 0.45),  // Courage
+            ]),
+            tilt_quat: quat_angle_axis(28.3f32.to_radians(), &Vec3::new(1.0, 0.0, 0.0)),
+        }
+    }
+
+    // Intervalos con material en toda la circunferencia: tramos contiguos del perfil con
+    // densidad suficiente, sin las bandas que solo existen dentro de arcos
+    pub fn bands(&self) -> Vec<(f32, f32)> {
+        let mut profile: Vec<RingBand> = self
+            .profile
+            .iter()
+            .filter(|band| !self.arcs.iter().any(|arc| arc.inner_radius <= band.inner && band.outer <= arc.outer_radius))
+            .copied()
+            .collect();
+        profile.sort_by(|a, b| a.inner.total_cmp(&b.inner));
+
+        let mut bands: Vec<(f32, f32)> = Vec::new();
+        for band in profile.iter().filter(|band| band.density >= MIN_MESH_DENSITY) {
+            match bands.last_mut() {
+                Some(last) if (band.inner - last.1).abs() < 1e-6 => last.1 = band.outer,
+                _ => bands.push((band.inner, band.outer)),
+            }
+        }
+
+        bands
+    }
+
+    // Malla del sistema completo: un disco por banda, así las divisiones quedan vacías, y un
+    // tramo por arco con tantos segmentos como le tocan de `segments` por vuelta
+    pub fn mesh(&self, segments: usize) -> Vec<Vertex> {
+        let discs = self
+            .bands()
+            .into_iter()
+            .flat_map(|(inner, outer)| ProceduralMesh::ring_disc(inner, outer, segments));
+        let arcs = self.arcs.iter().flat_map(|arc| {
+            let arc_segments = (segments as f32 * arc.span() / (2.0 * PI)).ceil() as usize;
+            ProceduralMesh::ring_arc(arc.inner_radius, arc.outer_radius, arc.start_angle, arc.start_angle + arc.span(), arc_segments)
+        });
+        discs.chain(arcs).collect()
+    }
+
+    // Dibuja la malla con la inclinación aplicada antes de la matriz de modelo del planeta
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex]) {
+        let mut ring_uniforms = uniforms.clone();
+        ring_uniforms.model_matrix = uniforms.model_matrix * quat_to_mat4(&self.tilt_quat);
+        ring_uniforms.ring_profile = Some(self.profile.clone());
+        ring_uniforms.ring_arcs = Some(self.arcs.clone());
+        render_rings(framebuffer, &ring_uniforms, vertex_array, RING_SHADER_INDEX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    // La división de Cassini debe ser un hueco en la malla (no solo poco opaca), para que se vea
+    // también con los anillos de canto
+    #[test]
+    fn cassini_gap() -> Result<(), String> {
+        let rings = RingSystem::saturn();
+        let cassini = (1.96, 2.02);
+        match rings.bands().iter().find(|(inner, outer)| *inner < cassini.1 && *outer > cassini.0) {
+            Some(band) => Err(format!("la banda {:?} cubre la division", band)),
+            None => Ok(()),
+        }
+    }
+
+    // El anillo Adams de Neptuno no se malla como disco: sus vértices quedan dentro de los cuatro
+    // arcos, cada arco tiene malla, y su opacidad solo rige dentro de él
+    #[test]
+    fn neptune_arcs() -> Result<(), String> {
+        let rings = RingSystem::neptune();
+        if rings.arcs.len() != 4 {
+            return Err(format!("{} arcos, se esperaban 4", rings.arcs.len()));
+        }
+        let adams = (rings.arcs[0].inner_radius, rings.arcs[0].outer_radius);
+        if let Some(band) = rings.bands().iter().find(|(inner, outer)| *inner < adams.1 && *outer > adams.0) {
+            return Err(format!("la banda {:?} cubre el anillo Adams completo", band));
+        }
+
+        let mesh = rings.mesh(512);
+        let mut vertices_per_arc = [0; 4];
+        for vertex in &mesh {
+            let radius = Vec2::new(vertex.position.x, vertex.position.z).magnitude();
+            if radius < adams.0 - 1e-4 || radius > adams.1 + 1e-4 {
+                continue;
+            }
+            let angle = vertex.position.z.atan2(vertex.position.x).rem_euclid(2.0 * std::f32::consts::PI);
+            let arc = rings.arcs.iter().position(|arc| {
+                angle >= arc.start_angle - 1e-4 && angle <= arc.end_angle + 1e-4
+            });
+            match arc {
+                Some(index) => vertices_per_arc[index] += 1,
+                None => return Err(format!("vértice del anillo Adams a {:.1}°, fuera de los arcos", angle.to_degrees())),
+            }
+        }
+        if let Some(index) = vertices_per_arc.iter().position(|&count| count == 0) {
+            return Err(format!("el arco {} no tiene malla", index));
+        }
+
+        let middle = |arc: usize| (rings.arcs[arc].start_angle + rings.arcs[arc].end_angle) * 0.5;
+        let radius = (adams.0 + adams.1) * 0.5;
+        if arc_opacity(&rings.arcs, radius, middle(3)) != rings.arcs[3].opacity {
+            return Err("la opacidad del arco no se aplica dentro de él".to_string());
+        }
+        if arc_opacity(&rings.arcs, radius, middle(3) + 1.0) != 1.0 || arc_opacity(&rings.arcs, 1.7, middle(0)) != 1.0 {
+            return Err("la opacidad de un arco se aplica fuera de él".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::framebuffer::FillStyle;
+use crate::sizes::SizeScale;
+use crate::uniforms::Uniforms;
+use crate::{Planet, project_point};
+
+const ROCHE_COLOR: u32 = 0xFF3030;
+const ROCHE_ALPHA: f32 = 0.55;
+const CIRCLE_SEGMENTS: usize = 96;
+// Segmentos más largos que esto (en píxeles) se descartan: son puntos casi detrás de la cámara
+const MAX_SEGMENT_LENGTH: f32 = 2048.0;
+
+// Esfera del límite de Roche de cada cuerpo con lunas (`toggle roche` en la paleta): tres
+// círculos ortogonales en rojo translúcido alrededor del cuerpo, con el radio del límite de su luna
+// que lo tiene más lejos. Se dibujan después de los cuerpos con prueba de profundidad, así que lo
+// que queda detrás del planeta no se ve y el planeta no se tapa.
+pub struct RocheOverlay {
+    pub enabled: bool,
+}
+
+impl RocheOverlay {
+    pub fn new() -> Self {
+        RocheOverlay { enabled: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    // `bodies` son los cuerpos en escena con su centro en el mundo
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms, planets: &[Planet], bodies: &[(usize, Vec3)], sphere_radius: f32, sizes: &SizeScale) {
+        if !self.enabled {
+            return;
+        }
+        for &(index, center) in bodies {
+            let Some(limit) = roche_radius(planets, index) else {
+                continue;
+            };
+            let radius = limit * sizes.radius(&planets[index]) * sphere_radius;
+            let axes = [Vec3::x(), Vec3::y(), Vec3::z()];
+            for (u, v) in [(axes[0], axes[1]), (axes[1], axes[2]), (axes[2], axes[0])] {
+                draw_circle(framebuffer, uniforms, center, u * radius, v * radius);
+            }
+        }
+    }
+}
+
+// Mayor límite de Roche (en radios del cuerpo) entre las lunas del cuerpo `index`; `None` si no
+// tiene lunas o no se conoce la densidad de ninguna
+pub fn roche_radius(planets: &[Planet], index: usize) -> Option<f32> {
+    planets
+        .iter()
+        .filter(|moon| moon.parent == Some(index))
+        .filter_map(|moon| planets[index].roche_limit(moon))
+        .reduce(f32::max)
+}
+
+// Círculo `center + u cos θ + v sin θ`, proyectado punto a punto como la órbita en `draw_orbit`
+fn draw_circle(framebuffer: &mut Framebuffer, uniforms: &Uniforms, center: Vec3, u: Vec3, v: Vec3) {
+    let point = |i: usize| {
+        let angle = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+        project_point(uniforms, center + u * angle.cos() + v * angle.sin()).filter(|p| p.z > 0.0 && p.z.is_finite())
+    };
+    let mut previous = point(0);
+    for i in 1..=CIRCLE_SEGMENTS {
+        let current = point(i);
+        if let (Some(start), Some(end)) = (previous, current) {
+            blend_segment(framebuffer, start, end);
+        }
+        previous = current;
+    }
+}
+
+// Segmento translúcido que solo se mezcla donde no hay nada más cerca que su extremo más lejano.
+// `blend_line` no pinta el último píxel, que es el primero del segmento siguiente.
+fn blend_segment(framebuffer: &mut Framebuffer, start: Vec3, end: Vec3) {
+    if (end.x - start.x).abs().max((end.y - start.y).abs()) > MAX_SEGMENT_LENGTH {
+        return;
+    }
+    let style = FillStyle::OPAQUE.alpha(ROCHE_ALPHA).depth(start.z.max(end.z));
+    framebuffer.blend_line((start.x, start.y), (end.x, end.y), ROCHE_COLOR, style);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solar_system;
+    use crate::color::Color;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    #[test]
+    fn roche_overlay() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        // Tierra-Luna: 2.44 (5514 / 3344)^(1/3), unos 2.88 radios terrestres; Marte y el Sol no tienen lunas
+        let planets = solar_system();
+        let earth_limit = roche_radius(&planets, 3).ok_or("la Tierra no tiene límite de Roche")?;
+        if (earth_limit - 2.882).abs() > 0.01 || roche_radius(&planets, 4).is_some() || roche_radius(&planets, 0).is_some() {
+            return Err(format!("límite de la Tierra {}, Marte {:?}, Sol {:?}", earth_limit, roche_radius(&planets, 4), roche_radius(&planets, 0)));
+        }
+
+        // Vista de frente: el círculo del plano XY pasa a 45° por el borde y se mezcla en rojo con lo que
+        // hay, y dentro, fuera de los otros dos círculos (que se ven de canto, como los ejes), no se
+        // pinta nada; con algo más cerca delante no se dibuja
+        let sizes = SizeScale::new();
+        let uniforms = test_uniforms(seeds);
+        let world_radius = earth_limit * sizes.radius(&planets[3]);
+        let diagonal = Vec3::new(1.0, 1.0, 0.0).normalize() * world_radius;
+        let edge = project_point(&uniforms, diagonal).ok_or("el borde no se proyecta")?;
+        let inside = project_point(&uniforms, diagonal * 0.5).ok_or("el interior no se proyecta")?;
+        let mut overlay = RocheOverlay::new();
+        overlay.toggle();
+        let render = |occluded: bool| {
+            let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+            framebuffer.buffer.fill(0x202020);
+            if occluded {
+                framebuffer.zbuffer.fill(0.01);
+            }
+            overlay.render(&mut framebuffer, &uniforms, &planets, &[(3, Vec3::zeros())], 1.0, &sizes);
+            framebuffer
+        };
+        let visible = render(false);
+        let near_edge = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (edge.x as isize + dx, edge.y as isize + dy)))
+            .map(|(x, y)| Color::from_hex(visible.buffer[y as usize * visible.width + x as usize]))
+            .any(|color| color.r > 0x80 && color.g < 0x40);
+        let interior = visible.buffer[inside.y as usize * visible.width + inside.x as usize];
+        if !near_edge || interior != 0x202020 {
+            return Err(format!("borde sin rojo ({}) o interior {:06X}", near_edge, interior));
+        }
+        if render(true).buffer.iter().any(|&pixel| pixel != 0x202020) {
+            return Err("el círculo se dibujó por delante de lo que está más cerca".to_string());
+        }
+        Ok(())
+    }
+}
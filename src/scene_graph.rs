@@ -0,0 +1,222 @@
+use nalgebra_glm::{Mat4, Vec3};
+
+// Qué malla dibuja un nodo: la esfera de un cuerpo (la resolución se elige al dibujar, según su
+// tamaño en pantalla) o los anillos de su cuerpo padre
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeMesh {
+    Sphere,
+    Rings,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drawable {
+    pub mesh: NodeMesh,
+    // Índice del shader, como `color_index` de los cuerpos
+    pub material: usize,
+}
+
+// Nodo del grafo: su transformación respecto del padre (o del mundo, si no tiene) y, si se dibuja,
+// su malla y material
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SceneNode {
+    pub local: Mat4,
+    pub parent: Option<usize>,
+    pub drawable: Option<Drawable>,
+}
+
+impl SceneNode {
+    pub fn new(local: Mat4, parent: Option<usize>) -> Self {
+        SceneNode { local, parent, drawable: None }
+    }
+
+    pub fn with_drawable(mut self, mesh: NodeMesh, material: usize) -> Self {
+        self.drawable = Some(Drawable { mesh, material });
+        self
+    }
+}
+
+// Lo que el renderizador recibe de cada nodo dibujable
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawItem {
+    pub node: usize,
+    pub world: Mat4,
+    pub mesh: NodeMesh,
+    pub material: usize,
+}
+
+// Grafo de escena: un Vec plano de nodos con el índice de su padre. Las matrices del mundo se
+// calculan recorriendo los nodos en un orden con cada padre antes que sus hijos, fijado al
+// construirlo (que es también cuando se rechazan los ciclos).
+pub struct SceneGraph {
+    nodes: Vec<SceneNode>,
+    order: Vec<usize>,
+    world: Vec<Mat4>,
+}
+
+impl SceneGraph {
+    pub fn new(nodes: Vec<SceneNode>) -> Result<Self, String> {
+        // Profundidad de cada nodo: se sube por los padres hasta una raíz o un nodo ya medido;
+        // volver a pasar por un nodo del mismo camino es un ciclo
+        let mut depths: Vec<Option<usize>> = vec![None; nodes.len()];
+        for start in 0..nodes.len() {
+            let mut chain = vec![start];
+            let mut current = start;
+            let mut base = 0;
+            while let Some(parent) = nodes[current].parent {
+                if parent >= nodes.len() {
+                    return Err(format!("el nodo {} tiene como padre al nodo {}, que no existe", current, parent));
+                }
+                if let Some(depth) = depths[parent] {
+                    base = depth + 1;
+                    break;
+                }
+                if chain.contains(&parent) {
+                    return Err(format!("el nodo {} es su propio ancestro", parent));
+                }
+                chain.push(parent);
+                current = parent;
+            }
+            for (offset, &node) in chain.iter().rev().enumerate() {
+                depths[node].get_or_insert(base + offset);
+            }
+        }
+
+        let mut order: Vec<usize> = (0..nodes.len()).collect();
+        order.sort_by_key(|&node| depths[node]);
+        let mut graph = SceneGraph { world: vec![Mat4::identity(); nodes.len()], nodes, order };
+        graph.update();
+        Ok(graph)
+    }
+
+    pub fn parent(&self, node: usize) -> Option<usize> {
+        self.nodes[node].parent
+    }
+
+    // Cambia la transformación local; las del mundo se recalculan en `update`
+    pub fn set_local(&mut self, node: usize, local: Mat4) {
+        self.nodes[node].local = local;
+    }
+
+    // Matrices del mundo de todos los nodos, cada padre antes que sus hijos
+    pub fn update(&mut self) {
+        for &node in &self.order {
+            let local = self.nodes[node].local;
+            self.world[node] = match self.nodes[node].parent {
+                Some(parent) => self.world[parent] * local,
+                None => local,
+            };
+        }
+    }
+
+    pub fn world_position(&self, node: usize) -> Vec3 {
+        let world = &self.world[node];
+        Vec3::new(world[(0, 3)], world[(1, 3)], world[(2, 3)])
+    }
+
+    // Los nodos dibujables en orden de índice, con su matriz del mundo
+    pub fn draw_list(&self) -> Vec<DrawItem> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(node, scene_node)| {
+                let drawable = scene_node.drawable?;
+                Some(DrawItem { node, world: self.world[node], mesh: drawable.mesh, material: drawable.material })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selftest::close_to;
+    use crate::{solar_system, body_position, system_scene, place_bodies};
+    use crate::rings::RingSystem;
+    use crate::sizes::SizeScale;
+
+    // Dos niveles: un hijo trasladado y escalado dentro de un padre trasladado. Tres niveles: una
+    // raíz girada 90° alrededor de Y, con los nodos guardados al revés (el nieto primero) para que
+    // el orden de cálculo no sea el del Vec.
+    #[test]
+    fn scene_hierarchies() -> Result<(), String> {
+        let translation = |x: f32, y: f32, z: f32| Mat4::new_translation(&Vec3::new(x, y, z));
+        let mut two = SceneGraph::new(vec![
+            SceneNode::new(translation(10.0, 0.0, 0.0), None),
+            SceneNode::new(translation(0.0, 0.0, 5.0) * Mat4::new_scaling(2.0), Some(0)).with_drawable(NodeMesh::Sphere, 3),
+        ])?;
+        if !close_to(two.world_position(1), Vec3::new(10.0, 0.0, 5.0)) || (two.world[1][(0, 0)] - 2.0).abs() > 1e-6 {
+            return Err(format!("hijo en {:?}", two.world_position(1)));
+        }
+        two.set_local(0, translation(-4.0, 1.0, 0.0));
+        two.update();
+        if !close_to(two.world_position(1), Vec3::new(-4.0, 1.0, 5.0)) {
+            return Err(format!("al mover el padre el hijo quedó en {:?}", two.world_position(1)));
+        }
+        let draws = two.draw_list();
+        if draws.len() != 1 || draws[0].node != 1 || draws[0].material != 3 || draws[0].world != two.world[1] {
+            return Err(format!("lista de dibujo {:?}", draws));
+        }
+
+        let turn = translation(0.0, 0.0, 3.0) * Mat4::from_euler_angles(0.0, std::f32::consts::FRAC_PI_2, 0.0);
+        let three = SceneGraph::new(vec![
+            SceneNode::new(translation(1.0, 0.0, 0.0), Some(1)),
+            SceneNode::new(translation(2.0, 0.0, 0.0), Some(2)),
+            SceneNode::new(turn, None),
+        ])?;
+        // Girado 90° alrededor de Y, +X pasa a -Z: el hijo queda 2 y el nieto 3 unidades hacia -Z
+        for (node, expected) in [(2, Vec3::new(0.0, 0.0, 3.0)), (1, Vec3::new(0.0, 0.0, 1.0)), (0, Vec3::new(0.0, 0.0, 0.0))] {
+            if !close_to(three.world_position(node), expected) {
+                return Err(format!("nodo {} de tres niveles en {:?}, se esperaba {:?}", node, three.world_position(node), expected));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn scene_cycles() -> Result<(), String> {
+        let node = |parent| SceneNode::new(Mat4::identity(), parent);
+        for (name, nodes) in [
+            ("propio padre", vec![node(Some(0))]),
+            ("ciclo de dos", vec![node(None), node(Some(2)), node(Some(1))]),
+            ("ciclo de tres", vec![node(Some(2)), node(Some(0)), node(Some(1)), node(Some(0))]),
+            ("padre inexistente", vec![node(None), node(Some(7))]),
+        ] {
+            if SceneGraph::new(nodes).is_ok() {
+                return Err(format!("{}: se aceptó", name));
+            }
+        }
+        SceneGraph::new(vec![node(Some(3)), node(Some(3)), node(Some(0)), node(None)]).map(|_| ())
+    }
+
+    // El grafo del sistema pone cada cuerpo donde `body_position`, la Luna dentro del marco de la
+    // Tierra sin heredar su tamaño ni su achatamiento, y los anillos de Saturno en Saturno
+    #[test]
+    fn solar_system_scene() -> Result<(), String> {
+        let planets = solar_system();
+        let ring_systems = [("Saturno", RingSystem::saturn(), Vec::new())];
+        let mut scene = system_scene(&planets, &ring_systems)?;
+        let sizes = SizeScale::new();
+        let time = 37.0;
+        place_bodies(&mut scene, &planets, time, &sizes, 2.0);
+
+        for index in 0..planets.len() {
+            if !close_to(scene.world_position(index), body_position(&planets, index, time)) {
+                return Err(format!("{} en {:?}, body_position da {:?}", planets[index].name, scene.world_position(index), body_position(&planets, index, time)));
+            }
+        }
+        let moon = planets.iter().position(|planet| planet.name == "Luna").ok_or("sin Luna")?;
+        let moon_sphere = scene.draw_list().into_iter().find(|item| item.mesh == NodeMesh::Sphere && scene.parent(item.node) == Some(moon)).ok_or("la Luna no tiene esfera")?;
+        if (moon_sphere.world[(0, 0)] - sizes.radius(&planets[moon])).abs() > 1e-6 || !close_to(scene.world_position(moon_sphere.node), scene.world_position(moon)) {
+            return Err(format!("esfera de la Luna con escala {} en {:?}", moon_sphere.world[(0, 0)], scene.world_position(moon_sphere.node)));
+        }
+
+        let saturn = planets.iter().position(|planet| planet.name == "Saturno").ok_or("sin Saturno")?;
+        let rings: Vec<_> = scene.draw_list().into_iter().filter(|item| item.mesh == NodeMesh::Rings).collect();
+        match rings.as_slice() {
+            [ring] if scene.parent(ring.node) == Some(saturn)
+                && close_to(scene.world_position(ring.node), scene.world_position(saturn))
+                && (ring.world[(1, 1)] - sizes.radius(&planets[saturn]) * 2.0).abs() < 1e-5 => Ok(()),
+            _ => Err(format!("{} nodos de anillos, se esperaba uno en Saturno", rings.len())),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Semilla global: todas las partes procedurales (ruido de cada planeta, texturas generadas...)
+// derivan la suya de aquí, para que una misma semilla reproduzca exactamente la misma escena
+
+// Semilla por defecto cuando no se pasa `--seed`
+pub const DEFAULT_SEED: u64 = 1337;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeedSource {
+    pub base: u64,
+}
+
+impl SeedSource {
+    pub fn new(base: u64) -> Self {
+        SeedSource { base }
+    }
+
+    // Lee `--seed <u64>` o `--seed random` de los argumentos del programa.
+    // Con `random` imprime la semilla elegida para poder repetir la escena.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let position = match args.iter().position(|arg| arg == "--seed") {
+            Some(position) => position,
+            None => return Ok(SeedSource::new(DEFAULT_SEED)),
+        };
+
+        match args.get(position + 1).map(|value| value.as_str()) {
+            Some("random") => {
+                let seed = rand::random::<u64>();
+                println!("Semilla: {}", seed);
+                Ok(SeedSource::new(seed))
+            }
+            Some(value) => value
+                .parse::<u64>()
+                .map(SeedSource::new)
+                .map_err(|_| format!("--seed espera un entero sin signo o 'random', no '{}'", value)),
+            None => Err("--seed espera un valor".to_string()),
+        }
+    }
+
+    // Semilla de un subsistema: hash estable (FNV-1a) de la semilla base, el nombre y el índice.
+    // No depende del orden en que se pidan ni de la versión del compilador.
+    pub fn derive(&self, subsystem: &str, index: u64) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        let mut hash = FNV_OFFSET;
+        let bytes = self.base.to_le_bytes().into_iter()
+            .chain(subsystem.bytes())
+            .chain(index.to_le_bytes());
+        for byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+        hash
+    }
+
+    // FastNoiseLite usa semillas i32
+    pub fn noise_seed(&self, subsystem: &str, index: u64) -> i32 {
+        self.derive(subsystem, index) as i32
+    }
+}
@@ -0,0 +1,232 @@
+use nalgebra_glm::{Vec2, Vec3, Mat4};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::Obj;
+use crate::Vertex;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::shaders::{vertex_shader, select_shader};
+use crate::texture::{Texture, generate_heightmap_png};
+use crate::triangle;
+use crate::uniforms::{Uniforms, Displacement, RenderMode, ColorBlindMode, create_noise, create_view_matrix, create_perspective_matrix, create_viewport_matrix, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE};
+use crate::rings::RingSystem;
+use crate::procedural_mesh::ProceduralMesh;
+use crate::seed::SeedSource;
+use crate::{SPHERE_MODEL, SKYBOX_TEXTURE, moon_height_map_path, moon_terrain_noise};
+
+// Tamaño del cuadro de las comprobaciones, también el de los tests de cada módulo
+pub const WIDTH: f32 = 800.0;
+pub const HEIGHT: f32 = 600.0;
+
+// Shaders registrados en `select_shader`, con el nombre que se muestra en el reporte
+const SHADERS: [(usize, &str); 13] = [
+    (0, "Sol"),
+    (1, "Mercurio"),
+    (2, "Venus"),
+    (3, "Tierra"),
+    (4, "Marte"),
+    (5, "Jupiter"),
+    (6, "Saturno"),
+    (7, "Urano"),
+    (8, "Anillos"),
+    (9, "Nave"),
+    (10, "Luna"),
+    (11, "Neptuno"),
+    (12, "Mundo oceanico"),
+];
+
+// Resultado de una comprobación: Ok o la descripción del fallo
+type Check = Result<(), String>;
+
+// Ejecuta todas las comprobaciones (`--self-test`), imprime el reporte y devuelve si pasaron todas
+pub fn run(seeds: &SeedSource) -> bool {
+    let mut checks: Vec<(String, Check)> = vec![
+        ("rasterizado de un triangulo conocido".to_string(), check_rasterization()),
+        ("transformacion de un vertice conocido".to_string(), check_vertex_transform(seeds)),
+    ];
+
+    for (index, name) in SHADERS.iter() {
+        checks.push((format!("shader {} ({})", index, name), check_shader(*index, seeds)));
+    }
+
+    checks.push((format!("normales de {}", SPHERE_MODEL), check_obj_normals(SPHERE_MODEL)));
+    checks.push(("normales de la esfera de alta resolucion".to_string(), check_normals(&ProceduralMesh::uv_sphere(1.0, 48, 96))));
+    checks.push(("normales de los anillos de Saturno".to_string(), check_normals(&RingSystem::saturn().mesh(64))));
+
+    checks.push((format!("textura {}", SKYBOX_TEXTURE), check_texture(SKYBOX_TEXTURE)));
+    let moon_height_map = moon_height_map_path(seeds);
+    checks.push((format!("textura {}", moon_height_map), check_moon_height_map(seeds, &moon_height_map)));
+
+    let mut failures = 0;
+    for (name, result) in &checks {
+        match result {
+            Ok(()) => println!("[PASS] {}", name),
+            Err(reason) => {
+                failures += 1;
+                println!("[FAIL] {}: {}", name, reason);
+            }
+        }
+    }
+    println!("{} de {} comprobaciones pasaron", checks.len() - failures, checks.len());
+
+    failures == 0
+}
+
+// Cámara en (0, 0, 10) mirando al origen, sin mapas ni efectos
+pub fn test_uniforms(seeds: &SeedSource) -> Uniforms {
+    Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix: create_view_matrix(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        projection_matrix: create_perspective_matrix(WIDTH, HEIGHT, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE),
+        viewport_matrix: create_viewport_matrix(WIDTH, HEIGHT),
+        time: 0.0,
+        noise: Arc::new(create_noise(seeds.noise_seed("planet", 3))),
+        displacement: Displacement::none(),
+        view_pos: Vec3::new(0.0, 0.0, 10.0),
+        height_map: None,
+        color_map: None,
+        parallax_map: None,
+        normal_map: None,
+        texture_lod: 0,
+        render_mode: RenderMode::Solid,
+        color_blind_mode: ColorBlindMode::None,
+        spacecraft_velocity: Vec3::new(0.0, 0.0, 0.0),
+        reflection_source: None,
+        ring_profile: None,
+        ring_arcs: None,
+        light_probe: None,
+        cutaway: None,
+        sun_radius: 0.0,
+        occluders: Vec::new(),
+        contrast: 1.0,
+        fill_rule: triangle::FillRule::TopLeft,
+    }
+}
+
+// Para los tests: dos puntos iguales salvo el redondeo
+#[cfg(test)]
+pub fn close_to(a: Vec3, b: Vec3) -> bool {
+    (a - b).magnitude() < 1e-4
+}
+
+// Un triángulo rectángulo de catetos 10 px cubre 50 px²: los centros de píxel
+// dentro (o en el borde) son 55, así que se admite ese margen
+fn check_rasterization() -> Check {
+    let corner = |x: f32, y: f32| {
+        let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+        vertex.transformed_position = Vec3::new(x, y, 1.0);
+        vertex
+    };
+
+    let fragments = triangle::triangle(&corner(0.0, 0.0), &corner(10.0, 0.0), &corner(0.0, 10.0), WIDTH as usize, HEIGHT as usize);
+    if fragments.len() < 45 || fragments.len() > 60 {
+        return Err(format!("{} fragmentos, se esperaban ~50", fragments.len()));
+    }
+    if let Some(fragment) = fragments.iter().find(|f| f.position.x + f.position.y > 10.0) {
+        return Err(format!("fragmento fuera del triangulo en ({}, {})", fragment.position.x, fragment.position.y));
+    }
+    if let Some(fragment) = fragments.iter().find(|f| (f.depth - 1.0).abs() > 1e-4) {
+        return Err(format!("profundidad {} en vez de 1.0", fragment.depth));
+    }
+    Ok(())
+}
+
+// Con la cámara en (0, 0, 10) mirando al origen, un punto trasladado a (0, 0, 3)
+// queda en el centro de la pantalla a 7 unidades de distancia
+fn check_vertex_transform(seeds: &SeedSource) -> Check {
+    let mut uniforms = test_uniforms(seeds);
+    uniforms.model_matrix = Mat4::new_translation(&Vec3::new(0.0, 0.0, 3.0));
+
+    let vertex = Vertex::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+    let transformed = vertex_shader(&vertex, &uniforms);
+
+    let expected = Vec3::new(WIDTH / 2.0, HEIGHT / 2.0, 7.0);
+    if (transformed.transformed_position - expected).magnitude() > 1e-3 {
+        return Err(format!("se obtuvo {:?}, se esperaba {:?}", transformed.transformed_position, expected));
+    }
+    if (transformed.world_position - Vec3::new(0.0, 0.0, 3.0)).magnitude() > 1e-5 {
+        return Err(format!("posicion de mundo {:?}", transformed.world_position));
+    }
+    Ok(())
+}
+
+// Un fragmento iluminado de frente por el Sol. Los colores son u8: un NaN en el cálculo
+// termina como negro, así que un resultado completamente negro se toma como fallo
+fn check_shader(index: usize, seeds: &SeedSource) -> Check {
+    let uniforms = test_uniforms(seeds);
+    let fragment = Fragment::new(
+        Vec2::new(400.0, 300.0),
+        Color::new(100, 100, 100),
+        7.0,
+        Vec3::new(-1.0, 0.0, 0.0),
+        1.0,
+        Vec3::new(0.3, 0.0, 0.0),
+        Vec3::new(10.0, 0.0, 0.0),
+    );
+
+    let color = panic::catch_unwind(AssertUnwindSafe(|| select_shader(index, &fragment, &uniforms)))
+        .map_err(|_| "el shader entro en panico".to_string())?;
+    if color.is_black() {
+        return Err("color negro (posible NaN)".to_string());
+    }
+    Ok(())
+}
+
+fn check_obj_normals(path: &str) -> Check {
+    let obj = Obj::load(path).map_err(|error| format!("no se pudo cargar: {}", error))?;
+    check_normals(&obj.get_vertex_array())
+}
+
+fn check_normals(vertices: &[Vertex]) -> Check {
+    if vertices.is_empty() {
+        return Err("la malla no tiene vertices".to_string());
+    }
+
+    let bad = vertices.iter().filter(|v| (v.normal.magnitude() - 1.0).abs() > 1e-3).count();
+    if bad > 0 {
+        return Err(format!("{} de {} normales no son unitarias", bad, vertices.len()));
+    }
+    Ok(())
+}
+
+fn check_texture(path: &str) -> Check {
+    load_checked_texture(path).map(|_| ())
+}
+
+fn load_checked_texture(path: &str) -> Result<Texture, String> {
+    let texture = Texture::load(path).map_err(|error| format!("no se pudo cargar: {}", error))?;
+    if texture.width == 0 || texture.height == 0 {
+        return Err(format!("dimensiones {}x{}", texture.width, texture.height));
+    }
+    if texture.mip_dimensions(texture.mip_count() - 1) != (1, 1) {
+        return Err("la cadena de mip-maps no termina en 1x1".to_string());
+    }
+    Ok(texture)
+}
+
+// El mapa de alturas de la Luna se genera la primera vez, igual que al arrancar
+fn check_moon_height_map(seeds: &SeedSource, path: &str) -> Check {
+    if !Path::new(path).exists() {
+        generate_heightmap_png(&moon_terrain_noise(seeds), 100.0, 512, 256, path)
+            .map_err(|error| format!("no se pudo generar: {}", error))?;
+    }
+
+    let texture = load_checked_texture(path)?;
+    if (texture.width, texture.height) != (512, 256) {
+        return Err(format!("dimensiones {}x{}, se esperaban 512x256", texture.width, texture.height));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::DEFAULT_SEED;
+
+    #[test]
+    fn self_test_passes() {
+        assert!(run(&SeedSource::new(DEFAULT_SEED)));
+    }
+}
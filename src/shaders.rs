@@ -4,11 +4,16 @@ use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
 use crate::color::Color;
-use crate::light::Light;
+use crate::light::{Light, shadow_factor};
 use rand::Rng;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 use fastnoise_lite::FastNoiseLite;
+use crate::texture::{Texture, spherical_uv, uv_to_direction, parallax_offset};
+use crate::rings::{sample_profile, arc_opacity};
+
+// |w| por debajo del cual un vértice se considera en el plano de la cámara
+pub const MIN_PROJECTION_W: f32 = 1e-5;
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   // Transformación de posición base
@@ -19,27 +24,40 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
       1.0,
   );
 
-  // Zoom para el relieve
-  let zoom = 5.0;
-  let displacement_amount = uniforms.noise.get_noise_3d(
-      vertex.position.x * zoom,
-      vertex.position.y * zoom,
-      vertex.position.z * zoom,
-  );
-
-  // Desplazamiento a lo largo de la normal del vértice
-  let displaced_position = vertex.position + vertex.normal * displacement_amount * 0.5;
+  // Desplazamiento a lo largo de la normal del vértice, solo si el objeto tiene relieve.
+  // Con un mapa de alturas asociado, la altura sale de la textura en vez del ruido.
+  let displacement = uniforms.displacement;
+  let displaced_position = if !displacement.enabled || displacement.amplitude == 0.0 {
+      vertex.position
+  } else if let Some(height_map) = &uniforms.height_map {
+      let height = height_map.sample_height_level(spherical_uv(vertex.normal), uniforms.texture_lod);
+      vertex.position + vertex.normal * (height - 0.5) * displacement.amplitude
+  } else {
+      let zoom = displacement.zoom;
+      let displacement_amount = uniforms.noise.get_noise_3d(
+          vertex.position.x * zoom,
+          vertex.position.y * zoom,
+          vertex.position.z * zoom,
+      );
+
+      vertex.position + vertex.normal * displacement_amount * displacement.amplitude
+  };
 
   // Transformación del vértice desplazado
-  let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * Vec4::new(
+  let world_position = uniforms.model_matrix * Vec4::new(
       displaced_position.x,
       displaced_position.y,
       displaced_position.z,
       1.0,
   );
-
-  // División en perspectiva
-  let w = transformed.w;
+  let view_position = uniforms.view_matrix * world_position;
+  let transformed = uniforms.projection_matrix * view_position;
+
+  // División en perspectiva. Con |w| casi 0 el vértice está en el plano de la cámara y la
+  // división daría ±inf: se marca y se deja en el centro de la pantalla para que nada
+  // más adelante reciba coordenadas infinitas
+  let unprojected = transformed.w.abs() < MIN_PROJECTION_W;
+  let w = if unprojected { f32::INFINITY } else { transformed.w };
   let ndc_position = Vec4::new(
       transformed.x / w,
       transformed.y / w,
@@ -50,10 +68,23 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
   // Aplicar la matriz de viewport
   let screen_position = uniforms.viewport_matrix * ndc_position;
 
-  // Transformar la normal
+  // La profundidad guardada es la distancia en espacio de vista (positiva hacia adelante),
+  // no la z no lineal de NDC, para repartir la precisión en toda la escena
+  let view_depth = -view_position.z;
+
+  // Transformar la normal con la transpuesta inversa: con escala no uniforme (planetas achatados)
+  // la matriz de modelo no conserva la perpendicularidad a la superficie
   let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
   let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
-  let transformed_normal = normal_matrix * vertex.normal;
+  let transformed_normal = (normal_matrix * vertex.normal).normalize();
+
+  let transformed_position = Vec3::new(screen_position.x, screen_position.y, view_depth);
+  debug_assert!(
+      unprojected || (transformed_position.x.is_finite() && transformed_position.y.is_finite() && transformed_position.z.is_finite()),
+      "vertex_shader: posición transformada no finita {:?} (w = {})",
+      transformed_position,
+      transformed.w,
+  );
 
   // Crear un nuevo vértice con atributos transformados
   Vertex {
@@ -61,33 +92,62 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
       normal: vertex.normal,
       tex_coords: vertex.tex_coords,
       color: vertex.color,
-      transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
+      transformed_position,
       transformed_normal,
+      world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+      mesh_index: vertex.mesh_index,
+      unprojected,
   }
 }
 
 
-pub fn select_shader(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+// La luz del Sol que llega al fragmento
+fn sun_light(fragment: &Fragment, uniforms: &Uniforms) -> Light {
     let sun_position = Vec3::new(0.0, 0.0, 0.0);
-    let sun_light = Light::new(
+    let mut sun_light = Light::new(
         sun_position,                // Posición del Sol
         Color::new(255, 255, 200),   // Color amarillo claro
         3.0,                         // Intensidad de la luz
     );
+    // Eclipses: cada cuerpo que se interpone tapa una parte del disco del Sol
+    sun_light.visibility = uniforms
+        .occluders
+        .iter()
+        .map(|occluder| shadow_factor(fragment.world_position, sun_position, uniforms.sun_radius, occluder.position, occluder.radius))
+        .product();
+    sun_light
+}
 
-    match index {
+pub fn select_shader(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let sun_light = sun_light(fragment, uniforms);
+
+    let color = match index {
         0 => sun_shader().0,                           // El Sol
         1 => apply_lighting(mercury_shader(fragment, uniforms), fragment, &sun_light),
         2 => apply_lighting(venus_shader(fragment, uniforms), fragment, &sun_light),
         3 => apply_lighting(earth_shader(fragment, uniforms), fragment, &sun_light),
         4 => apply_lighting(mars_shader(fragment, uniforms).0, fragment, &sun_light),
-        5 => apply_lighting(jupiter_shader(fragment, uniforms), fragment, &sun_light),
-        6 => apply_lighting(saturn_shader(fragment, uniforms), fragment, &sun_light),
-        7 => apply_lighting(uranus_shader(fragment, uniforms), fragment, &sun_light),
-        8 => ring_shader(fragment).0,                 // Anillos de Saturno (sin iluminación)
+        5 => apply_lighting_fresnel(jupiter_shader(fragment, uniforms), fragment, &sun_light, uniforms),
+        6 => apply_lighting_fresnel(saturn_shader(fragment, uniforms), fragment, &sun_light, uniforms),
+        7 => apply_lighting_fresnel(uranus_shader(fragment, uniforms), fragment, &sun_light, uniforms),
+        8 => ring_shader(fragment, uniforms).0,       // Anillos (sin iluminación; la opacidad va aparte)
         9 => spaceship_shader(fragment, uniforms),    // Nave espacial
+        10 => match (&uniforms.height_map, &uniforms.color_map) {
+            // Superficie a partir de texturas (la Luna)
+            (Some(height_tex), Some(color_tex)) => {
+                let color = terrain_shader(fragment, height_tex, color_tex, uniforms.texture_lod);
+                match &uniforms.normal_map {
+                    Some(normal_map) => apply_lighting(color, &with_mapped_normal(fragment, uniforms, normal_map), &sun_light),
+                    None => apply_lighting(color, fragment, &sun_light),
+                }
+            }
+            _ => apply_lighting(mercury_shader(fragment, uniforms), fragment, &sun_light),
+        },
+        11 => apply_lighting_fresnel(neptune_shader(fragment, uniforms), fragment, &sun_light, uniforms),
+        12 => water_world_shader(fragment, uniforms), // Mundo oceánico (ya iluminado)
         _ => sun_shader().0,                          // Por defecto: el Sol
-    }
+    };
+    uniforms.color_blind_mode.apply(color)
 }
 
 fn spaceship_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -99,8 +159,9 @@ fn apply_lighting(base_color: Color, fragment: &Fragment, light: &Light) -> Colo
     // Vector desde el fragmento hasta la fuente de luz
     let light_direction = (light.position - fragment.vertex_position).normalize();
 
-    // Producto punto para determinar la intensidad de la luz en este fragmento
-    let intensity = fragment.normal.dot(&light_direction).max(0.0);
+    // Producto punto para determinar la intensidad de la luz en este fragmento; en la sombra de
+    // un eclipse llega solo la parte visible del disco
+    let intensity = fragment.normal.dot(&light_direction).max(0.0) * light.visibility;
 
     // Atenuación de la luz según la distancia
     let distance = (light.position - fragment.vertex_position).magnitude();
@@ -111,40 +172,118 @@ fn apply_lighting(base_color: Color, fragment: &Fragment, light: &Light) -> Colo
     base_color.lerp(&light_effect, intensity as f32)
 }
 
-fn ring_shader(fragment: &Fragment) -> (Color, u32) {
-    // Coordenadas en 2D para determinar la distancia desde el centro de los anillos
-    let position = Vec2::new(fragment.vertex_position.x as f32, fragment.vertex_position.z as f32); // Usar X y Z para planos
-    let distance_from_center = position.magnitude(); // Calcular la distancia desde el centro
+// Aproximación de Schlick del término de Fresnel
+pub fn fresnel(n_dot_v: f32, f0: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - n_dot_v.clamp(0.0, 1.0)).powi(5)
+}
+
+// Reflectancia base y peso del reflejo para las atmósferas de los gigantes gaseosos
+const GAS_GIANT_F0: f32 = 0.04;
+const GAS_GIANT_REFLECTION: f32 = 0.15;
+
+fn apply_lighting_fresnel(base_color: Color, fragment: &Fragment, light: &Light, uniforms: &Uniforms) -> Color {
+    let lit_color = apply_lighting(base_color, fragment, light);
+
+    // Dirección desde el fragmento hacia la cámara
+    let view_direction = (uniforms.view_pos - fragment.world_position).normalize();
+    let n_dot_v = fragment.normal.dot(&view_direction).max(0.0);
+    let reflectance = fresnel(n_dot_v, GAS_GIANT_F0);
+
+    // El término difuso se oscurece hacia el limbo y lo que pierde vuelve como reflejo de la luz
+    // y, si hay sonda de luz, del fondo en la dirección reflejada
+    let mut color = lit_color * (1.0 - reflectance) + light.color * (reflectance * GAS_GIANT_REFLECTION * light.visibility);
+    if let Some(probe) = &uniforms.light_probe {
+        let reflected = fragment.normal * (2.0 * n_dot_v) - view_direction;
+        color = color + probe.sample(reflected) * reflectance;
+    }
+    color
+}
+
+// Color y opacidad de un fragmento semitransparente. Solo los anillos tienen opacidad propia;
+// el resto de shaders son opacos.
+pub fn select_shader_with_alpha(index: usize, fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    match index {
+        8 => {
+            let (color, alpha) = ring_shader(fragment, uniforms);
+            (uniforms.color_blind_mode.apply(color), alpha)
+        }
+        _ => (select_shader(index, fragment, uniforms), 1.0),
+    }
+}
 
-    // Definir el número de bandas y su ancho
-    let num_bands = 4; // Número total de bandas en los anillos
-    let max_distance = 1.0_f32; // Distancia máxima para las bandas (ajustar según el tamaño de los anillos)
-    let band_width = max_distance / num_bands as f32; // Ancho de cada banda
+// Escala del radio al muestrear el ruido (frecuencia 0.01: unos 30 anillos finos por radio
+// del planeta) y peso de esos anillos sobre la densidad de la banda
+const RINGLET_FREQUENCY: f32 = 3000.0;
+const RINGLET_STRENGTH: f32 = 0.3;
+
+// Brillo extra por dispersión hacia adelante con el Sol detrás de los anillos, y qué tan
+// alineados deben estar el Sol y la cámara para notarlo
+const FORWARD_SCATTER_BOOST: f32 = 1.5;
+const FORWARD_SCATTER_EXPONENT: i32 = 6;
+
+// Anillos a partir del perfil radial de densidad (`uniforms.ring_profile`): la densidad del
+// tramo escala el brillo y la opacidad, el ruido agrega anillos finos y las zonas poco densas
+// brillan a contraluz. Devuelve (color, opacidad).
+fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, f32) {
+    // Radio en espacio de objeto: la malla está en radios del planeta, en el plano XZ
+    let radius = Vec2::new(fragment.vertex_position.x, fragment.vertex_position.z).magnitude();
+    let band = match uniforms.ring_profile.as_ref().and_then(|profile| sample_profile(profile, radius).copied()) {
+        Some(band) => band,
+        None => return (Color::from_hex(0xA9A9A9), 1.0),
+    };
 
-    // Calcular en qué banda está el fragmento actual
-    let band_index = (distance_from_center / band_width).floor() as i32;
+    let ringlet = 1.0 + RINGLET_STRENGTH * uniforms.noise.get_noise_2d(radius * RINGLET_FREQUENCY, 0.0);
+    let density = (band.density * ringlet).clamp(0.0, 1.0);
 
-    // Variar el color de los anillos en función de su índice
-    let band_colors = [
-        Color::from_hex(0xB0C4DE), // Azul claro
-        Color::from_hex(0x708090), // Gris pizarra
-        Color::from_hex(0xA9A9A9), // Gris claro
-        Color::from_hex(0xF5F5DC), // Beige
-    ];
+    // Sol y cámara en lados opuestos del plano de los anillos: la luz atraviesa hacia la cámara
+    let sun_direction = (Vec3::new(0.0, 0.0, 0.0) - fragment.world_position).normalize();
+    let view_direction = (uniforms.view_pos - fragment.world_position).normalize();
+    let backlit = fragment.normal.dot(&sun_direction) * fragment.normal.dot(&view_direction) < 0.0;
+    let scatter = if backlit {
+        let alignment = (-sun_direction).dot(&view_direction).max(0.0);
+        FORWARD_SCATTER_BOOST * alignment.powi(FORWARD_SCATTER_EXPONENT) * (1.0 - 0.5 * band.density)
+    } else {
+        0.0
+    };
 
-    // Seleccionar el color basado en el índice de la banda y el número de bandas
-    let color = band_colors[(band_index.abs() % num_bands) as usize % band_colors.len()];
+    // Dentro de un arco incompleto, su opacidad atenúa la de la banda
+    let angle = fragment.vertex_position.z.atan2(fragment.vertex_position.x);
+    let opacity = uniforms.ring_arcs.as_ref().map_or(1.0, |arcs| arc_opacity(arcs, radius, angle));
+
+    let brightness = (0.35 + 0.65 * density) * (1.0 + scatter);
+    (Color::from_hex(band.color) * brightness, density * opacity)
+}
 
-    // Aplicar un efecto de difuminado en los bordes de las bandas
-    let edge_distance = (distance_from_center % band_width) / band_width;
-    let smooth_edge = (1.0_f32 - edge_distance).clamp(0.0_f32, 1.0_f32);
 
-    // Modificar la opacidad para dar un efecto de transparencia a los anillos
-    let final_color = color * smooth_edge;
+// Color de la superficie tomado de una textura; las zonas bajas del mapa de alturas
+// (fondos de cráteres) se oscurecen un poco para reforzar el relieve
+fn terrain_shader(fragment: &Fragment, height_tex: &Texture, color_tex: &Texture, lod: usize) -> Color {
+    let uv = spherical_uv(fragment.normal);
+    let base_color = color_tex.sample_level(uv, lod);
+    let height = height_tex.sample_height_level(uv, lod);
 
-    (final_color, 0)
+    base_color * (0.6 + 0.4 * height)
 }
 
+// Copia del fragmento con la normal del mapa, llevada del espacio tangente al del mundo
+// con la misma base este/sur que `parallax_position`
+fn with_mapped_normal(fragment: &Fragment, uniforms: &Uniforms, normal_map: &Texture) -> Fragment {
+    let direction = fragment.vertex_position.normalize();
+    let mapped = normal_map.sample_normal_level(spherical_uv(direction), uniforms.texture_lod);
+
+    let east = Vec3::new(-direction.z, 0.0, direction.x);
+    let normal = if east.magnitude() < 1e-4 {
+        fragment.normal
+    } else {
+        let model = mat4_to_mat3(&uniforms.model_matrix);
+        let east = east.normalize();
+        let tangent = (model * east).normalize();
+        let bitangent = (model * direction.cross(&east)).normalize();
+        (tangent * mapped.x + bitangent * mapped.y + fragment.normal * mapped.z).normalize()
+    };
+
+    Fragment { normal, ..*fragment }
+}
 
 fn sun_shader() -> (Color, u32) {
     let base_color = Color::from_float(1.0, 0.9, 0.5); // Color amarillo/dorado para el Sol
@@ -162,17 +301,23 @@ fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let snow_color = Color::new(255, 250, 250);     // Blanco para zonas polares
   let cloud_color = Color::new(255, 255, 255);    // Blanco para las nubes
 
-  // Zoom para el ruido que genera los biomas
+  // Zoom para el ruido que genera los biomas. Con relieve asociado, el punto donde se
+  // evalúan los biomas se corre por parallax para que las cordilleras parezcan tener altura.
   let zoom = 15.0;
+  let biome_position = match &uniforms.parallax_map {
+      Some(relief) => parallax_position(fragment, uniforms, relief),
+      None => fragment.vertex_position,
+  };
   let noise_value = uniforms.noise.get_noise_3d(
-      fragment.vertex_position.x * zoom,
-      fragment.vertex_position.y * zoom,
-      fragment.vertex_position.z * zoom,
+      biome_position.x * zoom,
+      biome_position.y * zoom,
+      biome_position.z * zoom,
   );
 
-  // Capa base para la superficie terrestre
+  // Capa base para la superficie terrestre; el océano refleja lo que ya hay en pantalla
   let base_color = if noise_value < -0.3 {
-      ocean_color.lerp(&Color::new(25, 105, 210), (noise_value + 0.3) / 0.3)
+      let water_color = ocean_color.lerp(&Color::new(25, 105, 210), (noise_value + 0.3) / 0.3);
+      apply_ocean_reflection(water_color, fragment, uniforms)
   } else if noise_value > 0.7 {
       land_color.lerp(&snow_color, (noise_value - 0.7) / 0.3)
   } else {
@@ -213,6 +358,142 @@ fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
 
 
+// Desplazamiento máximo del parallax, en unidades de UV
+const PARALLAX_SCALE: f32 = 0.004;
+
+// Posición (en espacio de objeto) donde muestrear la superficie tras el desplazamiento
+// por parallax según el mapa de alturas y la dirección hacia la cámara
+fn parallax_position(fragment: &Fragment, uniforms: &Uniforms, relief: &Texture) -> Vec3 {
+  let direction = fragment.vertex_position.normalize();
+  let uv = spherical_uv(direction);
+  let height = relief.sample_height(uv);
+
+  // Base tangente de la esfera: +u hacia el este y +v hacia el sur
+  let tangent = Vec3::new(-direction.z, 0.0, direction.x).normalize();
+  let bitangent = direction.cross(&tangent);
+  let view_dir = (uniforms.view_pos - fragment.world_position).normalize();
+  let view_tangent = Vec3::new(view_dir.dot(&tangent), view_dir.dot(&bitangent), view_dir.dot(&fragment.normal));
+
+  let shifted_uv = uv + parallax_offset(view_tangent, height, PARALLAX_SCALE);
+  uv_to_direction(shifted_uv) * fragment.vertex_position.magnitude()
+}
+
+// Reflectancia del agua a incidencia normal y pasos de la búsqueda del reflejo
+const WATER_F0: f32 = 0.02;
+const REFLECTION_STEPS: usize = 24;
+const REFLECTION_STEP_LENGTH: f32 = 1.5;
+
+fn apply_ocean_reflection(water_color: Color, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let view_direction = (uniforms.view_pos - fragment.world_position).normalize();
+  let n_dot_v = fragment.normal.dot(&view_direction).max(0.0);
+
+  match screen_space_reflection(fragment, uniforms, view_direction) {
+      Some(reflected) => water_color.lerp(&reflected, fresnel(n_dot_v, WATER_F0)),
+      None => water_color,
+  }
+}
+
+// Avanza por el rayo reflejado proyectando cada paso a pantalla hasta quedar detrás de algo
+// ya dibujado en la copia del frame; si sale de pantalla no hay reflejo disponible
+fn screen_space_reflection(fragment: &Fragment, uniforms: &Uniforms, view_direction: Vec3) -> Option<Color> {
+  let source = uniforms.reflection_source.as_ref()?;
+  let incident = -view_direction;
+  let reflected = incident - fragment.normal * (2.0 * incident.dot(&fragment.normal));
+
+  let mut last_pixel = None;
+  for step in 1..=REFLECTION_STEPS {
+      let point = fragment.world_position + reflected * (step as f32 * REFLECTION_STEP_LENGTH);
+      let view_position = uniforms.view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+      let depth = -view_position.z;
+      if depth <= 0.0 {
+          return None;
+      }
+
+      let clip = uniforms.projection_matrix * view_position;
+      let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+      let screen = uniforms.viewport_matrix * ndc;
+      if screen.x < 0.0 || screen.y < 0.0 {
+          return None;
+      }
+
+      let (x, y) = (screen.x as usize, screen.y as usize);
+      let (pixel, scene_depth) = match (source.read_pixel(x, y), source.read_depth(x, y)) {
+          (Some(pixel), Some(scene_depth)) => (pixel, scene_depth),
+          _ => return None,
+      };
+      if scene_depth < depth {
+          return Some(Color::from_hex(pixel));
+      }
+      last_pixel = Some(pixel);
+  }
+
+  // Sin impacto: el rayo se pierde en el fondo (el skybox)
+  last_pixel.map(Color::from_hex)
+}
+
+// Mundo oceánico (un exoplaneta hipotético cubierto de agua). Las olas son dos capas de ruido
+// que se desplazan en sentidos opuestos; su gradiente en la base este/sur de la esfera inclina
+// la normal, y con ella se iluminan el difuso y un brillo especular de Blinn-Phong muy
+// concentrado en las crestas. Devuelve el color ya iluminado.
+const WATER_WORLD_DEEP: u32 = 0x0B3C5D;
+const WATER_WORLD_SHALLOW: u32 = 0x1F7A7A;
+// Frecuencias y pesos de las dos capas de olas, y velocidad de cada una por unidad de tiempo
+const WAVE_FREQUENCIES: [f32; 2] = [12.0, 31.0];
+const WAVE_WEIGHTS: [f32; 2] = [0.7, 0.3];
+const WAVE_DRIFT: [f32; 2] = [0.01, -0.007];
+// Cuánto inclinan las olas la normal y paso de las diferencias para el gradiente
+const WAVE_STRENGTH: f32 = 0.02;
+const WAVE_EPSILON: f32 = 0.002;
+const WATER_SHININESS: f32 = 128.0;
+const WATER_SPECULAR: f32 = 1.2;
+
+fn water_world_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let light = sun_light(fragment, uniforms);
+    let direction = fragment.vertex_position.normalize();
+    let time = uniforms.time;
+    let wave_height = |point: Vec3| -> f32 {
+        WAVE_FREQUENCIES
+            .iter()
+            .zip(WAVE_WEIGHTS.iter().zip(WAVE_DRIFT))
+            .map(|(&frequency, (&weight, drift))| {
+                let sample = point * frequency + Vec3::new(time * drift, 0.0, time * drift);
+                weight * uniforms.noise.get_noise_3d(sample.x, sample.y, sample.z)
+            })
+            .sum()
+    };
+
+    // Gradiente de la altura de las olas en la base este/sur, llevado al espacio del mundo como
+    // en `with_mapped_normal`; en los polos la base no está definida y queda la normal lisa
+    let east = Vec3::new(-direction.z, 0.0, direction.x);
+    let normal = if east.magnitude() < 1e-4 {
+        fragment.normal
+    } else {
+        let east = east.normalize();
+        let south = direction.cross(&east);
+        let slope_east = (wave_height(direction + east * WAVE_EPSILON) - wave_height(direction - east * WAVE_EPSILON)) / (2.0 * WAVE_EPSILON);
+        let slope_south = (wave_height(direction + south * WAVE_EPSILON) - wave_height(direction - south * WAVE_EPSILON)) / (2.0 * WAVE_EPSILON);
+        let model = mat4_to_mat3(&uniforms.model_matrix);
+        let tangent = (model * east).normalize();
+        let bitangent = (model * south).normalize();
+        (fragment.normal - (tangent * slope_east + bitangent * slope_south) * WAVE_STRENGTH).normalize()
+    };
+
+    // Más claro donde las olas suben, más profundo en los valles
+    let depth = (wave_height(direction) * 0.5 + 0.5).clamp(0.0, 1.0);
+    let base_color = Color::from_hex(WATER_WORLD_DEEP).lerp(&Color::from_hex(WATER_WORLD_SHALLOW), depth);
+    let lit = apply_lighting(base_color, &Fragment { normal, ..*fragment }, &light);
+
+    // Blinn-Phong: el brillo sigue al vector medio entre la luz y la cámara
+    let light_direction = (light.position - fragment.world_position).normalize();
+    let view_direction = (uniforms.view_pos - fragment.world_position).normalize();
+    if normal.dot(&light_direction) <= 0.0 {
+        return lit;
+    }
+    let half_vector = (light_direction + view_direction).normalize();
+    let specular = normal.dot(&half_vector).max(0.0).powf(WATER_SHININESS) * WATER_SPECULAR * light.visibility;
+    lit + light.color * specular
+}
+
 fn mars_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, u32) {
     let noise_value = uniforms.noise.get_noise_2d(fragment.vertex_position.x, fragment.vertex_position.y);
     
@@ -305,13 +586,14 @@ fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         fragment.vertex_position.y * zoom,
     );
 
-    // Mezclar colores basado en el ruido
+    // Mezclar colores basado en el ruido; el contraste del cuerpo separa más las bandas
     let lerp_factor = noise_value.clamp(0.0, 1.0);
     let base_color = if lerp_factor < 0.5 {
         warm_yellow.lerp(&soft_orange, lerp_factor * 2.0)
     } else {
         soft_orange.lerp(&light_beige, (lerp_factor - 0.5) * 2.0)
-    };
+    }
+    .contrast(uniforms.contrast);
 
     // Normalizar la normal del fragmento
     let normal = fragment.normal.normalize();
@@ -395,6 +677,25 @@ fn venus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   base_color.lerp(&cloud_color, noise_value.abs())
 }
 
+// Neptuno: azul profundo con bandas tenues por latitud que se corren con el tiempo y vetas de
+// nubes claras donde el ruido es alto. La iluminación va aparte, con Fresnel.
+fn neptune_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let deep_blue = Color::new(40, 70, 170);
+    let azure = Color::new(70, 120, 220);
+    let cloud = Color::new(210, 225, 255);
+
+    let latitude = fragment.vertex_position.y;
+    let noise_value = uniforms.noise.get_noise_2d(
+        fragment.vertex_position.x * 6.0 + uniforms.time * 0.05,
+        latitude * 6.0,
+    );
+
+    let band = ((latitude * 9.0 + noise_value * 0.8).sin() * 0.5 + 0.5) * 0.6;
+    let base_color = deep_blue.lerp(&azure, band);
+    let streak = ((noise_value - 0.55) / 0.45).clamp(0.0, 1.0);
+    base_color.lerp(&cloud, streak)
+}
+
 fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Colores para las capas gaseosas de Urano
     let light_blue = Color::new(173, 216, 230);   // Azul claro
@@ -404,7 +705,7 @@ fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Configuración del ruido para las capas de gas
     let zoom = 8.0;
     let noise_value = uniforms.noise.get_noise_2d(
-        fragment.vertex_position.x * zoom + uniforms.time as f32 * 0.1, // Añade tiempo para simular movimiento
+        fragment.vertex_position.x * zoom + uniforms.time * 0.1, // Añade tiempo para simular movimiento
         fragment.vertex_position.y * zoom,
     );
 
@@ -443,4 +744,80 @@ fn uranus_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let combined_color = ambient_color + lit_color;
 
     combined_color
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use crate::{Framebuffer, render};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    // Con la cámara en (0, 0, 10), un vértice a una millonésima de su plano tiene |w| ≈ 1e-6 (sin
+    // la marca proyectaría a un millón de píxeles) y uno en z = 12 queda detrás: sus triángulos se
+    // descartan sin pánico y sin escribir un solo píxel
+    #[test]
+    fn unprojected_vertices() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let uniforms = test_uniforms(seeds);
+        let corner = |x: f32, y: f32, z: f32| Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+
+        let on_plane = vertex_shader(&corner(1.0, 0.0, 9.999_999), &uniforms);
+        let p = on_plane.transformed_position;
+        if !(on_plane.unprojected && p.x.is_finite() && p.y.is_finite() && p.z.is_finite()) {
+            return Err(format!("el vértice con w casi 0 quedó en {:?} sin marcar", p));
+        }
+        if vertex_shader(&corner(0.0, 0.0, 0.0), &uniforms).unprojected {
+            return Err("un vértice delante de la cámara quedó marcado".to_string());
+        }
+
+        for (name, far_corner) in [("en el plano de la camara", corner(1.0, 0.0, 9.999_999)), ("detras de la camara", corner(1.0, 0.0, 12.0))] {
+            let vertices = vec![corner(-1.0, -1.0, 0.0), corner(1.0, -1.0, 0.0), far_corner];
+            let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+            framebuffer.clear();
+            let before = framebuffer.buffer.clone();
+            panic::catch_unwind(AssertUnwindSafe(|| render(&mut framebuffer, &uniforms, &vertices, 1)))
+                .map_err(|_| format!("render entro en panico con un vertice {}", name))?;
+            if framebuffer.buffer != before {
+                return Err(format!("un triangulo con un vertice {} escribio pixeles", name));
+            }
+        }
+        Ok(())
+    }
+
+    // El mundo oceánico en una grilla de puntos alrededor de `normal`, a 10 unidades del Sol: cerca
+    // del punto subsolar, con la cámara del lado del Sol, el brillo de las crestas supera con creces
+    // al que se ve de costado; cerca del terminador (donde el difuso no satura) las olas se mueven
+    // con el tiempo
+    #[test]
+    fn water_world() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let brightest = |normal: Vec3, view_pos: Vec3, time: f32| -> (u32, Vec<u32>) {
+            let mut uniforms = test_uniforms(seeds);
+            uniforms.view_pos = view_pos;
+            uniforms.time = time;
+            let colors: Vec<u32> = (0..64)
+                .map(|step| {
+                    let offset = Vec3::new((step / 8) as f32 * 0.02 - 0.07, (step % 8) as f32 * 0.02 - 0.07, 0.0);
+                    let fragment = Fragment::new(Vec2::new(400.0, 300.0), Color::new(100, 100, 100), 7.0, normal, 1.0, (normal + offset).normalize(), Vec3::new(10.0, 0.0, 0.0));
+                    select_shader(12, &fragment, &uniforms).to_hex()
+                })
+                .collect();
+            let peak = colors.iter().map(|&pixel| (pixel >> 16 & 0xFF) + (pixel >> 8 & 0xFF) + (pixel & 0xFF)).max().unwrap_or(0);
+            (peak, colors)
+        };
+
+        let sunward = Vec3::new(-1.0, 0.0, 0.0);
+        let (glint, _) = brightest(sunward, Vec3::new(0.0, 0.0, 0.0), 0.0);
+        let (side, _) = brightest(sunward, Vec3::new(10.0, 0.0, 10.0), 0.0);
+        if glint < side + 150 {
+            return Err(format!("brillo de frente {} y de costado {} (suma de canales)", glint, side));
+        }
+        let terminator = Vec3::new(0.0, 0.0, 1.0);
+        if brightest(terminator, Vec3::new(10.0, 0.0, 10.0), 0.0).1 == brightest(terminator, Vec3::new(10.0, 0.0, 10.0), 2000.0).1 {
+            return Err("las olas no se movieron con el tiempo".to_string());
+        }
+        Ok(())
+    }
+}
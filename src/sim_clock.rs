@@ -0,0 +1,175 @@
+// Reloj de la simulación, el único dueño del tiempo. Se acumula en f64: sumando pasos en f32, a
+// tiempos grandes cada suma redondea (y a partir de 2^24 ticks un paso de 1 ya no avanza), y las
+// fases de las órbitas se irían corriendo. Cada cuadro toma `elapsed` para las posiciones de los
+// cuerpos y lo que se mide con ellas, y los shaders reciben `shader_time`, acotado para que no
+// pierda decimales. Lo que mueve el tiempo a mano (la barra, el buscador, la vista de referencia)
+// lo hace con `set`.
+//
+// La época es el tiempo 0, J2000, el mismo que el de las efemérides de `--spk` y el de la fecha
+// de la barra de búsqueda; `--epoch <años>` arranca esa cantidad de años de la Tierra después
+// (o antes, con un número negativo), así el modo abstracto y el de posiciones reales llevan la
+// misma cuenta.
+pub struct SimClock {
+    elapsed: f64,
+}
+
+// Período con que se repite el tiempo de los shaders (oleaje, bandas de Urano): hasta acá un f32
+// guarda de sobra los decimales que usan. Al darse la vuelta el patrón salta una vez.
+const SHADER_TIME_PERIOD: f64 = 65_536.0;
+
+impl SimClock {
+    pub fn new(start: f64) -> Self {
+        SimClock { elapsed: start }
+    }
+
+    // `year` es el año de la Tierra en unidades de tiempo de la simulación
+    pub fn from_args(args: &[String], year: f32) -> Result<Self, String> {
+        let years = match args.iter().position(|arg| arg == "--epoch") {
+            None => 0.0,
+            Some(position) => args
+                .get(position + 1)
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|years| years.is_finite())
+                .ok_or("--epoch espera los años de la Tierra desde J2000 en que arranca la simulacion, como 26.8")?,
+        };
+        Ok(SimClock::new(years * year as f64))
+    }
+
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn set(&mut self, time: f64) {
+        self.elapsed = time;
+    }
+
+    // Tiempo para `Uniforms::time`, en [0, SHADER_TIME_PERIOD)
+    pub fn shader_time(&self) -> f32 {
+        self.elapsed.rem_euclid(SHADER_TIME_PERIOD) as f32
+    }
+
+    pub fn advance(&mut self, step: f32) {
+        self.elapsed += step as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selftest::close_to;
+    use crate::{solar_system, body_position, orbit_offset, system_scene, place_bodies, seed_start_angles};
+    use crate::astronomy::next_opposition_time;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::sizes::SizeScale;
+
+    #[test]
+    fn start_phases() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        // Las fases que faltan salen de la semilla, siempre las mismas, y las dadas no se tocan
+        let mut planets = solar_system();
+        planets[3].start_angle = Some(1.0);
+        seed_start_angles(&mut planets, seeds);
+        let mut again = solar_system();
+        again[3].start_angle = Some(1.0);
+        seed_start_angles(&mut again, seeds);
+        let angles: Vec<f32> = planets.iter().map(|planet| planet.start_angle.unwrap_or(f32::NAN)).collect();
+        if angles != again.iter().map(|planet| planet.start_angle.unwrap_or(f32::NAN)).collect::<Vec<f32>>() || angles[3] != 1.0 {
+            return Err(format!("fases {:?}", angles));
+        }
+        if angles.iter().any(|angle| !(0.0..2.0 * std::f32::consts::PI).contains(angle)) || angles[1] == angles[2] {
+            return Err(format!("fases fuera de una vuelta o repetidas: {:?}", angles));
+        }
+        let position = body_position(&planets, 1, 0.0);
+        if (position - orbit_offset(&planets[1], angles[1])).magnitude() > 1e-4 {
+            return Err(format!("Mercurio arranca en {:?}, no en su fase de la época", position));
+        }
+
+        // Más de un millón de ticks en pasos de 0.37: el reloj no se desvía y la diferencia de fase
+        // entre Marte y Júpiter es la de la época más la de sus velocidades
+        let mut clock = SimClock::new(0.0);
+        let steps = 3_000_000;
+        for _ in 0..steps {
+            clock.advance(0.37);
+        }
+        let expected_time = steps as f64 * 0.37f32 as f64;
+        if (clock.elapsed() - expected_time).abs() > 1e-6 * expected_time {
+            return Err(format!("el reloj llegó a {} en vez de {}", clock.elapsed(), expected_time));
+        }
+        let (mars, jupiter) = (&planets[4], &planets[5]);
+        let tau = 2.0 * std::f64::consts::PI;
+        let difference = (jupiter.orbit_angle(clock.elapsed()) as f64 - mars.orbit_angle(clock.elapsed()) as f64).rem_euclid(tau);
+        let expected = (angles[5] as f64 - angles[4] as f64 + (jupiter.orbit_speed as f64 - mars.orbit_speed as f64) * expected_time).rem_euclid(tau);
+        let error = (difference - expected).abs();
+        if error.min(tau - error) > 1e-5 {
+            return Err(format!("después de {} la diferencia de fase es {} y debía ser {}", expected_time, difference, expected));
+        }
+
+        // Si el tiempo lo mueve otro (la barra, el buscador), el reloj sigue desde ahí
+        clock.set(5.0);
+        clock.advance(1.0);
+        if clock.elapsed() != 6.0 {
+            return Err(format!("tras mover el tiempo a 5 el reloj dio {}", clock.elapsed()));
+        }
+
+        let year = 2.0 * std::f32::consts::PI / 0.007;
+        let args: Vec<String> = ["--epoch", "2"].iter().map(|arg| arg.to_string()).collect();
+        if SimClock::from_args(&args, year).map(|clock| clock.elapsed()) != Ok(2.0 * year as f64) {
+            return Err("--epoch 2 no arranca dos años después de J2000".to_string());
+        }
+        if SimClock::from_args(&["--epoch".to_string(), "x".to_string()], year).is_ok() {
+            return Err("se aceptó --epoch x".to_string());
+        }
+
+        // La próxima oposición cuenta las fases de la época
+        let (earth, mars) = (&planets[3], &planets[4]);
+        let opposition = next_opposition_time(earth, mars, 0.0);
+        let gap = (earth.orbit_angle(opposition) - mars.orbit_angle(opposition)).rem_euclid(2.0 * std::f32::consts::PI);
+        if gap.min(2.0 * std::f32::consts::PI - gap) > 1e-3 {
+            return Err(format!("en la oposición la Tierra y Marte están a {} radianes", gap));
+        }
+        Ok(())
+    }
+
+    // A 10^9 unidades un f32 redondea de a 64: un paso de 1 no lo mueve. El reloj y la escena, en
+    // f64, siguen avanzando la fase de cada cuerpo exactamente lo que le toca por paso, y el tiempo de
+    // los shaders queda acotado.
+    #[test]
+    fn precise_time() -> Result<(), String> {
+        let planets = solar_system();
+        let mut clock = SimClock::new(1e9);
+        let time = clock.elapsed() as f32;
+        if time + 1.0 != time {
+            return Err("a 10^9 un paso de 1 no se pierde en f32; la prueba no prueba nada".to_string());
+        }
+        let tau = 2.0 * std::f64::consts::PI;
+        for step in 0..100 {
+            let before = clock.elapsed();
+            clock.advance(1.0);
+            let after = clock.elapsed();
+            if after - before != 1.0 {
+                return Err(format!("el paso {} avanzó {}", step, after - before));
+            }
+            for planet in planets.iter().filter(|planet| planet.orbit_speed > 0.0) {
+                let increment = (planet.orbit_angle(after) as f64 - planet.orbit_angle(before) as f64).rem_euclid(tau);
+                if (increment - planet.orbit_speed as f64).abs() > 2e-6 {
+                    return Err(format!("{} avanzó {} en el paso {}, se esperaba {}", planet.name, increment, step, planet.orbit_speed));
+                }
+            }
+        }
+
+        // Las posiciones de la escena salen del tiempo en f64: la Tierra se movió en esos 100 pasos
+        let mut scene = system_scene(&planets, &[])?;
+        let sizes = SizeScale::new();
+        place_bodies(&mut scene, &planets, clock.elapsed(), &sizes, 2.0);
+        let expected = orbit_offset(&planets[3], planets[3].orbit_angle(clock.elapsed()));
+        if !close_to(scene.world_position(3), expected) || close_to(expected, orbit_offset(&planets[3], planets[3].orbit_angle(1e9))) {
+            return Err(format!("la Tierra quedó en {:?}, se esperaba {:?}", scene.world_position(3), expected));
+        }
+
+        let shader_time = clock.shader_time();
+        if !(0.0..65_536.0).contains(&shader_time) || (shader_time - (1e9 + 100.0f64).rem_euclid(65_536.0) as f32).abs() > 1e-2 {
+            return Err(format!("tiempo de los shaders {}", shader_time));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use crate::Planet;
+
+// Radio real de cada cuerpo en radios terrestres. La Tierra mide 1.0 en la simulación, así que
+// son también sus radios en el modo de tamaños reales.
+const TRUE_RADII: [(&str, f32); 10] = [
+    ("Sol", 109.2),
+    ("Mercurio", 0.383),
+    ("Venus", 0.949),
+    ("Tierra", 1.0),
+    ("Marte", 0.532),
+    ("Júpiter", 11.21),
+    ("Saturno", 9.45),
+    ("Urano", 4.01),
+    ("Neptuno", 3.88),
+    ("Luna", 0.273),
+];
+
+// El Sol real se tragaría las órbitas de Mercurio (a 20) y de Venus: se queda en la mitad de la
+// de Mercurio
+const SUN_MAX_RADIUS: f32 = 10.0;
+
+// Duración del cambio de un modo al otro
+const TRANSITION_SECONDS: f32 = 1.0;
+
+// Proporción real respecto de la Tierra, si el cuerpo es del sistema solar (los sintéticos de
+// `--stress` no tienen)
+pub fn true_ratio(planet: &Planet) -> Option<f32> {
+    TRUE_RADII.iter().find(|(name, _)| *name == planet.name).map(|(_, ratio)| *ratio)
+}
+
+// Radio en el modo de tamaños reales: la proporción real, con el Sol recortado
+fn true_radius(planet: &Planet) -> f32 {
+    match true_ratio(planet) {
+        Some(ratio) if planet.parent.is_none() && planet.orbit_speed == 0.0 => ratio.min(SUN_MAX_RADIUS),
+        Some(ratio) => ratio,
+        None => planet.radius,
+    }
+}
+
+// Radio con que se dibuja el cuerpo con `blend` entre los tamaños didácticos (0) y los reales
+// (1). Todo lo que depende del tamaño (descarte, impostores, anillos, distancia de la cámara
+// al enfocar) lo toma de aquí.
+pub fn display_radius(planet: &Planet, blend: f32) -> f32 {
+    let t = blend.clamp(0.0, 1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+    planet.radius + (true_radius(planet) - planet.radius) * eased
+}
+
+// Modo de tamaños (Z o `toggle sizes`): los radios didácticos de la configuración o las
+// proporciones reales, con una transición de TRANSITION_SECONDS entre uno y otro
+pub struct SizeScale {
+    pub true_sizes: bool,
+    blend: f32,
+}
+
+impl SizeScale {
+    pub fn new() -> Self {
+        SizeScale { true_sizes: false, blend: 0.0 }
+    }
+
+    // Ya en el modo real, sin transición (`--true-sizes`)
+    pub fn true_sizes() -> Self {
+        SizeScale { true_sizes: true, blend: 1.0 }
+    }
+
+    pub fn toggle(&mut self) {
+        self.true_sizes = !self.true_sizes;
+    }
+
+    pub fn update(&mut self, delta_seconds: f32) {
+        let target = if self.true_sizes { 1.0 } else { 0.0 };
+        let step = delta_seconds / TRANSITION_SECONDS;
+        self.blend = if self.blend < target { (self.blend + step).min(target) } else { (self.blend - step).max(target) };
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.blend != if self.true_sizes { 1.0 } else { 0.0 }
+    }
+
+    pub fn radius(&self, planet: &Planet) -> f32 {
+        display_radius(planet, self.blend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec3, Mat4};
+    use crate::{Obj, Framebuffer, SPHERE_MODEL, render_body, solar_system};
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+    use crate::stress::stress_bodies;
+    use crate::uniforms::{create_model_matrix, create_view_matrix};
+
+    // Escenas de referencia en los dos modos: la Tierra y Júpiter desde la misma distancia cubren
+    // áreas en la proporción de sus radios al cuadrado (2 a 1 en el didáctico, 11.2 a 1 en el real).
+    // El cambio de modo dura un segundo y el Sol real queda recortado.
+    #[test]
+    fn true_sizes_and_transition() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let planets = solar_system();
+        let find = |name: &str| planets.iter().find(|planet| planet.name == name).ok_or(format!("no está {}", name));
+        let (sun, earth, jupiter) = (find("Sol")?, find("Tierra")?, find("Júpiter")?);
+        if display_radius(jupiter, 0.0) != jupiter.radius || (display_radius(jupiter, 1.0) - 11.21).abs() > 1e-4 {
+            return Err(format!("Júpiter mide {} y {}", display_radius(jupiter, 0.0), display_radius(jupiter, 1.0)));
+        }
+        if display_radius(sun, 1.0) >= planets[1].distance_from_sun || true_ratio(sun).unwrap_or(0.0) < 100.0 {
+            return Err(format!("el Sol real mide {} con la órbita de Mercurio a {}", display_radius(sun, 1.0), planets[1].distance_from_sun));
+        }
+        let synthetic = &stress_bodies(seeds, 1)[0];
+        if display_radius(synthetic, 1.0) != synthetic.radius || display_radius(earth, 0.5) != 1.0 {
+            return Err("un cuerpo sin proporción real cambió de tamaño".to_string());
+        }
+
+        let mut sizes = SizeScale::new();
+        sizes.toggle();
+        sizes.update(0.5);
+        let halfway = sizes.radius(jupiter);
+        if !sizes.is_transitioning() || halfway <= jupiter.radius || halfway >= 11.21 {
+            return Err(format!("a medio segundo Júpiter mide {}", halfway));
+        }
+        sizes.update(0.6);
+        if sizes.is_transitioning() || sizes.radius(jupiter) != display_radius(jupiter, 1.0) {
+            return Err("la transición no terminó después de un segundo".to_string());
+        }
+
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let vertices = obj.get_vertex_array();
+        let sphere_radius = obj.bounding_radius();
+        let eye = Vec3::new(0.0, 0.0, 40.0 * sphere_radius);
+        let coverage = |planet: &crate::Planet, sizes: &SizeScale| {
+            let radius = sizes.radius(planet);
+            let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+            let mut uniforms = test_uniforms(seeds);
+            uniforms.view_matrix = create_view_matrix(eye, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+            uniforms.view_pos = eye;
+            uniforms.model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), radius, Vec3::new(0.0, 0.0, 0.0));
+            render_body(&mut framebuffer, &uniforms, &vertices, planet.color_index, Vec3::new(0.0, 0.0, 0.0), radius * sphere_radius);
+            framebuffer.zbuffer.iter().filter(|depth| depth.is_finite()).count() as f32
+        };
+        for (mode, sizes) in [("didáctico", SizeScale::new()), ("real", SizeScale::true_sizes())] {
+            let expected = (sizes.radius(jupiter) / sizes.radius(earth)).powi(2);
+            let measured = coverage(jupiter, &sizes) / coverage(earth, &sizes).max(1.0);
+            if (measured / expected - 1.0).abs() > 0.1 {
+                return Err(format!("modo {}: Júpiter cubre {:.1} veces lo que la Tierra, se esperaban {:.1}", mode, measured, expected));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use image::DynamicImage;
+use nalgebra_glm::{Mat4, Vec3};
+use rayon::prelude::*;
+use std::path::Path;
+
+use crate::camera::{Camera, half_fov_angles};
+use crate::color::Color;
+use crate::framebuffer::BAND_HEIGHT;
+use crate::light::{LightProbe, sample_bilinear, sample_cube};
+use crate::texture::spherical_uv;
+use crate::{Framebuffer, SKYBOX_TEXTURE};
+
+// Archivos de las seis caras de un cubemap dentro de su carpeta, en el orden de la sonda de luz
+// (+X, -X, +Y, -Y, +Z, -Z)
+const CUBEMAP_FACES: [&str; 6] = ["px.png", "nx.png", "py.png", "ny.png", "pz.png", "nz.png"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkyboxFormat {
+    Equirectangular,
+    Cubemap,
+}
+
+// Fondo de la escena
+pub enum Skybox {
+    // Una imagen: en pantalla se estira sobre la ventana como siempre, y donde hace falta el cielo
+    // entero (la sonda de luz, el modo warp) se lee como equirectangular
+    Image(DynamicImage),
+    // Seis caras en el orden de CUBEMAP_FACES; gira con la cámara
+    Cubemap(Box<[DynamicImage; 6]>),
+    // Dos colores, arriba (+Y) y abajo, sin ninguna imagen
+    Gradient(Color, Color),
+}
+
+impl Skybox {
+    // Color del cielo en `direction` (no hace falta que sea unitaria)
+    pub fn sample(&self, direction: Vec3) -> Color {
+        match self {
+            Skybox::Image(image) => sample_bilinear(image, spherical_uv(direction)),
+            Skybox::Cubemap(faces) => sample_cube(faces, direction),
+            Skybox::Gradient(top, bottom) => gradient_color(*top, *bottom, direction),
+        }
+    }
+
+    pub fn light_probe(&self) -> LightProbe {
+        match self {
+            Skybox::Image(image) => LightProbe::capture(image),
+            _ => LightProbe::from_fn(|direction| self.sample(direction)),
+        }
+    }
+}
+
+fn gradient_color(top: Color, bottom: Color, direction: Vec3) -> Color {
+    let height = direction.normalize().y;
+    bottom.lerp(&top, (height + 1.0) * 0.5)
+}
+
+// Fondo elegido por línea de comandos: `--skybox <ruta>` (una imagen, o la carpeta de un cubemap
+// con `--skybox-format cubemap`) o `--skybox-gradient <arriba> <abajo>` con colores en hex, que
+// no necesita ningún archivo
+pub struct SkyboxConfig {
+    pub format: SkyboxFormat,
+    pub path: String,
+    pub gradient: Option<(Color, Color)>,
+}
+
+impl SkyboxConfig {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let value = |flag: &str| args.iter().position(|arg| arg == flag).map(|position| args.get(position + 1));
+
+        let format = match value("--skybox-format") {
+            None => SkyboxFormat::Equirectangular,
+            Some(Some(name)) if name == "equirectangular" => SkyboxFormat::Equirectangular,
+            Some(Some(name)) if name == "cubemap" => SkyboxFormat::Cubemap,
+            Some(Some(name)) => return Err(format!("--skybox-format espera equirectangular o cubemap, no '{}'", name)),
+            Some(None) => return Err("--skybox-format espera equirectangular o cubemap".to_string()),
+        };
+        let path = match value("--skybox") {
+            None => SKYBOX_TEXTURE.to_string(),
+            Some(Some(path)) => path.clone(),
+            Some(None) => return Err("--skybox espera la ruta de una imagen o de la carpeta de un cubemap".to_string()),
+        };
+        if format == SkyboxFormat::Cubemap && path == SKYBOX_TEXTURE {
+            return Err("--skybox-format cubemap necesita --skybox con la carpeta de las caras".to_string());
+        }
+
+        let gradient = match args.iter().position(|arg| arg == "--skybox-gradient") {
+            None => None,
+            Some(position) => {
+                let color = |index: usize| {
+                    args.get(position + index)
+                        .and_then(|hex| u32::from_str_radix(hex.trim_start_matches('#'), 16).ok())
+                        .map(Color::from_hex)
+                        .ok_or_else(|| "--skybox-gradient espera dos colores en hex (arriba y abajo), como 101838 000000".to_string())
+                };
+                Some((color(1)?, color(2)?))
+            }
+        };
+
+        Ok(SkyboxConfig { format, path, gradient })
+    }
+
+    pub fn load(&self) -> Result<Skybox, String> {
+        if let Some((top, bottom)) = self.gradient {
+            return Ok(Skybox::Gradient(top, bottom));
+        }
+        let open = |path: &Path| image::open(path).map_err(|error| format!("{}: {}", path.display(), error));
+        match self.format {
+            SkyboxFormat::Equirectangular => Ok(Skybox::Image(open(Path::new(&self.path))?)),
+            SkyboxFormat::Cubemap => {
+                let directory = Path::new(&self.path);
+                let [px, nx, py, ny, pz, nz] = CUBEMAP_FACES.map(|face| open(&directory.join(face)));
+                Ok(Skybox::Cubemap(Box::new([px?, nx?, py?, ny?, pz?, nz?])))
+            }
+        }
+    }
+}
+
+// Dibuja el fondo según la dirección que ve cada píxel con la cámara y la proyección del cuadro,
+// con profundidad infinita
+pub fn render_by_direction(framebuffer: &mut Framebuffer, camera: &Camera, projection: &Mat4, sky: impl Fn(Vec3) -> Color + Sync) {
+    paint_by_direction(framebuffer, camera, projection, |direction| Some((sky(direction), f32::INFINITY)));
+}
+
+// Recorre la pantalla con la dirección (no unitaria) que ve cada píxel; donde `paint` devuelve
+// color y profundidad los escribe, sin prueba de profundidad
+pub fn paint_by_direction(framebuffer: &mut Framebuffer, camera: &Camera, projection: &Mat4, paint: impl Fn(Vec3) -> Option<(Color, f32)> + Sync) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let (half_fov_x, half_fov_y) = half_fov_angles(projection);
+    let (tan_x, tan_y) = (half_fov_x.tan(), half_fov_y.tan());
+    let forward = (camera.center - camera.eye).normalize();
+    let right = forward.cross(&camera.up).normalize();
+    let up = right.cross(&forward);
+
+    framebuffer.split_rows_mut(BAND_HEIGHT).into_par_iter().for_each(|mut band| {
+        for y in band.rows() {
+            let ndc_y = 1.0 - 2.0 * y as f32 / (height - 1) as f32;
+            for x in 0..width {
+                let ndc_x = 2.0 * x as f32 / (width - 1) as f32 - 1.0;
+                let direction = forward + right * (ndc_x * tan_x) + up * (ndc_y * tan_y);
+
+                if let Some((color, depth)) = paint(direction) {
+                    band.paint(x, y, color.to_hex(), depth);
+                }
+            }
+        }
+    });
+}
+
+// Cubemap: cada píxel toma la cara del eje dominante de su rayo y la muestrea donde la atraviesa
+pub fn render_skybox_cubemap(framebuffer: &mut Framebuffer, faces: &[DynamicImage; 6], camera: &Camera, projection: &Mat4) {
+    render_by_direction(framebuffer, camera, projection, |direction| sample_cube(faces, direction));
+}
+
+// Cielo de dos colores según la altura del rayo de cada píxel
+pub fn render_skybox_gradient(framebuffer: &mut Framebuffer, top: Color, bottom: Color, camera: &Camera, projection: &Mat4) {
+    render_by_direction(framebuffer, camera, projection, |direction| gradient_color(top, bottom, direction));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uniforms::{create_perspective_matrix, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE};
+
+    // Cada cara del cubemap es de un color: el centro de la pantalla muestra la que mira la cámara.
+    // El degradado va del color de abajo al de arriba, y las opciones mal escritas se rechazan.
+    #[test]
+    fn skybox_formats() -> Result<(), String> {
+        let colors = [0xFF0000, 0x00FF00, 0x0000FF, 0xFFFF00, 0xFF00FF, 0x00FFFF];
+        let faces: [image::DynamicImage; 6] = colors.map(|hex| {
+            let color = Color::from_hex(hex);
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(4, 4, image::Rgba([color.r, color.g, color.b, 255])))
+        });
+        let (width, height) = (64, 48);
+        let projection = create_perspective_matrix(width as f32, height as f32, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE);
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+
+        for (face, direction) in [(0, Vec3::new(1.0, 0.0, 0.0)), (1, Vec3::new(-1.0, 0.0, 0.0)), (4, Vec3::new(0.0, 0.0, 1.0)), (5, Vec3::new(0.0, 0.0, -1.0))] {
+            let mut framebuffer = Framebuffer::new(width, height);
+            render_skybox_cubemap(&mut framebuffer, &faces, &Camera::new(origin, direction, up), &projection);
+            let center = framebuffer.buffer[height / 2 * width + width / 2];
+            if center != colors[face] {
+                return Err(format!("mirando hacia {:?} se ve {:06X} y no la cara {} ({:06X})", direction, center, face, colors[face]));
+            }
+        }
+
+        let mut framebuffer = Framebuffer::new(width, height);
+        render_skybox_gradient(&mut framebuffer, Color::new(255, 255, 255), Color::black(), &Camera::new(origin, Vec3::new(0.0, 0.0, -1.0), up), &projection);
+        let (top, middle, bottom) = (framebuffer.buffer[width / 2], framebuffer.buffer[height / 2 * width + width / 2], framebuffer.buffer[(height - 1) * width + width / 2]);
+        if !(top > middle && middle > bottom) || (Color::from_hex(middle).r as i32 - 128).abs() > 2 {
+            return Err(format!("degradado arriba {:06X}, al medio {:06X} y abajo {:06X}", top, middle, bottom));
+        }
+
+        let args = |line: &str| line.split_whitespace().map(str::to_string).collect::<Vec<String>>();
+        let default = SkyboxConfig::from_args(&args("SpaceTravel"))?;
+        if default.format != SkyboxFormat::Equirectangular || default.path != SKYBOX_TEXTURE || default.gradient.is_some() {
+            return Err("el fondo por omisión no es la imagen de siempre".to_string());
+        }
+        let gradient = SkyboxConfig::from_args(&args("SpaceTravel --skybox-gradient 102030 #FFFFFF"))?.load()?;
+        match &gradient {
+            Skybox::Gradient(top, bottom) if top.to_hex() == 0x102030 && bottom.to_hex() == 0xFFFFFF => {}
+            _ => return Err("--skybox-gradient no dio el degradado pedido".to_string()),
+        }
+        if gradient.light_probe().sample(up).to_hex() != 0x102030 {
+            return Err("la sonda de luz del degradado no ve arriba el color de arriba".to_string());
+        }
+        for bad in ["--skybox-format fisheye", "--skybox-format cubemap", "--skybox-gradient 102030", "--skybox"] {
+            if SkyboxConfig::from_args(&args(&format!("SpaceTravel {}", bad))).is_ok() {
+                return Err(format!("se aceptó '{}'", bad));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+use nalgebra_glm::Vec3;
+use std::f64::consts::PI;
+
+// Identificadores NAIF de los cuerpos y su distancia media real (km) al centro de su órbita,
+// para pasar las posiciones de SPICE a las distancias didácticas de la simulación
+const NAIF_BODIES: [(&str, i32, f64); 10] = [
+    ("Sol", 10, 0.0),
+    ("Mercurio", 199, 57_909_050.0),
+    ("Venus", 299, 108_208_000.0),
+    ("Tierra", 399, 149_598_023.0),
+    ("Marte", 499, 227_939_200.0),
+    ("Júpiter", 599, 778_570_000.0),
+    ("Saturno", 699, 1_433_530_000.0),
+    ("Urano", 799, 2_875_040_000.0),
+    ("Neptuno", 899, 4_495_060_000.0),
+    ("Luna", 301, 384_399.0),
+];
+
+// Baricentro del sistema solar: a menos de dos radios solares del Sol, vale como su centro
+const SOLAR_SYSTEM_BARYCENTER: i32 = 0;
+
+// Marcos de referencia de SPICE que se saben convertir al plano de la simulación
+const FRAME_J2000: i32 = 1;
+const FRAME_ECLIPJ2000: i32 = 17;
+// Oblicuidad de la eclíptica en J2000 (IAU 1976), en grados
+const J2000_OBLIQUITY: f64 = 23.439_291_1;
+
+// Tipos de segmento SPK: diferencias modificadas (1) y Chebyshev de posición (2)
+const SPK_TYPE_1: i32 = 1;
+const SPK_TYPE_2: i32 = 2;
+
+// Un año de la Tierra de la simulación (2π / 0.007 ticks, con su `orbit_speed`) es un año
+// juliano, y el tiempo 0 es J2000 (1 de enero de 2000 a mediodía)
+const EARTH_ORBIT_SPEED: f64 = 0.007;
+const JULIAN_YEAR_SECONDS: f64 = 365.25 * 86_400.0;
+
+// Segundos después de J2000 (tiempo de efemérides) que corresponden al tiempo de la simulación
+pub fn ephemeris_time(time: f64) -> f64 {
+    time * EARTH_ORBIT_SPEED / (2.0 * PI) * JULIAN_YEAR_SECONDS
+}
+
+pub fn naif_id(name: &str) -> Option<i32> {
+    NAIF_BODIES.iter().find(|(body, _, _)| *body == name).map(|(_, id, _)| *id)
+}
+
+fn real_distance_km(id: i32) -> Option<f64> {
+    NAIF_BODIES.iter().find(|(_, body, _)| *body == id).map(|(_, _, km)| *km).filter(|km| *km > 0.0)
+}
+
+// Una línea del bloque SUMMARY y los números de su ARRAY
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpkSegment {
+    pub target: i32,
+    pub center: i32,
+    pub frame: i32,
+    pub data_type: i32,
+    pub start_et: f64,
+    pub end_et: f64,
+    pub data: Vec<f64>,
+}
+
+impl SpkSegment {
+    // Posición (km, en el marco del segmento, relativa a `center`) en `et`, si el segmento lo cubre
+    pub fn position(&self, et: f64) -> Option<[f64; 3]> {
+        if et < self.start_et || et > self.end_et {
+            return None;
+        }
+        match self.data_type {
+            SPK_TYPE_2 => chebyshev_position(&self.data, et),
+            // El tipo 1 (diferencias modificadas de los integradores de JPL) se reconoce pero
+            // todavía no se evalúa: quien lo pida usa la órbita de la simulación
+            SPK_TYPE_1 => None,
+            _ => None,
+        }
+    }
+}
+
+// Tipo 2: registros [MID, RADIUS, coeficientes de X, de Y y de Z] de RSIZE números, seguidos de
+// INIT, INTLEN, RSIZE y N. Cada registro cubre INTLEN segundos desde INIT.
+fn chebyshev_position(data: &[f64], et: f64) -> Option<[f64; 3]> {
+    let [init, interval, record_size, records] = data.get(data.len().checked_sub(4)?..)? else {
+        return None;
+    };
+    let (record_size, records) = (*record_size as usize, *records as usize);
+    let coefficients = record_size.checked_sub(2)? / 3;
+    if *interval <= 0.0 || records == 0 || coefficients == 0 || data.len() < record_size * records + 4 {
+        return None;
+    }
+
+    let index = (((et - init) / interval).floor().max(0.0) as usize).min(records - 1);
+    let record = &data[index * record_size..(index + 1) * record_size];
+    let (mid, radius) = (record[0], record[1]);
+    if radius <= 0.0 {
+        return None;
+    }
+    let s = ((et - mid) / radius).clamp(-1.0, 1.0);
+
+    let mut position = [0.0; 3];
+    for (axis, value) in position.iter_mut().enumerate() {
+        let start = 2 + axis * coefficients;
+        *value = chebyshev(&record[start..start + coefficients], s);
+    }
+    Some(position)
+}
+
+// Σ cₖ Tₖ(s) por la recurrencia Tₖ₊₁ = 2s Tₖ - Tₖ₋₁
+fn chebyshev(coefficients: &[f64], s: f64) -> f64 {
+    let (mut previous, mut current) = (1.0, s);
+    let mut sum = coefficients[0];
+    for &coefficient in &coefficients[1..] {
+        sum += coefficient * current;
+        (previous, current) = (current, 2.0 * s * current - previous);
+    }
+    sum
+}
+
+// Archivo SPK en texto. Las SPK reales son binarias (DAF); este formato sigue su estructura
+// con bloques legibles:
+//
+//   BEGIN_COMMENT                         texto libre (origen de los datos, cobertura...)
+//   END_COMMENT
+//   BEGIN_SUMMARY                         una línea por segmento:
+//   399 10 1 2 -3.2e8 3.2e8                 objetivo centro marco tipo inicio fin (ET)
+//   END_SUMMARY
+//   BEGIN_ARRAY 1                         los números del segmento 1, en el orden de la DAF
+//   ...
+//   END_ARRAY
+//
+// Los números admiten el exponente con D de Fortran (1.5D+08).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpkFile {
+    pub comment: String,
+    pub segments: Vec<SpkSegment>,
+}
+
+impl SpkFile {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        Self::parse(&text).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut comment = String::new();
+        let mut summaries: Vec<[f64; 6]> = Vec::new();
+        let mut arrays: Vec<(usize, Vec<f64>)> = Vec::new();
+        let mut lines = text.lines().enumerate();
+
+        while let Some((number, line)) = lines.next() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["BEGIN_COMMENT"] => {
+                    for (_, line) in lines.by_ref().take_while(|(_, line)| line.trim() != "END_COMMENT") {
+                        comment.push_str(line);
+                        comment.push('\n');
+                    }
+                }
+                ["BEGIN_SUMMARY"] => {
+                    for (number, line) in lines.by_ref().take_while(|(_, line)| line.trim() != "END_SUMMARY") {
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let values = parse_numbers(line).map_err(|error| format!("línea {}: {}", number + 1, error))?;
+                        let summary: [f64; 6] = values
+                            .try_into()
+                            .map_err(|_| format!("línea {}: un resumen lleva objetivo, centro, marco, tipo, inicio y fin", number + 1))?;
+                        summaries.push(summary);
+                    }
+                }
+                ["BEGIN_ARRAY", index] => {
+                    let index = index
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|index| *index > 0)
+                        .ok_or_else(|| format!("línea {}: número de segmento inválido '{}'", number + 1, index))?;
+                    let mut data = Vec::new();
+                    for (number, line) in lines.by_ref().take_while(|(_, line)| line.trim() != "END_ARRAY") {
+                        data.extend(parse_numbers(line).map_err(|error| format!("línea {}: {}", number + 1, error))?);
+                    }
+                    arrays.push((index - 1, data));
+                }
+                _ => return Err(format!("línea {}: se esperaba un bloque, no '{}'", number + 1, line.trim())),
+            }
+        }
+
+        let mut segments: Vec<SpkSegment> = summaries
+            .iter()
+            .map(|summary| SpkSegment {
+                target: summary[0] as i32,
+                center: summary[1] as i32,
+                frame: summary[2] as i32,
+                data_type: summary[3] as i32,
+                start_et: summary[4],
+                end_et: summary[5],
+                data: Vec::new(),
+            })
+            .collect();
+        for (index, data) in arrays {
+            let count = segments.len();
+            let segment = segments
+                .get_mut(index)
+                .ok_or_else(|| format!("el segmento {} no está en el resumen ({} segmentos)", index + 1, count))?;
+            segment.data = data;
+        }
+        Ok(SpkFile { comment, segments })
+    }
+
+    // Los segmentos de `target` relativos a alguno de `centers` en un marco conocido, con la escala
+    // que lleva su distancia real a `orbit_radius` unidades de la simulación
+    pub fn body_ephemeris(&self, target: i32, centers: &[i32], orbit_radius: f32) -> Option<BodyEphemeris> {
+        let km_to_units = orbit_radius as f64 / real_distance_km(target)?;
+        let segments: Vec<SpkSegment> = self
+            .segments
+            .iter()
+            .filter(|segment| segment.target == target && centers.contains(&segment.center))
+            .filter(|segment| [FRAME_J2000, FRAME_ECLIPJ2000].contains(&segment.frame))
+            .cloned()
+            .collect();
+        if segments.is_empty() { None } else { Some(BodyEphemeris { segments, km_to_units }) }
+    }
+}
+
+// Centros aceptados para un cuerpo que orbita al Sol o a otro cuerpo
+pub fn orbit_centers(parent: Option<&str>) -> Vec<i32> {
+    match parent {
+        Some(name) => naif_id(name).into_iter().collect(),
+        None => vec![naif_id("Sol").unwrap_or(10), SOLAR_SYSTEM_BARYCENTER],
+    }
+}
+
+// Efemérides de un cuerpo ya pasadas a la simulación
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodyEphemeris {
+    segments: Vec<SpkSegment>,
+    km_to_units: f64,
+}
+
+impl BodyEphemeris {
+    // Posición respecto del centro de la órbita en el tiempo de la simulación, en el plano de la
+    // simulación (la eclíptica es XZ, con Y hacia el polo norte de la eclíptica)
+    pub fn offset(&self, time: f64) -> Option<Vec3> {
+        let et = ephemeris_time(time);
+        let (segment, [x, y, z]) = self.segments.iter().find_map(|segment| segment.position(et).map(|p| (segment, p)))?;
+        let (y, z) = if segment.frame == FRAME_J2000 {
+            // Ecuatorial a eclíptica: giro de la oblicuidad alrededor de X
+            let (sin, cos) = J2000_OBLIQUITY.to_radians().sin_cos();
+            (y * cos + z * sin, -y * sin + z * cos)
+        } else {
+            (y, z)
+        };
+        let scale = self.km_to_units;
+        Some(Vec3::new((x * scale) as f32, (z * scale) as f32, (y * scale) as f32))
+    }
+}
+
+fn parse_numbers(line: &str) -> Result<Vec<f64>, String> {
+    line.split_whitespace()
+        .map(|word| {
+            word.replace(['D', 'd'], "e")
+                .parse::<f64>()
+                .map_err(|_| format!("número inválido '{}'", word))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solar_system, orbit_offset, attach_ephemerides};
+
+    // SPK de prueba: la Tierra en dos registros de Chebyshev (tipo 2, eclíptica), la Luna en un
+    // registro en el ecuador de J2000 y Marte con un segmento de tipo 1, que todavía no se evalúa
+    const TEST_SPK: &str = "\
+BEGIN_COMMENT
+Segmentos sinteticos para el self-test
+END_COMMENT
+BEGIN_SUMMARY
+399 10 17 2 0.0 2000.0
+301 399 1 2 0.0 1000.0
+499 10 17 1 -1.0D+10 1.0D+10
+END_SUMMARY
+BEGIN_ARRAY 1
+500.0 500.0  1.0D+08 5.0D+07 0.0  0.0 1.0D+08 0.0  0.0 0.0 0.0
+1500.0 500.0  2.0D+08 0.0 0.0  1.0D+07 0.0 0.0  0.0 0.0 3.0D+06
+0.0 1000.0 11.0 2.0
+END_ARRAY
+BEGIN_ARRAY 2
+500.0 500.0  0.0  384399.0  0.0
+0.0 1000.0 5.0 1.0
+END_ARRAY
+BEGIN_ARRAY 3
+1.0 2.0 3.0
+END_ARRAY
+";
+
+    // Posiciones de Chebyshev en cada registro, el cambio de la eclíptica al plano de la simulación y
+    // el de J2000 a la eclíptica, y la órbita de siempre fuera de la cobertura o con tipo 1
+    #[test]
+    fn spk_text() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("spacetravel_selftest_{}.spk.txt", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        std::fs::write(&path, TEST_SPK).map_err(|error| format!("no se pudo escribir {}: {}", path, error))?;
+        let file = SpkFile::load(&path);
+        let samples = [
+            (399, 500.0, Some(Vec3::new(1e8, 0.0, 0.0))),
+            (399, 750.0, Some(Vec3::new(1.25e8, 1e8 * 0.5, 0.0))),
+            (399, 1750.0, Some(Vec3::new(2e8, 1e7, 3e6 * (2.0 * 0.25 - 1.0)))),
+            (399, 2500.0, None),
+            (499, 0.0, None),
+            (599, 500.0, None),
+        ];
+        let _ = std::fs::remove_file(&path);
+
+        let file = file?;
+        // Primer segmento del cuerpo que cubre el instante, en km relativos a su centro
+        let readings: Vec<Option<Vec3>> = samples
+            .iter()
+            .map(|(body, et, _)| {
+                let mut segments = file.segments.iter().filter(|segment| segment.target == *body);
+                let [x, y, z] = segments.find_map(|segment| segment.position(*et))?;
+                Some(Vec3::new(x as f32, y as f32, z as f32))
+            })
+            .collect();
+        if !file.comment.contains("sinteticos") || file.segments.len() != 3 {
+            return Err(format!("{} segmentos, comentario {:?}", file.segments.len(), file.comment));
+        }
+        for ((body, et, expected), reading) in samples.iter().zip(&readings) {
+            let matches = match (expected, reading) {
+                (Some(expected), Some(reading)) => (reading - expected).magnitude() <= expected.magnitude() * 1e-5,
+                (None, None) => true,
+                _ => false,
+            };
+            if !matches {
+                return Err(format!("cuerpo {} en {} s: {:?}, se esperaba {:?}", body, et, reading, expected));
+            }
+        }
+        if SpkFile::parse("BEGIN_SUMMARY\n399 10 17\nEND_SUMMARY\n").is_ok() || SpkFile::parse("hola\n").is_ok() {
+            return Err("se aceptó un archivo mal formado".to_string());
+        }
+
+        let mut planets = solar_system();
+        // Tierra, Luna y Marte: Marte tiene su segmento aunque no se pueda evaluar
+        let attached = attach_ephemerides(&mut planets, &file);
+        if attached != 3 {
+            return Err(format!("{} cuerpos con efemérides, se esperaban 3", attached));
+        }
+        let time_at = |et: f64| (et / ephemeris_time(1.0)) as f32;
+        let scale = |planet: usize, km: f32| planets[planet].distance_from_sun * km;
+        let obliquity = 23.439_291_f32.to_radians();
+        let moon = planets.iter().position(|planet| planet.name == "Luna").ok_or("no está la Luna")?;
+        let expected = [
+            (3, 1750.0, Vec3::new(scale(3, 2e8), scale(3, -1.5e6), scale(3, 1e7)) / 149_598_023.0),
+            (moon, 500.0, Vec3::new(0.0, -obliquity.sin(), obliquity.cos()) * planets[moon].distance_from_sun),
+        ];
+        for (index, et, expected) in expected {
+            let offset = planets[index].position_at(time_at(et) as f64);
+            if (offset - expected).magnitude() > 1e-3 * expected.magnitude() {
+                return Err(format!("{} en {} s está en {:?}, se esperaba {:?}", planets[index].name, et, offset, expected));
+            }
+        }
+        for (index, time) in [(3, time_at(5000.0)), (4, 10.0), (5, 10.0)] {
+            let planet = &planets[index];
+            if planet.position_at(time as f64) != orbit_offset(planet, planet.orbit_speed * time) {
+                return Err(format!("{} en {} no volvió a su órbita sin efemérides", planet.name, time));
+            }
+        }
+        Ok(())
+    }
+}
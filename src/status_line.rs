@@ -0,0 +1,92 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::Planet;
+
+// Intervalo mínimo entre dos líneas, para no saturar a un lector de pantalla
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+// Línea de estado en la salida estándar (`--status-line`) para que un lector de pantalla o un
+// log sigan la sesión: la vista, el cuerpo enfocado y la velocidad de la simulación. Solo se
+// escribe cuando algo cambia y como mucho una vez por segundo; si hubo varios cambios en ese
+// segundo se escribe el último en cuanto se cumple el intervalo.
+pub struct StatusLine {
+    pub enabled: bool,
+    last_written: String,
+    last_time: Option<Instant>,
+}
+
+impl StatusLine {
+    pub fn from_args(args: &[String]) -> Self {
+        StatusLine {
+            enabled: args.iter().any(|arg| arg == "--status-line"),
+            last_written: String::new(),
+            last_time: None,
+        }
+    }
+
+    // Texto que se escribiría ahora para `status`, si toca escribirlo
+    pub fn next_line(&mut self, status: &str, now: Instant) -> Option<String> {
+        if status == self.last_written {
+            return None;
+        }
+        if let Some(last_time) = self.last_time {
+            if now.duration_since(last_time) < MIN_INTERVAL {
+                return None;
+            }
+        }
+        self.last_written = status.to_string();
+        self.last_time = Some(now);
+        Some(self.last_written.clone())
+    }
+
+    pub fn update(&mut self, status: &str) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(line) = self.next_line(status, Instant::now()) {
+            let mut stdout = std::io::stdout();
+            // Si la salida está cerrada no hay a quién avisar
+            let _ = writeln!(stdout, "{}", line);
+            let _ = stdout.flush();
+        }
+    }
+}
+
+// Descripción del estado actual en una línea, con las mismas palabras que el HUD
+pub fn describe(view: &str, focused: Option<&Planet>, time_scale: f32, paused: bool) -> String {
+    let focus = focused.map_or("ninguno", |planet| planet.name);
+    let speed = if paused { "en pausa".to_string() } else { format!("x{}", time_scale) };
+    format!("vista: {}; enfoque: {}; velocidad: {}", view, focus, speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // La línea de estado solo se escribe al cambiar, como mucho una vez por segundo, y el último
+    // cambio no se pierde
+    #[test]
+    fn status_line() -> Result<(), String> {
+        let mut status = StatusLine::from_args(&[]);
+        let start = std::time::Instant::now();
+        let at = |millis: u64| start + std::time::Duration::from_millis(millis);
+
+        if status.next_line("vista: NAVE", at(0)).is_none() {
+            return Err("no se escribió el primer estado".to_string());
+        }
+        if status.next_line("vista: NAVE", at(2000)).is_some() {
+            return Err("se repitió un estado sin cambios".to_string());
+        }
+        if status.next_line("vista: Marte", at(2100)).is_none() {
+            return Err("no se escribió un cambio".to_string());
+        }
+        if status.next_line("vista: Venus", at(2500)).is_some() {
+            return Err("dos líneas en menos de un segundo".to_string());
+        }
+        if status.next_line("vista: Venus", at(3100)).as_deref() != Some("vista: Venus") {
+            return Err("el cambio retenido no se escribió al cumplirse el segundo".to_string());
+        }
+        Ok(())
+    }
+}
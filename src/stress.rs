@@ -0,0 +1,209 @@
+use nalgebra_glm::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::camera::Camera;
+use crate::hud::Contrast;
+use crate::motion_blur::MotionBlur;
+use crate::overlay::Overlay;
+use crate::seed::SeedSource;
+use crate::sizes::SizeScale;
+use crate::uniforms::{Displacement, create_noise};
+use crate::{Framebuffer, OverviewFrame, Planet, SceneAssets, initial_uniforms, place_bodies, render_overview, set_camera_matrices, solar_system, system_scene};
+
+// Órbitas de los cuerpos sintéticos: de dentro de la de Mercurio hasta más allá de Urano
+const MIN_ORBIT: f32 = 15.0;
+const MAX_ORBIT: f32 = 200.0;
+const MIN_RADIUS: f32 = 0.2;
+const MAX_RADIUS: f32 = 1.5;
+const MIN_ORBIT_SPEED: f32 = 0.001;
+const MAX_ORBIT_SPEED: f32 = 0.01;
+const MAX_OBLATENESS: f32 = 0.1;
+
+// Shaders de planeta que se pueden repetir: no el del Sol, ni el de la Tierra (se deja para el
+// final del cuadro por sus reflejos, y solo uno), ni el de la Luna (usa sus propios mapas). El
+// 12 es el mundo oceánico, que no tiene planeta en el sistema solar.
+const MATERIALS: [usize; 7] = [1, 2, 4, 5, 6, 7, 12];
+// Los rocosos (Mercurio, Venus, Marte) llevan relieve como sus planetas
+const ROCKY_MATERIALS: [usize; 3] = [1, 2, 4];
+
+// Cuadros medidos sin ventana, con el tamaño de la ventana
+const HEADLESS_FRAMES: usize = 60;
+const HEADLESS_WIDTH: usize = 800;
+const HEADLESS_HEIGHT: usize = 600;
+
+// Prueba de carga: `--stress <n>` añade n cuerpos sintéticos al sistema. Con `--headless` no
+// abre la ventana, mide HEADLESS_FRAMES cuadros y, con `--frame-budget <ms>`, falla si el
+// tiempo medio por cuadro lo supera (para CI).
+pub struct StressTest {
+    pub bodies: usize,
+    pub headless: bool,
+    pub frame_budget_ms: Option<f32>,
+}
+
+impl StressTest {
+    // `None` si no se pasó `--stress`
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let position = match args.iter().position(|arg| arg == "--stress") {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        let bodies = match args.get(position + 1) {
+            Some(value) => value
+                .parse::<usize>()
+                .map_err(|_| format!("--stress espera un número de cuerpos, no '{}'", value))?,
+            None => return Err("--stress espera un número de cuerpos".to_string()),
+        };
+
+        let frame_budget_ms = match args.iter().position(|arg| arg == "--frame-budget") {
+            Some(position) => match args.get(position + 1) {
+                Some(value) => Some(
+                    value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|ms| *ms > 0.0)
+                        .ok_or_else(|| format!("--frame-budget espera milisegundos, no '{}'", value))?,
+                ),
+                None => return Err("--frame-budget espera un valor".to_string()),
+            },
+            None => None,
+        };
+
+        Ok(Some(StressTest {
+            bodies,
+            headless: args.iter().any(|arg| arg == "--headless"),
+            frame_budget_ms,
+        }))
+    }
+}
+
+// `count` cuerpos con órbita, tamaño y material al azar, reproducibles con la semilla global.
+// Son planetas normales (orbitan el Sol), así que pasan por el mismo camino que los demás.
+pub fn stress_bodies(seeds: &SeedSource, count: usize) -> Vec<Planet> {
+    let mut rng = StdRng::seed_from_u64(seeds.derive("stress", 0));
+    (0..count)
+        .map(|index| {
+            let color_index = MATERIALS[rng.gen_range(0..MATERIALS.len())];
+            let displacement = if ROCKY_MATERIALS.contains(&color_index) {
+                Displacement::terrain(rng.gen_range(0.2..0.5), 5.0)
+            } else {
+                Displacement::none()
+            };
+            Planet {
+                // Los nombres viven lo que el programa, igual que los de `solar_system`
+                name: Box::leak(format!("S-{:03}", index + 1).into_boxed_str()),
+                distance_from_sun: rng.gen_range(MIN_ORBIT..MAX_ORBIT),
+                radius: rng.gen_range(MIN_RADIUS..MAX_RADIUS),
+                orbit_speed: rng.gen_range(MIN_ORBIT_SPEED..MAX_ORBIT_SPEED),
+                start_angle: None,
+                color_index,
+                displacement,
+                oblateness: rng.gen_range(0.0..MAX_OBLATENESS),
+                contrast: 1.0,
+                parent: None,
+                ephemeris: None,
+            }
+        })
+        .collect()
+}
+
+// Dibuja la vista general desde la posición inicial de la cámara durante HEADLESS_FRAMES
+// cuadros y escribe los contadores del último y el tiempo por cuadro. Solo se mide el paso de
+// los cuerpos (órbitas, cuerpos, anillos, nombres), que es lo que crece con su número.
+pub fn run_headless(test: &StressTest, seeds: &SeedSource, use_texture_cache: bool) -> bool {
+    let mut planets = solar_system();
+    planets.extend(stress_bodies(seeds, test.bodies));
+    let assets = SceneAssets::load(seeds, &planets, use_texture_cache, |_, _| {});
+
+    let camera = Camera::new(Vec3::new(50.0, 100.0, 250.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let noise = Arc::new(create_noise(seeds.noise_seed("default", 0)));
+    let mut uniforms = initial_uniforms(noise, HEADLESS_WIDTH, HEADLESS_HEIGHT, camera.eye);
+    let mut overlay = Overlay::new(HEADLESS_WIDTH, HEADLESS_HEIGHT);
+    let mut framebuffer = Framebuffer::new(HEADLESS_WIDTH, HEADLESS_HEIGHT);
+    let mut motion_blur = MotionBlur::new(planets.len());
+    let Ok(mut scene) = system_scene(&planets, &assets.ring_systems) else {
+        return false;
+    };
+    let sizes = SizeScale::new();
+
+    let mut frame_times = Vec::with_capacity(HEADLESS_FRAMES);
+    let mut visible = 0;
+    for frame in 0..HEADLESS_FRAMES {
+        let start = Instant::now();
+        framebuffer.clear();
+        set_camera_matrices(&mut uniforms, &camera, HEADLESS_WIDTH, HEADLESS_HEIGHT, None);
+        place_bodies(&mut scene, &planets, frame as f64, &sizes, assets.sphere_radius);
+        let overview = OverviewFrame { camera: &camera, scene: &scene, time: frame as f64, effective_time_scale: 1.0, contrast: Contrast::Normal, show_orbits: true, sizes: &sizes };
+        visible = render_overview(&mut framebuffer, &mut overlay, &mut uniforms, &planets, &assets, &mut motion_blur, overview);
+        overlay.composite(&mut framebuffer, 1.0);
+        frame_times.push(start.elapsed().as_secs_f32() * 1000.0);
+    }
+
+    let stats = framebuffer.fragment_stats;
+    let average = frame_times.iter().sum::<f32>() / frame_times.len() as f32;
+    let worst = frame_times.iter().cloned().fold(0.0, f32::max);
+    println!(
+        "cuerpos: {}  visibles: {}  triángulos: {}  fragmentos sombreados: {}",
+        planets.len(),
+        visible,
+        stats.triangles,
+        stats.shaded,
+    );
+    println!("{} cuadros de {}x{}: media {:.2} ms, peor {:.2} ms", HEADLESS_FRAMES, HEADLESS_WIDTH, HEADLESS_HEIGHT, average, worst);
+
+    match test.frame_budget_ms {
+        Some(budget) if average > budget => {
+            println!("FAIL: la media supera el presupuesto de {:.2} ms", budget);
+            false
+        }
+        Some(budget) => {
+            println!("PASS: dentro del presupuesto de {:.2} ms", budget);
+            true
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Obj, SPHERE_MODEL, render};
+    use crate::seed::DEFAULT_SEED;
+    use crate::selftest::{test_uniforms, WIDTH, HEIGHT};
+
+    // Los cuerpos de `--stress` se repiten con la misma semilla, cambian con otra y son planetas
+    // normales; y `render` cuenta los triángulos que envía al rasterizador
+    #[test]
+    fn stress_bodies_are_seeded() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let bodies = stress_bodies(seeds, 50);
+        if bodies.len() != 50 || bodies != stress_bodies(seeds, 50) {
+            return Err("los cuerpos cambian entre dos llamadas con la misma semilla".to_string());
+        }
+        if bodies == stress_bodies(&SeedSource::new(seeds.base.wrapping_add(1)), 50) {
+            return Err("otra semilla da los mismos cuerpos".to_string());
+        }
+        for body in &bodies {
+            // El Sol, la Tierra y la Luna tienen un trato aparte en el cuadro
+            if body.parent.is_some() || [0, 3, 10].contains(&body.color_index) || body.radius <= 0.0 || body.distance_from_sun <= 0.0 {
+                return Err(format!("{} no es un planeta normal (material {})", body.name, body.color_index));
+            }
+        }
+        let names: std::collections::HashSet<&str> = bodies.iter().map(|body| body.name).collect();
+        if names.len() != bodies.len() {
+            return Err("hay nombres repetidos".to_string());
+        }
+
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let vertices = obj.get_vertex_array();
+        let mut framebuffer = Framebuffer::new(WIDTH as usize, HEIGHT as usize);
+        render(&mut framebuffer, &test_uniforms(seeds), &vertices, 1);
+        let triangles = framebuffer.fragment_stats.triangles;
+        if triangles != vertices.len() / 3 {
+            return Err(format!("se contaron {} triángulos de {}", triangles, vertices.len() / 3));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+use nalgebra_glm::{Vec2, Vec3};
+use fastnoise_lite::FastNoiseLite;
+use image::{DynamicImage, GenericImageView, GrayImage, ImageResult, Luma, Rgb, RgbImage};
+use std::f32::consts::PI;
+use std::path::Path;
+use crate::color::Color;
+
+// Dimensión máxima por defecto al cargar texturas; las mayores se reducen al cargarlas
+pub const DEFAULT_MAX_TEXTURE_DIMENSION: u32 = 4096;
+
+// Altura del relieve (en radios del cuerpo por unidad de ruido) con que se calculan los mapas de normales
+const NORMAL_MAP_RELIEF: f32 = 0.02;
+
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    // Nivel 0 a resolución completa; cada nivel siguiente es la mitad del anterior hasta 1x1
+    levels: Vec<MipLevel>,
+}
+
+struct MipLevel {
+    width: u32,
+    height: u32,
+    data: Vec<u32>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: u32, y: u32) -> Color {
+        Color::from_hex(self.data[(y * self.width + x) as usize])
+    }
+
+    // Siguiente nivel: promedio de bloques de 2x2 (las dimensiones impares repiten el borde)
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut data = Vec::with_capacity((width * height) as usize);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = self.texel((x * 2 + dx).min(self.width - 1), (y * 2 + dy).min(self.height - 1));
+                    r += texel.r as u32;
+                    g += texel.g as u32;
+                    b += texel.b as u32;
+                }
+                data.push(Color::new((r / 4) as u8, (g / 4) as u8, (b / 4) as u8).to_hex());
+            }
+        }
+
+        MipLevel { width, height, data }
+    }
+}
+
+impl Texture {
+    pub fn from_heightmap(noise: &FastNoiseLite, zoom: f32, width: u32, height: u32) -> Self {
+        Texture::from_image(&image::DynamicImage::ImageLuma8(generate_heightmap(noise, zoom, width, height)))
+    }
+
+    pub fn load(file_path: &str) -> ImageResult<Self> {
+        Texture::load_with_max_dimension(file_path, DEFAULT_MAX_TEXTURE_DIMENSION)
+    }
+
+    // Carga la imagen reduciéndola si su lado mayor supera `max_dimension`,
+    // para que las texturas de 8k no ocupen memoria que nunca se va a muestrear
+    pub fn load_with_max_dimension(file_path: &str, max_dimension: u32) -> ImageResult<Self> {
+        let image = image::open(Path::new(file_path))?;
+        let (width, height) = image.dimensions();
+        if width.max(height) > max_dimension {
+            let resized = image.resize(max_dimension, max_dimension, image::imageops::FilterType::Triangle);
+            return Ok(Texture::from_image(&resized));
+        }
+        Ok(Texture::from_image(&image))
+    }
+
+    pub fn from_image(image: &image::DynamicImage) -> Self {
+        let (width, height) = image.dimensions();
+        let data = image
+            .pixels()
+            .map(|(_, _, pixel)| (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | (pixel[2] as u32))
+            .collect();
+
+        let mut levels = vec![MipLevel { width, height, data }];
+        while let Some(last) = levels.last() {
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        Texture { width, height, levels }
+    }
+
+    pub fn mip_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn mip_dimensions(&self, level: usize) -> (u32, u32) {
+        let mip = &self.levels[level.min(self.levels.len() - 1)];
+        (mip.width, mip.height)
+    }
+
+    // Elige el nivel según cuántos texeles caen en cada píxel de una esfera de radio
+    // `projected_radius` píxeles: en el centro del disco, un píxel abarca 1/r radianes
+    // y la textura reparte `width` texeles en 2π radianes de longitud
+    pub fn mip_level_for_radius(&self, projected_radius: f32) -> usize {
+        if projected_radius <= 0.0 {
+            return self.levels.len() - 1;
+        }
+
+        let texels_per_pixel = self.width as f32 / (2.0 * PI * projected_radius);
+        if texels_per_pixel <= 1.0 {
+            return 0;
+        }
+        (texels_per_pixel.log2().floor() as usize).min(self.levels.len() - 1)
+    }
+
+    // Muestreo bilineal en el nivel completo
+    pub fn sample(&self, uv: Vec2) -> Color {
+        self.sample_level(uv, 0)
+    }
+
+    // Muestreo bilineal dentro de un nivel; u se repite (longitud) y v se limita a los polos
+    pub fn sample_level(&self, uv: Vec2, level: usize) -> Color {
+        let mip = &self.levels[level.min(self.levels.len() - 1)];
+        let x = uv.x.rem_euclid(1.0) * mip.width as f32 - 0.5;
+        let y = uv.y.clamp(0.0, 1.0) * mip.height as f32 - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+
+        let wrap_x = |x: f32| (x as i32).rem_euclid(mip.width as i32) as u32;
+        let clamp_y = |y: f32| (y as i32).clamp(0, mip.height as i32 - 1) as u32;
+
+        let (left, right) = (wrap_x(x0), wrap_x(x0 + 1.0));
+        let (top, bottom) = (clamp_y(y0), clamp_y(y0 + 1.0));
+
+        let upper = mip.texel(left, top).lerp(&mip.texel(right, top), tx);
+        let lower = mip.texel(left, bottom).lerp(&mip.texel(right, bottom), tx);
+        upper.lerp(&lower, ty)
+    }
+
+    // Altura normalizada (0.0 a 1.0) a partir del canal rojo de un mapa en escala de grises
+    pub fn sample_height(&self, uv: Vec2) -> f32 {
+        self.sample(uv).r as f32 / 255.0
+    }
+
+    pub fn sample_height_level(&self, uv: Vec2, level: usize) -> f32 {
+        self.sample_level(uv, level).r as f32 / 255.0
+    }
+
+    // Normal en espacio tangente (x hacia +u, y hacia +v, z hacia afuera) de un mapa de normales
+    pub fn sample_normal_level(&self, uv: Vec2, level: usize) -> Vec3 {
+        let color = self.sample_level(uv, level);
+        let decode = |channel: u8| channel as f32 / 255.0 * 2.0 - 1.0;
+        let normal = Vec3::new(decode(color.r), decode(color.g), decode(color.b));
+        if normal.magnitude() < 1e-3 {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            normal.normalize()
+        }
+    }
+}
+
+// Coordenadas equirectangulares a partir de una dirección desde el centro de la esfera
+pub fn spherical_uv(direction: Vec3) -> Vec2 {
+    let direction = direction.normalize();
+    let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+    let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI;
+    Vec2::new(u, v)
+}
+
+// Inversa de `spherical_uv`: dirección unitaria que corresponde a unas coordenadas
+pub fn uv_to_direction(uv: Vec2) -> Vec3 {
+    let longitude = (uv.x - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - uv.y) * PI;
+    Vec3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.sin(),
+        latitude.cos() * longitude.sin(),
+    )
+}
+
+// Desplazamiento de UV por parallax: `view_dir` (hacia la cámara) en espacio tangente,
+// con x a lo largo de +u, y a lo largo de +v y z según la normal. Los puntos altos se
+// corren hacia el observador, más cuanto más rasante es la mirada.
+pub fn parallax_offset(view_dir: Vec3, height: f32, scale: f32) -> Vec2 {
+    // Limitar z evita desplazamientos enormes en el limbo del planeta
+    let z = view_dir.z.max(0.2);
+    Vec2::new(view_dir.x, view_dir.y) / z * (height * scale)
+}
+
+// Mapa de alturas equirectangular muestreando ruido 3D sobre la esfera
+// (así no hay costura en la longitud 180°)
+pub fn generate_heightmap(noise: &FastNoiseLite, zoom: f32, width: u32, height: u32) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        let direction = uv_to_direction(Vec2::new(
+            (x as f32 + 0.5) / width as f32,
+            (y as f32 + 0.5) / height as f32,
+        ));
+
+        let value = noise.get_noise_3d(direction.x * zoom, direction.y * zoom, direction.z * zoom);
+        Luma([((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0) as u8])
+    })
+}
+
+// Igual que `generate_heightmap`, guardando el resultado como PNG en escala de grises
+pub fn generate_heightmap_png(noise: &FastNoiseLite, zoom: f32, width: u32, height: u32, file_path: &str) -> ImageResult<()> {
+    let image = generate_heightmap(noise, zoom, width, height);
+
+    if let Some(parent) = Path::new(file_path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save(file_path)
+}
+
+// Mapa de normales equirectangular del relieve que describe el ruido sobre la esfera, para
+// iluminar el detalle sin evaluar el ruido en cada fragmento. La pendiente se calcula por
+// diferencias centrales a un texel de distancia hacia el este y hacia el sur; la normal queda
+// en espacio tangente (la misma base que `parallax_offset`) y se codifica de -1..1 a 0..255.
+pub fn generate_spherical_normal_map(noise: &FastNoiseLite, zoom: f32, width: u32, height: u32) -> DynamicImage {
+    let height_at = |direction: Vec3| noise.get_noise_3d(direction.x * zoom, direction.y * zoom, direction.z * zoom);
+    // Paso angular de un texel en latitud; se usa también en longitud para que la pendiente sea isótropa
+    let step = PI / height as f32;
+
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        let direction = uv_to_direction(Vec2::new(
+            (x as f32 + 0.5) / width as f32,
+            (y as f32 + 0.5) / height as f32,
+        ));
+
+        // Base tangente: este (+u) y sur (+v); en los polos el este no está definido
+        let east = Vec3::new(-direction.z, 0.0, direction.x);
+        let normal = if east.magnitude() < 1e-4 {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            let east = east.normalize();
+            let south = direction.cross(&east);
+            let slope = |axis: Vec3| {
+                let ahead = (direction + axis * step).normalize();
+                let behind = (direction - axis * step).normalize();
+                (height_at(ahead) - height_at(behind)) * NORMAL_MAP_RELIEF / (2.0 * step)
+            };
+            Vec3::new(-slope(east), -slope(south), 1.0).normalize()
+        };
+
+        let encode = |component: f32| ((component * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+        Rgb([encode(normal.x), encode(normal.y), encode(normal.z)])
+    });
+
+    DynamicImage::ImageRgb8(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moon_terrain_noise;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+
+    // Las normales del mapa deben ser unitarias, apuntar hacia afuera e inclinarse en contra de
+    // la pendiente del mapa de alturas hecho con el mismo ruido: donde el terreno sube hacia el
+    // este, la normal se inclina hacia el oeste (x negativa)
+    #[test]
+    fn normal_map() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let (width, height) = (128, 64);
+        let noise = moon_terrain_noise(seeds);
+        let normals = Texture::from_image(&generate_spherical_normal_map(&noise, 100.0, width, height));
+        let heights = generate_heightmap(&noise, 100.0, width, height);
+
+        let (mut compared, mut agreeing) = (0, 0);
+        for y in 1..height - 1 {
+            for x in 0..width {
+                let uv = Vec2::new((x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32);
+                let normal = normals.sample_normal_level(uv, 0);
+                if normal.z <= 0.0 {
+                    return Err(format!("normal hacia adentro en ({}, {})", x, y));
+                }
+
+                let east = heights.get_pixel((x + 1) % width, y)[0] as f32;
+                let west = heights.get_pixel((x + width - 1) % width, y)[0] as f32;
+                if (east - west).abs() >= 8.0 {
+                    compared += 1;
+                    if (normal.x < 0.0) == (east > west) {
+                        agreeing += 1;
+                    }
+                }
+            }
+        }
+
+        if compared == 0 {
+            return Err("el mapa de alturas no tiene pendientes para comparar".to_string());
+        }
+        if agreeing * 10 < compared * 9 {
+            return Err(format!("solo {} de {} pendientes coinciden con la normal", agreeing, compared));
+        }
+        Ok(())
+    }
+
+    // Cada nivel es la mitad del anterior (sin bajar de 1) hasta terminar en 1x1, también con
+    // dimensiones impares o una textura más ancha que alta
+    #[test]
+    fn mip_chain_dimensions() -> Result<(), String> {
+        for ((width, height), expected) in [
+            ((256, 128), vec![(256, 128), (128, 64), (64, 32), (32, 16), (16, 8), (8, 4), (4, 2), (2, 1), (1, 1)]),
+            ((5, 3), vec![(5, 3), (2, 1), (1, 1)]),
+            ((1, 1), vec![(1, 1)]),
+        ] {
+            let texture = Texture::from_image(&DynamicImage::ImageRgb8(RgbImage::new(width, height)));
+            let dimensions: Vec<(u32, u32)> = (0..texture.mip_count()).map(|level| texture.mip_dimensions(level)).collect();
+            if dimensions != expected {
+                return Err(format!("{}x{} da los niveles {:?}", width, height, dimensions));
+            }
+        }
+        Ok(())
+    }
+
+    // Con un texel por píxel o más grande se usa el nivel completo; cada vez que el disco se
+    // achica a la mitad se baja un nivel, y sin tamaño en pantalla se usa el de 1x1
+    #[test]
+    fn mip_level_selection() -> Result<(), String> {
+        let texture = Texture::from_image(&DynamicImage::ImageRgb8(RgbImage::new(1024, 512)));
+        let one_texel = 1024.0 / (2.0 * PI);
+        let last = texture.mip_count() - 1;
+        let cases = [
+            (one_texel * 3.0, 0),
+            (one_texel, 0),
+            (one_texel / 2.5, 1),
+            (one_texel / 4.5, 2),
+            (one_texel / 100.0, 6),
+            (1e-6, last),
+            (0.0, last),
+            (-1.0, last),
+        ];
+        for (radius, expected) in cases {
+            let level = texture.mip_level_for_radius(radius);
+            if level != expected {
+                return Err(format!("un disco de {:.2} px usa el nivel {}, se esperaba {}", radius, level, expected));
+            }
+        }
+        Ok(())
+    }
+
+    // El nivel de 1x1 es el color promedio de toda la textura
+    #[test]
+    fn smallest_mip_is_average_color() -> Result<(), String> {
+        // Cuadrantes de color liso: el nivel 1 tiene justo esos cuatro colores
+        let quadrants = [[200, 0, 40], [0, 100, 80], [40, 20, 0], [160, 0, 120]];
+        let image = RgbImage::from_fn(8, 8, |x, y| Rgb(quadrants[(y / 4 * 2 + x / 4) as usize]));
+        let texture = Texture::from_image(&DynamicImage::ImageRgb8(image));
+
+        let average = texture.sample_level(Vec2::new(0.5, 0.5), texture.mip_count() - 1);
+        if (average.r, average.g, average.b) != (100, 30, 60) {
+            return Err(format!("el nivel de 1x1 es {}, se esperaba (100, 30, 60)", average));
+        }
+        Ok(())
+    }
+}
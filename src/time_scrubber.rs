@@ -0,0 +1,143 @@
+use crate::Framebuffer;
+use crate::framebuffer::BarColors;
+use crate::hud::Contrast;
+use crate::sim_clock::SimClock;
+use crate::spice::ephemeris_time;
+use crate::time_slider::SliderLayout;
+
+// Años de la Tierra que abarca la barra, desde el tiempo 0 (J2000)
+const SPAN_YEARS: f32 = 50.0;
+// Cuadros que tarda una flecha mantenida en recorrer la barra entera
+const FRAMES_ACROSS: f32 = 300.0;
+const BAR_HEIGHT: usize = 6;
+const FILL_COLOR: u32 = 0xFFD54F;
+const EMPTY_COLOR: u32 = 0x404040;
+// Días del 1 de enero de 1970 al 1 de enero de 2000
+const DAYS_TO_2000: i64 = 10_957;
+
+// Barra para buscar un momento (T mantenida): muestra `time` entre 0 y `max_time` y las flechas
+// lo mueven. Mientras T está apretada el tiempo no avanza; al soltarla la simulación sigue desde
+// ahí. Una pulsación corta, sin mover el tiempo, sigue siendo la de la transferencia de Hohmann.
+pub struct TimeScrubber {
+    pub max_time: f32,
+    held: bool,
+    moved: bool,
+}
+
+impl TimeScrubber {
+    // `year` es el año de la Tierra en unidades de tiempo de la simulación
+    pub fn new(year: f32) -> Self {
+        TimeScrubber { max_time: SPAN_YEARS * year, held: false, moved: false }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.held
+    }
+
+    // Procesa un cuadro: `t_down` es la tecla T y `direction` las flechas (-1, 0 o 1), que mueven el
+    // reloj. Devuelve si T se acaba de soltar sin haber movido el tiempo, es decir, si fue una
+    // pulsación corta.
+    pub fn update(&mut self, t_down: bool, direction: f32, clock: &mut SimClock) -> bool {
+        if t_down {
+            if !self.held {
+                self.held = true;
+                self.moved = false;
+            }
+            if direction != 0.0 {
+                let step = (direction * self.max_time / FRAMES_ACROSS) as f64;
+                clock.set((clock.elapsed() + step).clamp(0.0, self.max_time as f64));
+                self.moved = true;
+            }
+            return false;
+        }
+        let tapped = self.held && !self.moved;
+        self.held = false;
+        tapped
+    }
+
+    pub fn fraction(&self, time: f32) -> f32 {
+        (time / self.max_time.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    // En el mismo lugar que la barra de F12, con la fecha encima del punto actual y las de los
+    // extremos a los lados
+    pub fn render(&self, framebuffer: &mut Framebuffer, layout: &SliderLayout, time: f32, contrast: Contrast) {
+        if !self.held {
+            return;
+        }
+        let top = layout.center_y.saturating_sub(BAR_HEIGHT / 2);
+        framebuffer.render_progress_bar(layout.x, top, layout.width, BAR_HEIGHT, self.fraction(time), BarColors { fill: contrast.text_color(FILL_COLOR), empty: EMPTY_COLOR });
+
+        let scale = contrast.text_scale(1);
+        let label_y = layout.label_y(contrast);
+        let white = contrast.text_color(0xFFFFFF);
+        framebuffer.draw_text(layout.x, label_y, &format_date(0.0), white, scale);
+        framebuffer.draw_text_right_aligned(layout.x + layout.width, label_y, &format_date(self.max_time), white, scale);
+        let x = layout.x + (self.fraction(time) * layout.width as f32) as usize;
+        let date_y = label_y.saturating_sub(Framebuffer::measure_text("", scale).1 + 4);
+        framebuffer.draw_text_centered(x, date_y, &format_date(time), contrast.text_color(FILL_COLOR), scale);
+    }
+}
+
+// Fecha del calendario gregoriano (año, mes, día) que corresponde al tiempo de la simulación,
+// con la escala de las efemérides: el tiempo 0 es J2000, el 1 de enero de 2000 a mediodía
+pub fn calendar_date(time: f32) -> (i64, u32, u32) {
+    let days = (ephemeris_time(time as f64) / 86_400.0 + 0.5).floor() as i64 + DAYS_TO_2000;
+    // Días desde 1970 a fecha civil (algoritmo de Howard Hinnant, con eras de 400 años desde el 1 de marzo)
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Fecha para el HUD, como 2000-01-01
+pub fn format_date(time: f32) -> String {
+    let (year, month, day) = calendar_date(time);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_scrubber() -> Result<(), String> {
+        // Un año de la Tierra es un año juliano desde J2000 (el 1 de enero de 2000 a mediodía)
+        let year = 2.0 * std::f32::consts::PI / 0.007;
+        let expected = [(0.0, (2000, 1, 1)), (year, (2000, 12, 31)), (4.0 * year, (2004, 1, 1)), (-year, (1999, 1, 1)), (year * 31.0 / 365.25, (2000, 2, 1))];
+        for (time, date) in expected {
+            if calendar_date(time) != date {
+                return Err(format!("el tiempo {} da {:?}, no {:?}", time, calendar_date(time), date));
+            }
+        }
+        if format_date(year * 59.0 / 365.25) != "2000-02-29" {
+            return Err(format!("el día 59 es {}", format_date(year * 59.0 / 365.25)));
+        }
+
+        // Con T apretada las flechas mueven el tiempo dentro de la barra; al soltarla no cuenta como
+        // pulsación. Soltarla sin moverlo sí.
+        let mut scrubber = TimeScrubber::new(year);
+        let mut clock = SimClock::new(10.0);
+        if scrubber.update(true, 0.0, &mut clock) || !scrubber.is_active() || !scrubber.update(false, 0.0, &mut clock) || clock.elapsed() != 10.0 {
+            return Err("una pulsación corta de T no se reconoce".to_string());
+        }
+        scrubber.update(true, -1.0, &mut clock);
+        if clock.elapsed() != 0.0 || scrubber.update(false, 0.0, &mut clock) || scrubber.is_active() {
+            return Err(format!("la barra llevó el tiempo a {} o tomó el arrastre por una pulsación", clock.elapsed()));
+        }
+        scrubber.update(true, 0.0, &mut clock);
+        for _ in 0..1000 {
+            scrubber.update(true, 1.0, &mut clock);
+        }
+        if clock.elapsed() != scrubber.max_time as f64 || scrubber.fraction(clock.elapsed() as f32) != 1.0 {
+            return Err(format!("al final de la barra el tiempo es {} de {}", clock.elapsed(), scrubber.max_time));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use crate::Framebuffer;
+use crate::hud::Contrast;
+use crate::sim_clock::SimClock;
+
+// Ventana por omisión a cada lado del tiempo actual, en años de la Tierra
+const DEFAULT_YEARS: f32 = 5.0;
+const MARGIN: usize = 20;
+const TRACK_HEIGHT: usize = 4;
+const HANDLE_WIDTH: usize = 10;
+const HANDLE_HEIGHT: usize = 16;
+const TICK_HEIGHT: usize = 8;
+// Más de tantas marcas (una por año) ya no se dibujan
+const MAX_TICKS: usize = 40;
+const TRACK_COLOR: u32 = 0x505A6E;
+const HANDLE_COLOR: u32 = 0xFFD54F;
+
+// Posición de la barra en pantalla: su borde izquierdo, su ancho y la línea central
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SliderLayout {
+    pub x: usize,
+    pub width: usize,
+    pub center_y: usize,
+}
+
+impl SliderLayout {
+    // A lo ancho de la pantalla, con la parte de abajo del tirador en `bottom`
+    pub fn new(screen_width: usize, bottom: usize) -> Self {
+        SliderLayout { x: MARGIN, width: screen_width.saturating_sub(2 * MARGIN), center_y: bottom.saturating_sub(HANDLE_HEIGHT / 2) }
+    }
+
+    pub fn top(&self) -> usize {
+        self.center_y.saturating_sub(HANDLE_HEIGHT / 2)
+    }
+
+    // Línea de los textos de la barra, encima del tirador
+    pub fn label_y(&self, contrast: Contrast) -> usize {
+        self.top().saturating_sub(Framebuffer::measure_text("", contrast.text_scale(1)).1 + 2)
+    }
+
+    // El tirador y la barra se pueden agarrar en todo el alto del tirador
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let half = HANDLE_WIDTH as f32 / 2.0;
+        x >= self.x as f32 - half
+            && x <= (self.x + self.width) as f32 + half
+            && y >= self.top() as f32
+            && y <= (self.center_y + HANDLE_HEIGHT / 2) as f32
+    }
+
+    // Fracción de la barra (0 a 1) bajo la coordenada x
+    fn fraction_at(&self, x: f32) -> f32 {
+        ((x - self.x as f32) / self.width.max(1) as f32).clamp(0.0, 1.0)
+    }
+
+    fn x_at(&self, fraction: f32) -> usize {
+        self.x + (fraction.clamp(0.0, 1.0) * self.width as f32).round() as usize
+    }
+}
+
+// Barra de tiempo (F12): arrastrar el tirador con el botón izquierdo lleva la simulación a otro
+// instante, dentro de una ventana de `--scrub-years` años de la Tierra (5 por omisión) a cada
+// lado del momento en que se agarró. Las posiciones son funciones del tiempo, así que los cuerpos
+// van derecho adonde estarían. Mientras se arrastra el tiempo no avanza y el ratón no mueve la
+// cámara; al soltar sigue desde ahí.
+pub struct TimeSlider {
+    pub visible: bool,
+    // Años de la Tierra a cada lado, y esa mitad de la ventana en unidades de tiempo de la simulación
+    years: f32,
+    half_window: f32,
+    year: f32,
+    // Tiempo al agarrar el tirador, centro de la ventana mientras dura el arrastre. En f64, como el
+    // reloj, para que soltar el tirador donde se agarró deje el tiempo exacto.
+    anchor: Option<f64>,
+    was_down: bool,
+}
+
+impl TimeSlider {
+    // `year` es el año de la Tierra en unidades de tiempo de la simulación
+    pub fn from_args(args: &[String], year: f32) -> Result<Self, String> {
+        let years = match args.iter().position(|arg| arg == "--scrub-years") {
+            None => DEFAULT_YEARS,
+            Some(position) => args
+                .get(position + 1)
+                .and_then(|value| value.parse::<f32>().ok())
+                .filter(|years| *years > 0.0 && years.is_finite())
+                .ok_or("--scrub-years espera los años a cada lado de la barra de tiempo, como 5")?,
+        };
+        Ok(TimeSlider { visible: false, years, half_window: years * year, year, anchor: None, was_down: false })
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.anchor = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    // Fracción de la barra que le toca a `time`; fuera del arrastre el tirador está en el medio
+    fn fraction(&self, time: f32) -> f32 {
+        let center = self.anchor.map_or(time, |anchor| anchor as f32);
+        0.5 + (time - center) / (2.0 * self.half_window)
+    }
+
+    // Procesa el ratón (posición en píxeles de la pantalla y botón izquierdo) y mueve el reloj si se
+    // está arrastrando. Devuelve si el ratón es de la barra en este cuadro: entonces la cámara no
+    // debe usarlo.
+    pub fn update(&mut self, layout: &SliderLayout, mouse: Option<(f32, f32)>, down: bool, clock: &mut SimClock) -> bool {
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+        if !self.visible {
+            return false;
+        }
+
+        if pressed && mouse.is_some_and(|(x, y)| layout.contains(x, y)) {
+            self.anchor = Some(clock.elapsed());
+        }
+        if !down {
+            self.anchor = None;
+            return false;
+        }
+        let Some(anchor) = self.anchor else {
+            return false;
+        };
+        if let Some((x, _)) = mouse {
+            clock.set(anchor + ((layout.fraction_at(x) - 0.5) * 2.0 * self.half_window) as f64);
+        }
+        true
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, layout: &SliderLayout, time: f32, contrast: Contrast) {
+        if !self.visible {
+            return;
+        }
+        let track_color = contrast.text_color(TRACK_COLOR);
+        framebuffer.draw_filled_rect(layout.x, layout.center_y.saturating_sub(TRACK_HEIGHT / 2), layout.width, TRACK_HEIGHT, track_color);
+
+        // Una marca por año y una más alta en el centro de la ventana
+        let center = self.anchor.map_or(time, |anchor| anchor as f32);
+        let ticks = self.years.floor() as usize;
+        if ticks * 2 < MAX_TICKS {
+            for offset in 1..=ticks {
+                for sign in [-1.0, 1.0] {
+                    let x = layout.x_at(self.fraction(center + sign * offset as f32 * self.year));
+                    framebuffer.draw_filled_rect(x, layout.center_y.saturating_sub(TICK_HEIGHT / 2), 1, TICK_HEIGHT, track_color);
+                }
+            }
+        }
+        framebuffer.draw_filled_rect(layout.x_at(0.5), layout.top(), 1, HANDLE_HEIGHT, track_color);
+
+        let scale = contrast.text_scale(1);
+        let label_y = layout.label_y(contrast);
+        let total = format!("{} ANOS", self.years);
+        let white = contrast.text_color(0xFFFFFF);
+        framebuffer.draw_text(layout.x, label_y, &format!("-{}", total), white, scale);
+        framebuffer.draw_text_right_aligned(layout.x + layout.width, label_y, &format!("+{}", total), white, scale);
+
+        let handle_x = layout.x_at(self.fraction(time));
+        framebuffer.draw_filled_rect(handle_x.saturating_sub(HANDLE_WIDTH / 2), layout.top(), HANDLE_WIDTH, HANDLE_HEIGHT, contrast.text_color(HANDLE_COLOR));
+        if self.is_dragging() {
+            let offset = (time - center) / self.year;
+            framebuffer.draw_text_centered(handle_x, label_y, &format!("{:+.1} ANOS", offset), contrast.text_color(HANDLE_COLOR), scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // La barra de 800 px mide 760 entre márgenes; con un año de 100 y 2 años a cada lado, el extremo
+    // derecho está 200 después del tiempo en que se agarró el tirador
+    #[test]
+    fn time_slider() -> Result<(), String> {
+        let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<String>>();
+        if TimeSlider::from_args(&args("--scrub-years 0"), 100.0).is_ok() || TimeSlider::from_args(&args("--scrub-years"), 100.0).is_ok() {
+            return Err("--scrub-years aceptó una ventana vacía".to_string());
+        }
+        let mut slider = TimeSlider::from_args(&args("--scrub-years 2"), 100.0)?;
+        let layout = SliderLayout::new(800, 570);
+        let handle = (400.0, layout.center_y as f32);
+        let mut clock = SimClock::new(50.0);
+
+        if slider.update(&layout, Some(handle), true, &mut clock) || slider.update(&layout, Some(handle), false, &mut clock) {
+            return Err("la barra oculta tomó el ratón".to_string());
+        }
+        slider.toggle();
+        if slider.update(&layout, Some((400.0, 100.0)), true, &mut clock) || slider.update(&layout, Some(handle), true, &mut clock) || slider.is_dragging() {
+            return Err("un clic fuera de la barra (aunque después pase por el tirador) la agarró".to_string());
+        }
+        slider.update(&layout, Some(handle), false, &mut clock);
+
+        if !slider.update(&layout, Some(handle), true, &mut clock) || (clock.elapsed() - 50.0).abs() > 1e-3 {
+            return Err(format!("agarrar el tirador en el medio dejó el tiempo en {}", clock.elapsed()));
+        }
+        // Ya agarrado, el ratón puede salir de la barra y el tiempo se topa en el extremo
+        if !slider.update(&layout, Some((1000.0, 100.0)), true, &mut clock) || (clock.elapsed() - 250.0).abs() > 1e-3 {
+            return Err(format!("arrastrar más allá del extremo derecho dio {}", clock.elapsed()));
+        }
+        slider.update(&layout, Some(((layout.x + layout.width / 4) as f32, 0.0)), true, &mut clock);
+        if (clock.elapsed() + 50.0).abs() > 1e-3 {
+            return Err(format!("a un cuarto de la barra el tiempo es {}, se esperaba -50", clock.elapsed()));
+        }
+        if slider.update(&layout, Some(handle), false, &mut clock) || slider.is_dragging() || (clock.elapsed() + 50.0).abs() > 1e-3 {
+            return Err("al soltar el tirador no quedó el tiempo elegido".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use nalgebra_glm::Vec3;
+
+use crate::command::find_body;
+use crate::sizes::SizeScale;
+use crate::uniforms::DEFAULT_FOV_Y;
+use crate::{Planet, focus_targets, MIN_TIME_SCALE, MAX_TIME_SCALE};
+
+// Campo de visión vertical (grados) que se puede pedir
+const MIN_FOV: f32 = 10.0;
+const MAX_FOV: f32 = 90.0;
+
+// Hacia dónde mira la cámara en un keyframe
+#[derive(Clone, Debug, PartialEq)]
+pub enum CameraTarget {
+    // Enfocando un cuerpo, como con su tecla
+    Body(usize),
+    Explicit { eye: Vec3, center: Vec3 },
+}
+
+// Curva del tramo que empieza en un keyframe: la de las transiciones de tamaño (suave) o recta
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Smooth,
+    Linear,
+}
+
+impl Easing {
+    fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Smooth => t * t * (3.0 - 2.0 * t),
+            Easing::Linear => t,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    // Segundos desde que empieza la reproducción
+    pub time: f32,
+    pub target: CameraTarget,
+    // Campo de visión vertical en grados
+    pub fov: f32,
+    pub time_scale: f32,
+    // Texto que se muestra desde este keyframe hasta el siguiente
+    pub caption: Option<String>,
+    pub easing: Easing,
+}
+
+// Lo que la línea de tiempo pide para un instante
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineFrame {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub fov: f32,
+    pub time_scale: f32,
+    pub caption: Option<String>,
+    // Cuerpo enfocado: el del keyframe al que se va, como en las transiciones con las teclas
+    pub focus: Option<usize>,
+}
+
+// Secuencia de keyframes para grabar demos repetibles. El archivo es un subconjunto de TOML:
+//
+//   [[keyframe]]
+//   time = 0                  # segundos, en orden creciente
+//   target = "Tierra"         # un cuerpo (su nombre o el alias en inglés), o bien
+//   eye = [0, 80, 200]        #   ojo y centro explícitos
+//   center = [0, 0, 0]
+//   fov = 50                  # grados, vertical (10 a 90)
+//   time_scale = 4
+//   caption = "La Tierra"
+//   easing = "linear"         # "smooth" por omisión
+//
+// La cámara, el campo de visión y la escala de tiempo que falten se heredan del keyframe
+// anterior (el primero necesita la cámara); el texto y la curva no.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Timeline {
+    pub fn load(path: &str, planets: &[Planet]) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        Self::parse(&text, planets).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn parse(text: &str, planets: &[Planet]) -> Result<Self, String> {
+        let mut tables: Vec<(usize, Vec<Field>)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "[[keyframe]]" {
+                tables.push((number + 1, Vec::new()));
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("línea {}: se esperaba [[keyframe]] o clave = valor", number + 1))?;
+            let value = parse_value(value.trim()).map_err(|error| format!("línea {}: {}", number + 1, error))?;
+            let (_, fields) = tables
+                .last_mut()
+                .ok_or_else(|| format!("línea {}: {} antes del primer [[keyframe]]", number + 1, key.trim()))?;
+            fields.push((number + 1, key.trim().to_string(), value));
+        }
+
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+        for (header, fields) in tables {
+            let previous = keyframes.last();
+            let mut time = None;
+            let mut target = None;
+            let (mut eye, mut center) = (None, None);
+            let mut fov = previous.map_or(DEFAULT_FOV_Y.to_degrees(), |keyframe| keyframe.fov);
+            let mut time_scale = previous.map_or(1.0, |keyframe| keyframe.time_scale);
+            let mut caption = None;
+            let mut easing = Easing::Smooth;
+
+            for (line, key, value) in fields {
+                let error = |expected: &str| format!("línea {}: {} espera {}", line, key, expected);
+                match (key.as_str(), value) {
+                    ("time", Value::Number(seconds)) if seconds >= 0.0 => time = Some(seconds),
+                    ("target", Value::Text(name)) => {
+                        let index = find_body(planets, &name).ok_or_else(|| format!("línea {}: no hay ningún cuerpo llamado {}", line, name))?;
+                        target = Some(CameraTarget::Body(index));
+                    }
+                    ("eye", Value::Vector(vector)) => eye = Some(vector),
+                    ("center", Value::Vector(vector)) => center = Some(vector),
+                    ("fov", Value::Number(degrees)) if (MIN_FOV..=MAX_FOV).contains(&degrees) => fov = degrees,
+                    ("time_scale", Value::Number(scale)) if (MIN_TIME_SCALE..=MAX_TIME_SCALE).contains(&scale) => time_scale = scale,
+                    ("caption", Value::Text(text)) => caption = Some(text),
+                    ("easing", Value::Text(name)) if name == "smooth" => easing = Easing::Smooth,
+                    ("easing", Value::Text(name)) if name == "linear" => easing = Easing::Linear,
+                    ("time", _) => return Err(error("segundos desde el inicio")),
+                    ("target", _) => return Err(error("el nombre de un cuerpo entre comillas")),
+                    ("eye" | "center", _) => return Err(error("[x, y, z]")),
+                    ("fov", _) => return Err(error(&format!("grados entre {} y {}", MIN_FOV, MAX_FOV))),
+                    ("time_scale", _) => return Err(error(&format!("una escala entre {} y {}", MIN_TIME_SCALE, MAX_TIME_SCALE))),
+                    ("caption", _) => return Err(error("un texto entre comillas")),
+                    ("easing", _) => return Err(error("\"smooth\" o \"linear\"")),
+                    _ => return Err(format!("línea {}: clave desconocida {}", line, key)),
+                }
+            }
+
+            let time = time.ok_or_else(|| format!("línea {}: el keyframe no tiene time", header))?;
+            if let Some(previous) = previous.filter(|previous| time <= previous.time) {
+                return Err(format!("línea {}: el keyframe de {} s está fuera de orden (el anterior es de {} s)", header, time, previous.time));
+            }
+            let target = match (target, eye, center) {
+                (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                    return Err(format!("línea {}: target y eye/center no van juntos", header))
+                }
+                (Some(target), None, None) => target,
+                (None, Some(eye), Some(center)) => CameraTarget::Explicit { eye, center },
+                (None, None, None) => match previous {
+                    Some(previous) => previous.target.clone(),
+                    None => return Err(format!("línea {}: el primer keyframe necesita target o eye y center", header)),
+                },
+                (None, _, _) => return Err(format!("línea {}: eye y center van juntos", header)),
+            };
+
+            keyframes.push(Keyframe { time, target, fov, time_scale, caption, easing });
+        }
+
+        if keyframes.is_empty() {
+            return Err("la línea de tiempo no tiene ningún [[keyframe]]".to_string());
+        }
+        Ok(Timeline { keyframes })
+    }
+
+    // Segundos hasta el último keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    // Cámara, campo de visión, escala de tiempo y texto en el segundo `t`. En el segundo de un
+    // keyframe la cámara queda exactamente en él.
+    pub fn sample(&self, t: f32, planets: &[Planet], sizes: &SizeScale) -> TimelineFrame {
+        let resolve = |keyframe: &Keyframe| match keyframe.target {
+            CameraTarget::Body(index) => focus_targets(planets, index, sizes),
+            CameraTarget::Explicit { eye, center } => (eye, center),
+        };
+        let focus = |keyframe: &Keyframe| match keyframe.target {
+            CameraTarget::Body(index) => Some(index),
+            CameraTarget::Explicit { .. } => None,
+        };
+        let still = |keyframe: &Keyframe, caption: Option<String>| {
+            let (eye, center) = resolve(keyframe);
+            TimelineFrame { eye, center, fov: keyframe.fov, time_scale: keyframe.time_scale, caption, focus: focus(keyframe) }
+        };
+
+        // Tramo [from, to) que contiene `t`; antes del primero y después del último, quieta
+        let segment = self.keyframes.windows(2).find(|pair| t >= pair[0].time && t < pair[1].time);
+        let Some([from, to]) = segment else {
+            let keyframe = if t < self.keyframes[0].time { &self.keyframes[0] } else { &self.keyframes[self.keyframes.len() - 1] };
+            return still(keyframe, keyframe.caption.clone().filter(|_| t >= keyframe.time));
+        };
+
+        let progress = from.easing.apply((t - from.time) / (to.time - from.time));
+        let ((from_eye, from_center), (to_eye, to_center)) = (resolve(from), resolve(to));
+        TimelineFrame {
+            eye: from_eye + (to_eye - from_eye) * progress,
+            center: from_center + (to_center - from_center) * progress,
+            fov: from.fov + (to.fov - from.fov) * progress,
+            time_scale: from.time_scale + (to.time_scale - from.time_scale) * progress,
+            caption: from.caption.clone(),
+            focus: focus(to),
+        }
+    }
+}
+
+// Reproducción de una línea de tiempo (`--timeline <archivo>`) con el reloj de los cuadros
+pub struct TimelinePlayer {
+    pub timeline: Timeline,
+    pub elapsed: f32,
+}
+
+impl TimelinePlayer {
+    pub fn new(timeline: Timeline) -> Self {
+        TimelinePlayer { timeline, elapsed: 0.0 }
+    }
+
+    // `None` si no se pasó `--timeline`
+    pub fn from_args(args: &[String], planets: &[Planet]) -> Result<Option<Self>, String> {
+        let Some(position) = args.iter().position(|arg| arg == "--timeline") else {
+            return Ok(None);
+        };
+        let path = args.get(position + 1).ok_or("--timeline espera la ruta de un archivo")?;
+        Ok(Some(TimelinePlayer::new(Timeline::load(path, planets)?)))
+    }
+
+    // Corta la reproducción: el próximo `step` ya devuelve `None`
+    pub fn abort(&mut self) {
+        self.elapsed = f32::INFINITY;
+    }
+
+    // El cuadro en el segundo actual, y avanza `delta_seconds` para el siguiente. `None` cuando
+    // ya pasó el último keyframe.
+    pub fn step(&mut self, delta_seconds: f32, planets: &[Planet], sizes: &SizeScale) -> Option<TimelineFrame> {
+        if self.elapsed > self.timeline.duration() {
+            return None;
+        }
+        let frame = self.timeline.sample(self.elapsed, planets, sizes);
+        self.elapsed += delta_seconds;
+        Some(frame)
+    }
+}
+
+// Línea, clave y valor de un campo de un keyframe
+type Field = (usize, String, Value);
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Number(f32),
+    Text(String),
+    Vector(Vec3),
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        return Ok(Value::Text(inner.to_string()));
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let numbers = inner
+            .split(',')
+            .map(|number| number.trim().parse::<f32>().map_err(|_| format!("número inválido '{}'", number.trim())))
+            .collect::<Result<Vec<f32>, String>>()?;
+        return match numbers.as_slice() {
+            [x, y, z] => Ok(Value::Vector(Vec3::new(*x, *y, *z))),
+            _ => Err(format!("un vector lleva tres números, no {}", numbers.len())),
+        };
+    }
+    text.parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .map(Value::Number)
+        .ok_or_else(|| format!("valor inválido '{}'", text))
+}
+
+// La línea sin su comentario (# fuera de las comillas)
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solar_system;
+
+    // Dos keyframes recorridos a pasos fijos, como sin ventana: la cámara cae exactamente en cada
+    // keyframe en su segundo. Los archivos fuera de orden o con cuerpos desconocidos se rechazan al leerlos.
+    #[test]
+    fn demo_timeline() -> Result<(), String> {
+        let planets = solar_system();
+        let sizes = SizeScale::new();
+        let text = "
+        [[keyframe]]
+        time = 0
+        eye = [0, 80, 200]   # vista general
+        center = [0, 0, 0]
+        caption = \"Sistema solar\"
+
+        [[keyframe]]
+        time = 2
+        target = \"earth\"
+        fov = 30
+        time_scale = 4
+    ";
+        let timeline = Timeline::parse(text, &planets)?;
+        let earth = planets.iter().position(|planet| planet.name == "Tierra").ok_or("no está la Tierra")?;
+        let (earth_eye, earth_center) = focus_targets(&planets, earth, &sizes);
+
+        let mut player = TimelinePlayer::new(timeline);
+        let mut frames = Vec::new();
+        while let Some(frame) = player.step(0.25, &planets, &sizes) {
+            frames.push(frame);
+        }
+        if frames.len() != 9 {
+            return Err(format!("se esperaban 9 cuadros de 0 a 2 s, hubo {}", frames.len()));
+        }
+        let (first, last) = (&frames[0], &frames[8]);
+        if first.eye != Vec3::new(0.0, 80.0, 200.0) || first.center != Vec3::new(0.0, 0.0, 0.0) || first.fov != DEFAULT_FOV_Y.to_degrees() {
+            return Err(format!("a los 0 s la cámara está en {:?} mirando a {:?}", first.eye, first.center));
+        }
+        if last.eye != earth_eye || last.center != earth_center || last.fov != 30.0 || last.time_scale != 4.0 || last.focus != Some(earth) {
+            return Err(format!("a los 2 s la cámara está en {:?} mirando a {:?}, no en la Tierra", last.eye, last.center));
+        }
+        let middle = &frames[4];
+        if middle.caption.as_deref() != Some("Sistema solar") || last.caption.is_some() {
+            return Err("el texto no sigue a su keyframe".to_string());
+        }
+        if !(middle.fov > 30.0 && middle.fov < first.fov) {
+            return Err(format!("a mitad de camino el campo de visión es {}", middle.fov));
+        }
+
+        let out_of_order = "[[keyframe]]\ntime = 3\ntarget = \"Sol\"\n[[keyframe]]\ntime = 1\n";
+        if Timeline::parse(out_of_order, &planets).is_ok() {
+            return Err("aceptó keyframes fuera de orden".to_string());
+        }
+        let unknown = "[[keyframe]]\ntime = 0\ntarget = \"Plutón\"\n";
+        if Timeline::parse(unknown, &planets).is_ok() {
+            return Err("aceptó un cuerpo que no existe".to_string());
+        }
+        Ok(())
+    }
+}
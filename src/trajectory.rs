@@ -0,0 +1,512 @@
+use nalgebra_glm::{Vec3, rotate_vec3};
+use std::f32::consts::PI;
+
+use crate::Framebuffer;
+use crate::framebuffer::DashPattern;
+use crate::physics::{SUN_GRAVITATIONAL_PARAMETER, ASTRONOMICAL_UNIT, GRAVITATIONAL_CONSTANT};
+use crate::sizes::SizeScale;
+use crate::uniforms::Uniforms;
+use crate::units::{Unit, format_quantity};
+use crate::{Planet, project_point};
+
+// Puntos de la media órbita de transferencia, equiespaciados en tiempo
+const TRAJECTORY_SAMPLES: usize = 200;
+const KEPLER_ITERATIONS: usize = 10;
+
+pub const TRAJECTORY_COLOR: u32 = 0xFF8C1A;
+const DASH_LENGTH: usize = 6;
+
+// Segmentos más largos que esto (en píxeles) se descartan: son puntos casi detrás de la cámara
+const MAX_SEGMENT_LENGTH: usize = 2048;
+
+// Cuerpo del que parte la nave
+const ORIGIN: &str = "Tierra";
+
+// Semieje mayor real de cada órbita (UA). Las distancias de la simulación son didácticas, así
+// que el Δv y la duración se calculan con estas; la forma dibujada usa las de la simulación.
+const REAL_ORBITS_AU: [(&str, f64); 7] = [
+    ("Mercurio", 0.387),
+    ("Venus", 0.723),
+    ("Tierra", 1.0),
+    ("Marte", 1.524),
+    ("Júpiter", 5.203),
+    ("Saturno", 9.537),
+    ("Urano", 19.19),
+];
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+// Masa (kg) y radio medio (m) reales de los planetas, para los sobrevuelos
+const REAL_BODIES: [(&str, f32, f32); 7] = [
+    ("Mercurio", 3.301e23, 2.4397e6),
+    ("Venus", 4.867e24, 6.0518e6),
+    ("Tierra", 5.972e24, 6.371e6),
+    ("Marte", 6.417e23, 3.3895e6),
+    ("Júpiter", 1.898e27, 6.9911e7),
+    ("Saturno", 5.683e26, 5.8232e7),
+    ("Urano", 8.681e25, 2.5362e7),
+];
+
+// Periapsis más bajo que se considera seguro, en radios del planeta. Es el del sobrevuelo óptimo:
+// cuanto más cerca, más gira la velocidad relativa y más cambia la velocidad respecto al Sol.
+const MIN_FLYBY_RADII: f32 = 1.1;
+
+// Colores de las tres fases del sobrevuelo
+pub const APPROACH_COLOR: u32 = 0x4FC3F7;
+pub const PERIAPSIS_COLOR: u32 = 0xFFEB3B;
+pub const DEPARTURE_COLOR: u32 = 0xEF5350;
+
+// Puntos por fase, y hasta dónde llegan la hipérbola dibujada y su arco del periapsis (en
+// distancias de periapsis al centro del planeta)
+const FLYBY_SAMPLES: usize = 48;
+const FLYBY_EXTENT: f32 = 30.0;
+const PERIAPSIS_ARC_EXTENT: f32 = 2.0;
+
+// Órbita de transferencia de Hohmann entre las órbitas circulares de `from` y `to` (ambas
+// alrededor del Sol), saliendo de donde está `from` en `departure_time`: media elipse con el
+// Sol en un foco, semieje mayor (r1 + r2) / 2 y el periapsis en la órbita interior.
+// Devuelve TRAJECTORY_SAMPLES puntos equiespaciados en tiempo, en el sentido en que orbita
+// `from`; vacío si alguno de los dos es una luna o las órbitas coinciden.
+pub fn hohmann_transfer(from: &Planet, to: &Planet, departure_time: f64) -> Vec<Vec3> {
+    let (r1, r2) = (from.distance_from_sun, to.distance_from_sun);
+    if from.parent.is_some() || to.parent.is_some() || r1 <= 0.0 || r2 <= 0.0 || r1 == r2 {
+        return Vec::new();
+    }
+
+    let semi_major = (r1 + r2) / 2.0;
+    let eccentricity = (r2 - r1).abs() / (r1 + r2);
+    let direction = if from.orbit_speed < 0.0 { -1.0 } else { 1.0 };
+    let departure_angle = from.orbit_angle(departure_time);
+
+    (0..TRAJECTORY_SAMPLES)
+        .map(|i| {
+            let t = i as f32 / (TRAJECTORY_SAMPLES - 1) as f32;
+            // Hacia afuera se sale del periapsis; hacia adentro, del apoapsis, y la posición es
+            // la simétrica de la que queda a (1 - t) de medio período del periapsis
+            let (swept, radius) = if r2 > r1 {
+                from_periapsis(PI * t, eccentricity, semi_major)
+            } else {
+                let (anomaly, radius) = from_periapsis(PI * (1.0 - t), eccentricity, semi_major);
+                (PI - anomaly, radius)
+            };
+            let angle = departure_angle + direction * swept;
+            Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin())
+        })
+        .collect()
+}
+
+// (anomalía verdadera, distancia al foco) con anomalía media `mean_anomaly` en [0, π]
+fn from_periapsis(mean_anomaly: f32, eccentricity: f32, semi_major: f32) -> (f32, f32) {
+    // Ecuación de Kepler M = E - e sin E por Newton
+    let mut eccentric = if eccentricity > 0.8 { PI } else { mean_anomaly };
+    for _ in 0..KEPLER_ITERATIONS {
+        let error = eccentric - eccentricity * eccentric.sin() - mean_anomaly;
+        eccentric -= error / (1.0 - eccentricity * eccentric.cos());
+    }
+
+    let half = eccentric * 0.5;
+    let true_anomaly = 2.0 * ((1.0 + eccentricity).sqrt() * half.sin()).atan2((1.0 - eccentricity).sqrt() * half.cos());
+    (true_anomaly, semi_major * (1.0 - eccentricity * eccentric.cos()))
+}
+
+// Costo de la transferencia con las órbitas reales: suma de los dos impulsos (km/s) y
+// duración (días), o `None` si alguno de los cuerpos no está en la tabla
+pub fn transfer_budget(from: &Planet, to: &Planet) -> Option<(f64, f64)> {
+    let (r1, r2) = (real_orbit(from)?, real_orbit(to)?);
+    let mu = SUN_GRAVITATIONAL_PARAMETER;
+    let semi_major = (r1 + r2) / 2.0;
+
+    let departure = (mu / r1).sqrt() * ((r2 / semi_major).sqrt() - 1.0);
+    let arrival = (mu / r2).sqrt() * (1.0 - (r1 / semi_major).sqrt());
+    let delta_v = (departure.abs() + arrival.abs()) / 1000.0;
+    let seconds = PI as f64 * (semi_major.powi(3) / mu).sqrt();
+    Some((delta_v, seconds / SECONDS_PER_DAY))
+}
+
+// Excentricidad de la hipérbola de un sobrevuelo con velocidad de exceso `excess_speed` (m/s),
+// parámetro gravitatorio `mu` y periapsis `closest_approach` (m)
+fn flyby_eccentricity(excess_speed: f32, mu: f32, closest_approach: f32) -> f32 {
+    1.0 + closest_approach * excess_speed * excess_speed / mu
+}
+
+// Velocidad de la nave después de sobrevolar un planeta, con la solución analítica de la
+// hipérbola (cónicas empalmadas): respecto al planeta, la velocidad de exceso v∞ conserva su
+// módulo y gira δ = 2·asin(1/e), con e = 1 + rp·v∞²/μ. El giro es en el plano de v∞ y la velocidad
+// del planeta, hacia esta; si son paralelas, en el plano que contiene al Sol. Unidades SI, con
+// `planet_pos` medida desde el Sol.
+pub fn gravity_assist(spacecraft_vel: Vec3, planet_pos: Vec3, planet_vel: Vec3, planet_mass: f32, closest_approach: f32) -> Vec3 {
+    let excess = spacecraft_vel - planet_vel;
+    let mu = GRAVITATIONAL_CONSTANT * planet_mass;
+    if excess.magnitude() == 0.0 || mu <= 0.0 || closest_approach <= 0.0 {
+        return spacecraft_vel;
+    }
+    let eccentricity = flyby_eccentricity(excess.magnitude(), mu, closest_approach);
+    let turn = 2.0 * (1.0 / eccentricity).asin();
+
+    let direction = excess.normalize();
+    let axis = [planet_vel, planet_pos]
+        .iter()
+        .filter_map(|other| other.try_normalize(0.0))
+        .map(|other| direction.cross(&other))
+        .find(|axis| axis.magnitude() > 1e-4)
+        .map_or(Vec3::new(0.0, 1.0, 0.0), |axis| axis.normalize());
+    planet_vel + rotate_vec3(&excess, turn, &axis)
+}
+
+// Las tres fases de la hipérbola de un sobrevuelo alrededor de `center`: aproximación, arco del
+// periapsis y salida, con el periapsis a `periapsis` de `center` (unidades de la escena). Las
+// asíntotas siguen a las velocidades de exceso de entrada y salida; vacías si casi no gira.
+pub fn flyby_arcs(center: Vec3, periapsis: f32, eccentricity: f32, excess_in: Vec3, excess_out: Vec3) -> [Vec<Vec3>; 3] {
+    let (incoming, outgoing) = (excess_in.normalize(), excess_out.normalize());
+    // El periapsis queda del lado opuesto hacia el que se curva la trayectoria, y ahí la
+    // velocidad va por la bisectriz de las dos asíntotas
+    let (Some(toward_periapsis), Some(along)) = ((incoming - outgoing).try_normalize(1e-6), (incoming + outgoing).try_normalize(1e-6)) else {
+        return [Vec::new(), Vec::new(), Vec::new()];
+    };
+
+    let semi_latus = periapsis * (1.0 + eccentricity);
+    // Anomalía verdadera donde la hipérbola está a `extent` periapsis del centro
+    let anomaly_at = |extent: f32| (((1.0 + eccentricity) / extent - 1.0) / eccentricity).clamp(-1.0, 1.0).acos();
+    let (near, far) = (anomaly_at(PERIAPSIS_ARC_EXTENT), anomaly_at(FLYBY_EXTENT));
+    let arc = |from: f32, to: f32| -> Vec<Vec3> {
+        (0..FLYBY_SAMPLES)
+            .map(|i| {
+                let anomaly = from + (to - from) * i as f32 / (FLYBY_SAMPLES - 1) as f32;
+                let radius = semi_latus / (1.0 + eccentricity * anomaly.cos());
+                center + (toward_periapsis * anomaly.cos() + along * anomaly.sin()) * radius
+            })
+            .collect()
+    };
+    [arc(-far, -near), arc(-near, near), arc(near, far)]
+}
+
+// Proyecta `points` a pantalla y los une (punteados si hay `dash`); los tramos con puntos detrás
+// de la cámara se saltan
+fn draw_path(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vec3], color: u32, dash: Option<usize>) {
+    let mut dashes = dash.map(DashPattern::new);
+    let mut previous: Option<(usize, usize)> = None;
+    for point in points {
+        let screen = project_point(uniforms, *point)
+            .filter(|p| p.z > 0.0 && p.x >= 0.0 && p.y >= 0.0 && p.x.is_finite() && p.y.is_finite())
+            .map(|p| (p.x as usize, p.y as usize));
+        if let (Some((x0, y0)), Some((x1, y1))) = (previous, screen) {
+            if x0.abs_diff(x1).max(y0.abs_diff(y1)) > MAX_SEGMENT_LENGTH {
+                previous = screen;
+                continue;
+            }
+            match &mut dashes {
+                Some(dashes) => framebuffer.draw_dashed_line(x0, y0, x1, y1, color, dashes),
+                None => framebuffer.draw_line(x0, y0, x1, y1, color),
+            }
+        }
+        previous = screen;
+    }
+}
+
+// Trayectoria de una nave desde la Tierra hasta el destino elegido con la tecla T, que recorre
+// los planetas y vuelve a ocultarla. Se calcula al elegir el destino, desde la posición de la
+// Tierra en ese momento, y se dibuja como un arco naranja punteado con su costo en el HUD.
+pub struct SpacecraftTrajectory {
+    destination: Option<usize>,
+    points: Vec<Vec3>,
+    label: String,
+}
+
+impl SpacecraftTrajectory {
+    pub fn new() -> Self {
+        SpacecraftTrajectory { destination: None, points: Vec::new(), label: String::new() }
+    }
+
+    // Pasa al siguiente destino (los planetas del Sol en orden, sin el origen) o se oculta tras el último
+    pub fn cycle(&mut self, planets: &[Planet], time: f64) {
+        let origin = match planets.iter().position(|planet| planet.name == ORIGIN) {
+            Some(origin) => origin,
+            None => return,
+        };
+        let start = self.destination.map_or(0, |index| index + 1);
+        self.destination = (start..planets.len()).find(|&index| {
+            index != origin && planets[index].parent.is_none() && planets[index].distance_from_sun > 0.0
+        });
+
+        self.points.clear();
+        self.label.clear();
+        if let Some(destination) = self.destination {
+            let (from, to) = (&planets[origin], &planets[destination]);
+            self.points = hohmann_transfer(from, to, time);
+
+            if let Some((delta_v, days)) = transfer_budget(from, to) {
+                let (mut delta_v_text, mut days_text) = (String::new(), String::new());
+                format_quantity(&mut delta_v_text, delta_v, Unit::Plain, 3);
+                format_quantity(&mut days_text, days, Unit::Plain, 3);
+                self.label = format!(
+                    "{} > {}  DV {} KM/S  {} DIAS",
+                    from.name.to_uppercase(),
+                    to.name.to_uppercase(),
+                    delta_v_text,
+                    days_text,
+                );
+            }
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    // Texto del HUD con el Δv y la duración
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    // Arco punteado proyectado a pantalla
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        draw_path(framebuffer, uniforms, &self.points, TRAJECTORY_COLOR, Some(DASH_LENGTH));
+    }
+}
+
+// Planificador de asistencias gravitatorias (tecla Y): con las teclas de planetas se elige el
+// origen y después el planeta del sobrevuelo. Se muestra la transferencia de Hohmann entre los dos
+// y, donde llega, el sobrevuelo óptimo (periapsis de MIN_FLYBY_RADII radios) con sus tres fases,
+// junto con lo que gana o pierde la nave respecto al Sol.
+pub struct FlybyPlanner {
+    pub active: bool,
+    origin: Option<usize>,
+    target: Option<usize>,
+    transfer: Vec<Vec3>,
+    arcs: [Vec<Vec3>; 3],
+    label: String,
+}
+
+impl FlybyPlanner {
+    pub fn new() -> Self {
+        FlybyPlanner {
+            active: false,
+            origin: None,
+            target: None,
+            transfer: Vec::new(),
+            arcs: [Vec::new(), Vec::new(), Vec::new()],
+            label: String::new(),
+        }
+    }
+
+    // Al cerrarlo o abrirlo se olvida la selección
+    pub fn toggle(&mut self) {
+        *self = FlybyPlanner { active: !self.active, ..FlybyPlanner::new() };
+    }
+
+    // Elige el origen o, si ya hay uno, el planeta del sobrevuelo y calcula el plan. Una tercera
+    // elección empieza otra vez por el origen. Las lunas no cuentan.
+    pub fn select(&mut self, planets: &[Planet], index: usize, time: f64, sizes: &SizeScale, sphere_radius: f32) {
+        if planets[index].parent.is_some() {
+            return;
+        }
+        match (self.origin, self.target) {
+            (Some(origin), None) if origin != index => self.target = Some(index),
+            (Some(_), None) => return,
+            _ => {
+                *self = FlybyPlanner { active: self.active, origin: Some(index), ..FlybyPlanner::new() };
+                return;
+            }
+        }
+        let (from, to) = (&planets[self.origin.unwrap_or(index)], &planets[index]);
+        self.transfer = hohmann_transfer(from, to, time);
+        self.label = format!("{} > {}  SIN DATOS DEL SOBREVUELO", from.name.to_uppercase(), to.name.to_uppercase());
+        let Some(&arrival) = self.transfer.last() else {
+            return;
+        };
+        let Some((planet_vel, excess_in, excess_out, eccentricity)) = flyby_plan(from, to, arrival) else {
+            return;
+        };
+        let periapsis = MIN_FLYBY_RADII * sizes.radius(to) * sphere_radius;
+        self.arcs = flyby_arcs(arrival, periapsis, eccentricity, excess_in, excess_out);
+
+        let gain = ((planet_vel + excess_out).magnitude() - (planet_vel + excess_in).magnitude()) / 1000.0;
+        let turn = 2.0 * (1.0 / eccentricity).asin();
+        let mut gain_text = String::new();
+        format_quantity(&mut gain_text, gain.abs() as f64, Unit::Plain, 3);
+        self.label = format!(
+            "{} > {}  DV {}{} KM/S  GIRO {:.0} GRADOS",
+            from.name.to_uppercase(),
+            to.name.to_uppercase(),
+            if gain < 0.0 { "-" } else { "+" },
+            gain_text,
+            turn.to_degrees(),
+        );
+    }
+
+    // Texto del HUD: qué elegir o el resultado del plan
+    pub fn label(&self) -> &str {
+        match (self.origin, self.target) {
+            (None, _) => "SOBREVUELO: ELEGIR ORIGEN",
+            (Some(_), None) => "SOBREVUELO: ELEGIR PLANETA",
+            _ => &self.label,
+        }
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        draw_path(framebuffer, uniforms, &self.transfer, TRAJECTORY_COLOR, Some(DASH_LENGTH));
+        for (arc, color) in self.arcs.iter().zip([APPROACH_COLOR, PERIAPSIS_COLOR, DEPARTURE_COLOR]) {
+            draw_path(framebuffer, uniforms, arc, color, None);
+        }
+    }
+}
+
+// Velocidad de un planeta en su órbita circular real (m/s)
+fn circular_speed(planet: &Planet) -> Option<f32> {
+    let radius = real_orbit(planet)?;
+    Some((SUN_GRAVITATIONAL_PARAMETER / radius).sqrt() as f32)
+}
+
+// Radio real de la órbita de un planeta (m); `None` para los que no están en la tabla
+pub fn real_orbit(planet: &Planet) -> Option<f64> {
+    REAL_ORBITS_AU
+        .iter()
+        .find(|(name, _)| *name == planet.name)
+        .map(|(_, au)| au * ASTRONOMICAL_UNIT)
+}
+
+// Sobrevuelo óptimo de `to` en `arrival` (punto de la escena donde termina la transferencia de
+// Hohmann desde `from`), con las órbitas reales. Las velocidades son tangentes a la órbita del
+// destino, en las direcciones de la escena: la del planeta y las de exceso de entrada y salida
+// (m/s), junto con la excentricidad de la hipérbola.
+pub fn flyby_plan(from: &Planet, to: &Planet, arrival: Vec3) -> Option<(Vec3, Vec3, Vec3, f32)> {
+    let (r1, r2) = (real_orbit(from)?, real_orbit(to)?);
+    let &(_, mass, radius) = REAL_BODIES.iter().find(|(name, _, _)| *name == to.name)?;
+    let outward = arrival.try_normalize(1e-6)?;
+    let direction = if to.orbit_speed < 0.0 { -1.0 } else { 1.0 };
+    let tangent = outward.cross(&Vec3::new(0.0, 1.0, 0.0)) * direction;
+
+    let semi_major = (r1 + r2) / 2.0;
+    let arrival_speed = (SUN_GRAVITATIONAL_PARAMETER * (2.0 / r2 - 1.0 / semi_major)).sqrt() as f32;
+    let planet_vel = tangent * circular_speed(to)?;
+    let spacecraft_vel = tangent * arrival_speed;
+    let closest_approach = MIN_FLYBY_RADII * radius;
+    let departure_vel = gravity_assist(spacecraft_vel, outward * r2 as f32, planet_vel, mass, closest_approach);
+
+    let excess_in = spacecraft_vel - planet_vel;
+    let eccentricity = flyby_eccentricity(excess_in.magnitude(), GRAVITATIONAL_CONSTANT * mass, closest_approach);
+    Some((planet_vel, excess_in, departure_vel - planet_vel, eccentricity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solar_system;
+
+    // Sale de la Tierra por el periapsis y llega a la órbita de Marte al otro lado del Sol, con
+    // el Δv y la duración de la transferencia real (unos 5,6 km/s y 259 días)
+    #[test]
+    fn earth_mars_hohmann_transfer() -> Result<(), String> {
+        let planets = solar_system();
+        let find = |name: &str| planets.iter().find(|planet| planet.name == name).ok_or(format!("no existe {}", name));
+        let (earth, mars) = (find("Tierra")?, find("Marte")?);
+        let departure_time = 3.7;
+
+        let points = hohmann_transfer(earth, mars, departure_time);
+        if points.len() != 200 {
+            return Err(format!("{} puntos, se esperaban 200", points.len()));
+        }
+        let angle = earth.orbit_speed * departure_time as f32;
+        let expected_start = Vec3::new(angle.cos(), 0.0, angle.sin()) * earth.distance_from_sun;
+        let expected_end = -Vec3::new(angle.cos(), 0.0, angle.sin()) * mars.distance_from_sun;
+        if (points[0] - expected_start).magnitude() > 1e-3 || (points[199] - expected_end).magnitude() > 1e-2 {
+            return Err(format!("extremos {:?} y {:?}, se esperaban {:?} y {:?}", points[0], points[199], expected_start, expected_end));
+        }
+        let radii = points.iter().map(|point| point.magnitude());
+        if radii.clone().zip(radii.skip(1)).any(|(previous, next)| next < previous) {
+            return Err("la distancia al Sol no crece a lo largo de la transferencia".to_string());
+        }
+
+        let back = hohmann_transfer(mars, earth, departure_time);
+        if (back[199].magnitude() - earth.distance_from_sun).abs() > 1e-2 {
+            return Err(format!("la vuelta termina a {:.3} del Sol", back[199].magnitude()));
+        }
+
+        let (delta_v, days) = transfer_budget(earth, mars).ok_or("sin órbitas reales para Tierra y Marte")?;
+        if (delta_v - 5.59).abs() > 0.05 || (days - 259.0).abs() > 2.0 {
+            return Err(format!("Δv {:.2} km/s y {:.1} días, se esperaban 5,59 km/s y 259 días", delta_v, days));
+        }
+        Ok(())
+    }
+
+    // Respecto al planeta la velocidad de exceso conserva el módulo y gira 2·asin(1/e); de lejos casi
+    // no cambia. Llegando a Júpiter desde la Tierra la nave gana velocidad respecto al Sol, y las tres
+    // fases de la hipérbola se empalman con el periapsis a la distancia pedida.
+    #[test]
+    fn gravity_assist_turns_excess_velocity() -> Result<(), String> {
+        let (mass, closest) = (5.972e24, 7.0e6);
+        let planet_vel = Vec3::new(0.0, 0.0, 29_780.0);
+        let spacecraft_vel = Vec3::new(3_000.0, 0.0, 25_000.0);
+        let after = gravity_assist(spacecraft_vel, Vec3::new(1.496e11, 0.0, 0.0), planet_vel, mass, closest);
+        let (excess_in, excess_out) = (spacecraft_vel - planet_vel, after - planet_vel);
+        if (excess_out.magnitude() / excess_in.magnitude() - 1.0).abs() > 1e-4 {
+            return Err(format!("v∞ pasó de {} a {} m/s", excess_in.magnitude(), excess_out.magnitude()));
+        }
+        let eccentricity = 1.0 + closest * excess_in.magnitude_squared() / (crate::physics::GRAVITATIONAL_CONSTANT * mass);
+        let expected = 2.0 * (1.0 / eccentricity).asin();
+        let turn = excess_in.normalize().dot(&excess_out.normalize()).clamp(-1.0, 1.0).acos();
+        if (turn - expected).abs() > 1e-3 {
+            return Err(format!("v∞ giró {:.4} rad, se esperaban {:.4}", turn, expected));
+        }
+        let distant = gravity_assist(spacecraft_vel, Vec3::new(1.496e11, 0.0, 0.0), planet_vel, mass, 1e15);
+        if (distant - spacecraft_vel).magnitude() > 1.0 {
+            return Err(format!("un sobrevuelo lejano cambió la velocidad en {} m/s", (distant - spacecraft_vel).magnitude()));
+        }
+
+        let planets = solar_system();
+        let index = |name: &str| planets.iter().position(|planet| planet.name == name).ok_or(format!("no está {}", name));
+        let (earth, jupiter) = (&planets[index("Tierra")?], &planets[index("Júpiter")?]);
+        let arrival = Vec3::new(0.0, 0.0, jupiter.distance_from_sun);
+        let (planet_vel, excess_in, excess_out, eccentricity) = flyby_plan(earth, jupiter, arrival).ok_or("no hay plan para Júpiter")?;
+        let gain = ((planet_vel + excess_out).magnitude() - (planet_vel + excess_in).magnitude()) / 1000.0;
+        if gain < 5.0 {
+            return Err(format!("Júpiter solo le dio {:.2} km/s a la nave", gain));
+        }
+
+        let periapsis = 3.0;
+        let [approach, around, departure] = flyby_arcs(arrival, periapsis, eccentricity, excess_in, excess_out);
+        let (Some(start), Some(end)) = (approach.first(), departure.last()) else {
+            return Err("la hipérbola quedó vacía".to_string());
+        };
+        let closest_point = around.iter().map(|point| (point - arrival).magnitude()).fold(f32::INFINITY, f32::min);
+        if (closest_point / periapsis - 1.0).abs() > 1e-3 {
+            return Err(format!("el periapsis dibujado está a {} en vez de {}", closest_point, periapsis));
+        }
+        if approach.last() != around.first() || around.last() != departure.first() {
+            return Err("las fases no se empalman".to_string());
+        }
+        if (arrival - start).normalize().dot(&excess_in.normalize()) < 0.9 || (end - arrival).normalize().dot(&excess_out.normalize()) < 0.9 {
+            return Err("las asíntotas no siguen a las velocidades de exceso".to_string());
+        }
+        Ok(())
+    }
+
+    // La ecuación de Kepler queda resuelta tanto en órbitas como la de la Tierra como en las muy
+    // excéntricas: la anomalía excéntrica de lo devuelto cumple M = E - e·sin E, y la media vuelta
+    // va del periapsis, a·(1 - e), al apoapsis, a·(1 + e)
+    #[test]
+    fn kepler_equation() -> Result<(), String> {
+        let semi_major = 1.5;
+        for eccentricity in [0.0, 0.0167, 0.2056, 0.7, 0.95] {
+            for step in 0..=16 {
+                let mean_anomaly = PI * step as f32 / 16.0;
+                let (true_anomaly, radius) = from_periapsis(mean_anomaly, eccentricity, semi_major);
+                let half = true_anomaly * 0.5;
+                let eccentric = 2.0 * ((1.0 - eccentricity).sqrt() * half.sin()).atan2((1.0 + eccentricity).sqrt() * half.cos());
+                let residual = eccentric - eccentricity * eccentric.sin() - mean_anomaly;
+                let expected_radius = semi_major * (1.0 - eccentricity * eccentric.cos());
+                if residual.abs() > 1e-4 || (radius - expected_radius).abs() > 1e-4 {
+                    return Err(format!("e = {}, M = {:.3}: residuo {:.2e}, distancia {} en vez de {}", eccentricity, mean_anomaly, residual, radius, expected_radius));
+                }
+            }
+
+            let (start, periapsis) = from_periapsis(0.0, eccentricity, semi_major);
+            let (end, apoapsis) = from_periapsis(PI, eccentricity, semi_major);
+            let (expected_periapsis, expected_apoapsis) = (semi_major * (1.0 - eccentricity), semi_major * (1.0 + eccentricity));
+            if start.abs() > 1e-4 || (end - PI).abs() > 1e-3 || (periapsis - expected_periapsis).abs() > 1e-4 || (apoapsis - expected_apoapsis).abs() > 1e-4 {
+                return Err(format!("e = {}: la media vuelta va de {} a {} rad, distancias {} y {}", eccentricity, start, end, periapsis, apoapsis));
+            }
+        }
+        Ok(())
+    }
+}
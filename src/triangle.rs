@@ -3,67 +3,329 @@ use crate::fragment::Fragment;
 use crate::vertex::{self, Vertex};
 use crate::color::Color;
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+// Qué hacer con los centros de píxel que caen justo sobre una arista
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+  // Regla superior-izquierda: el píxel es de la arista solo si es superior (horizontal con el
+  // interior debajo) o izquierda (con el interior a la derecha). Dos triángulos que comparten
+  // una arista se reparten sus píxeles sin repetir ninguno ni dejar huecos.
+  TopLeft,
+  // Todo píxel sobre la arista es del triángulo: las aristas compartidas se dibujan dos veces.
+  // Con `--fill-rule inclusive`, para comparar con la regla superior-izquierda.
+  Inclusive,
+}
+
+impl FillRule {
+  pub fn from_args(args: &[String]) -> Result<Self, String> {
+    let Some(position) = args.iter().position(|arg| arg == "--fill-rule") else {
+      return Ok(FillRule::TopLeft);
+    };
+    match args.get(position + 1).map(String::as_str) {
+      Some("top-left") => Ok(FillRule::TopLeft),
+      Some("inclusive") => Ok(FillRule::Inclusive),
+      _ => Err("--fill-rule espera top-left o inclusive".to_string()),
+    }
+  }
+}
+
+// Los vértices se fijan a una rejilla de 1/2^SUBPIXEL_BITS de píxel antes de evaluar las
+// aristas. En enteros, la función de arista de un triángulo es exactamente la opuesta de la
+// del vecino, así que ningún redondeo puede dar el mismo píxel a los dos o a ninguno.
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: f32 = (1 << SUBPIXEL_BITS) as f32;
+
+// Límite de las coordenadas en punto fijo (unos 4e9 píxeles): los productos caben en i128
+const MAX_FIXED_COORDINATE: i128 = 1 << 40;
+
+// Aristas de un triángulo en punto fijo, orientadas para que el interior sea positivo
+struct Edges {
+  vertices: [(i128, i128); 3],
+  orientation: i128,
+  area: i128,
+  // Por arista (opuesta a cada vértice): si se queda con los píxeles que caen justo encima
+  owns_boundary: [bool; 3],
+}
+
+impl Edges {
+  fn new(a: &Vec3, b: &Vec3, c: &Vec3, rule: FillRule) -> Option<Self> {
+    let vertices = [to_fixed(a), to_fixed(b), to_fixed(c)];
+    let area = fixed_edge_function(vertices[0], vertices[1], vertices[2]);
+    if area == 0 {
+      return None;
+    }
+    let orientation = area.signum();
+
+    let mut owns_boundary = [true; 3];
+    if rule == FillRule::TopLeft {
+      for (i, owns) in owns_boundary.iter_mut().enumerate() {
+        let (start, end) = (vertices[(i + 1) % 3], vertices[(i + 2) % 3]);
+        // Normal hacia el interior: el gradiente de la función de arista con su signo
+        let (inward_x, inward_y) = ((end.1 - start.1) * orientation, (start.0 - end.0) * orientation);
+        *owns = inward_x > 0 || (inward_x == 0 && inward_y > 0);
+      }
+    }
+
+    Some(Edges { vertices, orientation, area: area * orientation, owns_boundary })
+  }
+
+  // Coordenadas baricéntricas de un punto en píxeles, si el triángulo lo cubre
+  fn cover(&self, x: f32, y: f32) -> Option<(f32, f32, f32)> {
+    let point = (fixed(x), fixed(y));
+    let [a, b, c] = self.vertices;
+    let weights = [
+      fixed_edge_function(b, c, point) * self.orientation,
+      fixed_edge_function(c, a, point) * self.orientation,
+      fixed_edge_function(a, b, point) * self.orientation,
+    ];
+
+    let inside = weights
+      .iter()
+      .zip(self.owns_boundary.iter())
+      .all(|(&weight, &owns)| weight > 0 || (weight == 0 && owns));
+    if !inside {
+      return None;
+    }
+
+    let area = self.area as f64;
+    Some(((weights[0] as f64 / area) as f32, (weights[1] as f64 / area) as f32, (weights[2] as f64 / area) as f32))
+  }
+}
+
+fn fixed(value: f32) -> i128 {
+  ((value * SUBPIXEL_SCALE).round() as i128).clamp(-MAX_FIXED_COORDINATE, MAX_FIXED_COORDINATE)
+}
+
+fn to_fixed(v: &Vec3) -> (i128, i128) {
+  (fixed(v.x), fixed(v.y))
+}
+
+fn fixed_edge_function(a: (i128, i128), b: (i128, i128), c: (i128, i128)) -> i128 {
+  (c.0 - a.0) * (b.1 - a.1) - (c.1 - a.1) * (b.0 - a.0)
+}
+
+// Rasteriza un triángulo ya en coordenadas de pantalla, recorriendo solo la parte de su caja
+// envolvente que cae dentro del viewport de `width` x `height` píxeles
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, width: usize, height: usize) -> Vec<Fragment> {
+  triangle_with_fill_rule(v1, v2, v3, width, height, FillRule::TopLeft)
+}
+
+pub fn triangle_with_fill_rule(v1: &Vertex, v2: &Vertex, v3: &Vertex, width: usize, height: usize, rule: FillRule) -> Vec<Fragment> {
   let mut fragments = Vec::new();
   let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
-  let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+  // Un vértice detrás de la cámara proyecta al lado contrario de la pantalla: sin recorte
+  // contra el plano near, el triángulo cubriría la pantalla entera con atributos sin sentido
+  if a.z <= 0.0 || b.z <= 0.0 || c.z <= 0.0 {
+    return fragments;
+  }
 
-  let light_dir = Vec3::new(0.0, 0.0, 1.0);
+  // Triángulo menor que un píxel: puede no cubrir ningún centro de píxel y parpadear o
+  // desaparecer. Aporta siempre el píxel de su centroide (si gana la prueba de profundidad).
+  if is_subpixel(&a, &b, &c) {
+    let centroid = (a + b + c) / 3.0;
+    if centroid.x >= 0.0 && centroid.y >= 0.0 && (centroid.x as usize) < width && (centroid.y as usize) < height {
+      let third = 1.0 / 3.0;
+      fragments.push(interpolate_fragment([v1, v2, v3], centroid.x.floor(), centroid.y.floor(), (third, third, third)));
+    }
+    return fragments;
+  }
 
-  let triangle_area = edge_function(&a, &b, &c);
+  let (min_x, min_y, max_x, max_y) = match calculate_bounding_box(&a, &b, &c, width, height) {
+    Some(bounds) => bounds,
+    None => return fragments,
+  };
+  let edges = match Edges::new(&a, &b, &c, rule) {
+    Some(edges) => edges,
+    None => return fragments,
+  };
 
   // Iterate over each pixel in the bounding box
   for y in min_y..=max_y {
     for x in min_x..=max_x {
-      let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+      if let Some(weights) = edges.cover(x as f32 + 0.5, y as f32 + 0.5) {
+        fragments.push(interpolate_fragment([v1, v2, v3], x as f32, y as f32, weights));
+      }
+    }
+  }
 
-      // Calculate barycentric coordinates
-      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+  fragments
+}
+
+// Fragmento en el píxel (x, y) con los atributos de los tres vértices interpolados según los
+// pesos (w1, w2, w3)
+fn interpolate_fragment(vertices: [&Vertex; 3], x: f32, y: f32, weights: (f32, f32, f32)) -> Fragment {
+  let [v1, v2, v3] = vertices;
+  let (w1, w2, w3) = weights;
+  let light_dir = Vec3::new(0.0, 0.0, 1.0);
 
-      // Check if the point is inside the triangle
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+  // Interpolate normal
+  let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
+  let normal = normal.normalize();
 
-        // Interpolate normal
-        let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-        let normal = normal.normalize();
+  // Calculate lighting intensity
+  let intensity = dot(&normal, &light_dir).max(0.0);
 
-        // Calculate lighting intensity
-        let intensity = dot(&normal, &light_dir).max(0.0);
+  // Create a gray color (unchanged)
+  let color = Color::new(100, 100, 100); // Medium gray
 
-        // Create a gray color (unchanged)
-        let color = Color::new(100, 100, 100); // Medium gray
+  // Interpolate depth
+  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+  let depth = interpolate_depth(a.z, b.z, c.z, w1, w2, w3);
+
+  // Positions of the original vertex
+  let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+  let world_position = v1.world_position * w1 + v2.world_position * w2 + v3.world_position * w3;
+
+  Fragment::new(
+      Vec2::new(x, y),
+      color,
+      depth,
+      normal,
+      intensity,
+      vertex_position,
+      world_position,
+  )
+}
+
+// Caja envolvente de menos de un píxel de lado (y con coordenadas válidas)
+fn is_subpixel(a: &Vec3, b: &Vec3, c: &Vec3) -> bool {
+  let finite = [a, b, c].iter().all(|v| v.x.is_finite() && v.y.is_finite() && v.z.is_finite());
+  let span_x = a.x.max(b.x).max(c.x) - a.x.min(b.x).min(c.x);
+  let span_y = a.y.max(b.y).max(c.y) - a.y.min(b.y).min(c.y);
+  finite && span_x < 1.0 && span_y < 1.0
+}
 
-        // Interpolate depth
-        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+// Posiciones de las submuestras 2x2 dentro de cada píxel
+const SUBSAMPLES: [(f32, f32); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
 
-        // Positions of the original vertex
-        let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+// Cobertura mínima para píxeles que la geometría delgada cruza sin tocar ninguna submuestra
+const MIN_THIN_COVERAGE: f32 = 0.25;
 
-        fragments.push(Fragment::new(
-            Vec2::new(x as f32, y as f32),
-            color,
-            depth,
-            normal,
-            intensity,
-            vertex_position,
-        ));
+// Igual que `triangle`, pero evalúa 2x2 submuestras por píxel y devuelve la cobertura
+// (0.0 a 1.0) de cada fragmento. Pensado para geometría delgada como los anillos.
+pub fn triangle_coverage(v1: &Vertex, v2: &Vertex, v3: &Vertex, width: usize, height: usize) -> Vec<(Fragment, f32)> {
+  let mut fragments = Vec::new();
+  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+
+  let triangle_area = edge_function(&a, &b, &c);
+  if triangle_area == 0.0 || !triangle_area.is_finite() {
+    return fragments;
+  }
+
+  let (min_x, min_y, max_x, max_y) = match calculate_bounding_box(&a, &b, &c, width, height) {
+    Some(bounds) => bounds,
+    None => return fragments,
+  };
+  let edges = match Edges::new(&a, &b, &c, FillRule::TopLeft) {
+    Some(edges) => edges,
+    None => return fragments,
+  };
+
+  // Longitud de cada arista, para convertir las coordenadas baricéntricas en distancias en píxeles
+  let edge_lengths = [(c - b).magnitude(), (a - c).magnitude(), (b - a).magnitude()];
+
+  for y in min_y..=max_y {
+    for x in min_x..=max_x {
+      let mut covered = 0;
+      let mut sample_sum = Vec3::new(0.0, 0.0, 0.0);
+
+      for (ox, oy) in SUBSAMPLES.iter() {
+        // Las submuestras siguen la misma regla, para no sumar dos veces la arista compartida
+        if edges.cover(x as f32 + ox, y as f32 + oy).is_some() {
+          covered += 1;
+          sample_sum += Vec3::new(x as f32 + ox, y as f32 + oy, 0.0);
+        }
       }
+
+      let center = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+      let (coverage, point) = if covered > 0 {
+        (covered as f32 / SUBSAMPLES.len() as f32, sample_sum / covered as f32)
+      } else {
+        // Un triángulo casi de canto puede cruzar el píxel sin tocar ninguna submuestra:
+        // si el centro está a menos de medio píxel, se dibuja como una línea tenue y estable
+        let (w1, w2, w3) = barycentric_coordinates(&center, &a, &b, &c, triangle_area);
+        let height_scale = triangle_area.abs();
+        let within_half_pixel = [w1, w2, w3]
+          .iter()
+          .zip(edge_lengths.iter())
+          .all(|(w, length)| *length > 0.0 && -w * height_scale / length <= 0.5);
+        if !within_half_pixel {
+          continue;
+        }
+        (MIN_THIN_COVERAGE, center)
+      };
+
+      // Interpolar los atributos en el centroide de las submuestras cubiertas
+      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+      let (w1, w2, w3) = (w1.clamp(0.0, 1.0), w2.clamp(0.0, 1.0), w3.clamp(0.0, 1.0));
+      let weight_sum = w1 + w2 + w3;
+      if weight_sum <= 0.0 {
+        continue;
+      }
+      let (w1, w2, w3) = (w1 / weight_sum, w2 / weight_sum, w3 / weight_sum);
+
+      let normal = (v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3).normalize();
+      let intensity = dot(&normal, &Vec3::new(0.0, 0.0, 1.0)).max(0.0);
+      let depth = interpolate_depth(a.z, b.z, c.z, w1, w2, w3);
+      let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+      let world_position = v1.world_position * w1 + v2.world_position * w2 + v3.world_position * w3;
+
+      fragments.push((
+        Fragment::new(
+          Vec2::new(x as f32, y as f32),
+          Color::new(100, 100, 100),
+          depth,
+          normal,
+          intensity,
+          vertex_position,
+          world_position,
+        ),
+        coverage,
+      ));
     }
   }
 
   fragments
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
-    let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
-    let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
-    let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
-    let max_y = v1.y.max(v2.y).max(v3.y).ceil() as i32;
+// La profundidad es la distancia en espacio de vista; su inverso es lineal en pantalla,
+// así que se interpola 1/z para obtener una profundidad correcta en perspectiva
+pub fn interpolate_depth(za: f32, zb: f32, zc: f32, w1: f32, w2: f32, w3: f32) -> f32 {
+    if za <= 0.0 || zb <= 0.0 || zc <= 0.0 {
+        return za * w1 + zb * w2 + zc * w3;
+    }
+
+    let inverse_depth = w1 / za + w2 / zb + w3 / zc;
+    if inverse_depth > 0.0 {
+        1.0 / inverse_depth
+    } else {
+        za * w1 + zb * w2 + zc * w3
+    }
+}
 
-    (min_x, min_y, max_x, max_y)
+// Los modelos usan caras en sentido antihorario; como el viewport invierte el eje y,
+// en pantalla las caras frontales tienen área con signo positiva
+pub fn is_backface(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+    edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position) <= 0.0
+}
+
+// Caja envolvente recortada al viewport; `None` si queda fuera o las coordenadas no son válidas.
+// Sin recortar, un planeta enorme (o con vértices casi detrás de la cámara) recorrería
+// millones de píxeles fuera de pantalla.
+fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3, width: usize, height: usize) -> Option<(i32, i32, i32, i32)> {
+    if width == 0 || height == 0 || [v1, v2, v3].iter().any(|v| !v.x.is_finite() || !v.y.is_finite()) {
+        return None;
+    }
+
+    let min_x = v1.x.min(v2.x).min(v3.x).floor().max(0.0);
+    let min_y = v1.y.min(v2.y).min(v3.y).floor().max(0.0);
+    let max_x = v1.x.max(v2.x).max(v3.x).ceil().min((width - 1) as f32);
+    let max_y = v1.y.max(v2.y).max(v3.y).ceil().min((height - 1) as f32);
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+
+    Some((min_x as i32, min_y as i32, max_x as i32, max_y as i32))
 }
 
 fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
@@ -76,4 +338,118 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
 
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Un cuadrado partido en dos triángulos, girado y desplazado una fracción de píxel: con la
+  // regla superior-izquierda cada píxel con el centro dentro del cuadrado se cubre exactamente
+  // una vez (también los de la diagonal y los de aristas sobre centros de píxel). La regla
+  // inclusiva, en cambio, repite los de la diagonal cuando caen justo sobre ella.
+  #[test]
+  fn fill_rule() -> Result<(), String> {
+    let (size, half) = (48usize, 10.0f32);
+    let corner = |x: f32, y: f32| {
+      let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+      vertex.transformed_position = Vec3::new(x, y, 1.0);
+      vertex
+    };
+
+    let coverage = |corners: &[Vec2; 4], rule: FillRule, reversed: bool| {
+      let vertices: Vec<Vertex> = corners.iter().map(|c| corner(c.x, c.y)).collect();
+      let mut triangles = [[0, 1, 2], [0, 2, 3]];
+      if reversed {
+        triangles = [[2, 1, 0], [3, 2, 0]];
+      }
+      let mut counts = vec![0u32; size * size];
+      for [i, j, k] in triangles {
+        for fragment in triangle_with_fill_rule(&vertices[i], &vertices[j], &vertices[k], size, size, rule) {
+          counts[fragment.position.y as usize * size + fragment.position.x as usize] += 1;
+        }
+      }
+      counts
+    };
+
+    for angle_degrees in [0.0f32, 15.0, 30.0, 45.0, 90.0, 137.0] {
+      for offset in [0.0f32, 0.25, 0.5, 0.375] {
+        let (sin, cos) = angle_degrees.to_radians().sin_cos();
+        let center = Vec2::new(size as f32 * 0.5 + offset, size as f32 * 0.5 + offset * 0.5);
+        let corners = [(-half, -half), (half, -half), (half, half), (-half, half)]
+          .map(|(x, y)| center + Vec2::new(x * cos - y * sin, x * sin + y * cos));
+
+        for reversed in [false, true] {
+          let counts = coverage(&corners, FillRule::TopLeft, reversed);
+          for y in 0..size {
+            for x in 0..size {
+              let count = counts[y * size + x];
+              let pixel = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center;
+              let local = Vec2::new(pixel.x * cos + pixel.y * sin, -pixel.x * sin + pixel.y * cos);
+              let strictly_inside = local.x.abs() < half - 1e-3 && local.y.abs() < half - 1e-3;
+              let outside = local.x.abs() > half + 1e-3 || local.y.abs() > half + 1e-3;
+              if count > 1 || (strictly_inside && count != 1) || (outside && count != 0) {
+                return Err(format!(
+                  "{}° desplazado {}: el píxel ({}, {}) se cubrió {} veces",
+                  angle_degrees, offset, x, y, count
+                ));
+              }
+            }
+          }
+        }
+      }
+    }
+
+    // Cuadrado alineado con los centros de píxel: su diagonal pasa por ellos
+    let corners = [(4.5, 4.5), (20.5, 4.5), (20.5, 20.5), (4.5, 20.5)].map(|(x, y)| Vec2::new(x, y));
+    if !coverage(&corners, FillRule::Inclusive, false).iter().any(|&count| count > 1) {
+      return Err("la regla inclusiva no repitió la diagonal".to_string());
+    }
+    Ok(())
+  }
+
+  // Dos superficies casi coplanares a unas 150 unidades de la cámara, inclinadas de 145 a 155 y
+  // separadas 0.005. Un z-buffer de 24 bits en z normalizada con near 0.1 y far 1000 solo
+  // distingue unas 0.013 unidades a esa distancia y las mezclaría; la distancia a la cámara en
+  // `f32` las ordena en cada píxel que cubren las dos.
+  #[test]
+  fn coplanar_depth_resolution() -> Result<(), String> {
+    let (size, gap) = (64usize, 0.005f32);
+    let corner = |x: f32, y: f32, depth: f32| {
+      let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+      vertex.transformed_position = Vec3::new(x, y, depth);
+      vertex
+    };
+
+    let depths = |offset: f32| {
+      let extent = size as f32;
+      let corners = [(0.0, 0.0), (extent, 0.0), (extent, extent), (0.0, extent)]
+        .map(|(x, y)| corner(x, y, 145.0 + 10.0 * (x + y) / (2.0 * extent) + offset));
+      let mut depths = vec![None; size * size];
+      for [i, j, k] in [[0, 1, 2], [0, 2, 3]] {
+        for fragment in triangle(&corners[i], &corners[j], &corners[k], size, size) {
+          depths[fragment.position.y as usize * size + fragment.position.x as usize] = Some(fragment.depth);
+        }
+      }
+      depths
+    };
+
+    let (front, back) = (depths(0.0), depths(gap));
+    let mut covered = 0;
+    for (index, pair) in front.iter().zip(&back).enumerate() {
+      if let (Some(near), Some(far)) = pair {
+        if near >= far {
+          return Err(format!(
+            "en el píxel ({}, {}) la superficie de delante quedó a {} y la de detrás a {}",
+            index % size, index / size, near, far
+          ));
+        }
+        covered += 1;
+      }
+    }
+    if covered == 0 {
+      return Err("ninguna de las dos superficies llegó a cubrir un píxel".to_string());
+    }
+    Ok(())
+  }
+}
@@ -10,6 +10,11 @@ use crate::Camera;
 use crate::triangle;
 use crate::{vertex_shader};
 use crate::{FastNoiseLite, NoiseType, FractalType};
+use crate::texture::Texture;
+use crate::rings::{RingBand, RingArc};
+use crate::light::{LightProbe, Occluder};
+use crate::cutaway::CutawayPlane;
+use crate::color::{Color, simulate_protanopia, simulate_deuteranopia, simulate_tritanopia};
 
 #[derive(Clone)]
 pub struct Uniforms {
@@ -17,32 +22,160 @@ pub struct Uniforms {
     pub view_matrix: Mat4,
     pub projection_matrix: Mat4,
     pub viewport_matrix: Mat4,
-    pub time: u32,
+    // Tiempo de la simulación acotado (`SimClock::shader_time`), para lo que se mueve en los shaders
+    pub time: f32,
     pub noise: Arc<FastNoiseLite>,
+    pub displacement: Displacement,
+    pub view_pos: Vec3,
+    pub height_map: Option<Arc<Texture>>,
+    pub color_map: Option<Arc<Texture>>,
+    // Relieve para el parallax de la superficie (la Tierra); no desplaza la geometría
+    pub parallax_map: Option<Arc<Texture>>,
+    // Normales del relieve precalculadas (espacio tangente), para iluminar el detalle sin ruido
+    pub normal_map: Option<Arc<Texture>>,
+    // Nivel de mip-map con que se muestrean las texturas del objeto actual
+    pub texture_lod: usize,
+    pub render_mode: RenderMode,
+    // Daltonismo simulado sobre el color de los cuerpos
+    pub color_blind_mode: ColorBlindMode,
+    // Velocidad de la nave (m/s) en el modo warp; el fondo se ve con la aberración que le toca
+    pub spacecraft_velocity: Vec3,
+    // Copia del frame ya dibujado, para los reflejos en espacio de pantalla
+    pub reflection_source: Option<Arc<Framebuffer>>,
+    // Perfil radial de densidad de los anillos que se están dibujando
+    pub ring_profile: Option<Arc<Vec<RingBand>>>,
+    // Arcos incompletos de esos anillos, que modulan su opacidad
+    pub ring_arcs: Option<Arc<Vec<RingArc>>>,
+    // Radiancia del fondo en todas las direcciones, para los reflejos del entorno
+    pub light_probe: Option<Arc<LightProbe>>,
+    // Plano de la vista en corte del cuerpo que se está dibujando
+    pub cutaway: Option<CutawayPlane>,
+    // Radio del Sol en mundo y cuerpos que pueden eclipsarlo al que se está dibujando
+    pub sun_radius: f32,
+    pub occluders: Vec<Occluder>,
+    // Contraste del color base del cuerpo que se está dibujando (ver `Planet::contrast`)
+    pub contrast: f32,
+    // Regla para los píxeles que caen sobre las aristas (`--fill-rule`)
+    pub fill_rule: triangle::FillRule,
 }
 
-pub fn create_noise() -> FastNoiseLite {
-    create_cloud_noise() 
-    // create_cell_noise()
-    // create_ground_noise()
-    // create_lava_noise()
+// Modo de dibujo de las mallas, para depurar geometría y desplazamientos
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RenderMode {
+    Solid,
+    Wireframe,
+    Normals,
 }
 
-pub fn create_cloud_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
+impl RenderMode {
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Solid => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Normals,
+            RenderMode::Normals => RenderMode::Solid,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Solid => "SOLIDO",
+            RenderMode::Wireframe => "WIREFRAME",
+            RenderMode::Normals => "NORMALES",
+        }
+    }
+}
+
+// Tipo de daltonismo que se simula al sombrear (Ctrl+D), para revisar que los cuerpos se
+// distingan sin depender del color
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorBlindMode {
+    None,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl ColorBlindMode {
+    pub fn next(self) -> Self {
+        match self {
+            ColorBlindMode::None => ColorBlindMode::Protanopia,
+            ColorBlindMode::Protanopia => ColorBlindMode::Deuteranopia,
+            ColorBlindMode::Deuteranopia => ColorBlindMode::Tritanopia,
+            ColorBlindMode::Tritanopia => ColorBlindMode::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorBlindMode::None => "NINGUNO",
+            ColorBlindMode::Protanopia => "PROTANOPIA",
+            ColorBlindMode::Deuteranopia => "DEUTERANOPIA",
+            ColorBlindMode::Tritanopia => "TRITANOPIA",
+        }
+    }
+
+    pub fn apply(self, color: Color) -> Color {
+        match self {
+            ColorBlindMode::None => color,
+            ColorBlindMode::Protanopia => simulate_protanopia(color),
+            ColorBlindMode::Deuteranopia => simulate_deuteranopia(color),
+            ColorBlindMode::Tritanopia => simulate_tritanopia(color),
+        }
+    }
+}
+
+// Parámetros del relieve aplicado en el vertex shader a cada objeto.
+// La amplitud está en espacio de objeto, así que la matriz de modelo la escala
+// junto con el radio del planeta: las montañas mantienen su proporción.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Displacement {
+    pub amplitude: f32,
+    pub zoom: f32,
+    pub enabled: bool,
+}
+
+impl Displacement {
+    // Superficie perfectamente lisa (gigantes gaseosos, el Sol, anillos)
+    pub fn none() -> Self {
+        Displacement {
+            amplitude: 0.0,
+            zoom: 0.0,
+            enabled: false,
+        }
+    }
+
+    // Relieve de ruido para planetas rocosos
+    pub fn terrain(amplitude: f32, zoom: f32) -> Self {
+        Displacement {
+            amplitude,
+            zoom,
+            enabled: true,
+        }
+    }
+}
+
+pub fn create_noise(seed: i32) -> FastNoiseLite {
+    create_cloud_noise(seed)
+    // create_cell_noise(seed)
+    // create_ground_noise(seed)
+    // create_lava_noise(seed)
+}
+
+pub fn create_cloud_noise(seed: i32) -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(seed);
     noise.set_noise_type(Some(NoiseType::OpenSimplex2));
     noise
 }
 
-pub fn create_cell_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
+pub fn create_cell_noise(seed: i32) -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(seed);
     noise.set_noise_type(Some(NoiseType::Cellular));
     noise.set_frequency(Some(0.1));
     noise
 }
 
-pub fn create_ground_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
+pub fn create_ground_noise(seed: i32) -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(seed);
     
     // Use FBm fractal type to layer multiple octaves of noise
     noise.set_noise_type(Some(NoiseType::Cellular)); // Cellular noise for cracks
@@ -55,8 +188,8 @@ pub fn create_ground_noise() -> FastNoiseLite {
     noise
 }
 
-pub fn create_lava_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(42);
+pub fn create_lava_noise(seed: i32) -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(seed);
     
     // Use FBm for multi-layered noise, giving a "turbulent" feel
     noise.set_noise_type(Some(NoiseType::Perlin));  // Perlin noise for smooth, natural texture
@@ -70,6 +203,13 @@ pub fn create_lava_noise() -> FastNoiseLite {
 }
 
 pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    create_oblate_model_matrix(translation, scale, 0.0, rotation)
+}
+
+// Igual que `create_model_matrix`, pero achatando los polos: los ejes ecuatoriales (X, Z)
+// se escalan por `1 + oblateness` y el polar (Y) queda igual. Con escala no uniforme las
+// normales deben transformarse con la transpuesta inversa (ver `vertex_shader`).
+pub fn create_oblate_model_matrix(translation: Vec3, scale: f32, oblateness: f32, rotation: Vec3) -> Mat4 {
     let (sin_x, cos_x) = rotation.x.sin_cos();
     let (sin_y, cos_y) = rotation.y.sin_cos();
     let (sin_z, cos_z) = rotation.z.sin_cos();
@@ -97,11 +237,12 @@ pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat
 
     let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
 
+    let scale = Vec3::new(1.0 + oblateness, 1.0, 1.0 + oblateness) * scale;
     let transform_matrix = Mat4::new(
-        scale, 0.0,   0.0,   translation.x,
-        0.0,   scale, 0.0,   translation.y,
-        0.0,   0.0,   scale, translation.z,
-        0.0,   0.0,   0.0,   1.0,
+        scale.x, 0.0,     0.0,     translation.x,
+        0.0,     scale.y, 0.0,     translation.y,
+        0.0,     0.0,     scale.z, translation.z,
+        0.0,     0.0,     0.0,     1.0,
     );
 
     transform_matrix * rotation_matrix
@@ -112,13 +253,45 @@ pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
     look_at(&eye, &center, &up)
 }
 
-pub fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
+// Planos de recorte por defecto
+pub const DEFAULT_NEAR_PLANE: f32 = 0.1;
+pub const DEFAULT_FAR_PLANE: f32 = 1000.0;
+
+// Límites para los planos adaptativos
+const MIN_NEAR_PLANE: f32 = 0.05;
+const MAX_NEAR_PLANE: f32 = 5.0;
+const SCENE_EXTENT: f32 = 500.0;
+
+// Campo de visión vertical de la cámara (radianes). Con 800x600 deja el horizontal en unos 64°.
+pub const DEFAULT_FOV_Y: f32 = 50.0 * PI / 180.0;
+
+// Proyección en perspectiva con campo de visión vertical `fov_y` (radianes). Ojo con
+// `nalgebra_glm::perspective`: recibe la relación de aspecto antes que el campo de visión.
+pub fn create_perspective_matrix(window_width: f32, window_height: f32, fov_y: f32, near: f32, far: f32) -> Mat4 {
     let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
 
-    perspective(fov, aspect_ratio, near, far)
+    perspective(aspect_ratio, fov_y, near, far)
+}
+
+// Ajusta los planos near/far a la distancia entre la cámara y su objetivo:
+// acercarse a un planeta acerca el plano near, la vista de pájaro lo aleja
+pub fn adaptive_clip_planes(target_distance: f32) -> (f32, f32) {
+    let near = (target_distance * 0.01).clamp(MIN_NEAR_PLANE, MAX_NEAR_PLANE);
+    let far = (target_distance + SCENE_EXTENT).max(DEFAULT_FAR_PLANE);
+
+    (near, far)
+}
+
+// Planos near/far fijos con `--clip-planes <near> <far>`, en lugar de los adaptativos; `None` sin la opción
+pub fn clip_planes_from_args(args: &[String]) -> Result<Option<(f32, f32)>, String> {
+    let Some(position) = args.iter().position(|arg| arg == "--clip-planes") else {
+        return Ok(None);
+    };
+    let plane = |offset: usize| args.get(position + offset).and_then(|value| value.parse::<f32>().ok());
+    match (plane(1), plane(2)) {
+        (Some(near), Some(far)) if near > 0.0 && near < far && far.is_finite() => Ok(Some((near, far))),
+        _ => Err("--clip-planes espera near y far con 0 < near < far, como 0.1 1000".to_string()),
+    }
 }
 
 pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
@@ -128,4 +301,141 @@ pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
         0.0, 0.0, 1.0, 0.0,
         0.0, 0.0, 0.0, 1.0
     )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec4, rotate_x_vec3, rotate_y_vec3, rotate_z_vec3};
+    use crate::selftest::{WIDTH, HEIGHT};
+
+    // Punto del mundo a píxel con las tres matrices de cámara
+    fn to_screen(view: &Mat4, projection: &Mat4, viewport: &Mat4, point: Vec3) -> Vec3 {
+        let clip = projection * view * Vec4::new(point.x, point.y, point.z, 1.0);
+        let screen = viewport * (clip / clip.w);
+        Vec3::new(screen.x, screen.y, screen.z)
+    }
+
+    // Lo que la cámara mira cae en el centro del viewport, desde cualquier posición
+    #[test]
+    fn screen_center() -> Result<(), String> {
+        let projection = create_perspective_matrix(WIDTH, HEIGHT, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE);
+        let viewport = create_viewport_matrix(WIDTH, HEIGHT);
+        for (eye, center) in [
+            (Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0)),
+            (Vec3::new(30.0, 12.0, -5.0), Vec3::new(-4.0, 2.0, 7.0)),
+        ] {
+            let view = create_view_matrix(eye, center, Vec3::new(0.0, 1.0, 0.0));
+            let screen = to_screen(&view, &projection, &viewport, center);
+            if (screen.x - WIDTH / 2.0).abs() > 1e-3 || (screen.y - HEIGHT / 2.0).abs() > 1e-3 {
+                return Err(format!("el centro de la vista cae en ({:.3}, {:.3})", screen.x, screen.y));
+            }
+        }
+        Ok(())
+    }
+
+    // Con FOV vertical de 90° y la cámara a 10 unidades, el borde superior de la vista está a 10
+    // unidades del eje y el derecho a 10 · 4/3: un punto a la mitad de ese ancho en +X cae a un
+    // cuarto de la pantalla a la derecha del centro, y uno a 10 en +Y en el borde superior
+    #[test]
+    fn known_fov_pixel() -> Result<(), String> {
+        let projection = create_perspective_matrix(WIDTH, HEIGHT, 0.5 * PI, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE);
+        let viewport = create_viewport_matrix(WIDTH, HEIGHT);
+        let view = create_view_matrix(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let right = to_screen(&view, &projection, &viewport, Vec3::new(0.5 * 10.0 * WIDTH / HEIGHT, 0.0, 0.0));
+        if (right.x - 0.75 * WIDTH).abs() > 1e-2 || (right.y - HEIGHT / 2.0).abs() > 1e-2 {
+            return Err(format!("el punto en +X cae en ({:.3}, {:.3}), se esperaba ({}, {})", right.x, right.y, 0.75 * WIDTH, HEIGHT / 2.0));
+        }
+        let top = to_screen(&view, &projection, &viewport, Vec3::new(0.0, 10.0, 0.0));
+        if (top.x - WIDTH / 2.0).abs() > 1e-2 || top.y.abs() > 1e-2 {
+            return Err(format!("el punto en +Y cae en ({:.3}, {:.3}), se esperaba ({}, 0)", top.x, top.y, WIDTH / 2.0));
+        }
+        Ok(())
+    }
+
+    // La matriz de modelo rota (X, luego Y, luego Z), escala y por último traslada
+    #[test]
+    fn model_matrix_order() -> Result<(), String> {
+        let (translation, scale, rotation) = (Vec3::new(5.0, -2.0, 3.0), 2.5, Vec3::new(0.3, 1.1, -0.7));
+        let model = create_model_matrix(translation, scale, rotation);
+
+        for point in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.3, -0.8, 0.5)] {
+            let rotated = rotate_z_vec3(&rotate_y_vec3(&rotate_x_vec3(&point, rotation.x), rotation.y), rotation.z);
+            let expected = translation + rotated * scale;
+            let transformed = model * Vec4::new(point.x, point.y, point.z, 1.0);
+            if (transformed.xyz() - expected).magnitude() > 1e-4 || (transformed.w - 1.0).abs() > 1e-6 {
+                return Err(format!("{:?} da {:?}, se esperaba {:?}", point, transformed.xyz(), expected));
+            }
+        }
+        Ok(())
+    }
+
+    // La vista es la inversa de la transformación de la cámara en el mundo (ejes derecha, arriba
+    // y atrás, con origen en el ojo)
+    #[test]
+    fn view_matrix_inverse() -> Result<(), String> {
+        let (eye, center, up) = (Vec3::new(12.0, 7.0, -20.0), Vec3::new(-3.0, 1.0, 4.0), Vec3::new(0.0, 1.0, 0.0));
+        let forward = (center - eye).normalize();
+        let right = forward.cross(&up).normalize();
+        let camera_up = right.cross(&forward);
+        let world = Mat4::new(
+            right.x, camera_up.x, -forward.x, eye.x,
+            right.y, camera_up.y, -forward.y, eye.y,
+            right.z, camera_up.z, -forward.z, eye.z,
+            0.0, 0.0, 0.0, 1.0,
+        );
+
+        let product = create_view_matrix(eye, center, up) * world;
+        let error = (product - Mat4::identity()).abs().max();
+        if error > 1e-5 {
+            return Err(format!("vista · cámara se aleja de la identidad en {:.2e}", error));
+        }
+        Ok(())
+    }
+
+    // Un píxel llevado de vuelta al mundo con las inversas da un rayo que sale del ojo y que,
+    // proyectado otra vez, cae en el mismo píxel (lo que necesita la selección con el mouse)
+    #[test]
+    fn screen_ray_round_trip() -> Result<(), String> {
+        let eye = Vec3::new(40.0, 25.0, 60.0);
+        let view = create_view_matrix(eye, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let projection = create_perspective_matrix(WIDTH, HEIGHT, DEFAULT_FOV_Y, DEFAULT_NEAR_PLANE, DEFAULT_FAR_PLANE);
+        let viewport = create_viewport_matrix(WIDTH, HEIGHT);
+        let inverse = (viewport * projection * view).try_inverse().ok_or("las matrices no son invertibles")?;
+
+        for (x, y) in [(400.0, 300.0), (12.5, 40.0), (790.0, 580.0), (200.0, 450.0)] {
+            // z de la pantalla es el de NDC: -1 en el plano cercano y 1 en el lejano
+            let unproject = |z: f32| {
+                let world = inverse * Vec4::new(x, y, z, 1.0);
+                world.xyz() / world.w
+            };
+            let (near, far) = (unproject(-1.0), unproject(1.0));
+            let direction = (far - near).normalize();
+            if (near - eye).normalize().dot(&direction) < 1.0 - 1e-4 {
+                return Err(format!("el rayo de ({}, {}) no sale del ojo", x, y));
+            }
+
+            let along = to_screen(&view, &projection, &viewport, eye + direction * 37.0);
+            if (along.x - x).abs() > 0.05 || (along.y - y).abs() > 0.05 {
+                return Err(format!("({}, {}) vuelve como ({:.3}, {:.3})", x, y, along.x, along.y));
+            }
+        }
+        Ok(())
+    }
+
+    // `--clip-planes` fija los dos planos y rechaza los que no cumplen 0 < near < far
+    #[test]
+    fn clip_planes_args() -> Result<(), String> {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        if clip_planes_from_args(&[])?.is_some() || clip_planes_from_args(&args(&["--clip-planes", "0.5", "2000"]))? != Some((0.5, 2000.0)) {
+            return Err("--clip-planes no dio los planos pedidos".to_string());
+        }
+        for bad in [&["--clip-planes", "0", "100"][..], &["--clip-planes", "10", "5"], &["--clip-planes", "1"]] {
+            if clip_planes_from_args(&args(bad)).is_ok() {
+                return Err(format!("se aceptó {:?}", bad));
+            }
+        }
+        Ok(())
+    }
+}
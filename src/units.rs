@@ -0,0 +1,170 @@
+use std::fmt::Write;
+
+// Palabras de magnitud: se usa la primera con la que la parte entera no tiene más cifras
+// que las significativas pedidas (999.96 con 2 cifras → "1.0 mil")
+const MAGNITUDE_WORDS: [&str; 3] = ["", "mil", "mill."];
+
+// Equivalencia entre las unidades de la simulación y el mundo real
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistanceScale {
+    // Distancias y tamaños exagerados para que todo quepa en pantalla: no hay equivalencia en km
+    // (las distancias no son proporcionales a las reales; ver `light_travel::real_solar_distance`)
+    Didactic,
+}
+
+// Escala con que se muestran las distancias; la simulación usa la didáctica
+pub const DISTANCE_SCALE: DistanceScale = DistanceScale::Didactic;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    // Distancia en unidades de la simulación, mostrada según la escala
+    Distance(DistanceScale),
+    Kilometers,
+    Kelvin,
+    // Sin unidad (conteos, períodos en ticks)
+    Plain,
+}
+
+impl Unit {
+    // Valor convertido y símbolo con que se muestra
+    fn convert(self, value: f64) -> (f64, &'static str) {
+        match self {
+            Unit::Distance(DistanceScale::Didactic) => (value, "u"),
+            Unit::Kilometers => (value, "km"),
+            Unit::Kelvin => (value, "K"),
+            Unit::Plain => (value, ""),
+        }
+    }
+}
+
+// Escribe `value` en `out` (que se vacía antes) con `sig_figs` cifras significativas,
+// separador de miles "," y punto decimal, sin depender del locale del sistema.
+// Reutilizar `out` entre frames evita reservar memoria en cada llamada.
+pub fn format_quantity(out: &mut String, value: f64, unit: Unit, sig_figs: usize) {
+    out.clear();
+    let (value, symbol) = unit.convert(value);
+    let sig_figs = sig_figs.max(1);
+
+    if !value.is_finite() {
+        out.push('-');
+    } else {
+        let mut group = 0;
+        let mut magnitude = round_to_significant(value.abs(), sig_figs);
+        while group + 1 < MAGNITUDE_WORDS.len() && integer_digits(magnitude) > sig_figs {
+            group += 1;
+            magnitude = round_to_significant(value.abs() / 1000f64.powi(group as i32), sig_figs);
+        }
+
+        // Un valor que redondea a cero no lleva signo
+        if value < 0.0 && magnitude > 0.0 {
+            out.push('-');
+        }
+        write_grouped(out, magnitude, decimals_for(magnitude, sig_figs));
+        if group > 0 {
+            out.push(' ');
+            out.push_str(MAGNITUDE_WORDS[group]);
+        }
+    }
+
+    if !symbol.is_empty() {
+        out.push(' ');
+        out.push_str(symbol);
+    }
+}
+
+fn round_to_significant(value: f64, sig_figs: usize) -> f64 {
+    if value == 0.0 {
+        return 0.0;
+    }
+    // Con exponente negativo se divide por una potencia exacta de 10 en vez de multiplicar
+    // por 0.1, 0.01... que no son representables y dejarían 999.999... en vez de 1000
+    let exponent = sig_figs as i32 - 1 - decimal_exponent(value);
+    if exponent >= 0 {
+        let factor = 10f64.powi(exponent);
+        (value * factor).round() / factor
+    } else {
+        let factor = 10f64.powi(-exponent);
+        (value / factor).round() * factor
+    }
+}
+
+// floor(log10(value)) para value > 0, corregido por si log10 se queda corto en una potencia de 10
+fn decimal_exponent(value: f64) -> i32 {
+    let exponent = value.log10().floor() as i32;
+    if 10f64.powi(exponent + 1) <= value {
+        exponent + 1
+    } else if 10f64.powi(exponent) > value {
+        exponent - 1
+    } else {
+        exponent
+    }
+}
+
+// Cifras de la parte entera (0.5 tiene una: el cero)
+fn integer_digits(value: f64) -> usize {
+    let mut integer = value.trunc() as u64;
+    let mut digits = 1;
+    while integer >= 10 {
+        integer /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+fn decimals_for(value: f64, sig_figs: usize) -> usize {
+    if value == 0.0 {
+        return 0;
+    }
+    (sig_figs as i32 - 1 - decimal_exponent(value)).max(0) as usize
+}
+
+fn write_grouped(out: &mut String, value: f64, decimals: usize) {
+    let scale = 10u64.pow(decimals as u32);
+    let scaled = (value * scale as f64).round() as u64;
+    write_thousands(out, scaled / scale);
+    if decimals > 0 {
+        let _ = write!(out, ".{:0width$}", scaled % scale, width = decimals);
+    }
+}
+
+fn write_thousands(out: &mut String, value: u64) {
+    if value >= 1000 {
+        write_thousands(out, value / 1000);
+        let _ = write!(out, ",{:03}", value % 1000);
+    } else {
+        let _ = write!(out, "{}", value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Casos límite del redondeo, el signo y la elección de unidades de `format_quantity`
+    #[test]
+    fn quantity_format() -> Result<(), String> {
+        let cases = [
+            (999.96, Unit::Plain, 2, "1.0 mil"),
+            (999.96, Unit::Plain, 5, "999.96"),
+            (0.0, Unit::Plain, 3, "0"),
+            (-0.0001, Unit::Plain, 2, "-0.00010"),
+            (-1234.5, Unit::Kelvin, 3, "-1.23 mil K"),
+            (5772.0, Unit::Kelvin, 4, "5,772 K"),
+            (139_820.0, Unit::Kilometers, 6, "139,820 km"),
+            (1_392_700.0, Unit::Kilometers, 5, "1,392.7 mil km"),
+            (0.0345, Unit::Plain, 2, "0.035"),
+            (60.0, Unit::Distance(DistanceScale::Didactic), 3, "60.0 u"),
+            (1.5e8, Unit::Kilometers, 4, "150.0 mill. km"),
+            (1.0e12, Unit::Kilometers, 2, "1,000,000 mill. km"),
+        ];
+
+        let mut out = String::new();
+        for (value, unit, sig_figs, expected) in cases {
+            format_quantity(&mut out, value, unit, sig_figs);
+            if out != expected {
+                return Err(format!("{} con {} cifras ({:?}) da '{}', se esperaba '{}'", value, sig_figs, unit, out, expected));
+            }
+        }
+        Ok(())
+    }
+}
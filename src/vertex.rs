@@ -9,6 +9,13 @@ pub struct Vertex {
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub world_position: Vec3,
+  // Índice del vértice en su malla, si viene de una con vértices compartidos: las copias con
+  // el mismo índice se transforman igual (ver `VertexCache`)
+  pub mesh_index: Option<usize>,
+  // El vértice quedó en el plano de la cámara (|w| casi 0) y no tiene posición en pantalla:
+  // los triángulos que lo usan se descartan en vez de rasterizarse
+  pub unprojected: bool,
 }
 
 impl Vertex {
@@ -20,9 +27,17 @@ impl Vertex {
       color: Color::black(),
       transformed_position: position,
       transformed_normal: normal,
+      world_position: position,
+      mesh_index: None,
+      unprojected: false,
     }
   }
 
+  pub fn with_mesh_index(mut self, index: usize) -> Self {
+    self.mesh_index = Some(index);
+    self
+  }
+
   pub fn new_with_color(position: Vec3, color: Color) -> Self {
     Vertex {
       position,
@@ -31,6 +46,9 @@ impl Vertex {
       color,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      world_position: position,
+      mesh_index: None,
+      unprojected: false,
     }
   }
 
@@ -49,6 +67,9 @@ impl Default for Vertex {
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+      mesh_index: None,
+      unprojected: false,
     }
   }
 }
\ No newline at end of file
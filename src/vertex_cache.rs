@@ -0,0 +1,101 @@
+use crate::shaders::vertex_shader;
+use crate::uniforms::Uniforms;
+use crate::vertex::Vertex;
+
+// Entradas de la caché, como la caché post-transformación de una GPU
+pub const VERTEX_CACHE_SIZE: usize = 32;
+
+// Simulación de la caché post-transformación: guarda los últimos VERTEX_CACHE_SIZE vértices
+// transformados por su índice en la malla y, si vuelve a aparecer uno de esos índices, reusa
+// el resultado en vez de correr otra vez el vertex shader (que con relieve evalúa el ruido).
+// Reemplaza en orden FIFO. Vale para un solo dibujo: con otros uniforms el resultado cambia.
+pub struct VertexCache {
+    slots: [Option<(usize, Vertex)>; VERTEX_CACHE_SIZE],
+    next: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl VertexCache {
+    pub fn new() -> Self {
+        VertexCache { slots: std::array::from_fn(|_| None), next: 0, hits: 0, misses: 0 }
+    }
+
+    // `vertex_shader(vertex, uniforms)`, reusado si su índice está en la caché. Los vértices
+    // sin índice se transforman siempre y no ocupan entradas.
+    pub fn transform(&mut self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        let index = match vertex.mesh_index {
+            Some(index) => index,
+            None => {
+                self.misses += 1;
+                return vertex_shader(vertex, uniforms);
+            }
+        };
+
+        let cached = self.slots.iter().flatten().find(|(slot_index, _)| *slot_index == index);
+        if let Some((_, transformed)) = cached {
+            self.hits += 1;
+            return transformed.clone();
+        }
+
+        self.misses += 1;
+        let transformed = vertex_shader(vertex, uniforms);
+        self.slots[self.next] = Some((index, transformed.clone()));
+        self.next = (self.next + 1) % VERTEX_CACHE_SIZE;
+        transformed
+    }
+
+    // Fracción de los vértices pedidos que salieron de la caché
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.hits + self.misses;
+        if total == 0 { 0.0 } else { self.hits as f32 / total as f32 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Mat4;
+    use crate::{Obj, SPHERE_MODEL};
+    use crate::procedural_mesh::ProceduralMesh;
+    use crate::seed::{SeedSource, DEFAULT_SEED};
+    use crate::selftest::test_uniforms;
+    use crate::uniforms::Displacement;
+
+    // Cada vértice interior de una esfera lo comparten 6 triángulos, así que a lo sumo se acierta
+    // 5 de cada 6 (83%). Medido con 32 entradas: 77% en sphere.obj y 66% en la esfera UV, que va
+    // fila por fila y para cuando vuelve a un vértice de la fila de arriba ya lo descartó.
+    const MIN_OBJ_SPHERE_HIT_RATE: f32 = 0.75;
+
+    const MIN_UV_SPHERE_HIT_RATE: f32 = 0.6;
+
+    // La caché devuelve lo mismo que el vertex shader (con relieve, que depende del vértice) y en
+    // las esferas evita la mayoría de las transformaciones
+    #[test]
+    fn vertex_cache() -> Result<(), String> {
+        let seeds = &SeedSource::new(DEFAULT_SEED);
+        let obj = Obj::load(SPHERE_MODEL).map_err(|error| format!("no se pudo cargar: {}", error))?;
+        let obj = obj.transform(Mat4::new_translation(&-obj.bounding_center()));
+        let meshes = [
+            ("sphere.obj", obj.get_vertex_array(), MIN_OBJ_SPHERE_HIT_RATE),
+            ("esfera UV", ProceduralMesh::uv_sphere(obj.bounding_radius(), 48, 96), MIN_UV_SPHERE_HIT_RATE),
+        ];
+
+        let mut uniforms = test_uniforms(seeds);
+        uniforms.displacement = Displacement::terrain(0.5, 5.0);
+        for (name, vertices, min_hit_rate) in meshes {
+            let mut cache = VertexCache::new();
+            for vertex in &vertices {
+                let cached = cache.transform(vertex, &uniforms);
+                let expected = vertex_shader(vertex, &uniforms);
+                if cached.transformed_position != expected.transformed_position || cached.world_position != expected.world_position {
+                    return Err(format!("{}: la caché devolvió otro vértice", name));
+                }
+            }
+            if cache.hit_rate() < min_hit_rate {
+                return Err(format!("{}: aciertos {:.1}%, se esperaba al menos {:.0}%", name, cache.hit_rate() * 100.0, min_hit_rate * 100.0));
+            }
+        }
+        Ok(())
+    }
+}
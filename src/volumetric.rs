@@ -0,0 +1,96 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use rayon::prelude::*;
+
+use crate::effects::{EffectsBuffer, linear};
+use crate::aabb::Aabb;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::uniforms::Uniforms;
+
+// Muestras por rayo; la longitud del paso sale de dividir el tramo dentro de la caja
+const FOG_STEPS: usize = 32;
+
+// La niebla cambia lentamente en pantalla: se lanza un rayo por bloque de FOG_TILE x FOG_TILE
+// píxeles y se reparte el resultado, en vez de uno por píxel
+const FOG_TILE: usize = 4;
+
+// Escala de las coordenadas de mundo al muestrear el ruido
+const FOG_NOISE_ZOOM: f32 = 1.5;
+
+// Nebulosa de fondo: lanza un rayo por cada bloque de pantalla, acumula densidad muestreando
+// ruido 3D a lo largo del tramo dentro de `fog_bounds` y suma el color al buffer de efectos
+pub fn render_volumetric_fog(
+    effects: &mut EffectsBuffer,
+    camera: &Camera,
+    uniforms: &Uniforms,
+    fog_bounds: Aabb,
+    fog_color: Color,
+    fog_density: f32,
+) {
+    let inverse = (uniforms.projection_matrix * uniforms.view_matrix)
+        .try_inverse()
+        .unwrap_or(Mat4::identity());
+    let (width, height) = (effects.width, effects.height);
+    let tiles_x = width.div_ceil(FOG_TILE);
+    let tiles_y = height.div_ceil(FOG_TILE);
+
+    let amounts: Vec<f32> = (0..tiles_x * tiles_y)
+        .into_par_iter()
+        .map(|tile| {
+            let x = (tile % tiles_x) * FOG_TILE + FOG_TILE / 2;
+            let y = (tile / tiles_x) * FOG_TILE + FOG_TILE / 2;
+            let ndc_x = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+            let ndc_y = 1.0 - (y as f32 + 0.5) / height as f32 * 2.0;
+            let direction = unproject(&inverse, ndc_x, ndc_y, 1.0) - unproject(&inverse, ndc_x, ndc_y, -1.0);
+
+            march(uniforms, camera.eye, direction.normalize(), &fog_bounds, fog_density)
+        })
+        .collect();
+
+    let fog = linear(fog_color);
+    effects.light.par_chunks_mut(width.max(1)).enumerate().for_each(|(y, row)| {
+        for (x, light) in row.iter_mut().enumerate() {
+            let amount = amounts[(y / FOG_TILE) * tiles_x + x / FOG_TILE];
+            if amount > 0.0 {
+                *light += fog * amount;
+            }
+        }
+    });
+}
+
+fn unproject(inverse: &Mat4, x: f32, y: f32, z: f32) -> Vec3 {
+    let world = inverse * Vec4::new(x, y, z, 1.0);
+    Vec3::new(world.x, world.y, world.z) / world.w
+}
+
+// Cantidad de niebla (0.0 a 1.0) acumulada por el rayo dentro de la caja
+fn march(uniforms: &Uniforms, origin: Vec3, direction: Vec3, bounds: &Aabb, density: f32) -> f32 {
+    let (t_near, t_far) = match bounds.ray_intersection(origin, direction) {
+        Some(range) => range,
+        None => return 0.0,
+    };
+
+    let step_size = (t_far - t_near) / FOG_STEPS as f32;
+    let center = bounds.center();
+    let half_size = bounds.size() * 0.5;
+    let mut amount = 0.0;
+    for step in 0..FOG_STEPS {
+        let point = origin + direction * (t_near + (step as f32 + 0.5) * step_size);
+        let noise_value = uniforms.noise.get_noise_3d(
+            point.x * FOG_NOISE_ZOOM,
+            point.y * FOG_NOISE_ZOOM,
+            point.z * FOG_NOISE_ZOOM,
+        ) * 0.5 + 0.5;
+
+        // Se desvanece hacia los bordes (elipsoide inscrito) para que no se vea la forma de la caja
+        let offset = (point - center).component_div(&half_size);
+        let falloff = (1.0 - offset.magnitude_squared()).max(0.0);
+
+        amount += density * step_size * noise_value * falloff;
+        if amount >= 1.0 {
+            return 1.0;
+        }
+    }
+
+    amount
+}